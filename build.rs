@@ -0,0 +1,13 @@
+use std::{env, fs, path::PathBuf};
+
+/// Release metadata embedded in the binary, used when neither the network nor the cached
+/// releases are available.
+const RELEASE_SNAPSHOT: &str = "releases-snapshot.json";
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", RELEASE_SNAPSHOT);
+    let snapshot = fs::read(RELEASE_SNAPSHOT).expect("release snapshot");
+    let compressed = zstd::encode_all(snapshot.as_slice(), 19).expect("compress release snapshot");
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("releases-snapshot.json.zst");
+    fs::write(out, compressed).expect("write release snapshot");
+}
@@ -0,0 +1,94 @@
+//! The stable API of the library, for build plugins and other tools embedding vvm.
+//!
+//! Everything re-exported here follows semver: it only changes in a breaking way with a new
+//! major version of the crate. The rest of the crate root serves the `vvm` and `vyper` binaries,
+//! is hidden from the documentation and may change with any release.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), vvm_lib::api::VyperVmError> {
+//! use vvm_lib::api::{compile_project, install, installed_versions, resolve_version};
+//!
+//! let version = resolve_version("0.3").await?;
+//! if !installed_versions()?.contains(&version) {
+//!     install(&version).await?;
+//! }
+//! let outputs = compile_project(&version, &["Token.vy".into()], 4, |_, _, _| {}).await?;
+//! assert!(outputs.iter().all(|output| output.success));
+//! # Ok(())
+//! # }
+//! ```
+
+/// The semver types of the API, so callers don't need to depend on the same `semver` release.
+pub use semver::{Version, VersionReq};
+
+// errors
+pub use crate::error::{ErrorCategory, VyperVmError};
+
+// install
+#[cfg(feature = "blocking")]
+pub use crate::{blocking_install, blocking_install_with_observer};
+pub use crate::{
+    events::{InstallEvent, InstallObserver, InstallStream},
+    install, install_many, install_stream, install_with_observer, remove_version,
+};
+
+// resolve
+pub use crate::{
+    channel::{resolve_requirement, resolve_version},
+    pin::{resolve_for_dir, ResolvedVersion, VersionSource},
+    which::{which_for_dir, WhichVersion},
+};
+
+// list
+pub use crate::{
+    all_versions, current_version, installed_versions,
+    listing::{version_listing, VersionEntry, VersionListing},
+};
+
+// paths
+pub use crate::{
+    binary_path,
+    home::{active_home, set_home_dir, VVM_HOME_ENV},
+    layout::install_path,
+};
+
+// compile
+pub use crate::compile::{compile_files, compile_project, CompileOutput, OutputStream};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::{Path, PathBuf};
+
+    // changing any of these signatures needs a major release
+    #[test]
+    fn stable_signatures() {
+        let _: fn() -> Result<Vec<Version>, VyperVmError> = installed_versions;
+        let _: fn() -> Result<Option<Version>, VyperVmError> = current_version;
+        let _: fn(&Version) -> Result<(), VyperVmError> = remove_version;
+        let _: fn(&Version) -> Result<PathBuf, VyperVmError> = binary_path;
+        let _: fn(&Version) -> Result<PathBuf, VyperVmError> = install_path;
+        let _: fn(&Version) -> InstallStream = install_stream;
+        let _: fn(&Path) -> Result<Option<ResolvedVersion>, VyperVmError> =
+            |dir| resolve_for_dir(dir);
+        let _: fn(&Path) -> Result<Option<WhichVersion>, VyperVmError> = |dir| which_for_dir(dir);
+        let _ = |remote: Option<&[Version]>, limit| -> Result<VersionListing, VyperVmError> {
+            version_listing(remote, limit)
+        };
+
+        // futures are never polled, only their types are checked
+        let version = Version::new(0, 3, 10);
+        let requirement = VersionReq::STAR;
+        let _: &dyn std::future::Future<Output = Result<PathBuf, VyperVmError>> =
+            &install(&version);
+        let _: &dyn std::future::Future<Output = Result<Vec<Version>, VyperVmError>> =
+            &all_versions();
+        let _: &dyn std::future::Future<Output = Result<Version, VyperVmError>> =
+            &resolve_version("0.3");
+        let _: &dyn std::future::Future<Output = Result<Version, VyperVmError>> =
+            &resolve_requirement(&requirement);
+        let _: &dyn std::future::Future<Output = Result<Vec<CompileOutput>, VyperVmError>> =
+            &compile_project(&version, &[], 1, |_: &Path, _: OutputStream, _: &str| {});
+    }
+}
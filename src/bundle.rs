@@ -0,0 +1,215 @@
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    binary_name, binary_path, digest::Digests, do_install, error::VyperVmError, lock_file_path,
+    manifest::VersionOrigin, platform::platform, releases::hex_string, setup_home, sha256_digest,
+    try_lock_file,
+};
+
+/// Format identifier of the bundle manifest.
+const BUNDLE_FORMAT: &str = "vvm-rs-bundle-1";
+
+/// Name of the manifest file at the root of the bundle archive.
+const BUNDLE_MANIFEST: &str = "manifest.json";
+
+/// The manifest describing the content of a bundle.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    #[serde(rename = "_format")]
+    pub format: String,
+    /// Platform the bundled binaries were installed for.
+    pub platform: String,
+    pub entries: Vec<BundleEntry>,
+}
+
+/// A single Vyper binary packaged in a bundle.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleEntry {
+    pub version: Version,
+    /// Path of the binary inside the archive.
+    pub file: String,
+    #[serde(with = "hex_string")]
+    pub sha256: Vec<u8>,
+    /// Digests of the binary with every algorithm supported when the bundle was created, missing
+    /// in bundles of older releases of vvm.
+    #[serde(default, skip_serializing_if = "Digests::is_empty")]
+    pub digests: Digests,
+}
+
+/// Packages the provided installed versions, with a manifest of their checksums, into a
+/// zstd-compressed tarball at `out`, so they can be installed on a machine without internet.
+pub fn create_bundle(
+    versions: &[Version],
+    out: impl AsRef<Path>,
+) -> Result<BundleManifest, VyperVmError> {
+    let mut binaries = Vec::with_capacity(versions.len());
+    let mut entries = Vec::with_capacity(versions.len());
+    for version in versions {
+        let path = binary_path(version)?;
+        if !path.exists() {
+            return Err(VyperVmError::VersionNotInstalled(version.to_string()));
+        }
+        let binbytes = fs::read(&path)?;
+        entries.push(BundleEntry {
+            version: version.clone(),
+            file: format!("{}/{}", version, binary_name(version)),
+            sha256: sha256_digest(&binbytes),
+            digests: Digests::compute(&binbytes),
+        });
+        binaries.push(binbytes);
+    }
+
+    let manifest = BundleManifest {
+        format: BUNDLE_FORMAT.to_string(),
+        platform: platform().to_string(),
+        entries,
+    };
+
+    let file = fs::File::create(out.as_ref())?;
+    let encoder = zstd::Encoder::new(file, 0)?.auto_finish();
+    let mut builder = tar::Builder::new(encoder);
+
+    let manifest_bytes = serde_json::to_vec_pretty(&manifest)
+        .map_err(|err| VyperVmError::InvalidBundle(err.to_string()))?;
+    append_file(&mut builder, BUNDLE_MANIFEST, &manifest_bytes)?;
+    for (entry, binbytes) in manifest.entries.iter().zip(binaries) {
+        append_file(&mut builder, &entry.file, &binbytes)?;
+    }
+    builder.into_inner()?;
+
+    Ok(manifest)
+}
+
+/// Installs every version packaged in the bundle at `path`, verifying each binary against the
+/// checksums recorded in the bundle manifest.
+///
+/// Returns the paths to the installed Vyper binaries.
+pub fn install_bundle(path: impl AsRef<Path>) -> Result<Vec<PathBuf>, VyperVmError> {
+    let (manifest, mut files) = read_bundle(path)?;
+
+    if manifest.platform != platform().to_string() {
+        return Err(VyperVmError::InvalidBundle(format!(
+            "bundle was created for platform {}, this machine is {}",
+            manifest.platform,
+            platform()
+        )));
+    }
+
+    // verify everything before installing anything
+    for entry in manifest.entries.iter() {
+        let binbytes = files
+            .get(&entry.file)
+            .ok_or_else(|| VyperVmError::InvalidBundle(format!("missing file {}", entry.file)))?;
+        if sha256_digest(binbytes) != entry.sha256 || entry.digests.mismatch(binbytes).is_some() {
+            return Err(VyperVmError::ChecksumMismatch(entry.version.to_string()));
+        }
+    }
+
+    setup_home()?;
+    let mut installed = Vec::with_capacity(manifest.entries.len());
+    for entry in manifest.entries {
+        let binbytes = files.remove(&entry.file).unwrap_or_default();
+        let _lock = try_lock_file(lock_file_path(&entry.version)?)?;
+        let vyper_path = do_install(
+            entry.version.clone(),
+            binbytes,
+            &entry.file,
+            VersionOrigin::Bundle,
+        )?;
+        installed.push(vyper_path);
+    }
+
+    Ok(installed)
+}
+
+/// Reads the manifest and all files of the bundle at `path`.
+fn read_bundle(
+    path: impl AsRef<Path>,
+) -> Result<(BundleManifest, BTreeMap<String, Vec<u8>>), VyperVmError> {
+    let file = fs::File::open(path.as_ref())?;
+    let mut archive = tar::Archive::new(zstd::Decoder::new(file)?);
+
+    let mut manifest = None;
+    let mut files = BTreeMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().to_string();
+        let mut content = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut content)?;
+        if name == BUNDLE_MANIFEST {
+            manifest = Some(
+                serde_json::from_slice::<BundleManifest>(&content)
+                    .map_err(|err| VyperVmError::InvalidBundle(err.to_string()))?,
+            );
+        } else {
+            files.insert(name, content);
+        }
+    }
+
+    let manifest = manifest
+        .ok_or_else(|| VyperVmError::InvalidBundle(format!("missing {}", BUNDLE_MANIFEST)))?;
+    if manifest.format != BUNDLE_FORMAT {
+        return Err(VyperVmError::InvalidBundle(format!(
+            "unsupported bundle format {}",
+            manifest.format
+        )));
+    }
+
+    Ok((manifest, files))
+}
+
+fn append_file<W: std::io::Write>(
+    builder: &mut tar::Builder<W>,
+    path: &str,
+    content: &[u8],
+) -> Result<(), VyperVmError> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o755);
+    header.set_cksum();
+    builder.append_data(&mut header, path, content)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn bundle_roundtrip() {
+        let version = Version::new(0, 0, 1);
+        let sandbox = crate::testing::sandbox().unwrap();
+        // installs run the binary to verify it
+        let fake = crate::testing::FakeVyper::new();
+        sandbox.register(&version, &fake).unwrap();
+        let fake = fake.binary(&version);
+
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("bundle.tar.zst");
+        let manifest = create_bundle(std::slice::from_ref(&version), &bundle).unwrap();
+        assert_eq!(manifest.entries.len(), 1);
+        assert_eq!(manifest.entries[0].digests, Digests::compute(&fake));
+
+        let (read_manifest, files) = read_bundle(&bundle).unwrap();
+        assert_eq!(read_manifest, manifest);
+        assert_eq!(files[&manifest.entries[0].file], fake);
+
+        crate::remove_version(&version).unwrap();
+        let installed = install_bundle(&bundle).unwrap();
+        assert_eq!(fs::read(&installed[0]).unwrap(), fake);
+        assert_eq!(
+            crate::VersionManifest::read(&version).unwrap().origin,
+            Some(VersionOrigin::Bundle)
+        );
+        crate::remove_version(&version).unwrap();
+    }
+}
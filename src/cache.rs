@@ -0,0 +1,696 @@
+use crate::{descriptor::env_descriptor, error::VyperVmError, home::active_home, sha256_digest};
+use semver::Version;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::btree_map::BTreeMap,
+    fmt,
+    fs::{self},
+    io,
+    path::{Path, PathBuf},
+};
+
+use md5::Digest;
+
+// simplified cache based on ether-rs solc logic with adjustments for vyper
+// https://github.com/gakonst/ethers-rs/blob/c75608eda1e1fdc7366a7501c1a6b3f0216a25ea/ethers-solc/src/cache.rs
+
+// close to ether-rs solidity cache format
+const FORMAT_VERSION: &str = "vvm-rs-vyper-cache-1";
+
+type Result<T> = std::result::Result<T, VyperVmError>;
+
+/// The file name of the default cache file
+pub const VYPER_FILES_CACHE_FILENAME: &str = "vvm-vyper-files-cache.json";
+
+/// Output formats printing a single bytecode, the only `-f` values whose output is cached
+pub const CACHEABLE_FORMATS: &[&str] = &["bytecode", "bytecode_runtime", "blueprint_bytecode"];
+
+/// A cache file
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct VyperFilesCache {
+    #[serde(rename = "_format")]
+    pub format: String,
+    pub files: BTreeMap<PathBuf, CacheEntry>,
+}
+
+impl VyperFilesCache {
+    /// Create a new cache instance with empty entries
+    fn new() -> Self {
+        Self {
+            format: FORMAT_VERSION.to_string(),
+            files: BTreeMap::new(),
+        }
+    }
+
+    // loads existing cache or create a new one
+    pub fn get() -> Self {
+        if let Ok(cache) = get_cache_path().and_then(VyperFilesCache::read) {
+            cache
+        } else {
+            VyperFilesCache::new()
+        }
+    }
+
+    /// Returns true if the cache has no entries
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// How many entries the cache contains where each entry represents a source file
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns the corresponding `CacheEntry` for the file if it exists
+    pub fn entry(&self, file: impl AsRef<Path>) -> Option<&CacheEntry> {
+        self.files.get(file.as_ref())
+    }
+
+    /// Returns the `CacheEntry` of the file compiled to the output `format`, see
+    /// [`CompileSettings::output_format`]
+    pub fn format_entry(
+        &self,
+        file: impl AsRef<Path>,
+        format: Option<&str>,
+    ) -> Option<&CacheEntry> {
+        self.files.get(&entry_path(file.as_ref(), format))
+    }
+
+    /// Returns an entry compiled from the same content with the same compiler version and
+    /// settings, preferably the entry of `key.source`. Entries are shared by the paths a file is
+    /// reached through, e.g. symlinked workspaces or bind-mounted CI checkouts
+    pub fn lookup(&self, key: &CacheKey) -> Option<&CacheEntry> {
+        let same_input =
+            |entry: &&CacheEntry| entry.key.as_ref().is_some_and(|k| k.same_input(key));
+        self.format_entry(&key.source, key.settings.output_format.as_deref())
+            .filter(same_input)
+            .or_else(|| self.files.values().find(same_input))
+    }
+
+    /// Returns the corresponding `CacheEntry` for the file if it exists
+    pub fn entry_mut(&mut self, file: impl AsRef<Path>) -> Option<&mut CacheEntry> {
+        self.files.get_mut(file.as_ref())
+    }
+
+    /// adds or updates an entry in cache, `flags` are the compiler flags the bytecode was
+    /// produced with. Entries compiled with flags that are not [`CompileSettings`] have no
+    /// key and are never looked up. Each output format of a file has its own entry
+    pub fn add_entry(
+        &mut self,
+        file: impl AsRef<Path>,
+        bytecode: &str,
+        version: &Version,
+        flags: &[String],
+    ) -> Result<()> {
+        let metadata = ArtifactMetadata::new(version, file.as_ref(), flags)?;
+        let key = CacheKey::new(file.as_ref(), version, flags)?;
+        let format = key
+            .as_ref()
+            .and_then(|key| key.settings.output_format.as_deref());
+        let path = entry_path(file.as_ref(), format);
+        if let Some(entry) = self.entry_mut(&path) {
+            // update
+            entry.content_hash = get_file_hash(file.as_ref())?;
+            entry.deployed_bytecode = bytecode.to_string();
+            entry.version = Some(version.clone());
+            entry.metadata = Some(metadata.clone());
+            entry.key = key.clone();
+        }
+
+        // add new entry
+        let new_entry = CacheEntry {
+            content_hash: get_file_hash(file.as_ref())?,
+            source_name: file.as_ref().to_path_buf(),
+            version: Some(version.clone()),
+            deployed_bytecode: bytecode.to_string(),
+            metadata: Some(metadata),
+            key,
+        };
+
+        // other paths to the same content are found by their content
+        if let Some(key) = &new_entry.key {
+            self.files.retain(|stored, entry| {
+                *stored == path || !entry.key.as_ref().is_some_and(|k| k.same_input(key))
+            });
+        }
+        self.files.insert(path, new_entry);
+
+        Ok(())
+    }
+
+    /// Reads the cache json file from the given path
+    #[tracing::instrument(skip_all, name = "vyper-files-cache::read")]
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        tracing::trace!("reading vyper files cache at {}", path.display());
+        let cache: VyperFilesCache = read_json_file(path)?;
+        tracing::trace!(
+            "read cache \"{}\" with {} entries",
+            cache.format,
+            cache.files.len()
+        );
+        Ok(cache)
+    }
+
+    /// Write the cache as json file to the given path
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        create_parent_dir_all(path)?;
+        let file = fs::File::create(path)?;
+        tracing::trace!(
+            "writing cache with {} entries to json file: \"{}\"",
+            self.len(),
+            path.display()
+        );
+        serde_json::to_writer_pretty(file, self)?;
+        tracing::trace!("cache file located: \"{}\"", path.display());
+        Ok(())
+    }
+}
+
+/// A `CacheEntry` in the cache file represents a vyper file
+///
+/// TODO: add better cache invalidation of entries
+/// Simplified version of cache entry for basic caching
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheEntry {
+    /// hash to identify whether the content of the file changed
+    pub content_hash: String,
+    /// identifier name
+    pub source_name: PathBuf,
+    /// the compiler version that produced the bytecode, missing for entries written by older
+    /// versions of the wrapper
+    #[serde(default)]
+    pub version: Option<Version>,
+    // TODO: implement version
+    // pub version_requirement: Option<String>,
+    // TODO: implement version
+    // pub last_modified: : u6,
+    pub deployed_bytecode: String,
+    /// how the bytecode was produced, missing for entries written by older versions of the
+    /// wrapper
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<ArtifactMetadata>,
+    /// the key the bytecode is cached under, missing for entries written by older versions of
+    /// the wrapper, which are never looked up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<CacheKey>,
+}
+
+/// Reproducibility information embedded in every artifact written by the wrapper, so
+/// differences in the output can be traced back to differences in the toolchain or inputs
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactMetadata {
+    pub compiler_version: Version,
+    /// commit the compiler was built from, if known
+    pub compiler_commit: Option<String>,
+    /// version of vvm-rs that wrote the artifact
+    pub vvm_version: String,
+    /// sha256 hex digest of every input file
+    pub input_hashes: BTreeMap<PathBuf, String>,
+    /// flags passed to the compiler
+    pub flags: Vec<String>,
+}
+
+impl ArtifactMetadata {
+    /// Collects the metadata of an artifact compiled from `input` by `version`
+    pub fn new(version: &Version, input: &Path, flags: &[String]) -> Result<Self> {
+        let mut input_hashes = BTreeMap::new();
+        input_hashes.insert(
+            input.to_path_buf(),
+            hex::encode(sha256_digest(fs::read(input)?)),
+        );
+        Ok(Self {
+            compiler_version: version.clone(),
+            compiler_commit: env_descriptor(version).ok().and_then(|d| d.commit),
+            vvm_version: env!("CARGO_PKG_VERSION").to_string(),
+            input_hashes,
+            flags: flags.to_vec(),
+        })
+    }
+}
+
+/// Compiler settings changing the produced bytecode, normalized so equivalent invocations,
+/// e.g. `--no-optimize` and `--optimize none`, share cache entries
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompileSettings {
+    /// optimization mode, `gas` when not given like the compiler
+    pub optimize: String,
+    /// false with `--no-bytecode-metadata`
+    pub bytecode_metadata: bool,
+    /// target EVM version, the compiler's default when not given
+    pub evm_version: Option<String>,
+    /// output format requested with `-f`, one of [`CACHEABLE_FORMATS`] other than the
+    /// default `bytecode`, e.g. `blueprint_bytecode` for factory deployments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<String>,
+}
+
+impl Default for CompileSettings {
+    fn default() -> Self {
+        Self {
+            optimize: "gas".to_string(),
+            bytecode_metadata: true,
+            evm_version: None,
+            output_format: None,
+        }
+    }
+}
+
+impl CompileSettings {
+    /// Parses the settings from compiler flags, returns `None` if a flag is not a setting or
+    /// requests an output format that is not a single bytecode, so the output is not cacheable
+    pub fn from_flags(flags: &[String]) -> Option<Self> {
+        match Self::parse(flags)? {
+            (settings, sources) if sources.is_empty() => Some(settings),
+            _ => None,
+        }
+    }
+
+    /// Splits the arguments of a cacheable compiler invocation, a single source file and
+    /// settings, into the source file and the flags
+    pub fn split_source(args: &[String]) -> Option<(String, Vec<String>)> {
+        let (_, mut sources) = Self::parse(args)?;
+        if sources.len() != 1 {
+            return None;
+        }
+        let source = sources.remove(0);
+        let mut flags = args.to_vec();
+        let position = flags.iter().position(|arg| *arg == source)?;
+        flags.remove(position);
+        Some((source, flags))
+    }
+
+    /// Returns the settings and the positional arguments
+    fn parse(args: &[String]) -> Option<(Self, Vec<String>)> {
+        let mut settings = Self::default();
+        let mut sources = vec![];
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            let mut take_value = || inline.clone().or_else(|| args.next().cloned());
+            match name {
+                "--no-optimize" => settings.optimize = "none".to_string(),
+                "-O" | "--optimize" => settings.optimize = take_value()?,
+                "--no-bytecode-metadata" => settings.bytecode_metadata = false,
+                "--evm-version" => settings.evm_version = Some(take_value()?),
+                "-f" | "--format" => {
+                    let format = take_value()?;
+                    if !CACHEABLE_FORMATS.contains(&format.as_str()) {
+                        return None;
+                    }
+                    settings.output_format = Some(format).filter(|format| format != "bytecode");
+                }
+                _ if arg.starts_with('-') => return None,
+                _ => sources.push(arg.clone()),
+            }
+        }
+        Some((settings, sources))
+    }
+}
+
+/// Everything the cached bytecode of a source file depends on
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheKey {
+    /// the path the file was compiled through, metadata only: the content identifies the input
+    pub source: PathBuf,
+    /// md5 hex digest of the source file
+    pub content_hash: String,
+    pub compiler_version: Version,
+    pub settings: CompileSettings,
+}
+
+impl CacheKey {
+    /// Computes the key of compiling `file` with `version` and `flags`, `None` if the flags are
+    /// not cacheable, see [`CompileSettings::from_flags`]
+    pub fn new(file: &Path, version: &Version, flags: &[String]) -> Result<Option<Self>> {
+        let settings = match CompileSettings::from_flags(flags) {
+            Some(settings) => settings,
+            None => return Ok(None),
+        };
+        Ok(Some(Self {
+            source: file.to_path_buf(),
+            content_hash: get_file_hash(file)?,
+            compiler_version: version.clone(),
+            settings,
+        }))
+    }
+}
+
+impl CacheKey {
+    /// returns true if both keys compile the same content with the same compiler version and
+    /// settings, whatever the path of the source
+    pub fn same_input(&self, other: &CacheKey) -> bool {
+        self.content_hash == other.content_hash
+            && self.compiler_version == other.compiler_version
+            && self.settings == other.settings
+    }
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "source            {}", self.source.display())?;
+        writeln!(f, "content hash      {}", self.content_hash)?;
+        writeln!(f, "compiler version  {}", self.compiler_version)?;
+        writeln!(f, "optimize          {}", self.settings.optimize)?;
+        writeln!(f, "bytecode metadata {}", self.settings.bytecode_metadata)?;
+        writeln!(
+            f,
+            "evm version       {}",
+            self.settings.evm_version.as_deref().unwrap_or("default")
+        )?;
+        write!(
+            f,
+            "output format     {}",
+            self.settings.output_format.as_deref().unwrap_or("bytecode")
+        )
+    }
+}
+
+impl CacheEntry {
+    /// returns true if the file:
+    ///   - is new
+    ///   - has changed
+    ///
+    /// returns false if file is found and hash is the same
+    pub fn is_dirty(&self) -> bool {
+        if let Ok(hash) = get_file_hash(&self.source_name) {
+            if hash == self.content_hash {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+///// Helper Functions /////
+
+/// The path an entry is stored under, the source file for the default output format
+fn entry_path(file: &Path, format: Option<&str>) -> PathBuf {
+    match format {
+        Some(format) => PathBuf::from(format!("{}#{}", file.display(), format)),
+        None => file.to_path_buf(),
+    }
+}
+
+fn get_file_hash(path: impl AsRef<Path>) -> Result<String> {
+    let path = path.as_ref();
+    let file = std::fs::File::open(path)?;
+    let mut file = std::io::BufReader::new(file);
+
+    let mut hasher = md5::Md5::new();
+    let _ = io::copy(&mut file, &mut hasher)?;
+    let result = hasher.finalize();
+
+    Ok(hex::encode(result))
+}
+
+/// Reads the json file and deserialize it into the provided type
+fn read_json_file<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T> {
+    let path = path.as_ref();
+    let file = std::fs::File::open(path)?;
+    let file = std::io::BufReader::new(file);
+    let val: T = serde_json::from_reader(file)?;
+    Ok(val)
+}
+
+/// Creates the parent directory of the `file` and all its ancestors if it does not exist
+/// See [`std::fs::create_dir_all()`]
+fn create_parent_dir_all(file: impl AsRef<Path>) -> Result<()> {
+    let file = file.as_ref();
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(())
+}
+
+/// Get cache dir path
+pub fn get_cache_path() -> Result<PathBuf> {
+    Ok(active_home()?
+        .join("cache")
+        .join(VYPER_FILES_CACHE_FILENAME))
+}
+
+#[test]
+fn test_read_cache_file() -> Result<()> {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push(format!("test-data/{}", VYPER_FILES_CACHE_FILENAME));
+
+    let file_name = "test-data/Token.vy";
+
+    println!("path read cache {}", d.display());
+
+    let cache_result = VyperFilesCache::read(d);
+
+    assert!(cache_result.is_ok());
+
+    let cache = cache_result.unwrap();
+
+    assert_eq!(cache.len(), 1);
+
+    let cache_entry = cache
+        .entry(file_name)
+        .expect("expected cache entry to exist");
+
+    let CacheEntry {
+        source_name,
+        content_hash,
+        version,
+        deployed_bytecode,
+        metadata,
+        key,
+    } = cache_entry;
+
+    assert_eq!(source_name.as_os_str(), file_name);
+    assert_eq!(content_hash, "089f6055c2d023b76eed71e820e7b580");
+    assert_eq!(version, &None);
+    assert_eq!(metadata, &None);
+    assert_eq!(key, &None);
+    assert_eq!(
+        deployed_bytecode,
+        r#"0x61048561001161000039610485610000f36003361161000c5761046d565b60003560e01c34610473576306fdde03811861009f576004361861047357602080608052600a6040527f5465737420546f6b656e0000000000000000000000000000000000000000000060605260408160800181518082526020830160208301815181525050508051806020830101601f82600003163682375050601f19601f8251602001011690509050810190506080f35b6395d89b41811861012757600436186104735760208060805260046040527f544553540000000000000000000000000000000000000000000000000000000060605260408160800181518082526020830160208301815181525050508051806020830101601f82600003163682375050601f19601f8251602001011690509050810190506080f35b63313ce5678118610145576004361861047357601260405260206040f35b63a9059cbb81186101eb5760443618610473576004358060a01c610473576040526001336020526000526040600020805460243580820382811161047357905090508155506001604051602052600052604060002080546024358082018281106104735790509050815550604051337fddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef60243560605260206060a3600160605260206060f35b63095ea7b3811861026a5760443618610473576004358060a01c610473576040526024356002336020526000526040600020806040516020526000526040600020905055604051337f8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b92560243560605260206060a3600160605260206060f35b6323b872dd81186103575760643618610473576004358060a01c610473576040526024358060a01c610473576060526002604051602052600052604060002080336020526000526040600020905080546044358082038281116104735790509050815550600160405160205260005260406000208054604435808203828111610473579050905081555060016060516020526000526040600020805460443580820182811061047357905090508155506060516040517fddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef60443560805260206080a3600160805260206080f35b6341a9680381186103b75760443618610473576004358060a01c6104735760405260016040516020526000526040600020805460243580820182811061047357905090508155506000546024358082018281106104735790509050600055005b6318160ddd81186103d657600436186104735760005460405260206040f35b6370a0823181186104115760243618610473576004358060a01c61047357604052600160405160205260005260406000205460605260206060f35b63dd62ed3e811861046b5760443618610473576004358060a01c610473576040526024358060a01c610473576060526002604051602052600052604060002080606051602052600052604060002090505460805260206080f35b505b60006000fd5b600080fda165767970657283000306000b"#
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_get_file_hash() -> Result<()> {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("test-data/Token.vy");
+
+    let expected_hash = "089f6055c2d023b76eed71e820e7b580";
+    let hash = get_file_hash(path)?;
+    assert_eq!(hash, expected_hash);
+
+    Ok(())
+}
+
+#[test]
+fn test_cache_entry_is_dirty() -> Result<()> {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("test-data/Token.vy");
+
+    const BAD_HASH: &str = "b95e2a6f5312b7df45db0caa631f2d21";
+
+    let clean_entry = CacheEntry {
+        content_hash: "089f6055c2d023b76eed71e820e7b580".to_string(),
+        source_name: path.clone(),
+        version: None,
+        deployed_bytecode: "mockbytecode".to_string(),
+        metadata: None,
+        key: None,
+    };
+
+    let dirty_entry = CacheEntry {
+        content_hash: BAD_HASH.to_string(),
+        source_name: path.clone(),
+        version: None,
+        deployed_bytecode: "mockbytecode".to_string(),
+        metadata: None,
+        key: None,
+    };
+
+    assert!(!clean_entry.is_dirty());
+    assert!(dirty_entry.is_dirty());
+
+    Ok(())
+}
+
+#[test]
+fn test_add_cache_entry() -> Result<()> {
+    crate::testing::sandbox()?;
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("test-data/Token.vy");
+
+    const UPDATED_BYTECODE: &str = "mocknewbytecode";
+    const MOCK_BYTECODE: &str = "mockbytecode";
+    const CONTENT_HASH: &str = "089f6055c2d023b76eed71e820e7b580";
+
+    let new_entry = CacheEntry {
+        content_hash: "089f6055c2d023b76eed71e820e7b580".to_string(),
+        source_name: path.clone(),
+        version: None,
+        deployed_bytecode: MOCK_BYTECODE.to_string(),
+        metadata: None,
+        key: None,
+    };
+
+    let version = Version::new(0, 3, 3);
+    let mut cache = VyperFilesCache::new();
+
+    cache.add_entry(&path, MOCK_BYTECODE, &version, &[])?;
+
+    assert!(!cache.is_empty());
+    let first_entry_op = cache.entry(new_entry.source_name);
+    assert!(first_entry_op.is_some());
+    let first_entry = first_entry_op.unwrap();
+    assert_eq!(first_entry.deployed_bytecode, MOCK_BYTECODE);
+    assert_eq!(first_entry.content_hash, CONTENT_HASH);
+    assert_eq!(first_entry.version, Some(version.clone()));
+    let metadata = first_entry.metadata.as_ref().unwrap();
+    assert_eq!(metadata.compiler_version, version);
+    assert_eq!(metadata.vvm_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(metadata.input_hashes.len(), 1);
+    let key = CacheKey::new(&path, &version, &[])?.unwrap();
+    assert!(cache.lookup(&key).is_some());
+
+    // update
+    let flags = vec!["-f".to_string(), "abi".to_string()];
+    cache.add_entry(&path, UPDATED_BYTECODE, &version, &flags)?;
+    assert!(cache.len() == 1);
+    let updated_entry = cache.entry(path.clone());
+    assert!(updated_entry.is_some());
+    assert_eq!(updated_entry.unwrap().deployed_bytecode, UPDATED_BYTECODE);
+    assert_eq!(
+        updated_entry.unwrap().metadata.as_ref().unwrap().flags,
+        flags
+    );
+    // output formats other than bytecode are not cacheable
+    assert!(cache.lookup(&key).is_none());
+
+    let flags = vec!["--optimize=codesize".to_string()];
+    cache.add_entry(&path, UPDATED_BYTECODE, &version, &flags)?;
+    assert!(cache.lookup(&key).is_none());
+    let codesize = CacheKey::new(&path, &version, &["-O".to_string(), "codesize".to_string()])?;
+    assert!(cache.lookup(&codesize.unwrap()).is_some());
+
+    Ok(())
+}
+
+#[test]
+fn test_lookup_by_content() -> Result<()> {
+    crate::testing::sandbox()?;
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("test-data/Token.vy");
+    let dir = tempfile::tempdir()?;
+    let copy = dir.path().join("Token.vy");
+    let other = dir.path().join("Other.vy");
+    fs::copy(&path, &copy)?;
+    fs::copy(&path, &other)?;
+
+    let version = Version::new(0, 3, 3);
+    let mut cache = VyperFilesCache::new();
+    cache.add_entry(&path, "0x01", &version, &[])?;
+
+    // the same content through another path hits the entry
+    let key = CacheKey::new(&copy, &version, &[])?.unwrap();
+    assert_eq!(cache.lookup(&key).unwrap().source_name, path);
+    let other_version = CacheKey::new(&copy, &Version::new(0, 3, 4), &[])?.unwrap();
+    assert!(cache.lookup(&other_version).is_none());
+
+    // entries of the same content are deduplicated
+    cache.add_entry(&copy, "0x01", &version, &[])?;
+    assert_eq!(cache.len(), 1);
+    assert!(cache.entry(&copy).is_some());
+
+    // until the content differs
+    fs::write(&other, "# changed")?;
+    cache.add_entry(&other, "0x02", &version, &[])?;
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.lookup(&key).unwrap().deployed_bytecode, "0x01");
+
+    Ok(())
+}
+
+#[test]
+fn test_caches_bytecode_formats() -> Result<()> {
+    crate::testing::sandbox()?;
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("test-data/Token.vy");
+    let version = Version::new(0, 3, 3);
+    let format = |format: &str| vec!["-f".to_string(), format.to_string()];
+    let mut cache = VyperFilesCache::new();
+
+    cache.add_entry(&path, "0x01", &version, &format("bytecode"))?;
+    cache.add_entry(&path, "0x02", &version, &format("blueprint_bytecode"))?;
+    cache.add_entry(&path, "0x03", &version, &format("bytecode_runtime"))?;
+    assert_eq!(cache.len(), 3);
+
+    let lookup = |flags: &[String]| {
+        let key = CacheKey::new(&path, &version, flags).unwrap().unwrap();
+        cache
+            .lookup(&key)
+            .map(|entry| entry.deployed_bytecode.clone())
+    };
+    assert_eq!(lookup(&[]).as_deref(), Some("0x01"));
+    assert_eq!(
+        lookup(&format("blueprint_bytecode")).as_deref(),
+        Some("0x02")
+    );
+    assert_eq!(lookup(&format("bytecode_runtime")).as_deref(), Some("0x03"));
+    let entry = cache
+        .format_entry(&path, Some("blueprint_bytecode"))
+        .unwrap();
+    assert_eq!(entry.source_name, path);
+    assert!(!entry.is_dirty());
+
+    Ok(())
+}
+
+#[test]
+fn test_normalizes_compile_settings() -> Result<()> {
+    let flags = |flags: &[&str]| flags.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        CompileSettings::from_flags(&[]),
+        Some(CompileSettings::default())
+    );
+    assert_eq!(
+        CompileSettings::from_flags(&flags(&["--no-optimize"])),
+        CompileSettings::from_flags(&flags(&["--optimize", "none"]))
+    );
+    let settings =
+        CompileSettings::from_flags(&flags(&["--no-bytecode-metadata", "--evm-version=paris"]))
+            .unwrap();
+    assert!(!settings.bytecode_metadata);
+    assert_eq!(settings.evm_version.as_deref(), Some("paris"));
+    assert_eq!(CompileSettings::from_flags(&flags(&["--optimize"])), None);
+    assert_eq!(CompileSettings::from_flags(&flags(&["-f", "abi"])), None);
+    assert_eq!(
+        CompileSettings::from_flags(&flags(&["-f", "bytecode"])),
+        Some(CompileSettings::default())
+    );
+    assert_eq!(
+        CompileSettings::from_flags(&flags(&["--format=blueprint_bytecode"]))
+            .and_then(|settings| settings.output_format),
+        Some("blueprint_bytecode".to_string())
+    );
+    assert_eq!(
+        CompileSettings::split_source(&flags(&["-O", "codesize", "Token.vy"])),
+        Some(("Token.vy".to_string(), flags(&["-O", "codesize"])))
+    );
+    assert_eq!(
+        CompileSettings::split_source(&flags(&["A.vy", "B.vy"])),
+        None
+    );
+
+    Ok(())
+}
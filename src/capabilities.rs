@@ -0,0 +1,225 @@
+use semver::{Version, VersionReq};
+
+use crate::error::VyperVmError;
+
+/// Versions supporting `-f abi_python`, the format was dropped in 0.3.0.
+const ABI_PYTHON: &str = "<0.3.0";
+
+/// Versions supporting `--standard-json` on the `vyper` command line.
+const STANDARD_JSON: &str = ">=0.3.8";
+
+/// Versions supporting `-f blueprint_bytecode`, introduced with `create_from_blueprint`.
+const BLUEPRINT: &str = ">=0.3.4";
+
+/// Versions accepting `--optimize none|gas|codesize`, older ones only know `--no-optimize`.
+const OPTIMIZE_LEVELS: &str = ">=0.3.10";
+
+/// The versions accepting each `--evm-version`, oldest target first.
+const EVM_VERSIONS: &[(&str, &str)] = &[
+    ("byzantium", "<0.3.0"),
+    ("constantinople", "<0.3.0"),
+    ("petersburg", "<0.3.4"),
+    ("istanbul", "<0.4.0"),
+    ("berlin", ">=0.2.12, <0.4.0"),
+    ("london", ">=0.3.2"),
+    ("paris", ">=0.3.7"),
+    ("shanghai", ">=0.3.8"),
+    ("cancun", ">=0.3.9"),
+];
+
+/// What a compiler version supports, so callers can adapt flags to the compiler generation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompilerCapabilities {
+    /// `-f abi_python` output.
+    pub abi_python: bool,
+    /// `--standard-json` input and output.
+    pub standard_json: bool,
+    /// Accepted `--evm-version` targets, oldest first.
+    pub evm_versions: Vec<&'static str>,
+    /// `-f blueprint_bytecode` output.
+    pub blueprint: bool,
+    /// `--optimize none|gas|codesize`, instead of `--no-optimize` only.
+    pub optimize_levels: bool,
+}
+
+impl CompilerCapabilities {
+    /// Returns true if `--evm-version <evm_version>` is accepted.
+    pub fn supports_evm_version(&self, evm_version: &str) -> bool {
+        self.evm_versions.contains(&evm_version)
+    }
+
+    /// Checks compiler arguments against the capabilities of `version` before running it.
+    ///
+    /// Flags with an equivalent the compiler knows are translated, e.g. `--optimize none` to
+    /// `--no-optimize` before 0.3.10. Flags it does not know fail with
+    /// [`VyperVmError::UnsupportedArgument`] explaining what to use instead. Returns the
+    /// arguments to run the compiler with.
+    pub fn check_args(
+        &self,
+        version: &Version,
+        args: &[String],
+    ) -> Result<Vec<String>, VyperVmError> {
+        let unsupported = |argument: String, hint: String| {
+            Err(VyperVmError::UnsupportedArgument(
+                version.to_string(),
+                argument,
+                hint,
+            ))
+        };
+        let mut checked = vec![];
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            let value_flag = matches!(name, "-f" | "-O" | "--optimize" | "--evm-version");
+            let value = match inline {
+                Some(value) => value,
+                None if value_flag => match args.next() {
+                    Some(value) => value.clone(),
+                    // let the compiler report the missing value
+                    None => {
+                        checked.push(arg.clone());
+                        break;
+                    }
+                },
+                None => String::new(),
+            };
+            match name {
+                "-f" => {
+                    for format in value.split(',') {
+                        if format == "abi_python" && !self.abi_python {
+                            return unsupported(
+                                "-f abi_python".to_string(),
+                                "use -f abi, the abi_python format was removed in 0.3.0"
+                                    .to_string(),
+                            );
+                        }
+                        if format == "blueprint_bytecode" && !self.blueprint {
+                            return unsupported(
+                                "-f blueprint_bytecode".to_string(),
+                                "blueprints are supported since 0.3.4".to_string(),
+                            );
+                        }
+                    }
+                    checked.extend(["-f".to_string(), value]);
+                }
+                "--standard-json" if !self.standard_json => {
+                    return unsupported(
+                        name.to_string(),
+                        "use the vyper-json executable of this release".to_string(),
+                    );
+                }
+                "--evm-version" if !self.supports_evm_version(&value) => {
+                    return unsupported(
+                        format!("--evm-version {}", value),
+                        format!("supported EVM versions: {}", self.evm_versions.join(", ")),
+                    );
+                }
+                "-O" | "--optimize" if !self.optimize_levels => match value.as_str() {
+                    "none" => checked.push("--no-optimize".to_string()),
+                    // optimizing for gas is the only mode of older compilers
+                    "gas" => {}
+                    _ => {
+                        return unsupported(
+                            format!("{} {}", name, value),
+                            "optimization levels are supported since 0.3.10, use --no-optimize \
+                             to disable optimizations"
+                                .to_string(),
+                        );
+                    }
+                },
+                _ if value_flag => checked.extend([name.to_string(), value]),
+                _ => checked.push(arg.clone()),
+            }
+        }
+        Ok(checked)
+    }
+}
+
+/// Returns the capabilities of a compiler version from the table of known version ranges.
+/// Prereleases have the capabilities of their release.
+pub fn capabilities(version: &Version) -> CompilerCapabilities {
+    let release = Version::new(version.major, version.minor, version.patch);
+    let supports = |range: &str| {
+        VersionReq::parse(range)
+            .expect("capability ranges are valid")
+            .matches(&release)
+    };
+    CompilerCapabilities {
+        abi_python: supports(ABI_PYTHON),
+        standard_json: supports(STANDARD_JSON),
+        evm_versions: EVM_VERSIONS
+            .iter()
+            .filter(|(_, range)| supports(range))
+            .map(|(evm_version, _)| *evm_version)
+            .collect(),
+        blueprint: supports(BLUEPRINT),
+        optimize_levels: supports(OPTIMIZE_LEVELS),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_follow_compiler_generations() {
+        let old = capabilities(&Version::new(0, 2, 16));
+        assert!(old.abi_python && !old.standard_json && !old.blueprint);
+        assert!(old.supports_evm_version("berlin"));
+        assert!(!old.supports_evm_version("london"));
+
+        let current = capabilities(&Version::new(0, 3, 10));
+        assert!(!current.abi_python && current.standard_json && current.blueprint);
+        assert_eq!(
+            current.evm_versions,
+            vec!["istanbul", "berlin", "london", "paris", "shanghai", "cancun"]
+        );
+
+        let prerelease = capabilities(&Version::parse("0.4.0-rc.1").unwrap());
+        assert!(!prerelease.supports_evm_version("istanbul"));
+        assert!(prerelease.supports_evm_version("cancun"));
+    }
+
+    #[test]
+    fn checks_args_against_capabilities() {
+        let args = |args: &str| args.split(' ').map(String::from).collect::<Vec<_>>();
+        let old = Version::new(0, 3, 1);
+        let old_capabilities = capabilities(&old);
+        assert_eq!(
+            old_capabilities
+                .check_args(&old, &args("--optimize none --evm-version=berlin Token.vy"))
+                .unwrap(),
+            args("--no-optimize --evm-version berlin Token.vy")
+        );
+        assert_eq!(
+            old_capabilities
+                .check_args(&old, &args("-O gas -f abi,bytecode Token.vy"))
+                .unwrap(),
+            args("-f abi,bytecode Token.vy")
+        );
+        for unsupported in [
+            "-f abi_python Token.vy",
+            "-f bytecode,blueprint_bytecode Token.vy",
+            "--evm-version cancun Token.vy",
+            "--optimize codesize Token.vy",
+            "--standard-json input.json",
+        ] {
+            assert!(matches!(
+                old_capabilities.check_args(&old, &args(unsupported)),
+                Err(VyperVmError::UnsupportedArgument(..))
+            ));
+        }
+
+        let current = Version::new(0, 3, 10);
+        let passed = args("--optimize codesize --standard-json input.json");
+        assert_eq!(
+            capabilities(&current)
+                .check_args(&current, &passed)
+                .unwrap(),
+            passed
+        );
+    }
+}
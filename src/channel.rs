@@ -0,0 +1,143 @@
+use semver::{Version, VersionReq};
+
+use std::{
+    convert::TryFrom,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    config::Config, error::VyperVmError, input::VersionInput, platform, releases,
+    releases::Releases,
+};
+
+/// Name of the channel resolving to the latest stable release, accepted wherever a version is.
+pub const STABLE_CHANNEL: &str = "stable";
+
+/// How the `stable` channel picks a release, from `stable_denylist` and `stable_min_age_days`
+/// in the config.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StableRules {
+    /// Releases never considered stable, e.g. releases with known miscompilations.
+    pub denylist: Vec<Version>,
+    /// Releases published fewer days ago are not stable yet.
+    pub min_age_days: Option<u64>,
+}
+
+impl StableRules {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            denylist: config.stable_denylist.clone(),
+            min_age_days: config.stable_min_age_days,
+        }
+    }
+}
+
+/// Returns the newest release that is not a prerelease, not denylisted and, if required, was
+/// published at least `min_age_days` before `now`, in seconds since the Unix epoch.
+pub fn stable_release(releases: &Releases, rules: &StableRules, now: u64) -> Option<Version> {
+    releases
+        .releases
+        .keys()
+        .rev()
+        .filter(|version| version.pre.is_empty() && !releases.prereleases.contains(version))
+        .filter(|version| !rules.denylist.contains(version))
+        .find(|version| match rules.min_age_days {
+            Some(days) => releases
+                .published
+                .get(*version)
+                .and_then(|published| unix_timestamp(published))
+                .is_some_and(|published| published + days * 24 * 60 * 60 <= now),
+            None => true,
+        })
+        .cloned()
+}
+
+/// Resolves the `stable` channel from the upstream releases with the rules of the config.
+pub async fn stable_version() -> Result<Version, VyperVmError> {
+    let releases = releases::all_releases(platform::platform()).await?;
+    let rules = StableRules::from_config(&Config::load()?);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    stable_release(&releases, &rules, now).ok_or(VyperVmError::NoStableVersion)
+}
+
+/// Resolves a version given by the user, see [`VersionInput::parse`]: `stable`, `latest`, e.g.
+/// `0.3.7` or `v0.3.7`, `0.3` for the newest release of the line, or a requirement like `^0.3`.
+pub async fn resolve_version(version: &str) -> Result<Version, VyperVmError> {
+    match VersionInput::parse(version)? {
+        VersionInput::Stable => stable_version().await,
+        VersionInput::Exact(version) => Ok(version),
+        input => input.resolve(&crate::all_versions().await?),
+    }
+}
+
+/// Resolves `req` to the newest available release matching it.
+pub async fn resolve_requirement(req: &VersionReq) -> Result<Version, VyperVmError> {
+    VersionInput::Requirement(req.clone()).resolve(&crate::all_versions().await?)
+}
+
+/// Converts a UTC timestamp like `2023-10-04T16:27:20Z`, as published by GitHub, to seconds since
+/// the Unix epoch.
+fn unix_timestamp(timestamp: &str) -> Option<u64> {
+    let (date, time) = timestamp.trim_end_matches('Z').split_once('T')?;
+    let mut date = date.split('-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let mut time = time.split(':').map(|part| part.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+
+    // days since the epoch of a date of the proleptic Gregorian calendar
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    u64::try_from(days * 86_400 + hour * 3600 + minute * 60 + second).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_stable_release() {
+        let mut releases = Releases::default();
+        for (version, published) in [
+            ("0.3.7", "2022-10-03T00:00:00Z"),
+            ("0.3.9", "2023-05-29T00:00:00Z"),
+            ("0.3.10", "2023-10-04T16:27:20Z"),
+            ("0.4.0-rc.1", "2023-12-01T00:00:00Z"),
+        ] {
+            let version = Version::parse(version).unwrap();
+            releases.releases.insert(version.clone(), String::new());
+            releases.published.insert(version, published.to_string());
+        }
+        assert_eq!(unix_timestamp("1970-01-02T00:00:01Z"), Some(86_401));
+        let now = unix_timestamp("2023-10-10T00:00:00Z").unwrap();
+
+        let stable = |rules: &StableRules| stable_release(&releases, rules, now);
+        assert_eq!(
+            stable(&StableRules::default()),
+            Some(Version::new(0, 3, 10))
+        );
+        let rules = StableRules {
+            denylist: vec![Version::new(0, 3, 10)],
+            min_age_days: None,
+        };
+        assert_eq!(stable(&rules), Some(Version::new(0, 3, 9)));
+        let rules = StableRules {
+            denylist: vec![],
+            min_age_days: Some(30),
+        };
+        assert_eq!(stable(&rules), Some(Version::new(0, 3, 9)));
+
+        releases.prereleases.push(Version::new(0, 3, 10));
+        assert_eq!(
+            stable_release(&releases, &StableRules::default(), now),
+            Some(Version::new(0, 3, 9))
+        );
+    }
+}
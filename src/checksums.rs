@@ -0,0 +1,319 @@
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+    digest::{Digests, HashAlgorithm},
+    error::VyperVmError,
+    home::active_home,
+    http, metrics, releases,
+};
+
+/// File name of the checksum manifest in the VVM home directory.
+pub const CHECKSUMS_FILENAME: &str = "checksums.json";
+
+/// Where `vvm checksums update` fetches the upstream manifest from. The minisign signature is
+/// expected next to it, with a `.minisig` suffix.
+pub const CHECKSUMS_URL: &str =
+    "https://raw.githubusercontent.com/storming0x/vvm-rs/main/checksums.json";
+
+/// Format identifier of the checksum manifest.
+const CHECKSUMS_FORMAT: &str = "vvm-rs-checksums-1";
+
+/// The manifest shipped with this build of vvm, used until a newer one is fetched.
+const BUNDLED_CHECKSUMS: &str = include_str!("../checksums.json");
+
+/// Minisign public key of the publisher of the manifest, in the format of `minisign -G`. Only
+/// manifests signed with its secret key are installed by [`update_checksums`].
+const CHECKSUMS_PUBLIC_KEY: &str = include_str!("../checksums.pub");
+
+/// Whether artifacts without a known checksum are installed, see [`set_insecure`].
+static INSECURE: AtomicBool = AtomicBool::new(false);
+
+/// Known checksums of the Vyper release artifacts.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChecksumManifest {
+    #[serde(rename = "_format")]
+    pub format: String,
+    /// Increases with every published manifest, an older manifest is never installed over a
+    /// newer one.
+    pub serial: u64,
+    /// Checksums by version and artifact name.
+    pub checksums: BTreeMap<Version, BTreeMap<String, ArtifactChecksum>>,
+}
+
+/// The checksums of an artifact: a hex encoded SHA256 checksum, or digests by algorithm.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ArtifactChecksum {
+    Sha256(String),
+    Digests(Digests),
+}
+
+impl ArtifactChecksum {
+    pub fn digests(&self) -> Digests {
+        match self {
+            ArtifactChecksum::Sha256(checksum) => {
+                let mut digests = Digests::default();
+                if let Ok(checksum) = hex::decode(checksum.trim_start_matches("0x")) {
+                    digests.insert(HashAlgorithm::Sha256, &checksum);
+                }
+                digests
+            }
+            ArtifactChecksum::Digests(digests) => digests.clone(),
+        }
+    }
+}
+
+impl ChecksumManifest {
+    /// Returns the manifest shipped with vvm.
+    pub fn bundled() -> Result<Self, VyperVmError> {
+        Self::parse(BUNDLED_CHECKSUMS.as_bytes())
+    }
+
+    /// Loads the most recent of the manifest stored in `~/.vvm/checksums.json` and the bundled
+    /// one.
+    pub fn load() -> Result<Self, VyperVmError> {
+        let bundled = Self::bundled()?;
+        match fs::read(checksums_path()?) {
+            Ok(content) => {
+                let stored = Self::parse(&content)?;
+                Ok(if stored.serial >= bundled.serial {
+                    stored
+                } else {
+                    bundled
+                })
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(bundled),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Returns the known digests of the artifact of a version, if any digest of a supported
+    /// algorithm is known.
+    pub fn checksum(&self, version: &Version, artifact: &str) -> Option<Digests> {
+        let digests = self.checksums.get(version)?.get(artifact)?.digests();
+        (!digests.is_empty()).then_some(digests)
+    }
+
+    /// Number of artifacts with a known checksum.
+    pub fn len(&self) -> usize {
+        self.checksums.values().map(BTreeMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn parse(content: &[u8]) -> Result<Self, VyperVmError> {
+        let manifest: Self = serde_json::from_slice(content)
+            .map_err(|err| VyperVmError::InvalidChecksums(err.to_string()))?;
+        if manifest.format != CHECKSUMS_FORMAT {
+            return Err(VyperVmError::InvalidChecksums(format!(
+                "unsupported format {}",
+                manifest.format
+            )));
+        }
+        Ok(manifest)
+    }
+}
+
+/// Fetches the manifest at `url`, verifies it against its minisign signature with the public key
+/// of the publisher and stores it in `~/.vvm/checksums.json`.
+///
+/// A manifest older than the one currently in use is rejected.
+pub async fn update_checksums(url: &Url) -> Result<ChecksumManifest, VyperVmError> {
+    let content = fetch_signed(url, publisher_key()?).await?;
+    let manifest = ChecksumManifest::parse(&content)?;
+    let current = ChecksumManifest::load()?;
+    if manifest.serial < current.serial {
+        return Err(VyperVmError::InvalidChecksums(format!(
+            "fetched manifest {} is older than the current manifest {}",
+            manifest.serial, current.serial
+        )));
+    }
+
+    let tmp_path = active_home()?.join(format!(".{}.{}", CHECKSUMS_FILENAME, std::process::id()));
+    fs::write(&tmp_path, &content)?;
+    fs::rename(&tmp_path, checksums_path()?)?;
+
+    Ok(manifest)
+}
+
+/// Computes the manifest of every build published with a Vyper release, for every platform,
+/// downloading them all. `serial` must be higher than the serial of the last published manifest.
+///
+/// Used to publish the manifest, or the signed index of a mirror, see `vvm checksums generate`.
+pub async fn generate_checksums(serial: u64) -> Result<ChecksumManifest, VyperVmError> {
+    let mut checksums = BTreeMap::new();
+    for (version, artifacts) in releases::published_artifacts().await? {
+        let mut digests = BTreeMap::new();
+        for (name, url) in artifacts {
+            tracing::info!("computing the digests of {}", url);
+            let binbytes = fetch(&url).await?;
+            digests.insert(name, ArtifactChecksum::Digests(Digests::compute(&binbytes)));
+        }
+        checksums.insert(version, digests);
+    }
+    Ok(ChecksumManifest {
+        format: CHECKSUMS_FORMAT.to_string(),
+        serial,
+        checksums,
+    })
+}
+
+/// The base64 key of [`CHECKSUMS_PUBLIC_KEY`], the line after its untrusted comment.
+fn publisher_key() -> Result<&'static str, VyperVmError> {
+    CHECKSUMS_PUBLIC_KEY
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))
+        .ok_or_else(|| {
+            VyperVmError::InvalidChecksums(
+                "this build of vvm has no public key to verify manifests".to_string(),
+            )
+        })
+}
+
+/// Makes installs accept artifacts no known checksum covers, e.g. for `--insecure`, instead of
+/// failing with [`VyperVmError::UnknownChecksum`]. Such artifacts are installed unverified.
+pub fn set_insecure(insecure: bool) {
+    INSECURE.store(insecure, Ordering::Relaxed);
+}
+
+/// Returns true if installs accept artifacts without a known checksum, see [`set_insecure`].
+pub fn is_insecure() -> bool {
+    INSECURE.load(Ordering::Relaxed)
+}
+
+/// Fetches the checksum manifest at `url` and verifies it against its minisign signature, expected
+/// at the same URL with a `.minisig` suffix, with the provided base64 public key.
+pub(crate) async fn fetch_signed(url: &Url, public_key: &str) -> Result<Vec<u8>, VyperVmError> {
+    let content = fetch(url).await?;
+    let signature = fetch(&signature_url(url)?).await?;
+    verify_signed(content, &signature, public_key)
+}
+
+/// Blocking version of [`fetch_signed`]
+#[cfg(feature = "blocking")]
+pub(crate) fn blocking_fetch_signed(url: &Url, public_key: &str) -> Result<Vec<u8>, VyperVmError> {
+    let content = blocking_fetch(url)?;
+    let signature = blocking_fetch(&signature_url(url)?)?;
+    verify_signed(content, &signature, public_key)
+}
+
+fn signature_url(url: &Url) -> Result<Url, VyperVmError> {
+    Ok(Url::parse(&format!("{}.minisig", url))?)
+}
+
+/// Returns `content` if `signature` is a valid minisign signature of it by `public_key`.
+fn verify_signed(
+    content: Vec<u8>,
+    signature: &[u8],
+    public_key: &str,
+) -> Result<Vec<u8>, VyperVmError> {
+    let public_key = minisign_verify::PublicKey::from_base64(public_key)
+        .map_err(|err| VyperVmError::InvalidChecksums(err.to_string()))?;
+    let signature = minisign_verify::Signature::decode(&String::from_utf8_lossy(signature))
+        .map_err(|err| VyperVmError::InvalidChecksums(err.to_string()))?;
+    public_key
+        .verify(&content, &signature, false)
+        .map_err(|err| VyperVmError::InvalidChecksums(err.to_string()))?;
+    Ok(content)
+}
+
+async fn fetch(url: &Url) -> Result<Vec<u8>, VyperVmError> {
+    let res = http::client().get(url.clone()).send().await?;
+    metrics::record_response(res.status(), res.headers());
+    if !res.status().is_success() {
+        return Err(VyperVmError::UnsuccessfulResponse(
+            url.clone(),
+            res.status(),
+        ));
+    }
+    let content = res.bytes().await?;
+    metrics::record_bytes(content.len());
+    Ok(content.to_vec())
+}
+
+#[cfg(feature = "blocking")]
+fn blocking_fetch(url: &Url) -> Result<Vec<u8>, VyperVmError> {
+    let res = http::blocking_client().get(url.clone()).send()?;
+    metrics::record_response(res.status(), res.headers());
+    if !res.status().is_success() {
+        return Err(VyperVmError::UnsuccessfulResponse(
+            url.clone(),
+            res.status(),
+        ));
+    }
+    let content = res.bytes()?;
+    metrics::record_bytes(content.len());
+    Ok(content.to_vec())
+}
+
+/// Derive path to the checksum manifest in the VVM home directory.
+pub fn checksums_path() -> Result<PathBuf, VyperVmError> {
+    Ok(active_home()?.join(CHECKSUMS_FILENAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_manifest_is_valid() {
+        ChecksumManifest::bundled().unwrap();
+    }
+
+    #[ignore = "checksums.pub must first hold the minisign key the manifest is signed with"]
+    #[test]
+    fn publisher_key_is_valid() {
+        // a build without the key of the publisher refuses every manifest
+        let key = publisher_key().unwrap();
+        minisign_verify::PublicKey::from_base64(key).unwrap();
+    }
+
+    #[test]
+    fn looks_up_checksums() {
+        let manifest = ChecksumManifest::parse(
+            br#"{
+                "_format": "vvm-rs-checksums-1",
+                "serial": 1,
+                "checksums": {
+                    "0.3.3": {
+                        "vyper.0.3.3+commit.48e326f0.linux": "0x0102",
+                        "vyper.0.3.3+commit.48e326f0.windows.exe": { "blake3": "0304" }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let version = Version::new(0, 3, 3);
+        let digests = manifest
+            .checksum(&version, "vyper.0.3.3+commit.48e326f0.linux")
+            .unwrap();
+        assert_eq!(digests.get(HashAlgorithm::Sha256), Some(vec![1, 2]));
+        let digests = manifest
+            .checksum(&version, "vyper.0.3.3+commit.48e326f0.windows.exe")
+            .unwrap();
+        assert_eq!(digests.get(HashAlgorithm::Sha256), None);
+        assert_eq!(digests.get(HashAlgorithm::Blake3), Some(vec![3, 4]));
+        assert_eq!(
+            manifest.checksum(&version, "vyper.0.3.3+commit.48e326f0.darwin"),
+            None
+        );
+        assert_eq!(manifest.len(), 2);
+
+        assert!(
+            ChecksumManifest::parse(br#"{"_format": "other", "serial": 1, "checksums": {}}"#)
+                .is_err()
+        );
+    }
+}
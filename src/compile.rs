@@ -0,0 +1,654 @@
+use futures_util::{stream, StreamExt};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::Command,
+    time::{self, Instant},
+};
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Output, Stdio},
+    time::Duration,
+};
+
+use crate::{
+    binary_path,
+    cache::{get_cache_path, CacheKey, VyperFilesCache},
+    config::Config,
+    error::VyperVmError,
+    retry::BusyRetry,
+};
+
+/// The result of compiling a single file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompileOutput {
+    pub file: PathBuf,
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+    /// Whether the output was served from the cache instead of running the compiler.
+    pub cached: bool,
+    /// Time spent compiling the file, its share of the process of a batch, zero when cached.
+    pub duration: Duration,
+}
+
+impl CompileOutput {
+    /// Lines the compiler printed on stderr while compiling successfully.
+    pub fn warnings(&self) -> Vec<&str> {
+        if !self.success {
+            return vec![];
+        }
+        self.stderr
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect()
+    }
+}
+
+/// The stream of the compiler a line of output was printed on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputStream {
+    /// Compilation artifacts, e.g. the bytecode.
+    Stdout,
+    /// Errors, and warnings of successful compilations.
+    Stderr,
+}
+
+/// The result of running one version of the compiler, see [`exec_each`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecOutput {
+    pub version: Version,
+    pub success: bool,
+    /// Exit code of the compiler, `None` if it was killed by a signal.
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// File name of the compile database written next to the sources, see [`CompileCommand`].
+pub const COMPILE_COMMANDS_FILENAME: &str = "vyper_commands.json";
+
+/// An entry of the compile database, the exact compiler invocation for a source file so tools
+/// can replay it, like `compile_commands.json` for C compilers.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompileCommand {
+    /// Working directory of the invocation.
+    pub directory: PathBuf,
+    pub file: PathBuf,
+    pub compiler: PathBuf,
+    pub version: Version,
+    /// Full command line, starting with the compiler.
+    pub arguments: Vec<String>,
+}
+
+impl CompileCommand {
+    /// The command compiling `file` with `version` and `flags` from the current directory.
+    pub fn new(version: &Version, file: &Path, flags: &[String]) -> Result<Self, VyperVmError> {
+        let compiler = binary_path(version)?;
+        let arguments = std::iter::once(compiler.to_string_lossy().to_string())
+            .chain(flags.iter().cloned())
+            .chain(std::iter::once(file.to_string_lossy().to_string()))
+            .collect();
+        Ok(Self {
+            directory: env::current_dir()?,
+            file: file.to_path_buf(),
+            compiler,
+            version: version.clone(),
+            arguments,
+        })
+    }
+}
+
+/// Adds `commands` to the compile database at `path`, replacing the previous entries of the
+/// same files.
+pub fn write_compile_commands(
+    path: impl AsRef<Path>,
+    commands: &[CompileCommand],
+) -> Result<(), VyperVmError> {
+    let path = path.as_ref();
+    let mut database: Vec<CompileCommand> = match fs::read(path) {
+        Ok(content) => serde_json::from_slice(&content)?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => vec![],
+        Err(err) => return Err(err.into()),
+    };
+    database.retain(|entry| !commands.iter().any(|command| command.file == entry.file));
+    database.extend(commands.iter().cloned());
+    database.sort_by(|a, b| a.file.cmp(&b.file));
+    fs::write(path, serde_json::to_vec_pretty(&database)?)?;
+    Ok(())
+}
+
+/// Number of concurrent compiler processes: `compile_jobs` from the config, defaulting to the
+/// number of CPUs.
+pub fn compile_jobs() -> usize {
+    Config::load()
+        .ok()
+        .and_then(|config| config.compile_jobs)
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        })
+        .max(1)
+}
+
+/// How often [`output_with_timeout`] reports that a compilation is still running.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Maximum duration of a single compilation: `compile_timeout` seconds from the config, no limit
+/// by default.
+pub fn compile_timeout() -> Option<Duration> {
+    Config::load()
+        .ok()
+        .and_then(|config| config.compile_timeout)
+        .map(Duration::from_secs)
+}
+
+/// Files compiled per compiler process by [`compile_project`]: `compile_batch_size` from the
+/// config, every file in its own process by default.
+fn compile_batch_size() -> Option<usize> {
+    Config::load()
+        .ok()
+        .and_then(|config| config.compile_batch_size)
+        .filter(|size| *size > 1)
+}
+
+/// Runs `command` to completion and collects its output, calling `on_heartbeat` with the elapsed
+/// time every [`HEARTBEAT_INTERVAL`] while it runs.
+///
+/// The process is killed if it runs longer than `timeout`, `name` identifies it in the returned
+/// [`VyperVmError::CompileTimeout`].
+pub async fn output_with_timeout<F>(
+    mut command: Command,
+    name: &str,
+    timeout: Option<Duration>,
+    on_heartbeat: F,
+) -> Result<Output, VyperVmError>
+where
+    F: Fn(Duration),
+{
+    let start = Instant::now();
+    command.kill_on_drop(true);
+    let child = BusyRetry::load().run_async(|| command.spawn()).await?;
+    let output = child.wait_with_output();
+    tokio::pin!(output);
+    let mut heartbeat = time::interval_at(start + HEARTBEAT_INTERVAL, HEARTBEAT_INTERVAL);
+    let deadline = async {
+        match timeout {
+            Some(timeout) => time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            output = &mut output => return Ok(output?),
+            // dropping the output future kills the process
+            _ = &mut deadline => {
+                return Err(VyperVmError::CompileTimeout(
+                    name.to_string(),
+                    timeout.unwrap_or_default().as_secs(),
+                ))
+            }
+            _ = heartbeat.tick() => on_heartbeat(start.elapsed()),
+        }
+    }
+}
+
+/// Compiles every file with `version`, running at most `jobs` compiler processes at a time.
+///
+/// `on_line` is called with every line of output as soon as it is printed by the compiler,
+/// labeled with the file being compiled and the stream it was printed on, so the output of
+/// concurrent compilations can be streamed.
+///
+/// A compilation running longer than [`compile_timeout`] is killed and fails the whole call with
+/// [`VyperVmError::CompileTimeout`].
+///
+/// Returns the output of every compilation, in the order of `files`.
+pub async fn compile_files<F>(
+    version: &Version,
+    files: &[PathBuf],
+    flags: &[String],
+    jobs: usize,
+    on_line: F,
+) -> Result<Vec<CompileOutput>, VyperVmError>
+where
+    F: Fn(&Path, OutputStream, &str),
+{
+    let vyper = binary_path(version)?;
+    let timeout = compile_timeout();
+    let on_line = &on_line;
+    let mut outputs = stream::iter(files.iter().enumerate())
+        .map(|(i, file)| {
+            let vyper = &vyper;
+            async move {
+                let output = match timeout {
+                    Some(timeout) => {
+                        time::timeout(timeout, compile_file(vyper, file, flags, on_line))
+                            .await
+                            .unwrap_or_else(|_| {
+                                Err(VyperVmError::CompileTimeout(
+                                    file.display().to_string(),
+                                    timeout.as_secs(),
+                                ))
+                            })
+                    }
+                    None => compile_file(vyper, file, flags, on_line).await,
+                };
+                (i, output)
+            }
+        })
+        .buffer_unordered(jobs.max(1))
+        .collect::<Vec<_>>()
+        .await;
+    outputs.sort_by_key(|(i, _)| *i);
+    outputs.into_iter().map(|(_, output)| output).collect()
+}
+
+/// Runs every version of the compiler with the same `args`, at most `jobs` at a time.
+///
+/// Returns the output of every version, in the order of `versions`.
+pub async fn exec_each(
+    versions: &[Version],
+    args: &[String],
+    jobs: usize,
+) -> Result<Vec<ExecOutput>, VyperVmError> {
+    let binaries = versions
+        .iter()
+        .map(binary_path)
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut outputs = stream::iter(versions.iter().zip(&binaries).enumerate())
+        .map(|(i, (version, binary))| async move {
+            let output = Command::new(binary)
+                .args(args)
+                .stdin(Stdio::null())
+                .output()
+                .await
+                .map(|output| ExecOutput {
+                    version: version.clone(),
+                    success: output.status.success(),
+                    code: output.status.code(),
+                    stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+                    stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+                });
+            (i, output)
+        })
+        .buffer_unordered(jobs.max(1))
+        .collect::<Vec<_>>()
+        .await;
+    outputs.sort_by_key(|(i, _)| *i);
+    outputs.into_iter().map(|(_, output)| Ok(output?)).collect()
+}
+
+/// Directories never searched for sources: dependencies of JavaScript tooling, besides hidden
+/// directories such as `.git` or `.venv`.
+const IGNORED_SOURCE_DIRS: &[&str] = &["node_modules"];
+
+/// Returns the Vyper sources (`.vy` files) under `dir`, sorted, skipping hidden directories and
+/// dependency directories.
+pub fn project_sources(dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, VyperVmError> {
+    let mut sources = vec![];
+    let mut dirs = vec![dir.as_ref().to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if entry.file_type()?.is_dir() {
+                if !name.starts_with('.') && !IGNORED_SOURCE_DIRS.contains(&name.as_str()) {
+                    dirs.push(path);
+                }
+            } else if path.extension().is_some_and(|ext| ext == "vy") {
+                sources.push(path);
+            }
+        }
+    }
+    sources.sort();
+    Ok(sources)
+}
+
+/// Compiles every file of a project with `version`, reusing the cached bytecode of the files
+/// that did not change and compiling the others concurrently, see [`compile_files`].
+///
+/// Outputs with warnings are not cached, so the warnings are printed again by the next
+/// compilation.
+///
+/// Returns the output of every file, in the order of `files`.
+pub async fn compile_project<F>(
+    version: &Version,
+    files: &[PathBuf],
+    jobs: usize,
+    on_line: F,
+) -> Result<Vec<CompileOutput>, VyperVmError>
+where
+    F: Fn(&Path, OutputStream, &str),
+{
+    let mut cache = VyperFilesCache::get();
+
+    let mut outputs: Vec<Option<CompileOutput>> = Vec::with_capacity(files.len());
+    let mut dirty = vec![];
+    for file in files {
+        let key = CacheKey::new(file, version, &[])?.expect("no flags are cacheable");
+        match cache.lookup(&key) {
+            Some(entry) if !entry.is_dirty() => outputs.push(Some(CompileOutput {
+                file: file.clone(),
+                success: true,
+                stdout: entry.deployed_bytecode.clone(),
+                stderr: String::new(),
+                cached: true,
+                duration: Duration::ZERO,
+            })),
+            _ => {
+                outputs.push(None);
+                dirty.push(file.clone());
+            }
+        }
+    }
+
+    let compiled = match compile_batch_size() {
+        Some(size) if dirty.len() > 1 => {
+            compile_batched(version, &dirty, size, jobs, &on_line).await?
+        }
+        _ => compile_files(version, &dirty, &[], jobs, on_line).await?,
+    };
+    let mut compiled = compiled.into_iter();
+    for output in outputs.iter_mut().filter(|output| output.is_none()) {
+        let compiled = compiled.next().expect("one output per dirty file");
+        if compiled.success && compiled.warnings().is_empty() && compiled.stdout.starts_with("0x") {
+            cache.add_entry(&compiled.file, compiled.stdout.trim(), version, &[])?;
+        }
+        *output = Some(compiled);
+    }
+    if !dirty.is_empty() {
+        cache.write(get_cache_path()?)?;
+    }
+
+    Ok(outputs.into_iter().flatten().collect())
+}
+
+/// Compiles `files` with `size` files per compiler process, at most `jobs` processes at a time,
+/// to save the start-up time of a process per file. Only the outputs of successful batches
+/// without warnings can be split per file, the other batches are compiled again file by file.
+///
+/// Returns the output of every file, in the order of `files`.
+async fn compile_batched<F>(
+    version: &Version,
+    files: &[PathBuf],
+    size: usize,
+    jobs: usize,
+    on_line: &F,
+) -> Result<Vec<CompileOutput>, VyperVmError>
+where
+    F: Fn(&Path, OutputStream, &str),
+{
+    let vyper = binary_path(version)?;
+    let timeout = compile_timeout();
+    let batches = files.chunks(size.max(1)).collect::<Vec<_>>();
+    let mut results = stream::iter(batches.iter().enumerate())
+        .map(|(i, batch)| {
+            let vyper = &vyper;
+            async move {
+                let mut command = Command::new(vyper);
+                command
+                    .args(batch.iter())
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped());
+                let name = format!("a batch of {} files", batch.len());
+                // the timeout is per file
+                let timeout = timeout.map(|timeout| timeout * batch.len() as u32);
+                let start = Instant::now();
+                let output = output_with_timeout(command, &name, timeout, |_| {}).await;
+                let duration = start.elapsed();
+                (
+                    i,
+                    output.map(|output| split_batch(batch, &output, duration)),
+                )
+            }
+        })
+        .buffer_unordered(jobs.max(1))
+        .collect::<Vec<_>>()
+        .await;
+    results.sort_by_key(|(i, _)| *i);
+
+    let mut outputs = Vec::with_capacity(files.len());
+    for ((_, result), batch) in results.into_iter().zip(batches) {
+        match result? {
+            Some(split) => {
+                for output in &split {
+                    output
+                        .stdout
+                        .lines()
+                        .for_each(|line| on_line(&output.file, OutputStream::Stdout, line));
+                }
+                outputs.extend(split);
+            }
+            None => outputs.extend(compile_files(version, batch, &[], jobs, on_line).await?),
+        }
+    }
+    Ok(outputs)
+}
+
+/// Splits the output of a batch that took `duration` into the output of every file, one line of
+/// stdout per file, or `None` if it cannot be attributed.
+fn split_batch(
+    batch: &[PathBuf],
+    output: &Output,
+    duration: Duration,
+) -> Option<Vec<CompileOutput>> {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines = stdout.lines().collect::<Vec<_>>();
+    if !output.status.success() || !output.stderr.is_empty() || lines.len() != batch.len() {
+        return None;
+    }
+    Some(
+        batch
+            .iter()
+            .zip(lines)
+            .map(|(file, line)| CompileOutput {
+                file: file.clone(),
+                success: true,
+                stdout: format!("{}\n", line),
+                stderr: String::new(),
+                cached: false,
+                duration: duration / batch.len() as u32,
+            })
+            .collect(),
+    )
+}
+
+async fn compile_file<F>(
+    vyper: &Path,
+    file: &Path,
+    flags: &[String],
+    on_line: &F,
+) -> Result<CompileOutput, VyperVmError>
+where
+    F: Fn(&Path, OutputStream, &str),
+{
+    let mut command = Command::new(vyper);
+    command
+        .args(flags)
+        .arg(file)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // killed if the compilation times out
+        .kill_on_drop(true);
+    let start = Instant::now();
+    let mut child = BusyRetry::load().run_async(|| command.spawn()).await?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+
+    let (stdout, stderr) = tokio::try_join!(
+        read_lines(stdout, file, OutputStream::Stdout, on_line),
+        read_lines(stderr, file, OutputStream::Stderr, on_line)
+    )?;
+    let status = child.wait().await?;
+
+    Ok(CompileOutput {
+        file: file.to_path_buf(),
+        success: status.success(),
+        stdout,
+        stderr,
+        cached: false,
+        duration: start.elapsed(),
+    })
+}
+
+/// Reads all lines of `reader`, passing each one to `on_line` as soon as it is read.
+async fn read_lines<R, F>(
+    reader: R,
+    file: &Path,
+    stream: OutputStream,
+    on_line: &F,
+) -> Result<String, VyperVmError>
+where
+    R: AsyncRead + Unpin,
+    F: Fn(&Path, OutputStream, &str),
+{
+    let mut lines = BufReader::new(reader).lines();
+    let mut output = String::new();
+    while let Some(line) = lines.next_line().await? {
+        on_line(file, stream, &line);
+        output.push_str(&line);
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+#[cfg(all(test, target_family = "unix"))]
+mod tests {
+    use super::*;
+    use crate::testing::{sandbox, FakeVyper};
+    use std::{fs, sync::Mutex};
+
+    #[tokio::test]
+    async fn compiles_files_concurrently() {
+        let version = Version::new(0, 0, 5);
+        let fake = FakeVyper::new().shell(
+            "echo \"0x$(basename $1)\"\n[ \"$1\" = 3.vy ] && echo \"Warning: unused\" >&2\nexit 0",
+        );
+        sandbox().unwrap().register(&version, &fake).unwrap();
+
+        let files = (0..4)
+            .map(|i| PathBuf::from(format!("{}.vy", i)))
+            .collect::<Vec<_>>();
+        let lines = Mutex::new(vec![]);
+        let outputs = compile_files(&version, &files, &[], 2, |file, stream, line| {
+            lines
+                .lock()
+                .unwrap()
+                .push(format!("{}: {:?} {}", file.display(), stream, line))
+        })
+        .await
+        .unwrap();
+
+        for (i, output) in outputs.iter().enumerate() {
+            assert!(output.success);
+            assert_eq!(output.file, files[i]);
+            assert_eq!(output.stdout, format!("0x{}.vy\n", i));
+        }
+        assert_eq!(lines.lock().unwrap().len(), 5);
+        assert!(lines
+            .lock()
+            .unwrap()
+            .contains(&"3.vy: Stderr Warning: unused".to_string()));
+        assert!(outputs[0].warnings().is_empty());
+        assert_eq!(outputs[3].warnings(), vec!["Warning: unused"]);
+
+        let outputs = exec_each(std::slice::from_ref(&version), &["a.vy".to_string()], 1)
+            .await
+            .unwrap();
+        assert_eq!(outputs[0].code, Some(0));
+        assert_eq!(outputs[0].stdout, "0xa.vy\n");
+
+        let dir = tempfile::tempdir().unwrap();
+        let database = dir.path().join(COMPILE_COMMANDS_FILENAME);
+        let commands = files
+            .iter()
+            .map(|file| CompileCommand::new(&version, file, &[]).unwrap())
+            .collect::<Vec<_>>();
+        write_compile_commands(&database, &commands).unwrap();
+        let flags = ["-f".to_string(), "abi".to_string()];
+        let command = CompileCommand::new(&version, &files[0], &flags).unwrap();
+        write_compile_commands(&database, std::slice::from_ref(&command)).unwrap();
+        let written: Vec<CompileCommand> =
+            serde_json::from_slice(&fs::read(&database).unwrap()).unwrap();
+        assert_eq!(written.len(), 4);
+        assert_eq!(written[0], command);
+        assert_eq!(written[0].arguments[1..], ["-f", "abi", "0.vy"]);
+        crate::remove_version(&version).unwrap();
+    }
+
+    #[test]
+    fn finds_project_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        for path in [
+            "Token.vy",
+            "contracts/Vault.vy",
+            "contracts/README.md",
+            ".venv/lib/Dep.vy",
+            "node_modules/pkg/Dep.vy",
+        ] {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        assert_eq!(
+            project_sources(dir.path()).unwrap(),
+            vec![
+                dir.path().join("Token.vy"),
+                dir.path().join("contracts/Vault.vy")
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn compiles_batches_and_falls_back_per_file() {
+        let version = Version::new(0, 0, 15);
+        let fake = FakeVyper::new().shell(
+            "for f in \"$@\"; do\n  [ \"$f\" = bad.vy ] && echo \"error in $f\" >&2 && exit 1\n  echo \"0x$f $#\"\ndone",
+        );
+        sandbox().unwrap().register(&version, &fake).unwrap();
+
+        let files = ["a.vy", "b.vy", "c.vy", "bad.vy", "d.vy"]
+            .iter()
+            .map(PathBuf::from)
+            .collect::<Vec<_>>();
+        let outputs = compile_batched(&version, &files, 2, 2, &|_, _, _| {})
+            .await
+            .unwrap();
+        let stdout = outputs
+            .iter()
+            .map(|output| output.stdout.trim())
+            .collect::<Vec<_>>();
+        // the batch of c.vy and bad.vy failed and was compiled file by file
+        assert_eq!(stdout, ["0xa.vy 2", "0xb.vy 2", "0xc.vy 1", "", "0xd.vy 1"]);
+        assert!(!outputs[3].success);
+        assert_eq!(outputs[3].stderr, "error in bad.vy\n");
+        crate::remove_version(&version).unwrap();
+    }
+
+    #[tokio::test]
+    async fn kills_processes_that_time_out() {
+        crate::testing::sandbox().unwrap();
+        let mut command = Command::new("sleep");
+        command.arg("5");
+        let start = std::time::Instant::now();
+        let err = output_with_timeout(command, "sleep", Some(Duration::from_millis(100)), |_| {})
+            .await
+            .unwrap_err();
+        assert!(matches!(err, VyperVmError::CompileTimeout(name, _) if name == "sleep"));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let mut command = Command::new("echo");
+        command.arg("0x00").stdout(Stdio::piped());
+        let output = output_with_timeout(command, "echo", Some(Duration::from_secs(5)), |_| {})
+            .await
+            .unwrap();
+        assert_eq!(output.stdout, b"0x00\n");
+    }
+}
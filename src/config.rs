@@ -0,0 +1,244 @@
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{error::VyperVmError, home::active_home, manifest::VersionOrigin};
+
+/// File name of the vvm configuration in the VVM home directory.
+pub const CONFIG_FILENAME: &str = "config.toml";
+
+/// User configuration of vvm, read from `~/.vvm/config.toml`. Missing keys take their default
+/// value.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Keep local usage statistics in `stats.json`. Nothing is ever sent over the network.
+    pub usage_stats: bool,
+    /// GitHub token used to query releases when neither `VVM_GITHUB_TOKEN` nor `GITHUB_TOKEN` is
+    /// set, avoids the API rate limits of anonymous requests.
+    pub github_token: Option<String>,
+    /// Maximum number of compiler processes the wrapper runs at the same time, defaults to the
+    /// number of CPUs.
+    pub compile_jobs: Option<usize>,
+    /// Experimental: have the wrapper compile up to this many files per compiler process, saving
+    /// the start-up time of a process per file on large projects. Batches with errors or
+    /// warnings are compiled again file by file, so their output can be attributed.
+    pub compile_batch_size: Option<usize>,
+    /// Seconds after which the wrapper kills a hanging compiler process, no limit by default.
+    pub compile_timeout: Option<u64>,
+    /// Origins `vvm audit` rejects when no `--forbid` list is given, e.g. `["local-file"]`.
+    pub audit_forbidden_origins: Vec<VersionOrigin>,
+    /// Bandwidth in bytes per second shared by concurrent downloads, unlimited by default.
+    pub download_bandwidth: Option<u64>,
+    /// Maximum number of versions a process installs at the same time, across queues and
+    /// independent calls, 4 by default.
+    pub max_concurrent_installs: Option<usize>,
+    /// Maximum number of requests to GitHub running at the same time, 4 by default.
+    pub github_max_requests: Option<usize>,
+    /// Minimum milliseconds between the starts of two requests to GitHub, none by default.
+    pub github_request_delay_ms: Option<u64>,
+    /// Up to this many random milliseconds are added to `github_request_delay_ms`, so concurrent
+    /// CI jobs sharing an address don't send their requests in lockstep.
+    pub github_request_jitter_ms: Option<u64>,
+    /// Maximum number of pages of 100 releases fetched from GitHub, 10 by default.
+    pub github_max_release_pages: Option<usize>,
+    /// Seconds the release list fetched into `~/.vvm/releases.json` is used without asking
+    /// GitHub again, 1 hour by default. 0 always fetches it.
+    pub releases_cache_ttl: Option<u64>,
+    /// Request release lists and other metadata uncompressed over HTTP/1.1, for proxies breaking
+    /// compressed responses or HTTP/2.
+    pub legacy_http: bool,
+    /// Show a desktop notification when a batch of installs, e.g. `vvm install --all` or
+    /// `vvm sync`, finishes or fails.
+    pub notifications: bool,
+    /// Have the wrapper record its invocations in `vyper_commands.json` in the working
+    /// directory, for analyzers and IDEs replaying them.
+    pub compile_commands: bool,
+    /// Have the wrapper check arguments against the capabilities of the compiler in use,
+    /// translating flags it does not know to equivalents and rejecting the others.
+    pub validate_args: bool,
+    /// How the CLI answers yes/no questions.
+    pub prompt_policy: PromptPolicy,
+    /// Where binaries are downloaded from, in priority order: `github` or the base URL of a
+    /// mirror laid out like the GitHub downloads. Only `github` by default.
+    pub download_sources: Vec<String>,
+    /// Asset name patterns choosing the artifact of a release, e.g. `musl,linux-gnu` to prefer
+    /// those builds or `require:musl` to only use them. Overridden by `VVM_ARTIFACT`.
+    pub artifact_selector: Option<String>,
+    /// Releases the `stable` channel never resolves to.
+    pub stable_denylist: Vec<Version>,
+    /// Releases published fewer days ago are not `stable` yet, no minimum age by default.
+    pub stable_min_age_days: Option<u64>,
+    /// Keep versions removed with `vvm remove` in `~/.vvm/.trash` for this many days, restorable
+    /// with `vvm restore`. Removals are permanent when not set.
+    pub trash_days: Option<u64>,
+    /// How many times verifying or first running a freshly installed binary is retried while
+    /// another process, e.g. an antivirus, holds it. 5 by default.
+    pub busy_retries: Option<u32>,
+    /// Milliseconds between the retries of a busy binary, 200 by default.
+    pub busy_retry_delay_ms: Option<u64>,
+    /// Install versions into `<platform>-<arch>` subdirectories of their version directory, e.g.
+    /// `0.3.10/darwin-arm64/vyper-0.3.10`, for homes shared by machines of different platforms.
+    pub platform_dirs: bool,
+    /// Where the VVM home was moved to by `vvm relocate`. Only read from `~/.vvm/config.toml`,
+    /// which then holds nothing else.
+    pub home: Option<PathBuf>,
+    /// Name of the active profile of `profiles`, switched with `vvm profile use`.
+    pub profile: Option<String>,
+    /// Named toolchain profiles, e.g. `audit` or `legacy`, under `[profiles.<name>]`. Tables are
+    /// serialized last in TOML, so `profiles` and `mirrors` must stay the last fields.
+    pub profiles: BTreeMap<String, ProfileConfig>,
+    /// How the binaries of the mirrors in `download_sources` are verified, by mirror base URL.
+    pub mirrors: BTreeMap<String, MirrorConfig>,
+}
+
+/// A toolchain profile, under `[profiles.<name>]` in the config. `vvm sync` installs its
+/// versions while it is active.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileConfig {
+    /// The global version of the profile: a version, a release line like `0.3`, a requirement
+    /// or `stable`.
+    pub default: Option<String>,
+    /// Let requirements and release lines resolve to prereleases.
+    pub prereleases: bool,
+    /// Versions the profile keeps installed, declared like `default`.
+    pub versions: Vec<String>,
+}
+
+/// Verification of the binaries of a mirror, under `[mirrors."<base url>"]` in the config.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MirrorConfig {
+    /// Base64 minisign public key verifying `<base url>/index.json`, a checksum manifest of the
+    /// artifacts of the mirror signed in `<base url>/index.json.minisig`.
+    pub public_key: Option<String>,
+    /// Use the mirror without a signed index, trusting it to serve untampered binaries.
+    pub allow_unsigned: bool,
+    /// Name of the artifacts of the mirror when it serves builds named differently from the
+    /// releases, e.g. of a fork: a template with `{version}`, `{platform}` (`linux`, `darwin` or
+    /// `windows`) and `{commit}`, the commit of the release artifact.
+    pub artifact_name: Option<String>,
+}
+
+/// How the CLI answers yes/no questions, `always-yes` is meant for CI.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PromptPolicy {
+    /// Ask on the terminal.
+    #[default]
+    Ask,
+    AlwaysYes,
+    AlwaysNo,
+}
+
+impl FromStr for PromptPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ask" => Ok(PromptPolicy::Ask),
+            "always-yes" => Ok(PromptPolicy::AlwaysYes),
+            "always-no" => Ok(PromptPolicy::AlwaysNo),
+            s => Err(format!("unknown prompt policy {}", s)),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration, returning the default configuration if there is none.
+    pub fn load() -> Result<Self, VyperVmError> {
+        Self::read(&active_home()?.join(CONFIG_FILENAME))
+    }
+
+    /// Reads the configuration at `path`, returning the default configuration if there is none.
+    pub fn read(path: &Path) -> Result<Self, VyperVmError> {
+        match fs::read_to_string(path) {
+            Ok(content) => {
+                toml::from_str(&content).map_err(|err| VyperVmError::InvalidConfig(err.to_string()))
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the configuration to `~/.vvm/config.toml`, readable by the user only since it may
+    /// hold a GitHub token.
+    pub fn save(&self) -> Result<(), VyperVmError> {
+        self.write_to(&config_path()?)
+    }
+
+    /// Writes the configuration to `path`, readable by the user only. The content goes to a file
+    /// created readable by the user only, which then replaces `path`, so the token is never
+    /// readable by others, not even while writing.
+    pub fn write_to(&self, path: &Path) -> Result<(), VyperVmError> {
+        let content = toml::to_string_pretty(self)
+            .map_err(|err| VyperVmError::InvalidConfig(err.to_string()))?;
+        let tmp_path = path.with_extension(format!("toml.{}", std::process::id()));
+        // created anew, a leftover of a crash may have other permissions
+        let _ = fs::remove_file(&tmp_path);
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(target_family = "unix")]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let written = options
+            .open(&tmp_path)
+            .and_then(|mut file| file.write_all(content.as_bytes()))
+            .and_then(|_| fs::rename(&tmp_path, path));
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        Ok(written?)
+    }
+}
+
+/// Derive path to VVM's configuration file.
+pub fn config_path() -> Result<PathBuf, VyperVmError> {
+    Ok(active_home()?.join(CONFIG_FILENAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_keys_use_defaults() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config, Config::default());
+        let config: Config = toml::from_str("usage_stats = true").unwrap();
+        assert!(config.usage_stats);
+        let config: Config = toml::from_str("prompt_policy = \"always-yes\"").unwrap();
+        assert_eq!(config.prompt_policy, PromptPolicy::AlwaysYes);
+        assert_eq!("always-no".parse(), Ok(PromptPolicy::AlwaysNo));
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn config_is_readable_by_the_user_only() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILENAME);
+        fs::write(&path, "").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let config = Config {
+            github_token: Some("secret".to_string()),
+            ..Default::default()
+        };
+        config.write_to(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(Config::read(&path).unwrap(), config);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,158 @@
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    bin_path, binary_path, cache::get_cache_path, config::config_path, error::VyperVmError,
+    home::active_home, install_path, pin::resolve_for_dir, platform::platform,
+    releases::hex_string, sha256_digest,
+};
+
+/// File name of the environment descriptor stored next to every installed binary.
+pub const ENV_DESCRIPTOR_FILENAME: &str = "env.json";
+
+/// Describes an installed Vyper toolchain, so external build systems can consume it without
+/// knowing the VVM home layout.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvDescriptor {
+    pub version: Version,
+    /// Absolute path to the Vyper binary.
+    pub path: PathBuf,
+    /// Commit the binary was built from, if known from the release artifact name.
+    pub commit: Option<String>,
+    pub platform: String,
+    #[serde(with = "hex_string")]
+    pub sha256: Vec<u8>,
+}
+
+impl EnvDescriptor {
+    /// Creates the descriptor of the binary of `version` installed at `path`.
+    fn new(version: &Version, path: &Path, binbytes: &[u8], artifact: &str) -> Self {
+        Self {
+            version: version.clone(),
+            path: path.to_path_buf(),
+            commit: commit_from_artifact(artifact),
+            platform: platform().to_string(),
+            sha256: sha256_digest(binbytes),
+        }
+    }
+
+    fn write(&self) -> Result<(), VyperVmError> {
+        self.write_in(&install_path(&self.version)?)
+    }
+
+    /// Writes the descriptor into the install directory `dir`.
+    fn write_in(&self, dir: &Path) -> Result<(), VyperVmError> {
+        let file = fs::File::create(dir.join(ENV_DESCRIPTOR_FILENAME))?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+/// Derive path to the environment descriptor of a specific Vyper version.
+pub fn env_descriptor_path(version: &Version) -> Result<PathBuf, VyperVmError> {
+    Ok(install_path(version)?.join(ENV_DESCRIPTOR_FILENAME))
+}
+
+/// Returns the environment descriptor of an installed version. Descriptors missing for versions
+/// installed by older releases of vvm are generated from the installed binary.
+pub fn env_descriptor(version: &Version) -> Result<EnvDescriptor, VyperVmError> {
+    if let Ok(content) = fs::read(env_descriptor_path(version)?) {
+        return Ok(serde_json::from_slice(&content)?);
+    }
+
+    let path = binary_path(version)?;
+    let binbytes =
+        fs::read(&path).map_err(|_| VyperVmError::VersionNotInstalled(version.to_string()))?;
+    let descriptor = EnvDescriptor::new(version, &path, &binbytes, "");
+    descriptor.write()?;
+    Ok(descriptor)
+}
+
+/// Writes the environment descriptor for a freshly installed binary, which will be at `path`,
+/// into its staged install directory `dir`.
+pub(crate) fn write_env_descriptor(
+    version: &Version,
+    path: &Path,
+    binbytes: &[u8],
+    artifact: &str,
+    dir: &Path,
+) -> Result<EnvDescriptor, VyperVmError> {
+    let descriptor = EnvDescriptor::new(version, path, binbytes, artifact);
+    descriptor.write_in(dir)?;
+    Ok(descriptor)
+}
+
+/// Layout of the VVM home and the version in use, for build tools bootstrapping from vvm without
+/// knowing its conventions. The output of `vvm env --json`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HomeDescriptor {
+    pub home: PathBuf,
+    /// Directory of the shims, to add to `PATH`.
+    pub bin_dir: PathBuf,
+    /// Version in use in the directory: the pinned version, else the global version.
+    pub version: Option<Version>,
+    /// Absolute path of the binary of `version`, if installed.
+    pub binary: Option<PathBuf>,
+    /// Directory of the cache of compiled bytecode.
+    pub cache_dir: PathBuf,
+    pub config_path: PathBuf,
+}
+
+/// Describes the VVM home and the version in use in `dir`.
+pub fn home_descriptor(dir: impl AsRef<Path>) -> Result<HomeDescriptor, VyperVmError> {
+    let version = resolve_for_dir(dir)?.map(|resolved| resolved.version);
+    let cache_path = get_cache_path()?;
+    let binary = match &version {
+        Some(version) => Some(binary_path(version)?).filter(|path| path.is_file()),
+        None => None,
+    };
+    Ok(HomeDescriptor {
+        home: active_home()?.to_path_buf(),
+        bin_dir: bin_path()?,
+        binary,
+        version,
+        cache_dir: cache_path.parent().unwrap_or(&cache_path).to_path_buf(),
+        config_path: config_path()?,
+    })
+}
+
+/// Extracts the commit from an artifact name like `vyper.0.3.3+commit.48e326f0.linux`.
+fn commit_from_artifact(artifact: &str) -> Option<String> {
+    let (_, rest) = artifact.split_once("+commit.")?;
+    let commit = rest.split('.').next()?;
+    (!commit.is_empty()).then(|| commit.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commit_from_artifact() {
+        assert_eq!(
+            commit_from_artifact("vyper.0.3.3+commit.48e326f0.linux"),
+            Some("48e326f0".to_string())
+        );
+        assert_eq!(
+            commit_from_artifact("vyper.0.3.3+commit.48e326f0.windows.exe"),
+            Some("48e326f0".to_string())
+        );
+        assert_eq!(commit_from_artifact("0.3.3/vyper-0.3.3"), None);
+    }
+
+    #[test]
+    fn describes_home_layout() {
+        crate::testing::sandbox().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let descriptor = home_descriptor(dir.path()).unwrap();
+        assert_eq!(descriptor.home, crate::active_home().unwrap());
+        assert_eq!(descriptor.bin_dir, descriptor.home.join("bin"));
+        assert!(descriptor.cache_dir.starts_with(&descriptor.home));
+        assert_eq!(descriptor.config_path, descriptor.home.join("config.toml"));
+    }
+}
@@ -0,0 +1,196 @@
+use semver::Version;
+use serde_json::Value;
+
+use std::{collections::BTreeMap, path::Path};
+
+use crate::{compile::exec_each, error::VyperVmError, ExecOutput};
+
+/// What a compiler version produced for a source file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompilerOutput {
+    pub version: Version,
+    /// Size in bytes of the deployment bytecode.
+    pub bytecode_size: usize,
+    /// ABI entries by signature, e.g. `function transfer(address,uint256)`.
+    pub abi: BTreeMap<String, Value>,
+    /// Lines printed on stderr by a successful compilation.
+    pub warnings: Vec<String>,
+}
+
+/// The differences between the outputs of two compiler versions for the same source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputDiff {
+    pub old: CompilerOutput,
+    pub new: CompilerOutput,
+    pub abi_added: Vec<String>,
+    pub abi_removed: Vec<String>,
+    /// Entries with the same signature but e.g. different outputs or mutability.
+    pub abi_changed: Vec<String>,
+    pub warnings_added: Vec<String>,
+    pub warnings_removed: Vec<String>,
+}
+
+impl OutputDiff {
+    /// Compares two outputs.
+    pub fn new(old: CompilerOutput, new: CompilerOutput) -> Self {
+        let abi_added = new
+            .abi
+            .keys()
+            .filter(|sig| !old.abi.contains_key(*sig))
+            .cloned()
+            .collect();
+        let abi_removed = old
+            .abi
+            .keys()
+            .filter(|sig| !new.abi.contains_key(*sig))
+            .cloned()
+            .collect();
+        let abi_changed = old
+            .abi
+            .iter()
+            .filter(|(sig, entry)| new.abi.get(*sig).is_some_and(|other| other != *entry))
+            .map(|(sig, _)| sig.clone())
+            .collect();
+        let warnings_added = new
+            .warnings
+            .iter()
+            .filter(|w| !old.warnings.contains(w))
+            .cloned()
+            .collect();
+        let warnings_removed = old
+            .warnings
+            .iter()
+            .filter(|w| !new.warnings.contains(w))
+            .cloned()
+            .collect();
+        Self {
+            old,
+            new,
+            abi_added,
+            abi_removed,
+            abi_changed,
+            warnings_added,
+            warnings_removed,
+        }
+    }
+
+    /// Returns true if both versions produce the same bytecode size, ABI and warnings.
+    pub fn is_empty(&self) -> bool {
+        self.old.bytecode_size == self.new.bytecode_size
+            && self.abi_added.is_empty()
+            && self.abi_removed.is_empty()
+            && self.abi_changed.is_empty()
+            && self.warnings_added.is_empty()
+            && self.warnings_removed.is_empty()
+    }
+}
+
+/// Compiles `source` with two installed versions and compares their bytecode size, ABI and
+/// warnings.
+pub async fn diff_output(
+    source: impl AsRef<Path>,
+    old: &Version,
+    new: &Version,
+) -> Result<OutputDiff, VyperVmError> {
+    let source = source.as_ref().to_string_lossy().to_string();
+    let versions = [old.clone(), new.clone()];
+    let args = |format: &str| vec!["-f".to_string(), format.to_string(), source.clone()];
+    let bytecode = exec_each(&versions, &args("bytecode"), 2).await?;
+    let abi = exec_each(&versions, &args("abi"), 2).await?;
+
+    let mut outputs = bytecode
+        .into_iter()
+        .zip(abi)
+        .map(|(bytecode, abi)| compiler_output(bytecode, abi));
+    let old = outputs.next().expect("two versions")?;
+    let new = outputs.next().expect("two versions")?;
+    Ok(OutputDiff::new(old, new))
+}
+
+fn compiler_output(bytecode: ExecOutput, abi: ExecOutput) -> Result<CompilerOutput, VyperVmError> {
+    for output in [&bytecode, &abi] {
+        if !output.success {
+            return Err(VyperVmError::CompilationFailed(
+                output.version.to_string(),
+                output.stderr.trim().to_string(),
+            ));
+        }
+    }
+    let hex = bytecode.stdout.trim().trim_start_matches("0x");
+    let abi: Vec<Value> = serde_json::from_str(abi.stdout.trim())?;
+    Ok(CompilerOutput {
+        version: bytecode.version,
+        bytecode_size: hex.len() / 2,
+        abi: abi
+            .into_iter()
+            .map(|entry| (signature(&entry), entry))
+            .collect(),
+        warnings: bytecode
+            .stderr
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .collect(),
+    })
+}
+
+/// Returns the signature of an ABI entry, e.g. `function transfer(address,uint256)`.
+fn signature(entry: &Value) -> String {
+    let kind = entry["type"].as_str().unwrap_or("function");
+    let name = entry["name"].as_str().unwrap_or_default();
+    let inputs = entry["inputs"]
+        .as_array()
+        .map(|inputs| {
+            inputs
+                .iter()
+                .map(|input| input["type"].as_str().unwrap_or_default())
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .unwrap_or_default();
+    format!("{} {}({})", kind, name, inputs).replace(" (", "(")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(version: Version, bytecode: &str, abi: &str, stderr: &str) -> CompilerOutput {
+        let exec = |stdout: &str, stderr: &str| ExecOutput {
+            version: version.clone(),
+            success: true,
+            code: Some(0),
+            stdout: stdout.to_string(),
+            stderr: stderr.to_string(),
+        };
+        compiler_output(exec(bytecode, stderr), exec(abi, "")).unwrap()
+    }
+
+    #[test]
+    fn diffs_outputs() {
+        let old = output(
+            Version::new(0, 3, 3),
+            "0x6001",
+            r#"[{"type": "function", "name": "f", "inputs": [{"type": "uint256"}], "outputs": []},
+                {"type": "event", "name": "E", "inputs": []}]"#,
+            "Warning: unused variable\n",
+        );
+        let new = output(
+            Version::new(0, 3, 7),
+            "0x600160",
+            r#"[{"type": "function", "name": "f", "inputs": [{"type": "uint256"}], "outputs": [{"type": "bool"}]},
+                {"type": "constructor", "inputs": []}]"#,
+            "",
+        );
+        assert_eq!(old.bytecode_size, 2);
+        assert_eq!(new.bytecode_size, 3);
+
+        let diff = OutputDiff::new(old, new);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.abi_added, vec!["constructor()"]);
+        assert_eq!(diff.abi_removed, vec!["event E()"]);
+        assert_eq!(diff.abi_changed, vec!["function f(uint256)"]);
+        assert_eq!(diff.warnings_removed, vec!["Warning: unused variable"]);
+        assert!(diff.warnings_added.is_empty());
+    }
+}
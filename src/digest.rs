@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use crate::sha256_digest;
+
+/// A hash algorithm binaries can be verified with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Sha256,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// Every supported algorithm, recorded for new installs, bundles and manifests.
+    pub const ALL: [HashAlgorithm; 2] = [HashAlgorithm::Sha256, HashAlgorithm::Blake3];
+
+    /// Returns the digest of `bytes`.
+    pub fn digest(&self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha256 => sha256_digest(bytes),
+            HashAlgorithm::Blake3 => blake3::hash(bytes).as_bytes().to_vec(),
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake3 => "blake3",
+        };
+        f.write_str(s)
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "blake3" => Ok(HashAlgorithm::Blake3),
+            s => Err(format!("unknown hash algorithm {}", s)),
+        }
+    }
+}
+
+/// Hex encoded digests of a binary by algorithm name, e.g.
+/// `{"sha256": "…", "blake3": "…"}`.
+///
+/// Digests of algorithms this version of vvm does not know are kept but not verified, so
+/// manifests and bundles recording newer formats stay readable.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Digests(BTreeMap<String, String>);
+
+impl Digests {
+    /// Computes the digests of `bytes` with every supported algorithm.
+    pub fn compute(bytes: &[u8]) -> Self {
+        let mut digests = Self::default();
+        for algorithm in HashAlgorithm::ALL {
+            digests.insert(algorithm, &algorithm.digest(bytes));
+        }
+        digests
+    }
+
+    /// Adds the digest computed with `algorithm`.
+    pub fn insert(&mut self, algorithm: HashAlgorithm, digest: &[u8]) {
+        self.0.insert(algorithm.to_string(), hex::encode(digest));
+    }
+
+    /// Returns the digest computed with `algorithm`, if recorded.
+    pub fn get(&self, algorithm: HashAlgorithm) -> Option<Vec<u8>> {
+        self.0
+            .get(&algorithm.to_string())
+            .and_then(|digest| hex::decode(digest.trim_start_matches("0x")).ok())
+    }
+
+    /// The recorded digests of supported algorithms.
+    pub fn known(&self) -> impl Iterator<Item = (HashAlgorithm, Vec<u8>)> + '_ {
+        HashAlgorithm::ALL
+            .iter()
+            .copied()
+            .filter_map(move |algorithm| Some((algorithm, self.get(algorithm)?)))
+    }
+
+    /// Returns true if no digest of a supported algorithm is recorded, i.e. nothing can be
+    /// verified.
+    pub fn is_empty(&self) -> bool {
+        self.known().next().is_none()
+    }
+
+    /// Returns the first supported algorithm whose digest of `bytes` differs from the recorded
+    /// one, `None` if they all match.
+    pub fn mismatch(&self, bytes: &[u8]) -> Option<HashAlgorithm> {
+        self.known()
+            .find(|(algorithm, digest)| algorithm.digest(bytes) != *digest)
+            .map(|(algorithm, _)| algorithm)
+    }
+}
+
+impl fmt::Display for Digests {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digests = self
+            .0
+            .iter()
+            .map(|(algorithm, digest)| format!("{} {}", algorithm, digest))
+            .collect::<Vec<_>>();
+        f.write_str(&digests.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_known_algorithms_only() {
+        let digests = Digests::compute(b"fake vyper");
+        assert_eq!(digests.known().count(), 2);
+        assert_eq!(digests.mismatch(b"fake vyper"), None);
+        assert_eq!(digests.mismatch(b"evil vyper"), Some(HashAlgorithm::Sha256));
+
+        let mut newer: Digests =
+            serde_json::from_str(r#"{"sha512": "0102", "blake3": "00"}"#).unwrap();
+        assert!(!newer.is_empty());
+        assert_eq!(newer.mismatch(b"fake vyper"), Some(HashAlgorithm::Blake3));
+        newer.insert(
+            HashAlgorithm::Blake3,
+            &HashAlgorithm::Blake3.digest(b"fake vyper"),
+        );
+        assert_eq!(newer.mismatch(b"fake vyper"), None);
+        assert!(serde_json::to_string(&newer).unwrap().contains("sha512"));
+
+        let unknown: Digests = serde_json::from_str(r#"{"sha512": "0102"}"#).unwrap();
+        assert!(unknown.is_empty());
+    }
+}
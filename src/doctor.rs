@@ -0,0 +1,739 @@
+use reqwest::{
+    header::{HeaderMap, HeaderValue, USER_AGENT},
+    StatusCode,
+};
+use serde::Deserialize;
+use tokio::net::{lookup_host, TcpStream};
+use url::Url;
+
+use semver::Version;
+
+use std::{
+    env, fmt, fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    binary_path, cache,
+    config::Config,
+    error::VyperVmError,
+    gc::{gc, GcReport, LOCK_FILE_PREFIX},
+    global_version_path,
+    home::active_home,
+    http, install_path, layout, metrics,
+    platform::{supported_platform, SUPPORTED_PLATFORMS},
+    politeness,
+    probe::probe_version,
+    queue, releases, repair_global_version, sources,
+    sources::GITHUB_SOURCE,
+    unset_global_version, GlobalVersionRepair, STAGING_DIR_PREFIX,
+};
+
+/// Endpoint of the GitHub API reporting the rate limit, requests to it are not counted.
+const GITHUB_RATE_LIMIT: &str = "https://api.github.com/rate_limit";
+
+/// Timeout of every network probe, short so a blackholed connection is reported quickly.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Proxy variables reqwest reads, in the order it reads them for HTTPS requests.
+const PROXY_VARS: &[&str] = &["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"];
+
+/// Outcome of a diagnostic check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckStatus {
+    Ok,
+    /// Works, but likely to cause failures, e.g. an almost exhausted rate limit.
+    Warning,
+    Failed,
+    /// Not run because a check it depends on failed.
+    Skipped,
+}
+
+/// A diagnostic check of `vvm doctor`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DoctorCheck {
+    /// What is checked, e.g. `dns` or `github api`.
+    pub layer: &'static str,
+    /// The host, URL or path the check is about.
+    pub target: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl DoctorCheck {
+    fn new(
+        layer: &'static str,
+        target: impl fmt::Display,
+        status: CheckStatus,
+        detail: impl Into<String>,
+    ) -> Self {
+        Self {
+            layer,
+            target: target.to_string(),
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Checks the platform, the layout of the VVM home, its caches and the global version.
+pub fn local_diagnostics() -> Vec<DoctorCheck> {
+    let supported = SUPPORTED_PLATFORMS
+        .iter()
+        .map(|(os, arch, _)| format!("{}-{}", os, arch))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let target = format!("{}-{}", env::consts::OS, env::consts::ARCH);
+    let mut checks = vec![match supported_platform() {
+        Ok(_) => DoctorCheck::new("platform", target, CheckStatus::Ok, "binaries published"),
+        Err(err) => DoctorCheck::new(
+            "platform",
+            target,
+            CheckStatus::Failed,
+            format!("{} Supported: {}", err, supported),
+        ),
+    }];
+    let home = match active_home() {
+        Ok(home) => home,
+        Err(err) => {
+            checks.push(DoctorCheck::new(
+                "home",
+                "VVM_HOME",
+                CheckStatus::Failed,
+                err.to_string(),
+            ));
+            return checks;
+        }
+    };
+    let status = if home.is_dir() {
+        CheckStatus::Ok
+    } else {
+        CheckStatus::Failed
+    };
+    checks.push(DoctorCheck::new(
+        "home",
+        home.display(),
+        status,
+        if home.is_dir() { "exists" } else { "missing" },
+    ));
+    if !home.is_dir() {
+        return checks;
+    }
+    match install_checks(home) {
+        Ok(install) => checks.extend(install),
+        Err(err) => checks.push(DoctorCheck::new(
+            "install",
+            home.display(),
+            CheckStatus::Failed,
+            err.to_string(),
+        )),
+    }
+    match cache_checks() {
+        Ok(cache) => checks.extend(cache),
+        Err(err) => checks.push(DoctorCheck::new(
+            "cache",
+            "-",
+            CheckStatus::Failed,
+            err.to_string(),
+        )),
+    }
+
+    match global_version() {
+        Ok(GlobalVersion::Invalid(content)) => checks.push(DoctorCheck::new(
+            "global version",
+            global_version_path().unwrap_or_default().display(),
+            CheckStatus::Failed,
+            format!(
+                "`{}` is not a version, run `vvm doctor --fix` to unset it",
+                content
+            ),
+        )),
+        Ok(GlobalVersion::Set(version))
+            if !binary_path(&version).is_ok_and(|path| path.is_file()) =>
+        {
+            checks.push(DoctorCheck::new(
+                "global version",
+                &version,
+                CheckStatus::Failed,
+                "not installed, run `vvm doctor --fix` to switch to the newest installed version",
+            ))
+        }
+        Ok(GlobalVersion::Set(version)) => {
+            let check = match probe_version(&version) {
+                Ok(probe) => {
+                    DoctorCheck::new("global version", &version, CheckStatus::Ok, probe.output)
+                }
+                Err(err) => DoctorCheck::new(
+                    "global version",
+                    &version,
+                    CheckStatus::Failed,
+                    err.to_string(),
+                ),
+            };
+            checks.push(check);
+        }
+        Ok(GlobalVersion::Unset) => checks.push(DoctorCheck::new(
+            "global version",
+            "-",
+            CheckStatus::Warning,
+            "not set, run `vvm use <version>`",
+        )),
+        Err(err) => checks.push(DoctorCheck::new(
+            "global version",
+            "-",
+            CheckStatus::Failed,
+            err.to_string(),
+        )),
+    }
+    checks
+}
+
+/// Content of the global version file.
+enum GlobalVersion {
+    Unset,
+    Set(Version),
+    /// Neither empty nor a version, e.g. truncated by a full disk.
+    Invalid(String),
+}
+
+fn global_version() -> Result<GlobalVersion, VyperVmError> {
+    let content = match fs::read_to_string(global_version_path()?) {
+        Ok(content) => content.trim().to_string(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(GlobalVersion::Unset),
+        Err(err) => return Err(err.into()),
+    };
+    if content.is_empty() {
+        return Ok(GlobalVersion::Unset);
+    }
+    Ok(match Version::parse(&content) {
+        Ok(version) => GlobalVersion::Set(version),
+        Err(_) => GlobalVersion::Invalid(content),
+    })
+}
+
+/// Checks every version directory of the home for a complete binary of this machine, and the
+/// home for lock files and staged installs left behind by interrupted installs.
+fn install_checks(home: &Path) -> Result<Vec<DoctorCheck>, VyperVmError> {
+    let mut checks = vec![];
+    let entries = fs::read_dir(home)?
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+    let mut installed = 0;
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if path.is_dir() {
+            if let Ok(version) = Version::parse(&name) {
+                // only holds the installs of other platforms sharing the home
+                if !layout::installed_here(&version)? {
+                    continue;
+                }
+                let detail = match fs::metadata(binary_path(&version)?) {
+                    Ok(metadata) if metadata.len() > 0 => {
+                        installed += 1;
+                        continue;
+                    }
+                    Ok(_) => "binary partially written",
+                    Err(_) if is_empty_dir(&install_path(&version)?) => "empty version directory",
+                    Err(_) => "binary missing",
+                };
+                checks.push(DoctorCheck::new(
+                    "install",
+                    &version,
+                    CheckStatus::Failed,
+                    format!(
+                        "{}, run `vvm doctor --fix` and `vvm install {}`",
+                        detail, version
+                    ),
+                ));
+            } else if name.starts_with(STAGING_DIR_PREFIX) && !is_locked(&home.join(".lock-home")) {
+                checks.push(DoctorCheck::new(
+                    "install",
+                    path.display(),
+                    CheckStatus::Warning,
+                    "left by an interrupted install, run `vvm doctor --fix`",
+                ));
+            }
+        } else if name.starts_with(LOCK_FILE_PREFIX) && !is_locked(&path) {
+            checks.push(DoctorCheck::new(
+                "lock file",
+                path.display(),
+                CheckStatus::Warning,
+                "stale, left by an interrupted install, run `vvm doctor --fix`",
+            ));
+        }
+    }
+    if checks.is_empty() {
+        checks.push(DoctorCheck::new(
+            "install",
+            home.display(),
+            CheckStatus::Ok,
+            format!("{} versions installed", installed),
+        ));
+    }
+    Ok(checks)
+}
+
+fn is_empty_dir(path: &Path) -> bool {
+    fs::read_dir(path).map_or(true, |mut entries| entries.next().is_none())
+}
+
+/// Whether a running process holds the lock file at `path`. Unlike taking the lock, checking
+/// leaves the file in place.
+fn is_locked(path: &Path) -> bool {
+    use fs2::FileExt;
+    match fs::OpenOptions::new().read(true).write(true).open(path) {
+        Ok(file) => file.try_lock_exclusive().is_err(),
+        Err(_) => false,
+    }
+}
+
+/// The caches vvm keeps in the home, rebuilt when missing so a corrupted one can be removed.
+fn cache_paths() -> Result<Vec<PathBuf>, VyperVmError> {
+    Ok(vec![
+        cache::get_cache_path()?,
+        releases::releases_cache_path()?,
+        sources::source_health_path()?,
+        queue::queue_state_path()?,
+    ])
+}
+
+/// The parse error of a cache that is not valid JSON, `None` if it is valid or does not exist.
+fn corrupted_json(path: &Path) -> Option<String> {
+    let content = fs::read(path).ok()?;
+    serde_json::from_slice::<serde_json::Value>(&content)
+        .err()
+        .map(|err| err.to_string())
+}
+
+fn cache_checks() -> Result<Vec<DoctorCheck>, VyperVmError> {
+    let paths = cache_paths()?;
+    let checks = paths
+        .iter()
+        .filter_map(|path| {
+            let err = corrupted_json(path)?;
+            Some(DoctorCheck::new(
+                "cache",
+                path.display(),
+                CheckStatus::Failed,
+                format!(
+                    "corrupted: {}, run `vvm doctor --fix` to remove it, it is rebuilt",
+                    err
+                ),
+            ))
+        })
+        .collect::<Vec<_>>();
+    if !checks.is_empty() {
+        return Ok(checks);
+    }
+    let present = paths.iter().filter(|path| path.is_file()).count();
+    Ok(vec![DoctorCheck::new(
+        "cache",
+        "-",
+        CheckStatus::Ok,
+        format!("{} caches valid", present),
+    )])
+}
+
+/// Everything `vvm doctor --fix` repaired, see [`repair_home`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HomeRepair {
+    /// Caches that were not valid JSON, rebuilt on demand.
+    pub corrupted_caches: Vec<PathBuf>,
+    /// Partial installs, stale lock files and other leftovers, removed as by [`gc`].
+    pub leftovers: GcReport,
+    /// Repair of a global version that is no longer installed.
+    pub global_version: Option<GlobalVersionRepair>,
+    /// Content of a global version file that was not a version, and was unset.
+    pub invalid_global_version: Option<String>,
+}
+
+impl HomeRepair {
+    /// Returns true if nothing was repaired.
+    pub fn is_empty(&self) -> bool {
+        self.corrupted_caches.is_empty()
+            && self.leftovers.is_empty()
+            && self.global_version.is_none()
+            && self.invalid_global_version.is_none()
+    }
+}
+
+/// Repairs what [`local_diagnostics`] finds broken in the VVM home: removes corrupted caches,
+/// cleans up partial installs and stale lock files with [`gc`], then points the global version
+/// at an installed version, or unsets it.
+pub fn repair_home() -> Result<HomeRepair, VyperVmError> {
+    let mut repair = HomeRepair::default();
+    for path in cache_paths()? {
+        if corrupted_json(&path).is_some() {
+            fs::remove_file(&path)?;
+            repair.corrupted_caches.push(path);
+        }
+    }
+    repair.leftovers = gc()?;
+    match global_version()? {
+        GlobalVersion::Invalid(content) => {
+            unset_global_version()?;
+            repair.invalid_global_version = Some(content);
+        }
+        _ => repair.global_version = repair_global_version()?,
+    }
+    Ok(repair)
+}
+
+/// Checks every layer between vvm and GitHub, then the configured mirrors: proxy configuration,
+/// DNS, TCP, TLS, the GitHub API and its rate limit. The first failing layer is reported and the
+/// layers above it are skipped, so a failure points at the exact cause.
+pub async fn network_diagnostics() -> Vec<DoctorCheck> {
+    let mut checks = vec![];
+    let api = Url::parse(GITHUB_RATE_LIMIT).expect("valid URL");
+
+    // with a proxy, DNS and TCP are about the proxy, the TLS connection is tunneled through it
+    let proxy = match proxy_from_env() {
+        Ok(Some((var, proxy))) => {
+            checks.push(DoctorCheck::new(
+                "proxy",
+                &proxy,
+                CheckStatus::Ok,
+                format!("from {}", var),
+            ));
+            Some(proxy)
+        }
+        Ok(None) => {
+            checks.push(DoctorCheck::new("proxy", "-", CheckStatus::Ok, "none"));
+            None
+        }
+        Err(check) => {
+            checks.push(check);
+            return with_mirrors(skip_rest(checks, &api)).await;
+        }
+    };
+    let endpoint = proxy.as_ref().unwrap_or(&api);
+    if !reach(&mut checks, endpoint).await {
+        return with_mirrors(skip_rest(checks, &api)).await;
+    }
+
+    let mut headers = HeaderMap::new();
+    headers.insert(USER_AGENT, HeaderValue::from_static("reqwest"));
+    releases::add_gh_token(&mut headers);
+    let response = {
+        let _permit = politeness::github_permit().await;
+        client().get(api.clone()).headers(headers).send().await
+    };
+    let response = match response {
+        Ok(response) => {
+            checks.push(DoctorCheck::new(
+                "tls",
+                host(&api),
+                CheckStatus::Ok,
+                "handshake succeeded",
+            ));
+            response
+        }
+        Err(err) => {
+            checks.push(request_failure(&api, &err));
+            checks.push(skipped("github api", &api));
+            checks.push(skipped("rate limit", &api));
+            return with_mirrors(checks).await;
+        }
+    };
+    metrics::record_response(response.status(), response.headers());
+    let status = response.status();
+    if !status.is_success() {
+        let detail = match status {
+            StatusCode::UNAUTHORIZED => {
+                "token rejected, check GITHUB_TOKEN or github_token in the config".to_string()
+            }
+            _ => format!("unexpected response {}", status),
+        };
+        checks.push(DoctorCheck::new(
+            "github api",
+            &api,
+            CheckStatus::Failed,
+            detail,
+        ));
+        checks.push(skipped("rate limit", &api));
+        return with_mirrors(checks).await;
+    }
+    checks.push(DoctorCheck::new(
+        "github api",
+        &api,
+        CheckStatus::Ok,
+        status.to_string(),
+    ));
+    let check = match response.json::<RateLimitResponse>().await {
+        Ok(limits) => rate_limit_check(&limits.rate, unix_now()),
+        Err(err) => DoctorCheck::new("rate limit", &api, CheckStatus::Warning, err.to_string()),
+    };
+    checks.push(check);
+    with_mirrors(checks).await
+}
+
+/// Resolves and connects to the host of `url`, returns true if both succeeded.
+async fn reach(checks: &mut Vec<DoctorCheck>, url: &Url) -> bool {
+    let host = host(url);
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs = match tokio::time::timeout(PROBE_TIMEOUT, lookup_host((host.as_str(), port))).await
+    {
+        Ok(Ok(addrs)) => addrs.collect::<Vec<_>>(),
+        Ok(Err(err)) => {
+            checks.push(DoctorCheck::new(
+                "dns",
+                &host,
+                CheckStatus::Failed,
+                err.to_string(),
+            ));
+            checks.push(skipped("tcp", &host));
+            return false;
+        }
+        Err(_) => {
+            checks.push(DoctorCheck::new(
+                "dns",
+                &host,
+                CheckStatus::Failed,
+                "timed out",
+            ));
+            checks.push(skipped("tcp", &host));
+            return false;
+        }
+    };
+    checks.push(DoctorCheck::new(
+        "dns",
+        &host,
+        CheckStatus::Ok,
+        addrs
+            .iter()
+            .map(|addr| addr.ip().to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+    ));
+
+    let target = format!("{}:{}", host, port);
+    let check =
+        match tokio::time::timeout(PROBE_TIMEOUT, TcpStream::connect(addrs.as_slice())).await {
+            Ok(Ok(_)) => DoctorCheck::new("tcp", &target, CheckStatus::Ok, "connected"),
+            Ok(Err(err)) => DoctorCheck::new("tcp", &target, CheckStatus::Failed, err.to_string()),
+            Err(_) => DoctorCheck::new("tcp", &target, CheckStatus::Failed, "timed out"),
+        };
+    let connected = check.status == CheckStatus::Ok;
+    checks.push(check);
+    connected
+}
+
+/// Checks every mirror of `download_sources`: its signed index if it has a public key, its base
+/// URL otherwise.
+async fn with_mirrors(mut checks: Vec<DoctorCheck>) -> Vec<DoctorCheck> {
+    let config = Config::load().unwrap_or_default();
+    for source in config
+        .download_sources
+        .iter()
+        .filter(|source| *source != GITHUB_SOURCE)
+    {
+        let base = source.trim_end_matches('/');
+        let signed = config
+            .mirrors
+            .get(source)
+            .is_some_and(|mirror| mirror.public_key.is_some());
+        let url = if signed {
+            format!("{}/index.json", base)
+        } else {
+            format!("{}/", base)
+        };
+        let check = match client().get(&url).send().await {
+            // any answer of an unsigned mirror shows it is up, its layout is checked on download
+            Ok(response)
+                if response.status().is_success()
+                    || !signed && !response.status().is_server_error() =>
+            {
+                DoctorCheck::new(
+                    "mirror",
+                    &url,
+                    CheckStatus::Ok,
+                    response.status().to_string(),
+                )
+            }
+            Ok(response) => DoctorCheck::new(
+                "mirror",
+                &url,
+                CheckStatus::Failed,
+                format!("unexpected response {}", response.status()),
+            ),
+            Err(err) => DoctorCheck::new("mirror", &url, CheckStatus::Failed, describe(&err)),
+        };
+        checks.push(check);
+    }
+    checks
+}
+
+fn skip_rest(mut checks: Vec<DoctorCheck>, api: &Url) -> Vec<DoctorCheck> {
+    for layer in ["tls", "github api", "rate limit"] {
+        checks.push(skipped(layer, host(api)));
+    }
+    checks
+}
+
+fn skipped(layer: &'static str, target: impl fmt::Display) -> DoctorCheck {
+    DoctorCheck::new(
+        layer,
+        target,
+        CheckStatus::Skipped,
+        "a check it depends on failed",
+    )
+}
+
+/// Attributes a failed request to the layer that failed. DNS and TCP already succeeded, so a
+/// connection error is a TLS error, or a proxy refusing to tunnel.
+fn request_failure(url: &Url, err: &reqwest::Error) -> DoctorCheck {
+    let layer = if err.is_connect() || err.is_timeout() {
+        "tls"
+    } else {
+        "github api"
+    };
+    DoctorCheck::new(layer, host(url), CheckStatus::Failed, describe(err))
+}
+
+/// The message of an error and of its sources, reqwest hides the actual cause in them.
+fn describe(err: &(dyn std::error::Error + 'static)) -> String {
+    let mut messages = vec![err.to_string()];
+    let mut source = err.source();
+    while let Some(err) = source {
+        messages.push(err.to_string());
+        source = err.source();
+    }
+    messages.dedup();
+    messages.join(": ")
+}
+
+/// The proxy requests go through, and the variable it is set with. Fails with the check to
+/// report if the proxy is not a valid URL.
+fn proxy_from_env() -> Result<Option<(&'static str, Url)>, DoctorCheck> {
+    let (var, value) = match PROXY_VARS.iter().find_map(|var| {
+        env::var(var)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .map(|value| (*var, value))
+    }) {
+        Some(proxy) => proxy,
+        None => return Ok(None),
+    };
+    let no_proxy = env::var("NO_PROXY")
+        .or_else(|_| env::var("no_proxy"))
+        .unwrap_or_default();
+    if no_proxy.split(',').any(|host| {
+        let host = host.trim().trim_start_matches('.');
+        host == "*" || host == "github.com" || host == "api.github.com"
+    }) {
+        return Ok(None);
+    }
+    // like reqwest, a proxy without a scheme is an HTTP proxy
+    let url = if value.contains("://") {
+        value.clone()
+    } else {
+        format!("http://{}", value)
+    };
+    match Url::parse(&url) {
+        Ok(url) if url.host_str().is_some() => Ok(Some((var, url))),
+        _ => Err(DoctorCheck::new(
+            "proxy",
+            value,
+            CheckStatus::Failed,
+            format!("{} is not a valid proxy URL", var),
+        )),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RateLimitResponse {
+    rate: RateLimit,
+}
+
+#[derive(Debug, Deserialize)]
+struct RateLimit {
+    limit: u64,
+    remaining: u64,
+    /// Unix timestamp in seconds of the reset.
+    reset: u64,
+}
+
+/// Fails when the rate limit is exhausted, warns when less than a tenth of it remains.
+fn rate_limit_check(rate: &RateLimit, now: u64) -> DoctorCheck {
+    let resets = format!("resets in {} min", rate.reset.saturating_sub(now) / 60);
+    let (status, detail) = if rate.remaining == 0 {
+        (
+            CheckStatus::Failed,
+            format!("exhausted, {}, set GITHUB_TOKEN for a higher limit", resets),
+        )
+    } else if rate.remaining * 10 < rate.limit {
+        (
+            CheckStatus::Warning,
+            format!("{} of {} remaining, {}", rate.remaining, rate.limit, resets),
+        )
+    } else {
+        (
+            CheckStatus::Ok,
+            format!("{} of {} remaining", rate.remaining, rate.limit),
+        )
+    };
+    DoctorCheck::new("rate limit", "api.github.com", status, detail)
+}
+
+fn client() -> reqwest::Client {
+    http::client_builder()
+        .timeout(PROBE_TIMEOUT)
+        .build()
+        .expect("reqwest::Client::new()")
+}
+
+fn host(url: &Url) -> String {
+    url.host_str().unwrap_or_default().to_string()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_corrupted_caches_and_stale_locks() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("releases.json");
+        assert_eq!(corrupted_json(&cache), None);
+        fs::write(&cache, br#"{"builds": [{"version": "0.3.1"#).unwrap();
+        assert!(corrupted_json(&cache).is_some());
+        fs::write(&cache, br#"{"builds": []}"#).unwrap();
+        assert_eq!(corrupted_json(&cache), None);
+
+        use fs2::FileExt;
+        let lock = dir.path().join(".lock-vyper-0.3.1");
+        let file = fs::File::create(&lock).unwrap();
+        assert!(!is_locked(&lock));
+        file.lock_exclusive().unwrap();
+        assert!(is_locked(&lock));
+        drop(file);
+        assert!(!is_locked(&lock));
+        assert!(lock.exists());
+    }
+
+    #[test]
+    fn classifies_rate_limit() {
+        let rate = |remaining| RateLimit {
+            limit: 60,
+            remaining,
+            reset: 1_000 + 30 * 60,
+        };
+        assert_eq!(rate_limit_check(&rate(60), 1_000).status, CheckStatus::Ok);
+        let low = rate_limit_check(&rate(5), 1_000);
+        assert_eq!(low.status, CheckStatus::Warning);
+        assert_eq!(low.detail, "5 of 60 remaining, resets in 30 min");
+        assert_eq!(
+            rate_limit_check(&rate(0), 1_000).status,
+            CheckStatus::Failed
+        );
+    }
+}
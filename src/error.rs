@@ -2,34 +2,245 @@ use reqwest::StatusCode;
 use thiserror::Error;
 use url::Url;
 
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 /// Error types from the vvm_lib crate.
+///
+/// New variants are added in minor releases, so matching must have a wildcard arm. Consumers
+/// wanting to react to kinds of failures, e.g. retrying network errors, should use
+/// [`VyperVmError::category`] and its predicates, which are stable.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum VyperVmError {
     #[error("VVM global version not set")]
     GlobalVersionNotSet,
+    #[error("Global version changed concurrently: expected {0}, found {1}")]
+    GlobalVersionChanged(String, String),
     #[error("Unknown version provided")]
     UnknownVersion,
+    #[error("No release matches the rules of the stable channel")]
+    NoStableVersion,
+    #[error("No profile {0} in the config")]
+    UnknownProfile(String),
     #[error("Unsupported version {0} for platform {1}")]
     UnsupportedVersion(String, String),
+    #[error(
+        "Vyper publishes no binaries for {os} on {arch}, build it from source instead, e.g. `pip install vyper==<version>`"
+    )]
+    UnsupportedPlatform { os: String, arch: String },
     #[error("Version {0} not installed")]
     VersionNotInstalled(String),
+    #[error("Version {0} is already installed")]
+    VersionAlreadyInstalled(String),
+    #[error("Version {0} is not in the trash")]
+    VersionNotInTrash(String),
     #[error("Checksum mismatch for version {0}")]
     ChecksumMismatch(String),
+    #[error("No known checksum for {0}, run `vvm checksums update` or install it unverified with --insecure")]
+    UnknownChecksum(String),
+    #[error("Installed vyper {0} failed verification: {1}")]
+    InstallVerificationFailed(String, String),
+    #[error("Compilation with vyper {0} failed: {1}")]
+    CompilationFailed(String, String),
+    #[error("VVM home {0} is already in use, activate a home before any other call")]
+    HomeAlreadyInUse(PathBuf),
+    #[error("Compiling {0} timed out after {1} seconds")]
+    CompileTimeout(String, u64),
+    #[error("Running vyper {0} --version failed: {1}")]
+    ProbeFailed(String, String),
     #[error("Install step for solc version {0} timed out after {1} seconds")]
     Timeout(String, u64),
     #[error("Unable to patch solc binary for nixos. stdout: {0}. stderr: {1}")]
     CouldNotPatchForNixOs(String, String),
+    #[error("vyper {0} does not support {1}: {2}")]
+    UnsupportedArgument(String, String, String),
+    #[error("{0} needs network access, but vvm runs offline")]
+    Offline(String),
+    #[error("Version {0} is ambiguous, it matches {1}")]
+    AmbiguousVersion(String, String),
+    #[error("Invalid version {0}: {1}")]
+    InvalidVersionInput(String, String),
+    #[error("Invalid duration {0}, expected e.g. 90d, 2w, 12h, 30m or 45s")]
+    InvalidDuration(String),
+    #[error("Invalid config: {0}")]
+    InvalidConfig(String),
+    #[error(
+        "Could not detect the user home directory, set VVM_HOME to the directory vvm should use"
+    )]
+    HomeNotFound,
+    #[error("Cannot relocate the VVM home: {0}")]
+    InvalidRelocation(String),
+    #[error("Invalid bundle: {0}")]
+    InvalidBundle(String),
+    #[error("Invalid toolchain file: {0}")]
+    InvalidToolchainFile(String),
+    #[error("Invalid lock file: {0}")]
+    InvalidLockFile(String),
+    #[error("Invalid checksum manifest: {0}")]
+    InvalidChecksums(String),
+    #[error("Mirror {0} has no public_key to verify its index, set allow_unsigned = true to use it anyway")]
+    UnsignedMirror(String),
+    #[error("Mirror {0} does not list {1} in its signed index")]
+    UnlistedArtifact(String, String),
+    #[error("Invalid artifact name `{0}`: {1}")]
+    InvalidArtifactName(String, String),
     #[error(transparent)]
     IoError(#[from] std::io::Error),
     #[error(transparent)]
     ReqwestError(#[from] reqwest::Error),
     #[error(transparent)]
+    SerdeJsonError(#[from] serde_json::Error),
+    #[error(transparent)]
     SemverError(#[from] semver::Error),
     #[error(transparent)]
     UrlError(#[from] url::ParseError),
     #[error("Received unsuccessful response with code {1} for {0}")]
     UnsuccessfulResponse(Url, StatusCode),
+    /// The GitHub API rate limit is exhausted until `reset_at`, a unix timestamp in seconds.
+    #[error(
+        "GitHub API rate limit exceeded{}, set VVM_GITHUB_TOKEN or GITHUB_TOKEN for a higher limit",
+        resets_in(.reset_at)
+    )]
+    RateLimited { reset_at: Option<u64> },
     #[cfg(all(target_os = "windows", target_arch = "x86_64"))]
     #[error(transparent)]
     ZipError(#[from] zip::result::ZipError),
 }
+
+/// Stable categories of [`VyperVmError`]s, a variant never changes category.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCategory {
+    /// A request failed or was answered with an error, usually worth retrying.
+    Network,
+    /// A version, release, profile or directory does not exist.
+    NotFound,
+    /// A binary or manifest could not be verified.
+    Integrity,
+    /// Reading or writing the filesystem failed.
+    Io,
+    /// The VVM home is not in the state the operation expects, e.g. changed concurrently.
+    State,
+    /// An input, config or file given to vvm is invalid.
+    Invalid,
+    /// The platform, compiler or arguments are not supported.
+    Unsupported,
+    /// Running the compiler failed or timed out.
+    Compiler,
+}
+
+impl VyperVmError {
+    /// The category of the error.
+    pub fn category(&self) -> ErrorCategory {
+        use VyperVmError::*;
+        match self {
+            ReqwestError(_) | UnsuccessfulResponse(..) | RateLimited { .. } | Offline(_) => {
+                ErrorCategory::Network
+            }
+            UnknownVersion
+            | NoStableVersion
+            | UnknownProfile(_)
+            | UnsupportedVersion(..)
+            | VersionNotInstalled(_)
+            | VersionNotInTrash(_)
+            | HomeNotFound => ErrorCategory::NotFound,
+            ChecksumMismatch(_)
+            | UnknownChecksum(_)
+            | InstallVerificationFailed(..)
+            | InvalidChecksums(_)
+            | UnsignedMirror(_)
+            | UnlistedArtifact(..) => ErrorCategory::Integrity,
+            IoError(_) => ErrorCategory::Io,
+            #[cfg(all(target_os = "windows", target_arch = "x86_64"))]
+            ZipError(_) => ErrorCategory::Io,
+            GlobalVersionNotSet
+            | GlobalVersionChanged(..)
+            | VersionAlreadyInstalled(_)
+            | HomeAlreadyInUse(_) => ErrorCategory::State,
+            InvalidVersionInput(..)
+            | AmbiguousVersion(..)
+            | InvalidDuration(_)
+            | InvalidConfig(_)
+            | InvalidRelocation(_)
+            | InvalidBundle(_)
+            | InvalidToolchainFile(_)
+            | InvalidLockFile(_)
+            | InvalidArtifactName(..)
+            | SerdeJsonError(_)
+            | SemverError(_)
+            | UrlError(_) => ErrorCategory::Invalid,
+            UnsupportedPlatform { .. } | UnsupportedArgument(..) => ErrorCategory::Unsupported,
+            CompilationFailed(..)
+            | CompileTimeout(..)
+            | ProbeFailed(..)
+            | Timeout(..)
+            | CouldNotPatchForNixOs(..) => ErrorCategory::Compiler,
+        }
+    }
+
+    /// Returns true if a request failed, see [`ErrorCategory::Network`].
+    pub fn is_network(&self) -> bool {
+        self.category() == ErrorCategory::Network
+    }
+
+    /// Returns true if something does not exist, see [`ErrorCategory::NotFound`].
+    pub fn is_not_found(&self) -> bool {
+        self.category() == ErrorCategory::NotFound
+    }
+
+    /// Returns true if a binary or manifest could not be verified, see
+    /// [`ErrorCategory::Integrity`].
+    pub fn is_integrity(&self) -> bool {
+        self.category() == ErrorCategory::Integrity
+    }
+
+    /// Returns true if the filesystem failed, see [`ErrorCategory::Io`].
+    pub fn is_io(&self) -> bool {
+        self.category() == ErrorCategory::Io
+    }
+
+    /// Returns true if the VVM home is not in the expected state, see [`ErrorCategory::State`].
+    pub fn is_state(&self) -> bool {
+        self.category() == ErrorCategory::State
+    }
+}
+
+/// Describes when a rate limit resetting at the unix timestamp `reset_at` resets.
+fn resets_in(reset_at: &Option<u64>) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    match reset_at {
+        Some(reset_at) => format!(", resets in {} min", reset_at.saturating_sub(now) / 60 + 1),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn categorizes_errors() {
+        let not_found = VyperVmError::VersionNotInstalled("0.3.10".to_string());
+        assert!(not_found.is_not_found() && !not_found.is_network());
+        assert!(VyperVmError::ChecksumMismatch("0.3.10".to_string()).is_integrity());
+        assert!(VyperVmError::IoError(std::io::Error::from(std::io::ErrorKind::NotFound)).is_io());
+        assert!(VyperVmError::GlobalVersionNotSet.is_state());
+        assert_eq!(
+            VyperVmError::UnsuccessfulResponse(
+                Url::parse("https://api.github.com").unwrap(),
+                StatusCode::FORBIDDEN
+            )
+            .category(),
+            ErrorCategory::Network
+        );
+        let rate_limited = VyperVmError::RateLimited { reset_at: None };
+        assert!(rate_limited.is_network());
+        assert!(rate_limited.to_string().contains("GITHUB_TOKEN"));
+    }
+}
@@ -0,0 +1,134 @@
+use futures_util::stream::Stream;
+use semver::Version;
+use tokio::sync::mpsc::UnboundedReceiver;
+use url::Url;
+
+use std::{
+    fmt::Debug,
+    path::PathBuf,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Progress events emitted while installing a Vyper version.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InstallEvent {
+    /// Release metadata was fetched and the artifact to download was resolved.
+    MetadataFetched {
+        version: Version,
+        artifact: String,
+        url: Url,
+    },
+    /// A chunk of the binary was downloaded. `total` is `None` if the server did not report
+    /// the content length.
+    DownloadProgress { downloaded: u64, total: Option<u64> },
+    /// The downloaded binary is being verified.
+    Verifying,
+    /// The version was installed, with the path to the Vyper binary.
+    Installed(PathBuf),
+    /// The installation failed. This is always the last event of a stream.
+    Failed(String),
+}
+
+/// Stream of [`InstallEvent`]s returned by [`crate::install_stream`].
+///
+/// The stream ends after an [`InstallEvent::Installed`] or [`InstallEvent::Failed`] event.
+#[derive(Debug)]
+pub struct InstallStream {
+    rx: UnboundedReceiver<InstallEvent>,
+}
+
+impl InstallStream {
+    pub(crate) fn new(rx: UnboundedReceiver<InstallEvent>) -> Self {
+        Self { rx }
+    }
+}
+
+impl Stream for InstallStream {
+    type Item = InstallEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+/// Observes a single install, see [`crate::install_with_observer`], e.g. to drive a progress
+/// bar. Implementing [`InstallObserver::on_progress`] is enough to follow the download.
+pub trait InstallObserver {
+    /// `downloaded` bytes of the binary were received, out of `total` if the server reported
+    /// the content length.
+    fn on_progress(&mut self, _downloaded: u64, _total: Option<u64>) {}
+
+    /// Called with every event of the install, forwarding the download progress to
+    /// [`InstallObserver::on_progress`].
+    fn on_event(&mut self, event: &InstallEvent) {
+        if let InstallEvent::DownloadProgress { downloaded, total } = event {
+            self.on_progress(*downloaded, *total);
+        }
+    }
+}
+
+/// Receives the events of every install of a [`crate::DownloadQueue`], e.g. to report the
+/// progress of `vvm install --all` or `vvm sync` somewhere else than the terminal.
+///
+/// Every install ends with an [`InstallEvent::Installed`] or [`InstallEvent::Failed`] event,
+/// including the installs skipped because the version is installed already.
+pub trait Reporter: Debug + Send + Sync {
+    fn on_event(&self, version: &Version, event: &InstallEvent);
+
+    /// Called once, after the last install of the queue ended.
+    fn on_finished(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+
+    #[derive(Default)]
+    struct Progress(Vec<(u64, Option<u64>)>);
+
+    impl InstallObserver for Progress {
+        fn on_progress(&mut self, downloaded: u64, total: Option<u64>) {
+            self.0.push((downloaded, total));
+        }
+    }
+
+    #[test]
+    fn observer_receives_progress() {
+        let mut progress = Progress::default();
+        progress.on_event(&InstallEvent::DownloadProgress {
+            downloaded: 10,
+            total: None,
+        });
+        progress.on_event(&InstallEvent::Verifying);
+        assert_eq!(progress.0, vec![(10, None)]);
+    }
+
+    #[tokio::test]
+    async fn stream_yields_events_in_order() {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        tx.send(InstallEvent::DownloadProgress {
+            downloaded: 10,
+            total: Some(20),
+        })
+        .unwrap();
+        tx.send(InstallEvent::Verifying).unwrap();
+        tx.send(InstallEvent::Installed(PathBuf::from("vyper-0.3.3")))
+            .unwrap();
+        drop(tx);
+
+        let events = InstallStream::new(rx).collect::<Vec<_>>().await;
+        assert_eq!(
+            events,
+            vec![
+                InstallEvent::DownloadProgress {
+                    downloaded: 10,
+                    total: Some(20)
+                },
+                InstallEvent::Verifying,
+                InstallEvent::Installed(PathBuf::from("vyper-0.3.3")),
+            ]
+        );
+    }
+}
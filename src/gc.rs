@@ -0,0 +1,183 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use semver::Version;
+
+use crate::{
+    binary_path, cache,
+    config::Config,
+    error::VyperVmError,
+    home::active_home,
+    install_path, installed_versions, layout, lock_file_path, lock_home_exclusive,
+    trash::{expire_trash, trashed_versions},
+    try_lock_file_nonblocking, STAGING_DIR_PREFIX,
+};
+
+/// Prefix of the per-version lock files in the VVM home directory.
+pub(crate) const LOCK_FILE_PREFIX: &str = ".lock-vyper-";
+
+/// Summary of everything removed by [`gc`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GcReport {
+    /// Lock files left behind by interrupted installs.
+    pub lock_files: Vec<PathBuf>,
+    /// Version directories whose binary was only partially written, and staged installs that
+    /// never completed.
+    pub partial_downloads: Vec<PathBuf>,
+    /// Version directories without a binary.
+    pub empty_version_dirs: Vec<PathBuf>,
+    /// Source files of the cache entries that were dropped.
+    pub cache_entries: Vec<PathBuf>,
+    /// Removed versions kept in the trash for longer than `trash_days`.
+    pub expired_trash: Vec<PathBuf>,
+    /// Total number of bytes freed on disk.
+    pub reclaimed_bytes: u64,
+}
+
+impl GcReport {
+    /// Returns true if nothing was removed.
+    pub fn is_empty(&self) -> bool {
+        self.lock_files.is_empty()
+            && self.partial_downloads.is_empty()
+            && self.empty_version_dirs.is_empty()
+            && self.cache_entries.is_empty()
+            && self.expired_trash.is_empty()
+    }
+}
+
+/// Removes leftovers from the VVM home directory: orphaned lock files, partial downloads, version
+/// directories without a binary, and cache entries whose source file is gone or whose compiler
+/// version is no longer installed.
+///
+/// Versions that are currently being installed by another process are left untouched.
+pub fn gc() -> Result<GcReport, VyperVmError> {
+    let _home_lock = lock_home_exclusive()?;
+    let mut report = GcReport::default();
+
+    // collect upfront, locking below creates new lock files in the home directory
+    let entries = fs::read_dir(active_home()?)?.collect::<Result<Vec<_>, _>>()?;
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type()?.is_dir() {
+            if let Ok(version) = Version::parse(&name) {
+                gc_version_dir(&version, &mut report)?;
+            } else if name.starts_with(STAGING_DIR_PREFIX) {
+                // left by an install that crashed, running installs hold the home lock
+                report.reclaimed_bytes += dir_size(&entry.path())?;
+                fs::remove_dir_all(entry.path())?;
+                report.partial_downloads.push(entry.path());
+            }
+        } else if name.starts_with(LOCK_FILE_PREFIX) {
+            let path = entry.path();
+            // a lock that can be acquired is not held by any running install
+            if let Some(lock) = try_lock_file_nonblocking(path.clone())? {
+                drop(lock);
+                report.lock_files.push(path);
+            }
+        }
+    }
+
+    gc_cache(&mut report)?;
+    gc_trash(&mut report)?;
+
+    Ok(report)
+}
+
+fn gc_version_dir(version: &Version, report: &mut GcReport) -> Result<(), VyperVmError> {
+    // only holds the installs of other platforms sharing the home
+    if !layout::installed_here(version)? {
+        return Ok(());
+    }
+    let _lock = match try_lock_file_nonblocking(lock_file_path(version)?)? {
+        Some(lock) => lock,
+        // install in progress
+        None => return Ok(()),
+    };
+
+    let install_dir = install_path(version)?;
+    let binary = binary_path(version)?;
+    let size = dir_size(&install_dir)?;
+    match fs::metadata(&binary) {
+        Ok(metadata) if metadata.len() > 0 => return Ok(()),
+        Ok(_) => report.partial_downloads.push(install_dir.clone()),
+        Err(_) => report.empty_version_dirs.push(install_dir.clone()),
+    }
+    layout::remove_install(version)?;
+    report.reclaimed_bytes += size;
+    Ok(())
+}
+
+fn gc_cache(report: &mut GcReport) -> Result<(), VyperVmError> {
+    let cache_path = cache::get_cache_path()?;
+    let mut cache = match cache::VyperFilesCache::read(&cache_path) {
+        Ok(cache) => cache,
+        // nothing to collect if there is no cache yet
+        Err(_) => return Ok(()),
+    };
+
+    let installed = installed_versions()?;
+    let stale = cache
+        .files
+        .iter()
+        .filter(|(file, entry)| {
+            !file.exists()
+                || entry
+                    .version
+                    .as_ref()
+                    .is_some_and(|v| !installed.contains(v))
+        })
+        .map(|(file, _)| file.clone())
+        .collect::<Vec<_>>();
+    if stale.is_empty() {
+        return Ok(());
+    }
+
+    let size_before = fs::metadata(&cache_path)?.len();
+    for file in stale.iter() {
+        cache.files.remove(file);
+    }
+    cache.write(&cache_path)?;
+    let size_after = fs::metadata(&cache_path)?.len();
+
+    report.reclaimed_bytes += size_before.saturating_sub(size_after);
+    report.cache_entries.extend(stale);
+    Ok(())
+}
+
+fn gc_trash(report: &mut GcReport) -> Result<(), VyperVmError> {
+    // without trash_days, the trash is only emptied by hand
+    let ttl_days = match Config::load()?.trash_days {
+        Some(days) => days,
+        None => return Ok(()),
+    };
+    let sizes = trashed_versions()?
+        .into_iter()
+        .map(|entry| Ok((dir_size(&entry.path)?, entry.path)))
+        .collect::<Result<Vec<_>, VyperVmError>>()?;
+    for path in expire_trash(Duration::from_secs(ttl_days * 24 * 60 * 60))? {
+        report.reclaimed_bytes += sizes
+            .iter()
+            .find(|(_, trashed)| *trashed == path)
+            .map_or(0, |(size, _)| *size);
+        report.expired_trash.push(path);
+    }
+    Ok(())
+}
+
+/// Returns the total size of the files in the directory.
+pub(crate) fn dir_size(path: &Path) -> Result<u64, VyperVmError> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else {
+            size += metadata.len();
+        }
+    }
+    Ok(size)
+}
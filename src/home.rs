@@ -0,0 +1,137 @@
+use once_cell::sync::OnceCell;
+use tempfile::TempDir;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::{Config, CONFIG_FILENAME},
+    error::VyperVmError,
+};
+
+/// Environment variable setting the home explicitly, e.g. for services running without `HOME`.
+pub const VVM_HOME_ENV: &str = "VVM_HOME";
+
+/// Home set with [`VvmHome::activate`], or the user home once [`active_home`] is first called.
+static ACTIVE_HOME: OnceCell<VvmHome> = OnceCell::new();
+
+/// A VVM home directory: where versions, the global version, the cache and the config live.
+#[derive(Debug)]
+pub struct VvmHome {
+    path: PathBuf,
+    /// Keeps an ephemeral home alive, the directory is deleted when dropped.
+    _dir: Option<TempDir>,
+}
+
+impl VvmHome {
+    /// The home of the user: the directory set with `VVM_HOME`, else `~/.vvm` or where it was
+    /// moved to by [`relocate_home`](crate::relocate_home).
+    pub fn user() -> Result<Self, VyperVmError> {
+        if let Some(path) = std::env::var_os(VVM_HOME_ENV).filter(|path| !path.is_empty()) {
+            return Ok(Self::at(path));
+        }
+        let default = Self::default_path()?;
+        let path = Config::read(&default.join(CONFIG_FILENAME))?
+            .home
+            .unwrap_or(default);
+        Ok(Self { path, _dir: None })
+    }
+
+    /// The default home of the user, `~/.vvm`. Fails with [`VyperVmError::HomeNotFound`] when
+    /// the user home directory cannot be detected.
+    pub fn default_path() -> Result<PathBuf, VyperVmError> {
+        let home = ::home::home_dir().ok_or(VyperVmError::HomeNotFound)?;
+        Ok(home.join(".vvm"))
+    }
+
+    /// A home at an explicit path, which works without a user home directory.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            _dir: None,
+        }
+    }
+
+    /// A fresh home in a new `vvm-home-*` temporary directory, deleted with all installs when
+    /// the returned value is dropped. Use it to sandbox installs, e.g. in tests, together with
+    /// [`VvmHome::activate`].
+    pub fn ephemeral() -> Result<Self, VyperVmError> {
+        let dir = tempfile::Builder::new().prefix("vvm-home-").tempdir()?;
+        let path = dir.path().join(".vvm");
+        fs::create_dir_all(&path)?;
+        Ok(Self {
+            path,
+            _dir: Some(dir),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Makes this the home of every vvm function in the process. It must be called before any
+    /// of them, fails with [`VyperVmError::HomeAlreadyInUse`] otherwise.
+    ///
+    /// An activated ephemeral home lives until the end of the process.
+    pub fn activate(self) -> Result<&'static Path, VyperVmError> {
+        ACTIVE_HOME.set(self).map_err(|_| {
+            VyperVmError::HomeAlreadyInUse(
+                ACTIVE_HOME
+                    .get()
+                    .map(|home| home.path.clone())
+                    .unwrap_or_default(),
+            )
+        })?;
+        Ok(ACTIVE_HOME.get().expect("home was just set").path())
+    }
+}
+
+/// Returns the home of every vvm function in the process: the activated home if any, the user
+/// home otherwise.
+///
+/// Fails with [`VyperVmError::HomeNotFound`] when no home is activated, `VVM_HOME` is not set and
+/// the user home directory cannot be detected. Every path of the home, e.g.
+/// [`binary_path`](crate::binary_path), returns this error.
+pub fn active_home() -> Result<&'static Path, VyperVmError> {
+    ACTIVE_HOME
+        .get_or_try_init(VvmHome::user)
+        .map(VvmHome::path)
+}
+
+/// Makes `path` the home of every vvm function in the process, like
+/// `VvmHome::at(path).activate()`, e.g. for tools embedding vvm with their own data directory.
+/// It must be called before any of them, see [`VvmHome::activate`].
+pub fn set_home_dir(path: impl Into<PathBuf>) -> Result<&'static Path, VyperVmError> {
+    VvmHome::at(path).activate()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ephemeral_home_is_removed_on_drop() {
+        let sandbox = crate::testing::sandbox().unwrap();
+        let home = VvmHome::ephemeral().unwrap();
+        let path = home.path().to_path_buf();
+        assert!(path.is_dir());
+        assert!(path.ends_with(".vvm"));
+        drop(home);
+        assert!(!path.exists());
+
+        assert_eq!(VvmHome::at("/srv/vvm").path(), Path::new("/srv/vvm"));
+
+        // the sandbox already activated an ephemeral home
+        assert_eq!(active_home().unwrap(), sandbox.home());
+        assert!(matches!(
+            VvmHome::ephemeral().unwrap().activate(),
+            Err(VyperVmError::HomeAlreadyInUse(_))
+        ));
+        assert!(matches!(
+            set_home_dir("/srv/vvm"),
+            Err(VyperVmError::HomeAlreadyInUse(path)) if path == active_home().unwrap()
+        ));
+    }
+}
@@ -0,0 +1,40 @@
+use crate::config::Config;
+
+/// Whether metadata requests negotiate compressed responses and HTTP/2, unless `legacy_http` is
+/// set for proxies mangling them.
+fn modern_http() -> bool {
+    !Config::load().is_ok_and(|config| config.legacy_http)
+}
+
+/// Builder of the client of metadata requests, e.g. release lists, checksum files and manifests:
+/// gzip and brotli compressed responses, and HTTP/2 when the server offers it. Binaries are
+/// downloaded with plain clients, they don't compress and their progress counts raw bytes.
+pub(crate) fn client_builder() -> reqwest::ClientBuilder {
+    let modern = modern_http();
+    let builder = reqwest::Client::builder().gzip(modern).brotli(modern);
+    if modern {
+        builder
+    } else {
+        builder.http1_only()
+    }
+}
+
+/// The client of metadata requests, see [`client_builder`].
+pub(crate) fn client() -> reqwest::Client {
+    client_builder().build().expect("reqwest::Client::new()")
+}
+
+/// Blocking version of [`client`].
+#[cfg_attr(not(feature = "blocking"), allow(dead_code))]
+pub(crate) fn blocking_client() -> reqwest::blocking::Client {
+    let modern = modern_http();
+    let builder = reqwest::blocking::Client::builder()
+        .gzip(modern)
+        .brotli(modern);
+    let builder = if modern {
+        builder
+    } else {
+        builder.http1_only()
+    };
+    builder.build().expect("reqwest::Client::new()")
+}
@@ -0,0 +1,286 @@
+use semver::{Prerelease, Version, VersionReq};
+
+use crate::{channel::STABLE_CHANNEL, error::VyperVmError};
+
+/// Input resolving to the newest published release, prereleases included.
+pub const LATEST: &str = "latest";
+
+/// A version as typed by the user, parsed leniently so every binary accepts the same inputs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VersionInput {
+    /// The `stable` channel.
+    Stable,
+    /// The newest release, prereleases included and ignoring the rules of `stable`.
+    Latest,
+    /// A full version, e.g. `0.3.10`, `v0.3.10` or `0.1.0b17`.
+    Exact(Version),
+    /// A release line, e.g. `0.3`, resolving to its newest release.
+    Minor { major: u64, minor: u64 },
+    /// A semver requirement, e.g. `^0.3` or `~0.3.7`, resolving to the newest matching release.
+    Requirement(VersionReq),
+}
+
+impl VersionInput {
+    /// Parses a version given by the user: `stable`, `latest`, a version with or without a
+    /// leading `v`, Python style prereleases like `0.1.0b17` or `0.4.0rc1`, `MAJOR.MINOR` for the
+    /// newest release of a line, or a semver requirement starting with an operator.
+    pub fn parse(input: &str) -> Result<Self, VyperVmError> {
+        let input = input.trim();
+        if input == STABLE_CHANNEL {
+            return Ok(VersionInput::Stable);
+        }
+        if input == LATEST {
+            return Ok(VersionInput::Latest);
+        }
+        let invalid = |hint: &str| {
+            Err(VyperVmError::InvalidVersionInput(
+                input.to_string(),
+                hint.to_string(),
+            ))
+        };
+        if matches!(input, "newest" | "current") {
+            return invalid("use `stable` or `latest` for the newest release");
+        }
+        if input.starts_with(['^', '~', '=', '<', '>', '*']) {
+            return VersionReq::parse(input)
+                .map(VersionInput::Requirement)
+                .or_else(|err| invalid(&err.to_string()));
+        }
+        let version = input.strip_prefix(['v', 'V']).unwrap_or(input);
+        if let Ok(version) = Version::parse(version) {
+            return Ok(VersionInput::Exact(version));
+        }
+
+        let (release, pre) = split_python_prerelease(version);
+        let parts = release
+            .split('.')
+            .map(|part| part.parse::<u64>().ok())
+            .collect::<Option<Vec<_>>>();
+        match (parts.as_deref(), pre) {
+            (Some([major, minor, patch]), Some(pre)) => {
+                let mut version = Version::new(*major, *minor, *patch);
+                version.pre = Prerelease::new(&pre).map_err(|_| {
+                    VyperVmError::InvalidVersionInput(input.to_string(), "bad prerelease".into())
+                })?;
+                Ok(VersionInput::Exact(version))
+            }
+            (Some([major, minor]), None) => Ok(VersionInput::Minor {
+                major: *major,
+                minor: *minor,
+            }),
+            (Some([major]), None) => invalid(&format!(
+                "a major version is ambiguous, add the minor version, e.g. {}.3",
+                major
+            )),
+            _ => invalid("expected e.g. 0.3.10, v0.3.10, 0.3, ^0.3 or stable"),
+        }
+    }
+
+    /// Picks the version the input designates among `available`: itself if exact, else the newest
+    /// release of the line or matching the requirement, prereleases of a line only if it has
+    /// nothing else.
+    ///
+    /// Fails with the release lines of `available` as suggestions if none matches.
+    pub fn resolve(&self, available: &[Version]) -> Result<Version, VyperVmError> {
+        let (major, minor) = match self {
+            VersionInput::Exact(version) => return Ok(version.clone()),
+            VersionInput::Latest => {
+                return available
+                    .iter()
+                    .max()
+                    .cloned()
+                    .ok_or_else(|| no_release(LATEST, available))
+            }
+            VersionInput::Requirement(req) => {
+                return available
+                    .iter()
+                    .filter(|version| req.matches(version))
+                    .max()
+                    .cloned()
+                    .ok_or_else(|| no_release(&req.to_string(), available))
+            }
+            VersionInput::Stable => {
+                return available
+                    .iter()
+                    .filter(|version| version.pre.is_empty())
+                    .max()
+                    .cloned()
+                    .ok_or(VyperVmError::NoStableVersion)
+            }
+            VersionInput::Minor { major, minor } => (*major, *minor),
+        };
+        let line = available
+            .iter()
+            .filter(|version| version.major == major && version.minor == minor);
+        let newest = line
+            .clone()
+            .filter(|version| version.pre.is_empty())
+            .max()
+            .or_else(|| line.max());
+        newest
+            .cloned()
+            .ok_or_else(|| no_release(&format!("{}.{}", major, minor), available))
+    }
+}
+
+/// Picks the version `prefix` abbreviates, e.g. `0.3.1` for 0.3.10 when there is no 0.3.1, among
+/// the `installed` versions first and then the `available` ones. With a trailing `x` or `*`, e.g.
+/// `0.3.1x`, the newest match is picked.
+///
+/// Returns `None` if nothing matches, and fails listing the candidates if several do.
+pub fn match_prefix(
+    prefix: &str,
+    installed: &[Version],
+    available: &[Version],
+) -> Result<Option<Version>, VyperVmError> {
+    let input = prefix.trim();
+    let prefix = input.strip_prefix(['v', 'V']).unwrap_or(input);
+    let (prefix, newest) = match prefix.strip_suffix(['x', '*']) {
+        Some(prefix) => (prefix, true),
+        None => (prefix, false),
+    };
+    if prefix.is_empty() {
+        return Ok(None);
+    }
+    for versions in [installed, available] {
+        let mut matches = versions
+            .iter()
+            .filter(|version| version.to_string().starts_with(prefix))
+            .collect::<Vec<_>>();
+        matches.sort();
+        matches.dedup();
+        match matches.as_slice() {
+            [] => continue,
+            [.., last] if newest => return Ok(Some((*last).clone())),
+            [version] => return Ok(Some((*version).clone())),
+            candidates => {
+                return Err(VyperVmError::AmbiguousVersion(
+                    input.to_string(),
+                    candidates
+                        .iter()
+                        .map(|version| version.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                ))
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// The error of `input` designating none of `available`, suggesting their release lines.
+fn no_release(input: &str, available: &[Version]) -> VyperVmError {
+    let mut lines = available
+        .iter()
+        .map(|version| (version.major, version.minor))
+        .collect::<Vec<_>>();
+    lines.sort_unstable();
+    lines.dedup();
+    VyperVmError::InvalidVersionInput(
+        input.to_string(),
+        format!(
+            "no matching release, available lines: {}",
+            lines
+                .iter()
+                .map(|(major, minor)| format!("{}.{}", major, minor))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    )
+}
+
+/// Splits a Python style prerelease like `0.1.0b17` into `0.1.0` and the semver prerelease
+/// `beta.17`, as Vyper tagged its early releases.
+fn split_python_prerelease(version: &str) -> (&str, Option<String>) {
+    for (marker, name) in [("rc", "rc"), ("b", "beta"), ("a", "alpha")] {
+        if let Some((release, number)) = version.split_once(marker) {
+            if !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()) {
+                return (release, Some(format!("{}.{}", name, number)));
+            }
+        }
+    }
+    (version, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_lenient_versions() {
+        let exact = |version: &str| VersionInput::Exact(Version::parse(version).unwrap());
+        assert_eq!(VersionInput::parse("v0.3.10").unwrap(), exact("0.3.10"));
+        assert_eq!(VersionInput::parse(" 0.3.10 ").unwrap(), exact("0.3.10"));
+        assert_eq!(
+            VersionInput::parse("0.1.0b17").unwrap(),
+            exact("0.1.0-beta.17")
+        );
+        assert_eq!(
+            VersionInput::parse("v0.4.0rc1").unwrap(),
+            exact("0.4.0-rc.1")
+        );
+        assert_eq!(VersionInput::parse("stable").unwrap(), VersionInput::Stable);
+        assert_eq!(VersionInput::parse("latest").unwrap(), VersionInput::Latest);
+        assert_eq!(
+            VersionInput::parse("~0.3.7").unwrap(),
+            VersionInput::Requirement(VersionReq::parse("~0.3.7").unwrap())
+        );
+        for invalid in ["0", "newest", "0.3.x", "0.3.10.1", "^0.3.0.1", ""] {
+            assert!(matches!(
+                VersionInput::parse(invalid),
+                Err(VyperVmError::InvalidVersionInput(..))
+            ));
+        }
+
+        let available = ["0.2.16", "0.3.9", "0.3.10", "0.4.0-rc.1"]
+            .iter()
+            .map(|version| Version::parse(version).unwrap())
+            .collect::<Vec<_>>();
+        let resolve = |input: &str| VersionInput::parse(input).unwrap().resolve(&available);
+        assert_eq!(resolve("0.3").unwrap(), Version::new(0, 3, 10));
+        assert_eq!(
+            resolve("v0.4").unwrap(),
+            Version::parse("0.4.0-rc.1").unwrap()
+        );
+        assert_eq!(resolve("^0.3").unwrap(), Version::new(0, 3, 10));
+        assert_eq!(resolve("~0.3.7").unwrap(), Version::new(0, 3, 10));
+        assert_eq!(resolve("<0.3.10").unwrap(), Version::new(0, 3, 9));
+        assert_eq!(
+            resolve("latest").unwrap(),
+            Version::parse("0.4.0-rc.1").unwrap()
+        );
+        assert!(resolve("^0.5").is_err());
+        assert!(matches!(
+            resolve("0.5"),
+            Err(VyperVmError::InvalidVersionInput(_, hint)) if hint.ends_with("0.2, 0.3, 0.4")
+        ));
+    }
+
+    #[test]
+    fn matches_version_prefixes() {
+        let versions = |versions: &[&str]| {
+            versions
+                .iter()
+                .map(|version| Version::parse(version).unwrap())
+                .collect::<Vec<_>>()
+        };
+        let installed = versions(&["0.2.16", "0.3.10"]);
+        let available = versions(&["0.2.15", "0.2.16", "0.3.9", "0.3.10"]);
+        let prefix = |prefix: &str| match_prefix(prefix, &installed, &available);
+
+        assert_eq!(prefix("0.3.1").unwrap(), Some(Version::new(0, 3, 10)));
+        assert_eq!(prefix("v0.3.9").unwrap(), Some(Version::new(0, 3, 9)));
+        assert_eq!(prefix("0.4").unwrap(), None);
+        // several installed versions, or none but several available, need more digits
+        assert_eq!(prefix("0.2.1x").unwrap(), Some(Version::new(0, 2, 16)));
+        let installed = versions(&["0.3.1", "0.3.10"]);
+        assert!(matches!(
+            match_prefix("0.3.1", &installed, &available),
+            Err(VyperVmError::AmbiguousVersion(_, candidates)) if candidates == "0.3.1, 0.3.10"
+        ));
+        assert!(matches!(
+            match_prefix("0.2.1", &[], &available),
+            Err(VyperVmError::AmbiguousVersion(..))
+        ));
+    }
+}
@@ -0,0 +1,274 @@
+use semver::Version;
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    binary_name, binary_path,
+    config::Config,
+    descriptor::{EnvDescriptor, ENV_DESCRIPTOR_FILENAME},
+    error::VyperVmError,
+    installed_versions, lock_home_exclusive,
+    platform::{platform, Platform},
+    version_path,
+};
+
+/// Prefixes of the platform subdirectories of a version directory, see [`platform_dir_name`].
+const PLATFORM_DIR_PREFIXES: &[&str] = &["linux-", "darwin-", "windows-"];
+
+/// Directory the files of a flat install are gathered in while it is migrated, see
+/// [`migrate_to_platform_dirs`].
+const MIGRATING_DIRNAME: &str = ".migrating";
+
+/// Name of the subdirectory of a version directory holding the binary of this machine in a home
+/// shared across platforms, e.g. `darwin-arm64` or `linux-amd64`.
+pub fn platform_dir_name() -> String {
+    let os = match platform() {
+        Platform::Unsupported => env::consts::OS.to_string(),
+        platform => platform.to_string(),
+    };
+    let arch = match env::consts::ARCH {
+        "aarch64" => "arm64",
+        "x86_64" => "amd64",
+        arch => arch,
+    };
+    format!("{}-{}", os, arch)
+}
+
+fn is_platform_dir(path: &Path) -> bool {
+    path.is_dir()
+        && path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| PLATFORM_DIR_PREFIXES.iter().any(|p| name.starts_with(p)))
+}
+
+/// Platform subdirectories of a version directory, none in the flat layout.
+pub(crate) fn platform_dirs(version_dir: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(version_dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| is_platform_dir(path))
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+/// Derive path to the directory holding the binary of `version` for this machine: its platform
+/// subdirectory if it has one, e.g. `~/.vvm/0.3.10/darwin-arm64`, else the version directory.
+pub fn install_path(version: &Version) -> Result<PathBuf, VyperVmError> {
+    let version_dir = version_path(version.to_string().as_str())?;
+    let platform_dir = version_dir.join(platform_dir_name());
+    Ok(if platform_dir.is_dir() {
+        platform_dir
+    } else {
+        version_dir
+    })
+}
+
+/// Where a new install of `version` is moved to: its platform subdirectory with
+/// `platform_dirs = true`, or when another machine sharing the home namespaced it already.
+pub(crate) fn new_install_path(version: &Version) -> Result<PathBuf, VyperVmError> {
+    let version_dir = version_path(version.to_string().as_str())?;
+    let namespaced = Config::load().is_ok_and(|config| config.platform_dirs)
+        || !platform_dirs(&version_dir).is_empty();
+    Ok(if namespaced {
+        version_dir.join(platform_dir_name())
+    } else {
+        version_dir
+    })
+}
+
+/// Whether the directory of `version` holds a binary for this machine, and not only for the
+/// other platforms sharing the home.
+pub(crate) fn installed_here(version: &Version) -> Result<bool, VyperVmError> {
+    let version_dir = version_path(version.to_string().as_str())?;
+    Ok(version_dir.join(platform_dir_name()).is_dir()
+        || version_dir.join(binary_name(version)).is_file()
+        || platform_dirs(&version_dir).is_empty())
+}
+
+/// The files of the install of `version` for this machine: its platform subdirectory, or the
+/// entries of a flat version directory but the subdirectories of other platforms sharing it.
+fn install_entries(version: &Version) -> Result<Vec<PathBuf>, VyperVmError> {
+    let install_dir = install_path(version)?;
+    if install_dir != version_path(version.to_string().as_str())? {
+        return Ok(vec![install_dir]);
+    }
+    let mut entries = vec![];
+    for entry in fs::read_dir(&install_dir)? {
+        let path = entry?.path();
+        if !is_platform_dir(&path) {
+            entries.push(path);
+        }
+    }
+    Ok(entries)
+}
+
+/// Deletes the install of `version` for this machine, leaving the installs of other platforms
+/// sharing its version directory.
+pub(crate) fn remove_install(version: &Version) -> Result<(), VyperVmError> {
+    for path in install_entries(version)? {
+        if path.is_dir() {
+            fs::remove_dir_all(&path)?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+    remove_empty_version_dir(version);
+    Ok(())
+}
+
+/// Moves the install of `version` for this machine to the directory `target`, leaving the
+/// installs of other platforms sharing its version directory.
+pub(crate) fn move_install(version: &Version, target: &Path) -> Result<(), VyperVmError> {
+    let install_dir = install_path(version)?;
+    let entries = install_entries(version)?;
+    if entries == [install_dir] {
+        fs::rename(&entries[0], target)?;
+    } else {
+        fs::create_dir_all(target)?;
+        for path in entries {
+            fs::rename(
+                &path,
+                target.join(path.file_name().expect("entry has a name")),
+            )?;
+        }
+    }
+    remove_empty_version_dir(version);
+    Ok(())
+}
+
+/// Removes the version directory of `version` once the install of the last platform sharing it
+/// is gone.
+pub(crate) fn remove_empty_version_dir(version: &Version) {
+    // fails, as it should, while other platforms are installed
+    if let Ok(version_dir) = version_path(version.to_string().as_str()) {
+        let _ = fs::remove_dir(version_dir);
+    }
+}
+
+/// Moves the flat installs of this machine into their platform subdirectory, e.g.
+/// `~/.vvm/0.3.10/vyper-0.3.10` to `~/.vvm/0.3.10/darwin-arm64/vyper-0.3.10`, so machines of
+/// other platforms can install the same versions in a shared home. Installs whose environment
+/// descriptor names another platform are left alone.
+///
+/// Returns the migrated versions.
+pub fn migrate_to_platform_dirs() -> Result<Vec<Version>, VyperVmError> {
+    let _home_lock = lock_home_exclusive()?;
+    let mut migrated = vec![];
+    for version in installed_versions()? {
+        let version_dir = version_path(version.to_string().as_str())?;
+        let platform_dir = version_dir.join(platform_dir_name());
+        if platform_dir.exists() || !version_dir.join(binary_name(&version)).is_file() {
+            continue;
+        }
+        let descriptor = fs::read(version_dir.join(ENV_DESCRIPTOR_FILENAME))
+            .ok()
+            .and_then(|content| serde_json::from_slice::<EnvDescriptor>(&content).ok());
+        if descriptor
+            .as_ref()
+            .is_some_and(|descriptor| descriptor.platform != platform().to_string())
+        {
+            continue;
+        }
+
+        // the binary is moved last, so lookups find it until the platform directory replaces it
+        let binary = version_dir.join(binary_name(&version));
+        let migrating = version_dir.join(MIGRATING_DIRNAME);
+        fs::create_dir_all(&migrating)?;
+        for entry in fs::read_dir(&version_dir)? {
+            let path = entry?.path();
+            if path == migrating || path == binary || is_platform_dir(&path) {
+                continue;
+            }
+            fs::rename(
+                &path,
+                migrating.join(path.file_name().expect("entry has a name")),
+            )?;
+        }
+        fs::rename(&binary, migrating.join(binary_name(&version)))?;
+        fs::rename(&migrating, &platform_dir)?;
+
+        if let Some(mut descriptor) = descriptor {
+            descriptor.path = binary_path(&version)?;
+            fs::write(
+                platform_dir.join(ENV_DESCRIPTOR_FILENAME),
+                serde_json::to_vec_pretty(&descriptor)?,
+            )?;
+        }
+        migrated.push(version);
+    }
+    Ok(migrated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_flat_installs_to_platform_dirs() {
+        let version = Version::new(0, 0, 19);
+        crate::testing::sandbox().unwrap();
+        crate::setup_version(version.to_string().as_str()).unwrap();
+        let version_dir = version_path(version.to_string().as_str()).unwrap();
+        fs::write(binary_path(&version).unwrap(), b"fake vyper").unwrap();
+        assert_eq!(install_path(&version).unwrap(), version_dir);
+
+        // another platform sharing the home does not hide the flat install
+        let other = version_dir.join("windows-riscv64");
+        fs::create_dir_all(&other).unwrap();
+        assert!(installed_here(&version).unwrap());
+
+        assert!(migrate_to_platform_dirs().unwrap().contains(&version));
+        let platform_dir = version_dir.join(platform_dir_name());
+        assert_eq!(install_path(&version).unwrap(), platform_dir);
+        assert_eq!(
+            binary_path(&version).unwrap(),
+            platform_dir.join(binary_name(&version))
+        );
+        assert_eq!(
+            fs::read(binary_path(&version).unwrap()).unwrap(),
+            b"fake vyper"
+        );
+        assert!(other.is_dir());
+        assert!(installed_versions().unwrap().contains(&version));
+
+        fs::remove_dir_all(&platform_dir).unwrap();
+        assert!(!installed_here(&version).unwrap());
+        assert!(!installed_versions().unwrap().contains(&version));
+        fs::remove_dir_all(&version_dir).unwrap();
+    }
+
+    #[test]
+    fn removes_only_the_install_of_this_platform() {
+        let version = Version::new(0, 0, 25);
+        crate::testing::sandbox().unwrap();
+        crate::setup_version(version.to_string().as_str()).unwrap();
+        let version_dir = version_path(version.to_string().as_str()).unwrap();
+        fs::write(binary_path(&version).unwrap(), b"fake vyper").unwrap();
+        let others = [
+            version_dir.join("windows-riscv64"),
+            version_dir.join("linux-mips"),
+        ];
+        for other in &others {
+            fs::create_dir_all(other).unwrap();
+            fs::write(other.join(binary_name(&version)), b"other vyper").unwrap();
+        }
+
+        crate::remove_version(&version).unwrap();
+        assert!(!version_dir.join(binary_name(&version)).exists());
+        for other in &others {
+            assert!(other.join(binary_name(&version)).is_file());
+        }
+        assert!(!installed_versions().unwrap().contains(&version));
+
+        for other in &others {
+            fs::remove_dir_all(other).unwrap();
+        }
+        remove_empty_version_dir(&version);
+        assert!(!version_dir.exists());
+    }
+}
@@ -1,12 +1,13 @@
-use once_cell::sync::Lazy;
+//! Vyper Compiler Version Manager. Tools embedding vvm should depend on [`api`], the stable
+//! surface of the crate, the rest serves the `vvm` and `vyper` binaries.
+
 use semver::Version;
-// use sha2::Digest;
+use sha2::Digest;
 
 use std::{
-    ffi::OsString,
     fs,
     io::{Cursor, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use std::time::Duration;
@@ -14,35 +15,234 @@ use std::time::Duration;
 #[cfg(target_family = "unix")]
 use std::{fs::Permissions, os::unix::fs::PermissionsExt};
 
+pub mod api;
+
+mod bundle;
+#[doc(hidden)]
+pub use bundle::{create_bundle, install_bundle, BundleEntry, BundleManifest};
+
+mod cache;
+#[doc(hidden)]
+pub use cache::{
+    get_cache_path, ArtifactMetadata, CacheEntry, CacheKey, CompileSettings, VyperFilesCache,
+    CACHEABLE_FORMATS, VYPER_FILES_CACHE_FILENAME,
+};
+
+mod capabilities;
+#[doc(hidden)]
+pub use capabilities::{capabilities, CompilerCapabilities};
+
+mod channel;
+#[doc(hidden)]
+pub use channel::{
+    resolve_requirement, resolve_version, stable_release, stable_version, StableRules,
+    STABLE_CHANNEL,
+};
+
+mod checksums;
+#[doc(hidden)]
+pub use checksums::{
+    checksums_path, generate_checksums, is_insecure, set_insecure, update_checksums,
+    ArtifactChecksum, ChecksumManifest, CHECKSUMS_FILENAME, CHECKSUMS_URL,
+};
+
+mod compile;
+#[doc(hidden)]
+pub use compile::{
+    compile_files, compile_jobs, compile_project, compile_timeout, exec_each, output_with_timeout,
+    project_sources, write_compile_commands, CompileCommand, CompileOutput, ExecOutput,
+    OutputStream, COMPILE_COMMANDS_FILENAME, HEARTBEAT_INTERVAL,
+};
+
+mod config;
+#[doc(hidden)]
+pub use config::{config_path, Config, MirrorConfig, ProfileConfig, PromptPolicy, CONFIG_FILENAME};
+
+mod descriptor;
+#[doc(hidden)]
+pub use descriptor::{
+    env_descriptor, env_descriptor_path, home_descriptor, EnvDescriptor, HomeDescriptor,
+    ENV_DESCRIPTOR_FILENAME,
+};
+
+mod diff;
+#[doc(hidden)]
+pub use diff::{diff_output, CompilerOutput, OutputDiff};
+
+mod digest;
+#[doc(hidden)]
+pub use digest::{Digests, HashAlgorithm};
+
+mod doctor;
+#[doc(hidden)]
+pub use doctor::{
+    local_diagnostics, network_diagnostics, repair_home, CheckStatus, DoctorCheck, HomeRepair,
+};
+
 mod error;
-pub use error::VyperVmError;
+#[doc(hidden)]
+pub use error::{ErrorCategory, VyperVmError};
+
+mod gc;
+#[doc(hidden)]
+pub use gc::{gc, GcReport};
+
+mod events;
+#[doc(hidden)]
+pub use events::{InstallEvent, InstallObserver, InstallStream, Reporter};
+
+mod home;
+#[doc(hidden)]
+pub use home::{active_home, set_home_dir, VvmHome, VVM_HOME_ENV};
+
+mod http;
+
+mod input;
+#[doc(hidden)]
+pub use input::{match_prefix, VersionInput, LATEST};
+
+mod layout;
+#[doc(hidden)]
+pub use layout::{install_path, migrate_to_platform_dirs, platform_dir_name};
+
+mod listing;
+#[doc(hidden)]
+pub use listing::{version_listing, VersionEntry, VersionListing, LIST_JSON_SCHEMA};
+
+mod lock;
+#[doc(hidden)]
+pub use lock::{find_lock_file, LockStatus, VersionLock, LOCK_FILENAME};
+
+mod manifest;
+#[doc(hidden)]
+pub use manifest::{
+    audit_origins, last_used, manifest_path, record_last_used, VersionManifest, VersionOrigin,
+    MANIFEST_FILENAME,
+};
+
+mod metrics;
+#[doc(hidden)]
+pub use metrics::{http_metrics, HttpMetrics};
+
+mod notify;
+#[doc(hidden)]
+pub use notify::DesktopNotifier;
+
+mod pin;
+#[doc(hidden)]
+pub use pin::{
+    find_pin_file, pinned_version, read_pin_file, resolve_for_dir, ResolvedVersion, VersionSource,
+    PIN_FILE_NAMES,
+};
+
+mod plan;
+#[doc(hidden)]
+pub use plan::{
+    apply, plan, plan_install, plan_remove, resolve_artifact, InstallPlan, RemoveEntry, RemovePlan,
+    ResolvedArtifact, TaskPlan,
+};
 
 mod platform;
-pub use platform::{platform, Platform};
+#[doc(hidden)]
+pub use platform::{platform, supported_platform, Platform, SUPPORTED_PLATFORMS};
+
+mod politeness;
+#[doc(hidden)]
+pub use politeness::{GithubPoliteness, DEFAULT_GITHUB_MAX_REQUESTS};
+
+mod probe;
+#[doc(hidden)]
+pub use probe::{probe_version, VersionProbe};
+
+mod profile;
+#[doc(hidden)]
+pub use profile::{active_profile, profile_versions, use_profile, ProfileVersions};
+
+mod prune;
+#[doc(hidden)]
+pub use prune::{
+    orphaned_versions, parse_duration, unused_versions, OrphanReason, OrphanedVersion,
+};
+
+mod queue;
+#[doc(hidden)]
+pub use queue::{DownloadQueue, DEFAULT_DOWNLOAD_JOBS, DEFAULT_MAX_CONCURRENT_INSTALLS};
 
 mod releases;
-pub use releases::{all_releases, Releases};
+#[doc(hidden)]
+pub use releases::{
+    all_releases, fresh_releases, is_offline, offline_releases, release_for_version,
+    release_snapshot, releases_cache_path, set_artifact_selector, set_offline, ArtifactSelector,
+    Releases, UnmatchedRelease, ARTIFACT_SELECTOR_ENV, DEFAULT_MAX_RELEASE_PAGES,
+    RELEASES_CACHE_FILENAME,
+};
+
+mod relocate;
+#[doc(hidden)]
+pub use relocate::relocate_home;
+
+mod report;
+#[doc(hidden)]
+pub use report::{BuildReport, FileReport};
+
+mod retry;
+#[doc(hidden)]
+pub use retry::{is_busy, BusyRetry, DEFAULT_BUSY_RETRIES, DEFAULT_BUSY_RETRY_DELAY};
+
+mod sarif;
+#[doc(hidden)]
+pub use sarif::{parse_diagnostics, sarif_log, Diagnostic, DiagnosticLevel};
+
+mod shim;
+#[doc(hidden)]
+pub use shim::{
+    broken_shims, install_wrapper_shim, is_wrapper_shim, regenerate_shims, remove_shims,
+    session_shims, shim_path, shim_target,
+};
+
+mod sources;
+#[doc(hidden)]
+pub use sources::{artifact_urls, source_health_path, SourceHealth, SourceState, GITHUB_SOURCE};
+
+mod stats;
+#[doc(hidden)]
+pub use stats::{
+    record_cache_hit, record_cache_miss, record_install, record_use, stats_path, UsageStats,
+    STATS_FILENAME,
+};
+
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+mod suggest;
+#[doc(hidden)]
+pub use suggest::{suggest_versions, VersionSuggestions};
+
+mod toolchain;
+#[doc(hidden)]
+pub use toolchain::{find_toolchain_file, ToolchainFile, TOOLCHAIN_FILENAME};
+
+mod trash;
+#[doc(hidden)]
+pub use trash::{
+    expire_trash, restore_version, trash_path, trash_version, trashed_versions, TrashEntry,
+    TRASH_DIRNAME,
+};
+
+mod which;
+#[doc(hidden)]
+pub use which::{which_for_dir, which_for_req, WhichVersion};
 
 #[cfg(feature = "blocking")]
-pub use releases::blocking_all_releases;
-
-/// Declare path to Vyper Version Manager's home directory, "~/.vvm" on Unix-based machines.
-pub static VVM_HOME: Lazy<PathBuf> = Lazy::new(|| {
-    cfg_if::cfg_if! {
-        if #[cfg(test)] {
-            let dir = tempfile::tempdir().expect("could not create temp directory");
-            dir.path().join(".vvm")
-        } else {
-            let mut user_home = home::home_dir().expect("could not detect user home directory");
-            user_home.push(".vvm");
-            user_home
-        }
-    }
-});
+#[doc(hidden)]
+pub use releases::{blocking_all_releases, blocking_release_for_version};
 
 /// The timeout to use for requests to the source
 const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
 
+/// Prefix of the directories installs are staged in before being moved into place.
+pub(crate) const STAGING_DIR_PREFIX: &str = ".staging-";
+
 // Installer type that copies binary data to the appropriate Vyper binary file:
 // 1. create target file to copy binary data
 // 2. copy data
@@ -54,12 +254,13 @@ struct Installer {
 }
 
 impl Installer {
-    /// Installs the vyper version at the version specific destination and returns the path to the installed Vyper file.
-    fn install(&self) -> Result<PathBuf, VyperVmError> {
-        let version_path = version_path(self.version.to_string().as_str());
-        let vyper_path = version_path.join(&format!("vyper-{}", self.version));
+    /// Writes the vyper binary to the staging directory `dir` and returns the path it will have
+    /// once the staging directory is moved to `target`.
+    fn install(&self, dir: &Path, target: &Path) -> Result<PathBuf, VyperVmError> {
+        let vyper_path = target.join(binary_name(&self.version));
+        let staged_path = dir.join(binary_name(&self.version));
         // create vyper file.
-        let mut f = fs::File::create(&vyper_path)?;
+        let mut f = fs::File::create(&staged_path)?;
 
         #[cfg(target_family = "unix")]
         f.set_permissions(Permissions::from_mode(0o777))?;
@@ -67,64 +268,251 @@ impl Installer {
         // copy contents over
         let mut content = Cursor::new(&self.binbytes);
         std::io::copy(&mut content, &mut f)?;
+        drop(f);
+
+        // read the binary back, a scanner may still hold it
+        let written = BusyRetry::load().run(|| fs::read(&staged_path))?;
+        if written != self.binbytes {
+            return Err(VyperVmError::ChecksumMismatch(self.version.to_string()));
+        }
 
         Ok(vyper_path)
     }
 }
 
+/// A directory an install is staged in, removed with everything in it when dropped unless it was
+/// moved into place with [`Staging::commit`].
+struct Staging {
+    path: PathBuf,
+}
+
+impl Staging {
+    fn new(version: &Version) -> Result<Self, VyperVmError> {
+        let path = active_home()?.join(format!(
+            "{}{}-{}",
+            STAGING_DIR_PREFIX,
+            version,
+            std::process::id()
+        ));
+        if path.exists() {
+            fs::remove_dir_all(&path)?;
+        }
+        fs::create_dir_all(&path)?;
+        Ok(Self { path })
+    }
+
+    /// Replaces the install directory `target` by the staged one. A previous install is only
+    /// removed once the new one is in place.
+    fn commit(self, target: &Path) -> Result<(), VyperVmError> {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let previous = self.path.with_extension("previous");
+        let replaced = target.exists();
+        if replaced {
+            fs::rename(target, &previous)?;
+        }
+        if let Err(err) = fs::rename(&self.path, target) {
+            if replaced {
+                fs::rename(&previous, target)?;
+            }
+            return Err(err.into());
+        }
+        if replaced {
+            // the new install is complete, failing to clean up the old one is not an error
+            let _ = fs::remove_dir_all(&previous);
+        }
+        // nothing left to clean up
+        std::mem::forget(self);
+        Ok(())
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
 /// Derive path to a specific Vyper version's binary.
-pub fn version_path(version: &str) -> PathBuf {
-    let mut version_path = VVM_HOME.to_path_buf();
-    version_path.push(&version);
-    version_path
+#[doc(hidden)]
+pub fn version_path(version: &str) -> Result<PathBuf, VyperVmError> {
+    Ok(active_home()?.join(version))
+}
+
+/// Derive path to the Vyper binary of a specific version, e.g. `~/.vvm/0.3.3/vyper-0.3.3`, or
+/// `~/.vvm/0.3.3/darwin-arm64/vyper-0.3.3` in a home shared across platforms, see
+/// [`install_path`]. It has an `.exe` extension on Windows so it can be spawned.
+pub fn binary_path(version: &Version) -> Result<PathBuf, VyperVmError> {
+    Ok(install_path(version)?.join(binary_name(version)))
+}
+
+/// File name of the Vyper binary of a specific version.
+pub(crate) fn binary_name(version: &Version) -> String {
+    format!("vyper-{}{}", version, std::env::consts::EXE_SUFFIX)
+}
+
+/// Derive path to VVM's shim directory, the directory users add to their `PATH`.
+#[doc(hidden)]
+pub fn bin_path() -> Result<PathBuf, VyperVmError> {
+    Ok(active_home()?.join("bin"))
 }
 
 /// Derive path to VVM's global version file.
-pub fn global_version_path() -> PathBuf {
-    let mut global_version_path = VVM_HOME.to_path_buf();
-    global_version_path.push(".global-version");
-    global_version_path
+#[doc(hidden)]
+pub fn global_version_path() -> Result<PathBuf, VyperVmError> {
+    Ok(active_home()?.join(".global-version"))
 }
 
 /// Reads the currently set global version for Vyper. Returns None if none has yet been set.
 pub fn current_version() -> Result<Option<Version>, VyperVmError> {
-    let v = fs::read_to_string(global_version_path().as_path())?;
+    let v = fs::read_to_string(global_version_path()?)?;
     Ok(Version::parse(v.trim_end_matches('\n').to_string().as_str()).ok())
 }
 
-/// Sets the provided version as the global version for Vyper.
+/// Sets the provided version as the global version for Vyper, pointing the shims at it.
+#[doc(hidden)]
 pub fn use_version(version: &Version) -> Result<(), VyperVmError> {
-    let mut v = fs::File::create(global_version_path().as_path())?;
-    v.write_all(version.to_string().as_bytes())?;
-    Ok(())
+    let _lock = lock_global_version()?;
+    regenerate_shims(version)?;
+    write_global_version(version.to_string().as_str())
+}
+
+/// Sets `new` as the global version only if the global version is still `expected`, where `None`
+/// means no global version set. Fails with [`VyperVmError::GlobalVersionChanged`] if another
+/// process changed the global version in the meantime.
+#[doc(hidden)]
+pub fn switch_version(expected: Option<&Version>, new: &Version) -> Result<(), VyperVmError> {
+    let _lock = lock_global_version()?;
+    let current = current_version()?;
+    if current.as_ref() != expected {
+        return Err(VyperVmError::GlobalVersionChanged(
+            expected.map_or_else(|| "none".to_string(), |v| v.to_string()),
+            current.map_or_else(|| "none".to_string(), |v| v.to_string()),
+        ));
+    }
+    regenerate_shims(new)?;
+    write_global_version(new.to_string().as_str())
 }
 
 /// Unset the global version. This should be done if all versions are removed.
+#[doc(hidden)]
 pub fn unset_global_version() -> Result<(), VyperVmError> {
-    let mut v = fs::File::create(global_version_path().as_path())?;
-    v.write_all("".as_bytes())?;
+    let _lock = lock_global_version()?;
+    remove_shims()?;
+    write_global_version("")
+}
+
+/// What [`repair_global_version`] did about a global version that is no longer installed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[doc(hidden)]
+pub enum GlobalVersionRepair {
+    /// The newest installed version became the global version.
+    Switched { missing: Version, new: Version },
+    /// No version is installed anymore, the global version was unset.
+    Unset { missing: Version },
+}
+
+impl std::fmt::Display for GlobalVersionRepair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GlobalVersionRepair::Switched { missing, new } => write!(
+                f,
+                "The global version {} is no longer installed, switched to {}",
+                missing, new
+            ),
+            GlobalVersionRepair::Unset { missing } => write!(
+                f,
+                "The global version {} is no longer installed and no other version is, unset it",
+                missing
+            ),
+        }
+    }
+}
+
+/// Repairs the global version when its binary vanished, e.g. deleted by hand or by a cache
+/// cleanup: the newest installed version becomes the global version, or the global version is
+/// unset when no version is installed, as `vvm remove` does for the version in use.
+///
+/// Returns `None` if the global version is installed or not set.
+#[doc(hidden)]
+pub fn repair_global_version() -> Result<Option<GlobalVersionRepair>, VyperVmError> {
+    let missing = match current_version()? {
+        Some(version) if !binary_path(&version)?.exists() => version,
+        _ => return Ok(None),
+    };
+    let _lock = lock_global_version()?;
+    // another process may have repaired it in the meantime
+    if current_version()?.as_ref() != Some(&missing) {
+        return Ok(None);
+    }
+    let mut newest = None;
+    for version in installed_versions()?.into_iter().rev() {
+        if binary_path(&version)?.exists() {
+            newest = Some(version);
+            break;
+        }
+    }
+    match newest {
+        Some(new) => {
+            regenerate_shims(&new)?;
+            write_global_version(new.to_string().as_str())?;
+            Ok(Some(GlobalVersionRepair::Switched { missing, new }))
+        }
+        None => {
+            remove_shims()?;
+            write_global_version("")?;
+            Ok(Some(GlobalVersionRepair::Unset { missing }))
+        }
+    }
+}
+
+/// Locks the global version file exclusively until the returned file is dropped, blocking while
+/// another process holds the lock.
+fn lock_global_version() -> Result<fs::File, VyperVmError> {
+    use fs2::FileExt;
+    // unlike the install lock files, this one is never removed so that every process locks the
+    // same file
+    let lock_file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(active_home()?.join(".lock-global-version"))?;
+    lock_file.lock_exclusive()?;
+    Ok(lock_file)
+}
+
+/// Replaces the global version file atomically, so readers never see a partially written version.
+fn write_global_version(content: &str) -> Result<(), VyperVmError> {
+    let path = global_version_path()?;
+    let tmp_path = active_home()?.join(format!(".global-version.{}", std::process::id()));
+    let mut v = fs::File::create(&tmp_path)?;
+    v.write_all(content.as_bytes())?;
+    fs::rename(tmp_path, path)?;
     Ok(())
 }
 
 /// Reads the list of Vyper versions that have been installed in the machine. The version list is
 /// sorted in ascending order.
 pub fn installed_versions() -> Result<Vec<Version>, VyperVmError> {
-    let home_dir = VVM_HOME.to_path_buf();
-    println!("home_dir {:?}", &home_dir);
+    let home_dir = active_home()?.to_path_buf();
     let mut versions = vec![];
     for v in fs::read_dir(&home_dir)? {
         let v = v?;
-        if v.file_name() != OsString::from(".global-version".to_string()) {
-            versions.push(Version::parse(
-                &v.path()
-                    .file_name()
-                    .ok_or(VyperVmError::UnknownVersion)?
-                    .to_str()
-                    .ok_or(VyperVmError::UnknownVersion)?
-                    .to_string()
-                    .as_str()
-                    .replace("vyper-", ""),
-            )?);
+        // the home directory also holds the global version, lock files, the shim and cache
+        // directories, so only version directories are considered
+        if !v.file_type()?.is_dir() {
+            continue;
+        }
+        if let Some(version) = v
+            .file_name()
+            .to_str()
+            .and_then(|name| Version::parse(name.trim_start_matches("vyper-")).ok())
+        {
+            if layout::installed_here(&version)? {
+                versions.push(version);
+            }
         }
     }
     versions.sort();
@@ -134,6 +522,7 @@ pub fn installed_versions() -> Result<Vec<Version>, VyperVmError> {
 
 /// Blocking version of [`all_versions`]
 #[cfg(feature = "blocking")]
+#[doc(hidden)]
 pub fn blocking_all_versions() -> Result<Vec<Version>, VyperVmError> {
     Ok(releases::blocking_all_releases(platform::platform())?.into_versions())
 }
@@ -149,167 +538,175 @@ pub async fn all_versions() -> Result<Vec<Version>, VyperVmError> {
 /// Blocking version of [`install`]
 #[cfg(feature = "blocking")]
 pub fn blocking_install(version: &Version) -> Result<PathBuf, VyperVmError> {
-    setup_home()?;
-
-    let artifacts = releases::blocking_all_releases(platform::platform())?;
-    let artifact = artifacts
-        .get_artifact(version)
-        .ok_or(VyperVmError::UnknownVersion)?;
-    let download_url =
-        releases::artifact_url(platform::platform(), version, artifact.to_string().as_str())?;
-
-    // TODO: implement checksum for vyper binaries
-    // let checksum = artifacts
-    //     .get_checksum(version)
-    //     .unwrap_or_else(|| panic!("checksum not available: {:?}", version.to_string()));
-
-    let res = reqwest::blocking::Client::builder()
-        .timeout(REQUEST_TIMEOUT)
-        .build()
-        .expect("reqwest::Client::new()")
-        .get(download_url.clone())
-        .send()?;
-
-    if !res.status().is_success() {
-        return Err(VyperVmError::UnsuccessfulResponse(
-            download_url,
-            res.status(),
-        ));
-    }
-
-    let binbytes = res.bytes()?;
-    // TODO: implement checksum for vyper binaries
-    // ensure_checksum(&binbytes, version, checksum)?;
+    struct Silent;
+    impl InstallObserver for Silent {}
+    blocking_install_with_observer(version, &mut Silent)
+}
 
-    // lock file to indicate that installation of this Vyper version will be in progress.
-    let lock_path = lock_file_path(version);
-    // wait until lock file is released, possibly by another parallel thread trying to install the
-    // same version of Vyper.
-    let _lock = try_lock_file(lock_path)?;
+/// Blocking version of [`install_with_observer`]
+#[cfg(feature = "blocking")]
+pub fn blocking_install_with_observer(
+    version: &Version,
+    observer: &mut dyn InstallObserver,
+) -> Result<PathBuf, VyperVmError> {
+    setup_home()?;
 
-    do_install(
-        version.clone(),
-        binbytes.to_vec(),
-        artifact.to_string().as_str(),
-    )
+    plan::blocking_plan_install(version)?
+        .blocking_apply_with_events(|event| observer.on_event(&event))
 }
 
 /// Installs the provided version of Vyper in the machine.
 ///
 /// Returns the path to the Vyper file.
 pub async fn install(version: &Version) -> Result<PathBuf, VyperVmError> {
-    setup_home()?;
+    install_with_events(version, |_| {}).await
+}
 
-    let artifacts = releases::all_releases(platform::platform()).await?;
-    let artifact = artifacts
-        .releases
-        .get(version)
-        .ok_or(VyperVmError::UnknownVersion)?;
-    let download_url =
-        releases::artifact_url(platform::platform(), version, artifact.to_string().as_str())?;
-
-    // TODO: implement checksum for vyper binaries
-    // let checksum = artifacts
-    //     .get_checksum(version)
-    //     .unwrap_or_else(|| panic!("checksum not available: {:?}", version.to_string()));
-
-    let res = reqwest::Client::builder()
-        .timeout(REQUEST_TIMEOUT)
-        .build()
-        .expect("reqwest::Client::new()")
-        .get(download_url.clone())
-        .send()
-        .await?;
-
-    if !res.status().is_success() {
-        return Err(VyperVmError::UnsuccessfulResponse(
-            download_url,
-            res.status(),
-        ));
-    }
+/// Installs the provided version of Vyper in the machine, reporting the download progress and
+/// every other [`InstallEvent`] to `observer` as they happen.
+pub async fn install_with_observer(
+    version: &Version,
+    observer: &mut dyn InstallObserver,
+) -> Result<PathBuf, VyperVmError> {
+    install_with_events(version, |event| observer.on_event(&event)).await
+}
+
+/// Installs the provided versions of Vyper concurrently through a [`DownloadQueue`], running at
+/// most `jobs` installs at a time.
+///
+/// Returns the result of every install, in the order they finished.
+pub async fn install_many(
+    versions: &[Version],
+    jobs: usize,
+) -> Vec<(Version, Result<PathBuf, VyperVmError>)> {
+    DownloadQueue::new(jobs).run(versions).await
+}
 
-    let binbytes = res.bytes().await?;
-    // TODO: implement checksum for vyper binaries
-    // ensure_checksum(&binbytes, version, checksum)?;
+/// Installs the provided version of Vyper in the machine, reporting progress as a stream of
+/// [`InstallEvent`]s.
+///
+/// The installation runs on a spawned task, so this must be called from within a tokio runtime.
+pub fn install_stream(version: &Version) -> InstallStream {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let version = version.clone();
+    tokio::spawn(async move {
+        let result = install_with_events(&version, |event| {
+            let _ = tx.send(event);
+        })
+        .await;
+        if let Err(err) = result {
+            let _ = tx.send(InstallEvent::Failed(err.to_string()));
+        }
+    });
+    InstallStream::new(rx)
+}
 
-    // lock file to indicate that installation of this Vyper version will be in progress.
-    let lock_path = lock_file_path(version);
-    // wait until lock file is released, possibly by another parallel thread trying to install the
-    // same version of Vyper.
-    let _lock = try_lock_file(lock_path)?;
+async fn install_with_events<F>(version: &Version, on_event: F) -> Result<PathBuf, VyperVmError>
+where
+    F: FnMut(InstallEvent),
+{
+    setup_home()?;
 
-    do_install(
-        version.clone(),
-        binbytes.to_vec(),
-        artifact.to_string().as_str(),
-    )
+    plan::plan_install(version)
+        .await?
+        .apply_with_events(on_event)
+        .await
 }
 
+/// Installs `binbytes` as the binary of `version`. The binary, its descriptor and its manifest
+/// are staged first and moved into place together, so a failed install leaves nothing behind.
 fn do_install(
     version: Version,
     binbytes: Vec<u8>,
-    _artifact: &str,
+    artifact: &str,
+    origin: VersionOrigin,
 ) -> Result<PathBuf, VyperVmError> {
-    let installer = {
-        setup_version(version.to_string().as_str())?;
-
-        Installer { version, binbytes }
-    };
-
-    installer.install()
+    let _home_lock = lock_home_shared()?;
+    let staging = Staging::new(&version)?;
+    let target = layout::new_install_path(&version)?;
+    let installer = Installer { version, binbytes };
+
+    let vyper_path = installer.install(&staging.path, &target)?;
+    probe::verify_binary(
+        &staging.path.join(binary_name(&installer.version)),
+        &installer.version,
+    )?;
+    descriptor::write_env_descriptor(
+        &installer.version,
+        &vyper_path,
+        &installer.binbytes,
+        artifact,
+        &staging.path,
+    )?;
+    manifest::write_install_manifest(
+        &installer.version,
+        origin,
+        Digests::compute(&installer.binbytes),
+        &staging.path,
+    )?;
+    staging.commit(&target)?;
+    // usage statistics are best effort and never fail an install
+    let _ = stats::record_install(&installer.version);
+    Ok(vyper_path)
 }
 
 /// Removes the provided version of Vyper from the machine.
 pub fn remove_version(version: &Version) -> Result<(), VyperVmError> {
-    fs::remove_dir_all(version_path(version.to_string().as_str()))?;
-    Ok(())
+    layout::remove_install(version)
 }
 
 /// Setup VVM home directory.
+#[doc(hidden)]
 pub fn setup_home() -> Result<PathBuf, VyperVmError> {
     // create ~/.vvm
-    let home_dir = VVM_HOME.to_path_buf();
+    let home_dir = active_home()?.to_path_buf();
     if !home_dir.as_path().exists() {
         fs::create_dir_all(home_dir.clone())?;
     }
     // create ~/.vvm/.global-version
-    let mut global_version = VVM_HOME.to_path_buf();
-    global_version.push(".global-version");
+    let global_version = global_version_path()?;
     if !global_version.as_path().exists() {
         fs::File::create(global_version.as_path())?;
     }
     Ok(home_dir)
 }
 
+#[cfg(any(test, feature = "testing"))]
 fn setup_version(version: &str) -> Result<(), VyperVmError> {
-    let v = version_path(version);
+    let v = version_path(version)?;
     if !v.exists() {
         fs::create_dir_all(v.as_path())?
     }
     Ok(())
 }
 
-// TODO: implement checksum for vyper binaries
-// fn ensure_checksum(
-//     binbytes: impl AsRef<[u8]>,
-//     version: &Version,
-//     expected_checksum: Vec<u8>,
-// ) -> Result<(), VyperVmError> {
-//     let mut hasher = sha2::Sha256::new();
-//     hasher.update(binbytes);
-//     let cs = &hasher.finalize()[..];
-//     // checksum does not match
-//     if cs != expected_checksum {
-//         return Err(VyperVmError::ChecksumMismatch(version.to_string()));
-//     }
-//     Ok(())
-// }
+/// Computes the SHA-256 digest of the provided bytes.
+pub(crate) fn sha256_digest(binbytes: impl AsRef<[u8]>) -> Vec<u8> {
+    sha2::Sha256::digest(binbytes.as_ref()).to_vec()
+}
+
+/// Fails with [`VyperVmError::ChecksumMismatch`] unless `binbytes` match every known digest of
+/// `expected`.
+pub(crate) fn ensure_checksum(
+    binbytes: impl AsRef<[u8]>,
+    version: &Version,
+    expected: &Digests,
+) -> Result<(), VyperVmError> {
+    match expected.mismatch(binbytes.as_ref()) {
+        Some(algorithm) => {
+            tracing::warn!("{} digest of vyper {} does not match", algorithm, version);
+            Err(VyperVmError::ChecksumMismatch(version.to_string()))
+        }
+        None => Ok(()),
+    }
+}
 
 /// Creates the file and locks it exclusively, this will block if the file is currently locked
 fn try_lock_file(lock_path: PathBuf) -> Result<LockFile, VyperVmError> {
     use fs2::FileExt;
     let _lock_file = fs::OpenOptions::new()
         .create(true)
+        .truncate(false)
         .read(true)
         .write(true)
         .open(&lock_path)?;
@@ -320,6 +717,24 @@ fn try_lock_file(lock_path: PathBuf) -> Result<LockFile, VyperVmError> {
     })
 }
 
+/// Like [`try_lock_file`], but returns `None` instead of blocking if the file is currently locked
+fn try_lock_file_nonblocking(lock_path: PathBuf) -> Result<Option<LockFile>, VyperVmError> {
+    use fs2::FileExt;
+    let _lock_file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(&lock_path)?;
+    if _lock_file.try_lock_exclusive().is_err() {
+        return Ok(None);
+    }
+    Ok(Some(LockFile {
+        lock_path,
+        _lock_file,
+    }))
+}
+
 /// Represents a lockfile that's removed once dropped
 struct LockFile {
     _lock_file: fs::File,
@@ -332,9 +747,50 @@ impl Drop for LockFile {
     }
 }
 
+/// An advisory lock on the whole VVM home directory, released once dropped.
+///
+/// Installs hold it shared, operations removing several versions at once hold it exclusively, so
+/// they never interleave with installs from other processes sharing the same home directory.
+#[doc(hidden)]
+pub struct HomeLock {
+    _lock_file: fs::File,
+}
+
+/// Takes the home lock shared, blocking while a destructive operation is running.
+#[doc(hidden)]
+pub fn lock_home_shared() -> Result<HomeLock, VyperVmError> {
+    use fs2::FileExt;
+    let lock_file = open_home_lock()?;
+    FileExt::lock_shared(&lock_file)?;
+    Ok(HomeLock {
+        _lock_file: lock_file,
+    })
+}
+
+/// Takes the home lock exclusively, blocking until running installs are done.
+#[doc(hidden)]
+pub fn lock_home_exclusive() -> Result<HomeLock, VyperVmError> {
+    use fs2::FileExt;
+    let lock_file = open_home_lock()?;
+    lock_file.lock_exclusive()?;
+    Ok(HomeLock {
+        _lock_file: lock_file,
+    })
+}
+
+// the file is never removed, removing it would let two processes lock different files
+fn open_home_lock() -> Result<fs::File, VyperVmError> {
+    Ok(fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(active_home()?.join(".lock-home"))?)
+}
+
 /// Returns the lockfile to use for a specific file
-fn lock_file_path(version: &Version) -> PathBuf {
-    VVM_HOME.join(&format!(".lock-vyper-{}", version))
+fn lock_file_path(version: &Version) -> Result<PathBuf, VyperVmError> {
+    Ok(active_home()?.join(format!(".lock-vyper-{}", version)))
 }
 
 #[cfg(test)]
@@ -365,8 +821,108 @@ mod tests {
         )
     }
 
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn failed_installs_leave_nothing_behind() {
+        let version = Version::new(0, 0, 13);
+        testing::sandbox().unwrap();
+
+        let staging = Staging::new(&version).unwrap();
+        let staged = staging.path.clone();
+        fs::write(staged.join("partial"), b"fake").unwrap();
+        drop(staging);
+        assert!(!staged.exists());
+        assert!(!version_path("0.0.13").unwrap().exists());
+
+        // installs run the binary to verify it
+        let fake = testing::FakeVyper::new().binary(&version);
+        let other_version = testing::FakeVyper::new().binary(&Version::new(0, 0, 12));
+        let err = do_install(
+            version.clone(),
+            other_version,
+            "vyper.0.0.13",
+            VersionOrigin::LocalFile,
+        )
+        .unwrap_err();
+        assert!(matches!(err, VyperVmError::InstallVerificationFailed(..)));
+        assert!(!version_path("0.0.13").unwrap().exists());
+
+        do_install(
+            version.clone(),
+            fake.clone(),
+            "vyper.0.0.13",
+            VersionOrigin::LocalFile,
+        )
+        .unwrap();
+        assert_eq!(fs::read(binary_path(&version).unwrap()).unwrap(), fake);
+        assert!(env_descriptor_path(&version).unwrap().exists());
+        assert_eq!(
+            VersionManifest::read(&version).unwrap().origin,
+            Some(VersionOrigin::LocalFile)
+        );
+
+        // a reinstall replaces the previous install once complete
+        let new_fake = testing::FakeVyper::new()
+            .version("0.0.13+commit.1")
+            .binary(&version);
+        do_install(
+            version.clone(),
+            new_fake.clone(),
+            "vyper.0.0.13",
+            VersionOrigin::Bundle,
+        )
+        .unwrap();
+        assert_eq!(fs::read(binary_path(&version).unwrap()).unwrap(), new_fake);
+        let staging_prefix = format!("{}{}-", STAGING_DIR_PREFIX, version);
+        assert!(!fs::read_dir(active_home().unwrap())
+            .unwrap()
+            .any(|entry| entry
+                .unwrap()
+                .file_name()
+                .to_string_lossy()
+                .starts_with(&staging_prefix)));
+        remove_version(&version).unwrap();
+    }
+
+    #[test]
+    fn exclusive_home_lock_excludes_installs() {
+        use fs2::FileExt;
+        testing::sandbox().unwrap();
+        let lock = lock_home_exclusive().unwrap();
+        assert!(FileExt::try_lock_shared(&open_home_lock().unwrap()).is_err());
+        drop(lock);
+    }
+
+    #[test]
+    fn test_ensure_checksum() {
+        let version = Version::new(0, 3, 10);
+        let mut expected = Digests::default();
+        expected.insert(HashAlgorithm::Sha256, &sha256_digest(b"vyper"));
+        ensure_checksum(b"vyper", &version, &expected).unwrap();
+        assert!(matches!(
+            ensure_checksum(b"evil vyper", &version, &expected),
+            Err(VyperVmError::ChecksumMismatch(v)) if v == "0.3.10"
+        ));
+    }
+
+    #[test]
+    fn test_switch_version() {
+        testing::sandbox().unwrap();
+        let old = Version::new(0, 0, 2);
+        let new = Version::new(0, 0, 3);
+        use_version(&old).unwrap();
+
+        assert!(matches!(
+            switch_version(Some(&new), &new),
+            Err(VyperVmError::GlobalVersionChanged(_, _))
+        ));
+        switch_version(Some(&old), &new).unwrap();
+        assert_eq!(current_version().unwrap(), Some(new));
+    }
+
     #[tokio::test]
     async fn test_install() {
+        crate::testing::sandbox().unwrap();
         let versions = all_releases(platform())
             .await
             .unwrap()
@@ -380,6 +936,7 @@ mod tests {
     #[ignore]
     #[tokio::test]
     async fn test_installed_versions() {
+        crate::testing::sandbox().unwrap();
         let versions = all_releases(platform())
             .await
             .unwrap()
@@ -389,7 +946,7 @@ mod tests {
         let rand_version = versions.choose(&mut rand::thread_rng()).unwrap();
         assert!(install(rand_version).await.is_ok());
         let installed_versions = installed_versions().unwrap_or_default();
-        assert!(installed_versions.len() > 0);
+        assert!(!installed_versions.is_empty());
         assert!(&installed_versions.contains(rand_version));
     }
 
@@ -405,10 +962,10 @@ mod tests {
 
     #[tokio::test]
     async fn test_version() {
+        crate::testing::sandbox().unwrap();
         let version = "0.3.3".parse().unwrap();
         install(&version).await.unwrap();
-        let vyper_path =
-            version_path(version.to_string().as_str()).join(&format!("vyper-{}", version));
+        let vyper_path = binary_path(&version).unwrap();
         let output = Command::new(&vyper_path)
             .arg("--version")
             .stdin(Stdio::piped())
@@ -427,8 +984,7 @@ mod tests {
     fn blocking_test_version() {
         let version = "0.3.3".parse().unwrap();
         blocking_install(&version).unwrap();
-        let vyper_path =
-            version_path(version.to_string().as_str()).join(&format!("vyper-{}", version));
+        let vyper_path = binary_path(&version).unwrap();
         let output = Command::new(&vyper_path)
             .arg("--version")
             .stdin(Stdio::piped())
@@ -456,6 +1012,7 @@ mod tests {
     #[ignore]
     #[tokio::test(flavor = "multi_thread")]
     async fn can_install_parallel_async() {
+        crate::testing::sandbox().unwrap();
         let version: Version = "0.3.3".parse().unwrap();
         let cloned_version = version.clone();
         let t = tokio::task::spawn(async move { install(&cloned_version).await });
@@ -466,6 +1023,7 @@ mod tests {
     // ensures we can download the latest native Vyper for apple silicon
     #[tokio::test(flavor = "multi_thread")]
     async fn can_download_latest_macos() {
+        crate::testing::sandbox().unwrap();
         let latest: Version = "0.3.3".parse().unwrap();
 
         let artifacts = all_releases(Platform::MacOs).await.unwrap();
@@ -473,13 +1031,15 @@ mod tests {
         let artifact = artifacts.releases.get(&latest).unwrap();
         let download_url =
             artifact_url(Platform::MacOs, &latest, artifact.to_string().as_str()).unwrap();
-        // TODO: implement checksum for vyper binaries
-        // let checksum = artifacts.get_checksum(&latest).unwrap();
+        let checksum = ChecksumManifest::load()
+            .unwrap()
+            .checksum(&latest, artifact);
 
         let resp = reqwest::get(download_url).await.unwrap();
         assert!(resp.status().is_success());
-        let _binbytes = resp.bytes().await.unwrap();
-        // TODO: implement checksum for vyper binaries
-        // ensure_checksum(&binbytes, &latest, checksum).unwrap();
+        let binbytes = resp.bytes().await.unwrap();
+        if let Some(checksum) = checksum {
+            ensure_checksum(&binbytes, &latest, &checksum).unwrap();
+        }
     }
 }
@@ -0,0 +1,157 @@
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use std::{collections::BTreeSet, path::PathBuf};
+
+use crate::{
+    binary_path, current_version,
+    error::VyperVmError,
+    gc::dir_size,
+    install_path, installed_versions,
+    manifest::{last_used, VersionManifest, VersionOrigin},
+    prune::installed_at,
+    stats::UsageStats,
+};
+
+/// JSON Schema of [`VersionListing`], printed by `vvm list --schema`.
+pub const LIST_JSON_SCHEMA: &str = include_str!("schema/list.json");
+
+/// Version of [`LIST_JSON_SCHEMA`], incremented on every backwards incompatible change.
+const LIST_SCHEMA_VERSION: u32 = 1;
+
+/// Machine readable overview of the known versions, the output of `vvm list --json`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionListing {
+    pub schema_version: u32,
+    pub current: Option<Version>,
+    /// Known versions, newest first.
+    pub versions: Vec<VersionEntry>,
+}
+
+/// A single version of a [`VersionListing`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionEntry {
+    pub version: Version,
+    pub installed: bool,
+    pub current: bool,
+    /// Unix timestamp in seconds of the install.
+    pub installed_at: Option<u64>,
+    /// Unix timestamp in seconds of the last run, if known.
+    pub last_used: Option<u64>,
+    pub size_bytes: Option<u64>,
+    /// Absolute path of the installed binary.
+    pub path: Option<PathBuf>,
+    /// Where the installed binary came from, if recorded.
+    pub origin: Option<VersionOrigin>,
+    pub advisories: Vec<String>,
+}
+
+/// Lists the installed versions, and the `remote` ones if provided, keeping the `limit` newest.
+pub fn version_listing(
+    remote: Option<&[Version]>,
+    limit: Option<usize>,
+) -> Result<VersionListing, VyperVmError> {
+    let current = current_version()?;
+    let installed = installed_versions()?;
+    let stats = UsageStats::read()?;
+
+    let mut versions = installed.iter().cloned().collect::<BTreeSet<_>>();
+    versions.extend(remote.unwrap_or_default().iter().cloned());
+
+    let versions = versions
+        .into_iter()
+        .rev()
+        .take(limit.unwrap_or(usize::MAX))
+        .map(|version| {
+            let is_installed = installed.contains(&version);
+            let mut advisories = vec![];
+            if !version.pre.is_empty() {
+                advisories.push("prerelease".to_string());
+            }
+            if remote.is_some_and(|remote| is_installed && !remote.contains(&version)) {
+                advisories.push("not-in-remote".to_string());
+            }
+            Ok(VersionEntry {
+                current: current.as_ref() == Some(&version),
+                installed_at: is_installed.then(|| installed_at(&version)),
+                last_used: last_used(&version, &stats),
+                size_bytes: if is_installed {
+                    Some(dir_size(&install_path(&version)?)?)
+                } else {
+                    None
+                },
+                path: if is_installed {
+                    Some(binary_path(&version)?)
+                } else {
+                    None
+                },
+                origin: if is_installed {
+                    VersionManifest::read(&version)?.origin
+                } else {
+                    None
+                },
+                installed: is_installed,
+                advisories,
+                version,
+            })
+        })
+        .collect::<Result<Vec<_>, VyperVmError>>()?;
+
+    Ok(VersionListing {
+        schema_version: LIST_SCHEMA_VERSION,
+        current,
+        versions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listing_matches_schema() {
+        let schema: serde_json::Value = serde_json::from_str(LIST_JSON_SCHEMA).unwrap();
+        assert_eq!(
+            schema["properties"]["schema_version"]["const"],
+            LIST_SCHEMA_VERSION
+        );
+
+        let listing = VersionListing {
+            schema_version: LIST_SCHEMA_VERSION,
+            current: None,
+            versions: vec![VersionEntry {
+                version: Version::new(0, 3, 3),
+                installed: false,
+                current: false,
+                installed_at: None,
+                last_used: None,
+                size_bytes: None,
+                path: None,
+                origin: None,
+                advisories: vec![],
+            }],
+        };
+        let value = serde_json::to_value(&listing).unwrap();
+        let keys = |value: &serde_json::Value| {
+            value
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect::<BTreeSet<_>>()
+        };
+        let required = |schema: &serde_json::Value| {
+            schema["required"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|key| key.as_str().unwrap().to_string())
+                .collect::<BTreeSet<_>>()
+        };
+        assert_eq!(keys(&value), required(&schema));
+        assert_eq!(
+            keys(&value["versions"][0]),
+            required(&schema["properties"]["versions"]["items"])
+        );
+    }
+}
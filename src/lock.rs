@@ -0,0 +1,162 @@
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    binary_path,
+    digest::{Digests, HashAlgorithm},
+    error::VyperVmError,
+};
+
+/// Name of the file locking the digests of the compilers of a project.
+pub const LOCK_FILENAME: &str = "vvm.lock";
+
+/// The compilers a project was built with and the digests of their binaries, e.g.
+///
+/// ```toml
+/// [versions."0.3.10"]
+/// sha256 = "…"
+/// blake3 = "…"
+/// ```
+///
+/// Checked by `vvm lock verify` against the installed binaries, without installing anything.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VersionLock {
+    pub versions: BTreeMap<Version, Digests>,
+}
+
+impl VersionLock {
+    /// Locks the installed binaries of `versions` with every supported digest.
+    pub fn from_installed(versions: &[Version]) -> Result<Self, VyperVmError> {
+        let mut lock = Self::default();
+        for version in versions {
+            let path = binary_path(version)?;
+            let binbytes = fs::read(&path)
+                .map_err(|_| VyperVmError::VersionNotInstalled(version.to_string()))?;
+            lock.versions
+                .insert(version.clone(), Digests::compute(&binbytes));
+        }
+        Ok(lock)
+    }
+
+    /// Reads the lock file at `path`.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, VyperVmError> {
+        let content = fs::read_to_string(path.as_ref())?;
+        toml::from_str(&content).map_err(|err| VyperVmError::InvalidLockFile(err.to_string()))
+    }
+
+    /// Writes the lock file to `path`.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), VyperVmError> {
+        let content =
+            toml::to_string(self).map_err(|err| VyperVmError::InvalidLockFile(err.to_string()))?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Checks the installed binary of every locked version against its digests.
+    pub fn verify(&self) -> Vec<(Version, LockStatus)> {
+        self.versions
+            .iter()
+            .map(|(version, digests)| (version.clone(), verify_binary(version, digests)))
+            .collect()
+    }
+}
+
+/// How the installed binary of a locked version compares to the lock.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LockStatus {
+    /// Every locked digest of a supported algorithm matches.
+    Matches,
+    /// The version is not installed.
+    NotInstalled,
+    /// The binary differs from the lock.
+    Mismatch(HashAlgorithm),
+    /// The lock has no digest of a supported algorithm, nothing can be verified.
+    NoDigest,
+}
+
+impl LockStatus {
+    /// Returns true if the binary is exactly the locked one.
+    pub fn is_match(&self) -> bool {
+        *self == LockStatus::Matches
+    }
+}
+
+impl fmt::Display for LockStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockStatus::Matches => f.write_str("matches the lock"),
+            LockStatus::NotInstalled => f.write_str("is not installed"),
+            LockStatus::Mismatch(algorithm) => {
+                write!(f, "{} digest differs from the lock", algorithm)
+            }
+            LockStatus::NoDigest => f.write_str("has no supported digest in the lock"),
+        }
+    }
+}
+
+fn verify_binary(version: &Version, digests: &Digests) -> LockStatus {
+    if digests.is_empty() {
+        return LockStatus::NoDigest;
+    }
+    match binary_path(version).map(fs::read) {
+        Ok(Ok(binbytes)) => digests
+            .mismatch(&binbytes)
+            .map_or(LockStatus::Matches, LockStatus::Mismatch),
+        _ => LockStatus::NotInstalled,
+    }
+}
+
+/// Finds the nearest lock file, walking up from `dir` to the filesystem root.
+pub fn find_lock_file(dir: impl AsRef<Path>) -> Option<PathBuf> {
+    dir.as_ref()
+        .ancestors()
+        .map(|ancestor| ancestor.join(LOCK_FILENAME))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_installed_binaries() {
+        let version = Version::new(0, 0, 16);
+        crate::testing::sandbox().unwrap();
+        crate::setup_version(version.to_string().as_str()).unwrap();
+        fs::write(binary_path(&version).unwrap(), "fake vyper").unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(LOCK_FILENAME);
+        let mut lock = VersionLock::from_installed(std::slice::from_ref(&version)).unwrap();
+        lock.versions
+            .insert(Version::new(0, 0, 17), Digests::compute(b"never installed"));
+        lock.write(&path).unwrap();
+        let lock = VersionLock::read(&path).unwrap();
+        assert_eq!(find_lock_file(dir.path()), Some(path));
+
+        let status = |lock: &VersionLock| {
+            lock.verify()
+                .into_iter()
+                .map(|(_, s)| s)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            status(&lock),
+            vec![LockStatus::Matches, LockStatus::NotInstalled]
+        );
+
+        fs::write(binary_path(&version).unwrap(), "evil vyper").unwrap();
+        assert_eq!(
+            status(&lock)[0],
+            LockStatus::Mismatch(HashAlgorithm::Sha256)
+        );
+        crate::remove_version(&version).unwrap();
+    }
+}
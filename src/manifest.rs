@@ -0,0 +1,226 @@
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{
+    digest::Digests,
+    error::VyperVmError,
+    install_path, installed_versions,
+    probe::VersionProbe,
+    stats::{unix_timestamp, UsageStats},
+};
+
+/// File name of the metadata manifest stored in every version directory.
+pub const MANIFEST_FILENAME: &str = "manifest.json";
+
+/// Seconds within which another run of a version does not rewrite its last use, so builds
+/// running the compiler in a loop don't rewrite the manifest every time.
+const LAST_USED_RESOLUTION: u64 = 60;
+
+/// Where the binary of an installed version came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VersionOrigin {
+    /// Downloaded from the Vyper GitHub releases.
+    Github,
+    /// Downloaded from a host other than GitHub.
+    Mirror,
+    /// Imported from a file on the machine, without verification.
+    LocalFile,
+    /// Installed from a bundle created by `vvm bundle create`.
+    Bundle,
+    /// Built from source.
+    Source,
+}
+
+impl fmt::Display for VersionOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            VersionOrigin::Github => "github",
+            VersionOrigin::Mirror => "mirror",
+            VersionOrigin::LocalFile => "local-file",
+            VersionOrigin::Bundle => "bundle",
+            VersionOrigin::Source => "source",
+        };
+        f.write_str(s)
+    }
+}
+
+impl FromStr for VersionOrigin {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "github" => Ok(VersionOrigin::Github),
+            "mirror" => Ok(VersionOrigin::Mirror),
+            "local-file" => Ok(VersionOrigin::LocalFile),
+            "bundle" => Ok(VersionOrigin::Bundle),
+            "source" => Ok(VersionOrigin::Source),
+            s => Err(format!("unknown origin {}", s)),
+        }
+    }
+}
+
+/// Mutable bookkeeping vvm keeps about an installed version.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionManifest {
+    /// Cached result of running the binary with `--version`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe: Option<VersionProbe>,
+    /// Where the binary came from, `None` for versions installed before it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<VersionOrigin>,
+    /// Digests of the binary when it was installed, `None` for versions installed before they
+    /// were recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digests: Option<Digests>,
+    /// Unix timestamp in seconds of the last run through vvm or the wrapper, `None` if the
+    /// version never ran since it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<u64>,
+}
+
+impl VersionManifest {
+    /// Reads the manifest of an installed version, returning an empty manifest if there is none.
+    pub fn read(version: &Version) -> Result<Self, VyperVmError> {
+        match fs::read(manifest_path(version)?) {
+            Ok(content) => Ok(serde_json::from_slice(&content)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the manifest of an installed version.
+    pub fn write(&self, version: &Version) -> Result<(), VyperVmError> {
+        let file = fs::File::create(manifest_path(version)?)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+/// Records that `version` was run now in its manifest. Runs are frequent and concurrent, so the
+/// manifest is replaced atomically without taking a lock, the last run winning.
+pub fn record_last_used(version: &Version) -> Result<(), VyperVmError> {
+    let now = unix_timestamp();
+    let mut manifest = VersionManifest::read(version)?;
+    if manifest
+        .last_used
+        .is_some_and(|last_used| now.saturating_sub(last_used) < LAST_USED_RESOLUTION)
+    {
+        return Ok(());
+    }
+    manifest.last_used = Some(now);
+    let path = manifest_path(version)?;
+    let tmp_path = path.with_extension(format!("json.{}", std::process::id()));
+    fs::write(&tmp_path, serde_json::to_vec_pretty(&manifest)?)?;
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+/// Returns the unix timestamp of the last run of `version`, recorded by its manifest or by the
+/// usage statistics of older releases of vvm.
+pub fn last_used(version: &Version, stats: &UsageStats) -> Option<u64> {
+    let recorded = VersionManifest::read(version)
+        .ok()
+        .and_then(|manifest| manifest.last_used);
+    recorded.max(stats.last_used.get(version).copied())
+}
+
+/// Writes the manifest of a freshly installed version, with its origin and digests, into its
+/// staged version directory `dir`.
+pub(crate) fn write_install_manifest(
+    version: &Version,
+    origin: VersionOrigin,
+    digests: Digests,
+    dir: &Path,
+) -> Result<(), VyperVmError> {
+    let mut manifest = VersionManifest::read(version)?;
+    manifest.origin = Some(origin);
+    manifest.digests = Some(digests);
+    let file = fs::File::create(dir.join(MANIFEST_FILENAME))?;
+    serde_json::to_writer_pretty(file, &manifest)?;
+    Ok(())
+}
+
+/// Returns the installed versions whose origin is one of `forbidden`, for `vvm audit`.
+pub fn audit_origins(
+    forbidden: &[VersionOrigin],
+) -> Result<Vec<(Version, VersionOrigin)>, VyperVmError> {
+    let mut findings = vec![];
+    for version in installed_versions()? {
+        if let Some(origin) = VersionManifest::read(&version)?.origin {
+            if forbidden.contains(&origin) {
+                findings.push((version, origin));
+            }
+        }
+    }
+    Ok(findings)
+}
+
+/// Derive path to the metadata manifest of a specific Vyper version.
+pub fn manifest_path(version: &Version) -> Result<PathBuf, VyperVmError> {
+    Ok(install_path(version)?.join(MANIFEST_FILENAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_origin() {
+        let version = Version::new(0, 0, 8);
+        crate::testing::sandbox().unwrap();
+        crate::setup_version(version.to_string().as_str()).unwrap();
+        assert_eq!(VersionManifest::read(&version).unwrap().origin, None);
+
+        write_install_manifest(
+            &version,
+            VersionOrigin::LocalFile,
+            Digests::compute(b"fake vyper"),
+            &crate::version_path(version.to_string().as_str()).unwrap(),
+        )
+        .unwrap();
+        let manifest = VersionManifest::read(&version).unwrap();
+        assert_eq!(manifest.origin, Some(VersionOrigin::LocalFile));
+        assert_eq!(manifest.digests, Some(Digests::compute(b"fake vyper")));
+        assert!(audit_origins(&[VersionOrigin::LocalFile])
+            .unwrap()
+            .contains(&(version.clone(), VersionOrigin::LocalFile)));
+        assert!(!audit_origins(&[VersionOrigin::Github])
+            .unwrap()
+            .iter()
+            .any(|(v, _)| *v == version));
+
+        for origin in ["github", "mirror", "local-file", "bundle", "source"] {
+            assert_eq!(origin.parse::<VersionOrigin>().unwrap().to_string(), origin);
+        }
+        crate::remove_version(&version).unwrap();
+    }
+
+    #[test]
+    fn records_last_use() {
+        let version = Version::new(0, 0, 18);
+        crate::testing::sandbox().unwrap();
+        crate::setup_version(version.to_string().as_str()).unwrap();
+        let stats = UsageStats::default();
+        assert_eq!(last_used(&version, &stats), None);
+
+        record_last_used(&version).unwrap();
+        let recorded = VersionManifest::read(&version).unwrap().last_used.unwrap();
+        assert!(recorded + 5 > unix_timestamp());
+        assert_eq!(last_used(&version, &stats), Some(recorded));
+
+        // older statistics don't hide a more recent run
+        let mut stats = UsageStats::default();
+        stats.last_used.insert(version.clone(), recorded - 100);
+        assert_eq!(last_used(&version, &stats), Some(recorded));
+        stats.last_used.insert(version.clone(), recorded + 100);
+        assert_eq!(last_used(&version, &stats), Some(recorded + 100));
+        crate::remove_version(&version).unwrap();
+    }
+}
@@ -0,0 +1,73 @@
+use reqwest::{header::HeaderMap, StatusCode};
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static REQUESTS: AtomicU64 = AtomicU64::new(0);
+static BYTES: AtomicU64 = AtomicU64::new(0);
+static NOT_MODIFIED: AtomicU64 = AtomicU64::new(0);
+// u64::MAX until a response reports the remaining rate limit
+static RATE_LIMIT_REMAINING: AtomicU64 = AtomicU64::new(u64::MAX);
+
+/// HTTP activity of the current process, to diagnose slow or rate-limited environments.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HttpMetrics {
+    /// Number of requests sent.
+    pub requests: u64,
+    /// Number of response body bytes received.
+    pub bytes: u64,
+    /// Number of `304 Not Modified` responses, served from a cached ETag.
+    pub not_modified: u64,
+    /// GitHub API rate limit remaining, as reported by the latest response.
+    pub rate_limit_remaining: Option<u64>,
+}
+
+/// Returns the HTTP metrics recorded since the process started.
+pub fn http_metrics() -> HttpMetrics {
+    let rate_limit_remaining = RATE_LIMIT_REMAINING.load(Ordering::Relaxed);
+    HttpMetrics {
+        requests: REQUESTS.load(Ordering::Relaxed),
+        bytes: BYTES.load(Ordering::Relaxed),
+        not_modified: NOT_MODIFIED.load(Ordering::Relaxed),
+        rate_limit_remaining: (rate_limit_remaining != u64::MAX).then_some(rate_limit_remaining),
+    }
+}
+
+/// Records a received response.
+pub(crate) fn record_response(status: StatusCode, headers: &HeaderMap) {
+    REQUESTS.fetch_add(1, Ordering::Relaxed);
+    if status == StatusCode::NOT_MODIFIED {
+        NOT_MODIFIED.fetch_add(1, Ordering::Relaxed);
+    }
+    if let Some(remaining) = headers
+        .get("x-ratelimit-remaining")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+    {
+        RATE_LIMIT_REMAINING.store(remaining, Ordering::Relaxed);
+    }
+}
+
+/// Records received response body bytes.
+pub(crate) fn record_bytes(bytes: usize) {
+    BYTES.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn records_responses() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("42"));
+        record_response(StatusCode::NOT_MODIFIED, &headers);
+        record_bytes(10);
+
+        let metrics = http_metrics();
+        assert!(metrics.requests >= 1);
+        assert!(metrics.not_modified >= 1);
+        assert!(metrics.bytes >= 10);
+        assert!(metrics.rate_limit_remaining.is_some());
+    }
+}
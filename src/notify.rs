@@ -0,0 +1,139 @@
+use semver::Version;
+
+use std::{process::Command, sync::Mutex};
+
+use crate::events::{InstallEvent, Reporter};
+
+/// Title of the notifications.
+const TITLE: &str = "vvm";
+
+/// Reports the outcome of a queue of installs, e.g. `vvm install --all` or `vvm sync` running in
+/// the background, in a desktop notification once it finished. Enabled with
+/// `notifications = true` in the config.
+///
+/// The notification is shown with the notification command of the platform: `notify-send` on
+/// Linux, `osascript` on macOS and PowerShell on Windows. Failing to show it never fails the
+/// installs.
+#[derive(Debug, Default)]
+pub struct DesktopNotifier {
+    installed: Mutex<Vec<Version>>,
+    failed: Mutex<Vec<(Version, String)>>,
+}
+
+impl DesktopNotifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The message of the notification, `None` if the queue had nothing to install.
+    fn message(&self) -> Option<String> {
+        let installed = self.installed.lock().unwrap();
+        let mut failed = self.failed.lock().unwrap();
+        let total = installed.len() + failed.len();
+        if total == 0 {
+            return None;
+        }
+        if failed.is_empty() {
+            return Some(format!("Installed {} Vyper versions", total));
+        }
+        failed.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Some(format!(
+            "{} of {} installs failed: {}",
+            failed.len(),
+            total,
+            failed
+                .iter()
+                .map(|(version, _)| version.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+}
+
+impl Reporter for DesktopNotifier {
+    fn on_event(&self, version: &Version, event: &InstallEvent) {
+        match event {
+            InstallEvent::Installed(_) => self.installed.lock().unwrap().push(version.clone()),
+            InstallEvent::Failed(err) => self
+                .failed
+                .lock()
+                .unwrap()
+                .push((version.clone(), err.clone())),
+            _ => {}
+        }
+    }
+
+    fn on_finished(&self) {
+        if let Some(message) = self.message() {
+            // not waited for, the Windows balloon stays up for a while
+            if let Err(err) = notification_command(TITLE, &message).spawn() {
+                tracing::debug!("failed to show a desktop notification: {}", err);
+            }
+        }
+    }
+}
+
+fn notification_command(title: &str, message: &str) -> Command {
+    if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification {} with title {}",
+            applescript_string(message),
+            applescript_string(title)
+        ));
+        command
+    } else if cfg!(target_os = "windows") {
+        let quote = |text: &str| format!("'{}'", text.replace('\'', "''"));
+        let mut command = Command::new("powershell");
+        command.args(["-NoProfile", "-Command"]).arg(format!(
+            "Add-Type -AssemblyName System.Windows.Forms; \
+             $n = New-Object System.Windows.Forms.NotifyIcon; \
+             $n.Icon = [System.Drawing.SystemIcons]::Information; $n.Visible = $true; \
+             $n.ShowBalloonTip(10000, {}, {}, 'Info'); Start-Sleep -Seconds 10; $n.Dispose()",
+            quote(title),
+            quote(message)
+        ));
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.arg(title).arg(message);
+        command
+    }
+}
+
+/// Quotes `text` as an AppleScript string literal, where only `"` and `\` are escaped.
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn summarizes_installs() {
+        let notifier = DesktopNotifier::new();
+        assert_eq!(notifier.message(), None);
+
+        let installed = InstallEvent::Installed(PathBuf::from("vyper"));
+        notifier.on_event(&Version::new(0, 3, 7), &InstallEvent::Verifying);
+        notifier.on_event(&Version::new(0, 3, 7), &installed);
+        notifier.on_event(&Version::new(0, 3, 9), &installed);
+        assert_eq!(notifier.message().unwrap(), "Installed 2 Vyper versions");
+
+        let failed = InstallEvent::Failed("checksum mismatch".to_string());
+        notifier.on_event(&Version::new(0, 3, 8), &failed);
+        notifier.on_event(&Version::new(0, 2, 1), &failed);
+        assert_eq!(
+            notifier.message().unwrap(),
+            "2 of 4 installs failed: 0.2.1, 0.3.8"
+        );
+    }
+
+    #[test]
+    fn quotes_applescript_strings() {
+        assert_eq!(applescript_string("vvm"), r#""vvm""#);
+        assert_eq!(applescript_string(r#"a "b" \ c é"#), r#""a \"b\" \\ c é""#);
+    }
+}
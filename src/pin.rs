@@ -0,0 +1,104 @@
+use semver::Version;
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{current_version, error::VyperVmError, input::VersionInput};
+
+/// Names of the files pinning the Vyper version of a project, in order of precedence.
+pub const PIN_FILE_NAMES: [&str; 2] = [".vyper-version", ".vvm-version"];
+
+/// Where the effective Vyper version for a directory comes from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VersionSource {
+    /// Pinned by the project file at the given path.
+    Pin(PathBuf),
+    /// The global version set with `vvm use`.
+    Global,
+}
+
+impl fmt::Display for VersionSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionSource::Pin(path) => write!(f, "pinned by {}", path.display()),
+            VersionSource::Global => f.write_str("global version"),
+        }
+    }
+}
+
+/// The effective Vyper version for a directory, together with its source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolvedVersion {
+    pub version: Version,
+    pub source: VersionSource,
+}
+
+/// Finds the nearest pin file, walking up from `dir` to the filesystem root.
+pub fn find_pin_file(dir: impl AsRef<Path>) -> Option<PathBuf> {
+    dir.as_ref().ancestors().find_map(|ancestor| {
+        PIN_FILE_NAMES
+            .iter()
+            .map(|name| ancestor.join(name))
+            .find(|path| path.is_file())
+    })
+}
+
+/// Reads the version pinned by the pin file at `path`.
+pub fn read_pin_file(path: impl AsRef<Path>) -> Result<Version, VyperVmError> {
+    let content = fs::read_to_string(path.as_ref())?;
+    match VersionInput::parse(&content)? {
+        VersionInput::Exact(version) => Ok(version),
+        _ => Err(VyperVmError::InvalidVersionInput(
+            content.trim().to_string(),
+            "a pin file needs an exact version, e.g. 0.3.10".to_string(),
+        )),
+    }
+}
+
+/// Returns the version pinned for `dir` by the nearest pin file and the path to that file, if
+/// any.
+pub fn pinned_version(dir: impl AsRef<Path>) -> Result<Option<(Version, PathBuf)>, VyperVmError> {
+    match find_pin_file(dir) {
+        Some(path) => Ok(Some((read_pin_file(&path)?, path))),
+        None => Ok(None),
+    }
+}
+
+/// Resolves the effective Vyper version for `dir`: the nearest pin file wins over the global
+/// version. Returns `None` if neither is set.
+pub fn resolve_for_dir(dir: impl AsRef<Path>) -> Result<Option<ResolvedVersion>, VyperVmError> {
+    if let Some((version, path)) = pinned_version(dir)? {
+        return Ok(Some(ResolvedVersion {
+            version,
+            source: VersionSource::Pin(path),
+        }));
+    }
+    Ok(current_version()?.map(|version| ResolvedVersion {
+        version,
+        source: VersionSource::Global,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_nearest_pin_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("contracts").join("tokens");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(dir.path().join(".vyper-version"), "0.3.3\n").unwrap();
+
+        let pin = find_pin_file(&nested).unwrap();
+        assert_eq!(pin, dir.path().join(".vyper-version"));
+        assert_eq!(read_pin_file(&pin).unwrap(), Version::new(0, 3, 3));
+
+        fs::write(nested.join(".vvm-version"), "v0.3.7").unwrap();
+        let (version, path) = pinned_version(&nested).unwrap().unwrap();
+        assert_eq!(version, Version::new(0, 3, 7));
+        assert_eq!(path, nested.join(".vvm-version"));
+    }
+}
@@ -0,0 +1,887 @@
+use reqwest::header::CACHE_CONTROL;
+use semver::{Version, VersionReq};
+use url::Url;
+
+use std::{
+    fmt,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    all_versions, binary_path,
+    checksums::{self, ChecksumManifest},
+    current_version,
+    digest::Digests,
+    do_install, ensure_checksum,
+    error::VyperVmError,
+    events::InstallEvent,
+    gc::dir_size,
+    install_path, installed_versions, layout, lock_file_path, lock_home_exclusive,
+    manifest::VersionOrigin,
+    metrics, platform,
+    platform::Platform,
+    politeness,
+    queue::{install_permit, DownloadQueue, Throttle, DEFAULT_DOWNLOAD_JOBS},
+    releases,
+    sources::{self, SourceDigests},
+    try_lock_file, REQUEST_TIMEOUT,
+};
+
+/// Everything an install of a version would do, computed without touching the filesystem.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstallPlan {
+    pub version: Version,
+    /// Name of the release artifact to download.
+    pub artifact: String,
+    pub url: Url,
+    /// Sources tried in order when the download from `url` fails, see
+    /// [`sources::artifact_urls`].
+    pub mirrors: Vec<Url>,
+    /// Size in bytes of the download, if reported by the release.
+    pub size: Option<u64>,
+    /// Digests the download is verified against, if known.
+    pub checksum: Option<Digests>,
+    /// Where the binary is written.
+    pub destination: PathBuf,
+}
+
+impl InstallPlan {
+    fn new(artifact: ResolvedArtifact, mirrors: Vec<Url>) -> Result<Self, VyperVmError> {
+        Ok(Self {
+            destination: binary_path(&artifact.version)?,
+            version: artifact.version,
+            artifact: artifact.name,
+            url: artifact.url,
+            mirrors,
+            size: artifact.size,
+            checksum: artifact.digest,
+        })
+    }
+
+    /// Downloads, verifies and installs the binary, see [`crate::install`].
+    pub async fn apply(&self) -> Result<PathBuf, VyperVmError> {
+        self.apply_with_events(|_| {}).await
+    }
+
+    pub(crate) async fn apply_with_events<F>(&self, on_event: F) -> Result<PathBuf, VyperVmError>
+    where
+        F: FnMut(InstallEvent),
+    {
+        self.apply_throttled(on_event, None).await
+    }
+
+    /// Applies the plan, sharing the bandwidth of `throttle` with other downloads.
+    pub(crate) async fn apply_throttled<F>(
+        &self,
+        mut on_event: F,
+        throttle: Option<&Throttle>,
+    ) -> Result<PathBuf, VyperVmError>
+    where
+        F: FnMut(InstallEvent),
+    {
+        let _slot = install_permit().await;
+        let mut last_err = None;
+        let mut download = None;
+        for url in std::iter::once(&self.url).chain(self.mirrors.iter()) {
+            on_event(InstallEvent::MetadataFetched {
+                version: self.version.clone(),
+                artifact: self.artifact.clone(),
+                url: url.clone(),
+            });
+            let mut downloaded = self.download(url, &mut on_event, throttle, false).await;
+            if let Err(VyperVmError::ChecksumMismatch(_)) = downloaded {
+                // most mismatches are corrupted transfers, tampering fails again
+                tracing::warn!("download from {} is corrupted, downloading it again", url);
+                on_event(InstallEvent::MetadataFetched {
+                    version: self.version.clone(),
+                    artifact: self.artifact.clone(),
+                    url: url.clone(),
+                });
+                downloaded = self.download(url, &mut on_event, throttle, true).await;
+            }
+            match downloaded {
+                Ok(binbytes) => {
+                    sources::record_download(url, true);
+                    download = Some((url, binbytes));
+                    break;
+                }
+                Err(err) => {
+                    source_failed(url, &err);
+                    last_err = Some(err);
+                }
+            }
+        }
+        match download {
+            Some((url, binbytes)) => self.install(url, binbytes, on_event),
+            None => Err(last_err.expect("at least one source")),
+        }
+    }
+
+    /// Blocking version of [`InstallPlan::apply_with_events`]
+    #[cfg(feature = "blocking")]
+    pub(crate) fn blocking_apply_with_events<F>(
+        &self,
+        mut on_event: F,
+    ) -> Result<PathBuf, VyperVmError>
+    where
+        F: FnMut(InstallEvent),
+    {
+        let _slot = crate::queue::blocking_install_permit();
+        let mut last_err = None;
+        let mut download = None;
+        for url in std::iter::once(&self.url).chain(self.mirrors.iter()) {
+            on_event(InstallEvent::MetadataFetched {
+                version: self.version.clone(),
+                artifact: self.artifact.clone(),
+                url: url.clone(),
+            });
+            let mut downloaded = self.blocking_download(url, &mut on_event, false);
+            if let Err(VyperVmError::ChecksumMismatch(_)) = downloaded {
+                // most mismatches are corrupted transfers, tampering fails again
+                tracing::warn!("download from {} is corrupted, downloading it again", url);
+                on_event(InstallEvent::MetadataFetched {
+                    version: self.version.clone(),
+                    artifact: self.artifact.clone(),
+                    url: url.clone(),
+                });
+                downloaded = self.blocking_download(url, &mut on_event, true);
+            }
+            match downloaded {
+                Ok(binbytes) => {
+                    sources::record_download(url, true);
+                    download = Some((url, binbytes));
+                    break;
+                }
+                Err(err) => {
+                    source_failed(url, &err);
+                    last_err = Some(err);
+                }
+            }
+        }
+        match download {
+            Some((url, binbytes)) => self.install(url, binbytes, on_event),
+            None => Err(last_err.expect("at least one source")),
+        }
+    }
+
+    /// Installs the binary downloaded from `url`.
+    fn install<F>(
+        &self,
+        url: &Url,
+        binbytes: Vec<u8>,
+        mut on_event: F,
+    ) -> Result<PathBuf, VyperVmError>
+    where
+        F: FnMut(InstallEvent),
+    {
+        // lock file to indicate that installation of this Vyper version will be in progress.
+        let lock_path = lock_file_path(&self.version)?;
+        // wait until lock file is released, possibly by another parallel thread trying to install
+        // the same version of Vyper.
+        let _lock = try_lock_file(lock_path)?;
+
+        let origin = if url.host_str() == Some("github.com") {
+            VersionOrigin::Github
+        } else {
+            VersionOrigin::Mirror
+        };
+        let vyper_path = do_install(self.version.clone(), binbytes, &self.artifact, origin)?;
+        on_event(InstallEvent::Installed(vyper_path.clone()));
+        Ok(vyper_path)
+    }
+
+    /// Downloads the artifact from a source and verifies its checksum. A `retry` bypasses the
+    /// caches between vvm and the source, which may hold a corrupted copy.
+    async fn download<F>(
+        &self,
+        url: &Url,
+        on_event: &mut F,
+        throttle: Option<&Throttle>,
+        retry: bool,
+    ) -> Result<Vec<u8>, VyperVmError>
+    where
+        F: FnMut(InstallEvent),
+    {
+        // fails before downloading from a mirror that cannot be verified
+        let digests =
+            sources::source_digests(url, platform::platform(), &self.version, &self.artifact)
+                .await?;
+        self.ensure_verifiable(&digests, &ChecksumManifest::load()?)?;
+        let _permit = match url.host_str() {
+            Some("github.com") => Some(politeness::github_permit().await),
+            _ => None,
+        };
+        let mut request = reqwest::Client::builder()
+            .no_gzip()
+            .no_brotli()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("reqwest::Client::new()")
+            .get(if retry {
+                cache_busted(url)
+            } else {
+                url.clone()
+            });
+        if retry {
+            request = request.header(CACHE_CONTROL, "no-cache");
+        }
+        let mut res = request.send().await?;
+        metrics::record_response(res.status(), res.headers());
+
+        if !res.status().is_success() {
+            return Err(VyperVmError::UnsuccessfulResponse(
+                url.clone(),
+                res.status(),
+            ));
+        }
+
+        let total = res.content_length();
+        let mut binbytes = Vec::with_capacity(total.unwrap_or_default() as usize);
+        while let Some(chunk) = res.chunk().await? {
+            metrics::record_bytes(chunk.len());
+            if let Some(throttle) = throttle {
+                throttle.consume(chunk.len()).await;
+            }
+            binbytes.extend_from_slice(&chunk);
+            on_event(InstallEvent::DownloadProgress {
+                downloaded: binbytes.len() as u64,
+                total,
+            });
+        }
+
+        on_event(InstallEvent::Verifying);
+        self.verify(&binbytes, &digests)?;
+        Ok(binbytes)
+    }
+
+    /// Blocking version of [`InstallPlan::download`]
+    #[cfg(feature = "blocking")]
+    fn blocking_download<F>(
+        &self,
+        url: &Url,
+        on_event: &mut F,
+        retry: bool,
+    ) -> Result<Vec<u8>, VyperVmError>
+    where
+        F: FnMut(InstallEvent),
+    {
+        use std::io::Read;
+
+        // fails before downloading from a mirror that cannot be verified
+        let digests = sources::blocking_source_digests(
+            url,
+            platform::platform(),
+            &self.version,
+            &self.artifact,
+        )?;
+        self.ensure_verifiable(&digests, &ChecksumManifest::load()?)?;
+        let mut request = reqwest::blocking::Client::builder()
+            .no_gzip()
+            .no_brotli()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("reqwest::Client::new()")
+            .get(if retry {
+                cache_busted(url)
+            } else {
+                url.clone()
+            });
+        if retry {
+            request = request.header(CACHE_CONTROL, "no-cache");
+        }
+        let mut res = request.send()?;
+        metrics::record_response(res.status(), res.headers());
+
+        if !res.status().is_success() {
+            return Err(VyperVmError::UnsuccessfulResponse(
+                url.clone(),
+                res.status(),
+            ));
+        }
+
+        let total = res.content_length();
+        let mut binbytes = Vec::with_capacity(total.unwrap_or_default() as usize);
+        let mut chunk = [0; 64 * 1024];
+        loop {
+            let read = res.read(&mut chunk)?;
+            if read == 0 {
+                break;
+            }
+            metrics::record_bytes(read);
+            binbytes.extend_from_slice(&chunk[..read]);
+            on_event(InstallEvent::DownloadProgress {
+                downloaded: binbytes.len() as u64,
+                total,
+            });
+        }
+
+        on_event(InstallEvent::Verifying);
+        self.verify(&binbytes, &digests)?;
+        Ok(binbytes)
+    }
+
+    /// Checks a download against the known checksums and the digests listed by its source.
+    fn verify(&self, binbytes: &[u8], digests: &SourceDigests) -> Result<(), VyperVmError> {
+        for checksum in self.expected_digests(digests) {
+            ensure_checksum(binbytes, &self.version, checksum)?;
+        }
+        Ok(())
+    }
+
+    /// Fails before downloading from a source if nothing would verify the download, unless
+    /// installs are insecure, see [`ensure_known_checksum`].
+    fn ensure_verifiable(
+        &self,
+        digests: &SourceDigests,
+        manifest: &ChecksumManifest,
+    ) -> Result<(), VyperVmError> {
+        if self.expected_digests(digests).next().is_some() {
+            return Ok(());
+        }
+        ensure_known_checksum(&self.version, &self.artifact, manifest)
+    }
+
+    fn expected_digests<'a>(
+        &'a self,
+        digests: &'a SourceDigests,
+    ) -> impl Iterator<Item = &'a Digests> {
+        let known = self
+            .checksum
+            .iter()
+            .filter(move |_| digests.release_artifact);
+        known.chain(digests.listed.iter())
+    }
+}
+
+/// Fails with [`VyperVmError::UnknownChecksum`] for an artifact no checksum covers, unless
+/// installs are insecure, see [`checksums::set_insecure`]. Only warns while `manifest`, the
+/// checksum manifest in use, lists nothing, as most releases publish no checksums of their own.
+fn ensure_known_checksum(
+    version: &Version,
+    artifact: &str,
+    manifest: &ChecksumManifest,
+) -> Result<(), VyperVmError> {
+    if !checksums::is_insecure() && !manifest.is_empty() {
+        return Err(VyperVmError::UnknownChecksum(format!(
+            "vyper {} ({})",
+            version, artifact
+        )));
+    }
+    tracing::warn!(
+        "installing {} of vyper {} UNVERIFIED: no known checksum covers it",
+        artifact,
+        version
+    );
+    Ok(())
+}
+
+/// Reports a failed download from `url`. Only failed requests count against the health of the
+/// source, not a config rejecting it or a corrupted download.
+fn source_failed(url: &Url, err: &VyperVmError) {
+    tracing::warn!("download from {} failed: {}", url, err);
+    if err.is_network() {
+        sources::record_download(url, false);
+    }
+}
+
+impl fmt::Display for InstallPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.size {
+            Some(size) => writeln!(f, "download {} ({} bytes)", self.url, size)?,
+            None => writeln!(f, "download {}", self.url)?,
+        }
+        for mirror in self.mirrors.iter() {
+            writeln!(f, "fallback {}", mirror)?;
+        }
+        match &self.checksum {
+            Some(checksum) => writeln!(f, "verify   {}", checksum)?,
+            None => writeln!(f, "verify   no known checksum")?,
+        }
+        write!(f, "write    {}", self.destination.display())
+    }
+}
+
+/// The release artifact of a version for a platform, for tools fetching binaries themselves,
+/// e.g. through an artifact proxy, see [`resolve_artifact`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolvedArtifact {
+    pub version: Version,
+    pub platform: Platform,
+    /// Name of the release artifact.
+    pub name: String,
+    /// URL of the artifact on the preferred download source.
+    pub url: Url,
+    /// Size in bytes of the artifact, if reported by the release.
+    pub size: Option<u64>,
+    /// Digests the artifact is verified against, if known.
+    pub digest: Option<Digests>,
+}
+
+impl ResolvedArtifact {
+    /// Checks downloaded bytes against the known digests of the artifact. Fails if there are
+    /// none once the checksum manifest lists checksums, unless installs are insecure, see
+    /// [`checksums::set_insecure`].
+    pub fn verify(&self, binbytes: &[u8]) -> Result<(), VyperVmError> {
+        match &self.digest {
+            Some(digest) if digest.mismatch(binbytes).is_some() => {
+                Err(VyperVmError::ChecksumMismatch(self.version.to_string()))
+            }
+            Some(_) => Ok(()),
+            None => ensure_known_checksum(&self.version, &self.name, &ChecksumManifest::load()?),
+        }
+    }
+
+    /// Verifies the artifact fetched by other means and installs it like [`crate::install`]
+    /// would, recording it as installed from a mirror.
+    pub fn install(&self, binbytes: Vec<u8>) -> Result<PathBuf, VyperVmError> {
+        if self.platform != platform::platform() {
+            return Err(VyperVmError::UnsupportedVersion(
+                self.version.to_string(),
+                self.platform.to_string(),
+            ));
+        }
+        self.verify(&binbytes)?;
+        let _lock = try_lock_file(lock_file_path(&self.version)?)?;
+        let vyper_path = do_install(
+            self.version.clone(),
+            binbytes,
+            &self.name,
+            VersionOrigin::Mirror,
+        )?;
+        Ok(vyper_path)
+    }
+}
+
+/// Fetches the release metadata of `version` and resolves its artifact for `platform`, without
+/// downloading it.
+pub async fn resolve_artifact(
+    version: &Version,
+    platform: Platform,
+) -> Result<ResolvedArtifact, VyperVmError> {
+    Ok(resolve_sources(version, platform).await?.0)
+}
+
+/// Resolves the artifact of `version`, together with the fallback URLs of the other sources.
+///
+/// When GitHub cannot be reached the metadata comes from the cached or bundled list of releases,
+/// so installs from mirrors keep working.
+async fn resolve_sources(
+    version: &Version,
+    platform: Platform,
+) -> Result<(ResolvedArtifact, Vec<Url>), VyperVmError> {
+    let releases = match releases::release_for_version(platform, version).await {
+        Err(err @ VyperVmError::UnknownVersion) => return Err(err),
+        Err(err) => {
+            tracing::warn!("fetching the release {} failed: {}", version, err);
+            releases::all_releases(platform).await?
+        }
+        Ok(releases) => releases,
+    };
+    let name = artifact_name(&releases, version)?;
+    let digest = releases::published_digests(&releases, version, &name).await;
+    resolved_sources(releases, version, platform, name, digest)
+}
+
+/// Blocking version of [`resolve_sources`]
+#[cfg(feature = "blocking")]
+fn blocking_resolve_sources(
+    version: &Version,
+    platform: Platform,
+) -> Result<(ResolvedArtifact, Vec<Url>), VyperVmError> {
+    let releases = match releases::blocking_release_for_version(platform, version) {
+        Err(err @ VyperVmError::UnknownVersion) => return Err(err),
+        Err(err) => {
+            tracing::warn!("fetching the release {} failed: {}", version, err);
+            releases::blocking_all_releases(platform)?
+        }
+        Ok(releases) => releases,
+    };
+    let name = artifact_name(&releases, version)?;
+    let digest = releases::blocking_published_digests(&releases, version, &name);
+    resolved_sources(releases, version, platform, name, digest)
+}
+
+fn artifact_name(releases: &releases::Releases, version: &Version) -> Result<String, VyperVmError> {
+    Ok(releases
+        .get_artifact(version)
+        .ok_or(VyperVmError::UnknownVersion)?
+        .clone())
+}
+
+/// Resolves the artifact `name` of `version` on the download sources, verified against the
+/// `published` digests of the release or else the checksum manifest.
+fn resolved_sources(
+    releases: releases::Releases,
+    version: &Version,
+    platform: Platform,
+    name: String,
+    published: Option<Digests>,
+) -> Result<(ResolvedArtifact, Vec<Url>), VyperVmError> {
+    let mut mirrors = sources::artifact_urls(platform, version, &name)?;
+    let url = mirrors.remove(0);
+    let digest = match published {
+        Some(digest) => Some(digest),
+        None => ChecksumManifest::load()?.checksum(version, &name),
+    };
+
+    let artifact = ResolvedArtifact {
+        version: version.clone(),
+        platform,
+        size: releases.sizes.get(version).copied(),
+        name,
+        url,
+        digest,
+    };
+    Ok((artifact, mirrors))
+}
+
+/// Fetches the release metadata of `version` and computes its install plan.
+pub async fn plan_install(version: &Version) -> Result<InstallPlan, VyperVmError> {
+    releases::ensure_online(format!("installing {}", version))?;
+    let (artifact, mirrors) = resolve_sources(version, platform::supported_platform()?).await?;
+    InstallPlan::new(artifact, mirrors)
+}
+
+/// Blocking version of [`plan_install`]
+#[cfg(feature = "blocking")]
+pub(crate) fn blocking_plan_install(version: &Version) -> Result<InstallPlan, VyperVmError> {
+    releases::ensure_online(format!("installing {}", version))?;
+    let (artifact, mirrors) = blocking_resolve_sources(version, platform::supported_platform()?)?;
+    InstallPlan::new(artifact, mirrors)
+}
+
+/// Everything a removal of installed versions would delete.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RemovePlan {
+    pub entries: Vec<RemoveEntry>,
+}
+
+/// A version directory deleted by a [`RemovePlan`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RemoveEntry {
+    pub version: Version,
+    pub path: PathBuf,
+    /// Size in bytes of the directory.
+    pub size: u64,
+}
+
+impl RemovePlan {
+    /// Deletes the planned install directories.
+    pub fn apply(&self) -> Result<(), VyperVmError> {
+        for entry in self.entries.iter() {
+            layout::remove_install(&entry.version)?;
+        }
+        Ok(())
+    }
+
+    /// Total number of bytes the plan frees.
+    pub fn size(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.size).sum()
+    }
+}
+
+impl fmt::Display for RemovePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in self.entries.iter() {
+            writeln!(
+                f,
+                "delete   {} ({} bytes)",
+                entry.path.display(),
+                entry.size
+            )?;
+        }
+        write!(f, "total    {} bytes", self.size())
+    }
+}
+
+/// Computes the plan removing the provided installed versions.
+pub fn plan_remove(versions: &[Version]) -> Result<RemovePlan, VyperVmError> {
+    let installed = installed_versions()?;
+    let mut entries = Vec::with_capacity(versions.len());
+    for version in versions {
+        if !installed.contains(version) {
+            return Err(VyperVmError::VersionNotInstalled(version.to_string()));
+        }
+        let path = install_path(version)?;
+        let size = match dir_size(&path) {
+            Ok(size) => size,
+            // removed concurrently, nothing left to delete
+            Err(VyperVmError::IoError(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+                continue
+            }
+            Err(err) => return Err(err),
+        };
+        entries.push(RemoveEntry {
+            version: version.clone(),
+            path,
+            size,
+        });
+    }
+    Ok(RemovePlan { entries })
+}
+
+/// The installs and removals bringing the machine to a desired toolchain state, see [`plan`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TaskPlan {
+    /// Versions to install, the newest available version for every unsatisfied requirement.
+    pub installs: Vec<InstallPlan>,
+    /// Installed versions matching no requirement.
+    pub removals: RemovePlan,
+    /// Requirements no available version satisfies.
+    pub unsatisfiable: Vec<VersionReq>,
+}
+
+impl TaskPlan {
+    /// Returns true if the machine is already in the desired state.
+    pub fn is_empty(&self) -> bool {
+        self.installs.is_empty() && self.removals.entries.is_empty()
+    }
+
+    /// Drops the removals from the plan, keeping installed versions that are not required.
+    pub fn keep_extras(mut self) -> Self {
+        self.removals = RemovePlan::default();
+        self
+    }
+}
+
+impl fmt::Display for TaskPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for install in self.installs.iter() {
+            writeln!(f, "{}", install)?;
+        }
+        for req in self.unsatisfiable.iter() {
+            writeln!(f, "unsatisfiable {}", req)?;
+        }
+        write!(f, "{}", self.removals)
+    }
+}
+
+/// Computes the minimal set of installs and removals such that every requirement in `desired`
+/// is satisfied by an installed version, and every installed version satisfies a requirement.
+///
+/// Requirements already satisfied by an installed version do not need network access. The
+/// global version is never removed.
+pub async fn plan(desired: &[VersionReq]) -> Result<TaskPlan, VyperVmError> {
+    let installed = installed_versions()?;
+    let current = current_version()?;
+
+    let unsatisfied = desired
+        .iter()
+        .filter(|req| !installed.iter().any(|v| req.matches(v)))
+        .collect::<Vec<_>>();
+
+    let mut task_plan = TaskPlan::default();
+    if !unsatisfied.is_empty() {
+        let available = all_versions().await?;
+        let mut to_install: Vec<Version> = vec![];
+        for req in unsatisfied {
+            // a version picked for another requirement may satisfy this one too
+            if to_install.iter().any(|v| req.matches(v)) {
+                continue;
+            }
+            match available.iter().rev().find(|v| req.matches(v)) {
+                Some(version) => to_install.push(version.clone()),
+                None => task_plan.unsatisfiable.push(req.clone()),
+            }
+        }
+        for version in to_install {
+            task_plan.installs.push(plan_install(&version).await?);
+        }
+    }
+
+    let extras = installed
+        .into_iter()
+        .filter(|v| current.as_ref() != Some(v))
+        .filter(|v| !desired.iter().any(|req| req.matches(v)))
+        .collect::<Vec<_>>();
+    task_plan.removals = plan_remove(&extras)?;
+
+    Ok(task_plan)
+}
+
+/// Applies the plan computed by [`plan`]: installs first, through a [`DownloadQueue`], then
+/// removals while holding the home lock exclusively.
+///
+/// Returns the paths to the installed Vyper binaries.
+pub async fn apply(task_plan: &TaskPlan) -> Result<Vec<PathBuf>, VyperVmError> {
+    let mut installed = Vec::with_capacity(task_plan.installs.len());
+    let results = DownloadQueue::new(DEFAULT_DOWNLOAD_JOBS)
+        .run_plans(&task_plan.installs)
+        .await;
+    for (_, result) in results {
+        installed.push(result?);
+    }
+    if !task_plan.removals.entries.is_empty() {
+        let _home_lock = lock_home_exclusive()?;
+        task_plan.removals.apply()?;
+    }
+    Ok(installed)
+}
+
+/// Returns `url` with a query parameter no cache has seen, so downloading a corrupted artifact
+/// again reaches the source.
+pub(crate) fn cache_busted(url: &Url) -> Url {
+    let nonce = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let mut url = url.clone();
+    url.query_pairs_mut()
+        .append_pair("vvm-retry", &nonce.to_string());
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    #[test]
+    fn busts_caches_of_retried_downloads() {
+        let url = Url::parse("https://github.com/vyperlang/vyper/releases/download/v0.3.10/vyper")
+            .unwrap();
+        let busted = cache_busted(&url);
+        assert_eq!(busted.path(), url.path());
+        assert!(busted
+            .query_pairs()
+            .any(|(key, value)| key == "vvm-retry" && !value.is_empty()));
+    }
+
+    #[test]
+    fn refuses_downloads_nothing_verifies() {
+        let url = Url::parse("https://mirror.example.com/vyper/v0.3.10/vyper").unwrap();
+        let mut plan = InstallPlan {
+            version: Version::new(0, 3, 10),
+            artifact: "vyper".to_string(),
+            url: url.clone(),
+            mirrors: vec![],
+            size: None,
+            checksum: None,
+            destination: PathBuf::from("vyper"),
+        };
+        let mut digests = SourceDigests {
+            listed: None,
+            release_artifact: true,
+        };
+        let mut empty = ChecksumManifest::bundled().unwrap();
+        empty.checksums.clear();
+        let mut manifest = empty.clone();
+        manifest
+            .checksums
+            .entry(Version::new(0, 3, 9))
+            .or_default()
+            .insert(
+                "vyper".to_string(),
+                checksums::ArtifactChecksum::Digests(Digests::compute(b"vyper 0.3.9")),
+            );
+        assert!(matches!(
+            plan.ensure_verifiable(&digests, &manifest),
+            Err(VyperVmError::UnknownChecksum(_))
+        ));
+        // an empty manifest covers no release yet, so nothing is refused
+        plan.ensure_verifiable(&digests, &empty).unwrap();
+
+        // the checksums of the release don't cover a mirror's own build
+        plan.checksum = Some(Digests::compute(b"vyper"));
+        digests.release_artifact = false;
+        assert!(plan.ensure_verifiable(&digests, &manifest).is_err());
+
+        digests.listed = Some(Digests::compute(b"mirrored vyper"));
+        plan.ensure_verifiable(&digests, &manifest).unwrap();
+        assert!(plan.verify(b"mirrored vyper", &digests).is_ok());
+        assert!(plan.verify(b"vyper", &digests).is_err());
+    }
+
+    #[test]
+    fn only_failed_requests_count_against_sources() {
+        crate::testing::sandbox().unwrap();
+        let url = Url::parse("https://unhealthy.example.com/vyper/v0.3.10/vyper").unwrap();
+        let failures = || {
+            sources::SourceHealth::read()
+                .unwrap()
+                .sources
+                .get("https://unhealthy.example.com")
+                .map(|state| state.failures)
+        };
+
+        source_failed(&url, &VyperVmError::UnsignedMirror(url.to_string()));
+        source_failed(&url, &VyperVmError::ChecksumMismatch("0.3.10".to_string()));
+        assert_eq!(failures(), None);
+
+        source_failed(&url, &VyperVmError::Offline("testing".to_string()));
+        assert_eq!(failures(), Some(1));
+        sources::record_download(&url, true);
+        assert_eq!(failures(), None);
+    }
+
+    #[test]
+    fn plans_and_applies_removal() {
+        let version = Version::new(0, 0, 6);
+        crate::testing::sandbox().unwrap();
+        crate::setup_version(version.to_string().as_str()).unwrap();
+        fs::write(binary_path(&version).unwrap(), b"fake vyper").unwrap();
+
+        let plan = plan_remove(std::slice::from_ref(&version)).unwrap();
+        assert_eq!(plan.entries.len(), 1);
+        assert_eq!(plan.size(), 10);
+        assert!(binary_path(&version).unwrap().exists());
+
+        plan.apply().unwrap();
+        assert!(!crate::version_path(version.to_string().as_str())
+            .unwrap()
+            .exists());
+        assert!(plan_remove(&[version]).is_err());
+    }
+
+    #[tokio::test]
+    async fn satisfied_requirements_need_no_install() {
+        let version = Version::new(0, 0, 7);
+        crate::testing::sandbox().unwrap();
+        crate::setup_version(version.to_string().as_str()).unwrap();
+        fs::write(binary_path(&version).unwrap(), b"fake vyper").unwrap();
+
+        let task_plan = plan(&[VersionReq::parse("=0.0.7").unwrap()]).await.unwrap();
+        assert!(task_plan.installs.is_empty());
+        assert!(task_plan.unsatisfiable.is_empty());
+        assert!(task_plan
+            .removals
+            .entries
+            .iter()
+            .all(|entry| entry.version != version));
+        assert!(task_plan.keep_extras().removals.entries.is_empty());
+        crate::remove_version(&version).unwrap();
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn installs_prefetched_artifact() {
+        let version = Version::new(0, 0, 11);
+        crate::testing::sandbox().unwrap();
+        let fake = crate::testing::FakeVyper::new().binary(&version);
+        let mut artifact = ResolvedArtifact {
+            version: version.clone(),
+            platform: platform::platform(),
+            name: "vyper.0.0.11".to_string(),
+            url: Url::parse("https://proxy.example.com/vyper.0.0.11").unwrap(),
+            size: None,
+            digest: Some(Digests::compute(&fake)),
+        };
+        assert!(artifact.install(b"tampered".to_vec()).is_err());
+        assert!(!binary_path(&version).unwrap().exists());
+
+        assert_eq!(
+            artifact.install(fake.clone()).unwrap(),
+            binary_path(&version).unwrap()
+        );
+        crate::remove_version(&version).unwrap();
+
+        artifact.platform = if artifact.platform == Platform::Linux {
+            Platform::MacOs
+        } else {
+            Platform::Linux
+        };
+        assert!(artifact.install(fake.clone()).is_err());
+    }
+}
@@ -2,6 +2,17 @@ use std::fmt::Formatter;
 use std::str::FromStr;
 use std::{env, fmt};
 
+use crate::error::VyperVmError;
+
+/// Operating systems and architectures Vyper publishes binaries for, as in `std::env::consts`.
+pub const SUPPORTED_PLATFORMS: &[(&str, &str, Platform)] = &[
+    ("linux", "x86_64", Platform::Linux),
+    ("linux", "aarch64", Platform::Linux),
+    ("macos", "x86_64", Platform::MacOs),
+    ("macos", "aarch64", Platform::MacOs),
+    ("windows", "x86_64", Platform::Windows),
+];
+
 /// Types of supported platforms.
 #[derive(Clone, Debug, Copy, PartialEq, Eq)]
 pub enum Platform {
@@ -39,20 +50,40 @@ impl FromStr for Platform {
 
 /// Read the current machine's platform.
 pub fn platform() -> Platform {
-    match (env::consts::OS, env::consts::ARCH) {
-        ("linux", "x86_64") => Platform::Linux,
-        ("linux", "aarch64") => Platform::Linux,
-        ("macos", "x86_64") => Platform::MacOs,
-        ("macos", "aarch64") => Platform::MacOs,
-        ("windows", "x86_64") => Platform::Windows,
-        _ => Platform::Unsupported,
+    platform_of(env::consts::OS, env::consts::ARCH)
+}
+
+/// Read the current machine's platform, failing with [`VyperVmError::UnsupportedPlatform`] if
+/// Vyper publishes no binaries for it, e.g. on i686 or riscv64.
+pub fn supported_platform() -> Result<Platform, VyperVmError> {
+    match platform() {
+        Platform::Unsupported => Err(VyperVmError::UnsupportedPlatform {
+            os: env::consts::OS.to_string(),
+            arch: env::consts::ARCH.to_string(),
+        }),
+        platform => Ok(platform),
     }
 }
 
+fn platform_of(os: &str, arch: &str) -> Platform {
+    SUPPORTED_PLATFORMS
+        .iter()
+        .find(|(supported_os, supported_arch, _)| *supported_os == os && *supported_arch == arch)
+        .map_or(Platform::Unsupported, |(_, _, platform)| *platform)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn unusual_architectures_are_unsupported() {
+        assert_eq!(platform_of("linux", "aarch64"), Platform::Linux);
+        assert_eq!(platform_of("linux", "x86"), Platform::Unsupported);
+        assert_eq!(platform_of("linux", "riscv64"), Platform::Unsupported);
+        assert_eq!(platform_of("windows", "aarch64"), Platform::Unsupported);
+    }
+
     #[test]
     #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
     fn get_platform() {
@@ -0,0 +1,130 @@
+use once_cell::sync::Lazy;
+use rand::Rng;
+use tokio::{
+    sync::{Semaphore, SemaphorePermit},
+    time::{self, Instant},
+};
+
+use std::{sync::Mutex, time::Duration};
+
+use crate::config::Config;
+
+/// Requests to GitHub running at the same time when none is configured.
+pub const DEFAULT_GITHUB_MAX_REQUESTS: usize = 4;
+
+/// Gate of every request this process sends to GitHub, see [`GithubPoliteness`].
+static GITHUB: Lazy<RequestGate> = Lazy::new(|| RequestGate::new(GithubPoliteness::load()));
+
+/// How requests to GitHub are spread when many operations run at once, e.g. `vvm install --all`
+/// or `vvm sync`, so shared CI addresses stay within the secondary rate limits of GitHub.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GithubPoliteness {
+    /// Requests running at the same time.
+    pub max_requests: usize,
+    /// Minimum delay between the starts of two requests.
+    pub delay: Duration,
+    /// Upper bound of a random delay added to `delay`, so concurrent runs don't move in lockstep.
+    pub jitter: Duration,
+}
+
+impl Default for GithubPoliteness {
+    fn default() -> Self {
+        Self {
+            max_requests: DEFAULT_GITHUB_MAX_REQUESTS,
+            delay: Duration::ZERO,
+            jitter: Duration::ZERO,
+        }
+    }
+}
+
+impl GithubPoliteness {
+    /// The policy set with `github_max_requests`, `github_request_delay_ms` and
+    /// `github_request_jitter_ms` in the config.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_requests: config
+                .github_max_requests
+                .unwrap_or(DEFAULT_GITHUB_MAX_REQUESTS)
+                .max(1),
+            delay: Duration::from_millis(config.github_request_delay_ms.unwrap_or_default()),
+            jitter: Duration::from_millis(config.github_request_jitter_ms.unwrap_or_default()),
+        }
+    }
+
+    /// The policy of the config, the default one if it cannot be loaded.
+    pub fn load() -> Self {
+        Config::load()
+            .map(|config| Self::from_config(&config))
+            .unwrap_or_default()
+    }
+}
+
+/// Limits the requests running at the same time and paces their starts.
+#[derive(Debug)]
+pub(crate) struct RequestGate {
+    permits: Semaphore,
+    policy: GithubPoliteness,
+    /// Earliest start of the next request.
+    next_start: Mutex<Option<Instant>>,
+}
+
+impl RequestGate {
+    fn new(policy: GithubPoliteness) -> Self {
+        Self {
+            permits: Semaphore::new(policy.max_requests),
+            policy,
+            next_start: Mutex::new(None),
+        }
+    }
+
+    /// Waits for a free slot and the turn of the request, which runs while the permit is held.
+    pub(crate) async fn acquire(&self) -> SemaphorePermit<'_> {
+        let permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("the semaphore is never closed");
+        let start = {
+            let mut next_start = self.next_start.lock().unwrap();
+            let start = next_start.map_or_else(Instant::now, |next| next.max(Instant::now()));
+            *next_start = Some(start + self.policy.delay + self.jitter());
+            start
+        };
+        time::sleep_until(start).await;
+        permit
+    }
+
+    fn jitter(&self) -> Duration {
+        if self.policy.jitter.is_zero() {
+            return Duration::ZERO;
+        }
+        rand::thread_rng().gen_range(Duration::ZERO..=self.policy.jitter)
+    }
+}
+
+/// Waits until a request to GitHub may be sent, the permit must be held until its response is
+/// read.
+pub(crate) async fn github_permit() -> SemaphorePermit<'static> {
+    GITHUB.acquire().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn paces_and_limits_requests() {
+        let gate = RequestGate::new(GithubPoliteness {
+            max_requests: 1,
+            delay: Duration::from_millis(50),
+            jitter: Duration::from_millis(10),
+        });
+        let start = Instant::now();
+        let first = gate.acquire().await;
+        assert!(gate.permits.try_acquire().is_err());
+        drop(first);
+        drop(gate.acquire().await);
+        drop(gate.acquire().await);
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
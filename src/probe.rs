@@ -0,0 +1,157 @@
+use semver::{BuildMetadata, Version};
+use serde::{Deserialize, Serialize};
+
+use std::{fs, path::Path, process::Command};
+
+use crate::{
+    binary_path, error::VyperVmError, manifest::VersionManifest, releases::hex_string,
+    retry::BusyRetry, sha256_digest,
+};
+
+/// Output of running a Vyper binary with `--version`, keyed by the hash of the binary.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionProbe {
+    /// SHA-256 of the binary that was probed.
+    #[serde(with = "hex_string")]
+    pub sha256: Vec<u8>,
+    /// Trimmed stdout of `vyper --version`, e.g. `0.3.3+commit.48e326f0`.
+    pub output: String,
+}
+
+impl VersionProbe {
+    /// Returns the version reported by the binary without its build metadata, if it could be
+    /// parsed.
+    pub fn version(&self) -> Option<Version> {
+        let reported = self.output.split_whitespace().next()?;
+        let mut version = Version::parse(reported.trim_start_matches('v')).ok()?;
+        version.build = BuildMetadata::EMPTY;
+        Some(version)
+    }
+}
+
+/// Returns the output of `vyper --version` for an installed version.
+///
+/// The result is cached in the version's manifest and reused for as long as the binary hash
+/// matches, so repeated health checks don't spawn a process every time.
+pub fn probe_version(version: &Version) -> Result<VersionProbe, VyperVmError> {
+    let path = binary_path(version)?;
+    let binbytes =
+        fs::read(&path).map_err(|_| VyperVmError::VersionNotInstalled(version.to_string()))?;
+    let sha256 = sha256_digest(&binbytes);
+
+    let mut manifest = VersionManifest::read(version)?;
+    if let Some(probe) = manifest.probe.as_ref() {
+        if probe.sha256 == sha256 {
+            return Ok(probe.clone());
+        }
+    }
+
+    // the first run of a fresh install may find it still held by a scanner
+    let output = BusyRetry::load().run(|| Command::new(&path).arg("--version").output())?;
+    if !output.status.success() {
+        return Err(VyperVmError::ProbeFailed(
+            version.to_string(),
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    let probe = VersionProbe {
+        sha256,
+        output: String::from_utf8_lossy(&output.stdout).trim().to_string(),
+    };
+
+    manifest.probe = Some(probe.clone());
+    manifest.write(version)?;
+    Ok(probe)
+}
+
+/// Runs the binary of `version` at `path`, e.g. staged by an install, with `--version` and fails
+/// with [`VyperVmError::InstallVerificationFailed`] unless it runs and reports `version`, so a
+/// truncated download or the artifact of another version is never installed.
+pub(crate) fn verify_binary(path: &Path, version: &Version) -> Result<(), VyperVmError> {
+    let failed =
+        |reason: String| VyperVmError::InstallVerificationFailed(version.to_string(), reason);
+    let output = BusyRetry::load()
+        .run(|| Command::new(path).arg("--version").output())
+        .map_err(|err| failed(format!("it does not run: {}", err)))?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(failed(format!("--version failed: {}", stderr)));
+    }
+    if reported_release(&stdout) != Some((version.major, version.minor, version.patch)) {
+        return Err(failed(format!("it reports version `{}`", stdout)));
+    }
+    Ok(())
+}
+
+/// The release of a `--version` output, e.g. `(0, 4, 0)` for `0.4.0rc1+commit.abcdef`. Only the
+/// numbers are compared, pre-releases are spelled differently by the compiler and semver.
+fn reported_release(output: &str) -> Option<(u64, u64, u64)> {
+    let reported = output.split_whitespace().next()?.trim_start_matches('v');
+    let mut numbers = reported.splitn(3, '.').map(|part| {
+        part.chars()
+            .take_while(char::is_ascii_digit)
+            .collect::<String>()
+            .parse::<u64>()
+            .ok()
+    });
+    Some((numbers.next()??, numbers.next()??, numbers.next()??))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_reported_version() {
+        let probe = VersionProbe {
+            sha256: vec![],
+            output: "0.3.3+commit.48e326f0".to_string(),
+        };
+        assert_eq!(probe.version(), Some(Version::new(0, 3, 3)));
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn verifies_installed_binaries() {
+        use crate::testing::FakeVyper;
+
+        let version = Version::parse("0.4.0-rc.1").unwrap();
+        let sandbox = crate::testing::sandbox().unwrap();
+        let install = |fake: FakeVyper| sandbox.register(&version, &fake).unwrap();
+        let path = install(FakeVyper::new().version("0.4.0rc1+commit.00000000"));
+        verify_binary(&path, &version).unwrap();
+
+        install(FakeVyper::new().version("0.3.10+commit.00000000"));
+        let err = verify_binary(&path, &version).unwrap_err();
+        assert!(err.is_integrity());
+        assert!(err.to_string().contains("0.3.10"));
+
+        // a truncated download
+        fs::write(&path, "\x7fELF").unwrap();
+        assert!(verify_binary(&path, &version).is_err());
+        crate::remove_version(&version).unwrap();
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn reuses_cached_probe() {
+        let version = Version::new(0, 0, 4);
+        let fake = crate::testing::FakeVyper::new().version("0.0.4+commit.00000000");
+        crate::testing::sandbox()
+            .unwrap()
+            .register(&version, &fake)
+            .unwrap();
+
+        let probe = probe_version(&version).unwrap();
+        assert_eq!(probe.version(), Some(version.clone()));
+
+        // tamper with the cached output, it should be returned without running the binary
+        let mut manifest = VersionManifest::read(&version).unwrap();
+        manifest.probe.as_mut().unwrap().output = "cached".to_string();
+        manifest.write(&version).unwrap();
+        assert_eq!(probe_version(&version).unwrap().output, "cached");
+
+        crate::remove_version(&version).unwrap();
+    }
+}
@@ -0,0 +1,168 @@
+use semver::{Version, VersionReq};
+
+use crate::{
+    config::{Config, ProfileConfig},
+    error::VyperVmError,
+    input::VersionInput,
+};
+
+/// The versions a profile declares, resolved among the available releases.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProfileVersions {
+    /// The global version of the profile.
+    pub default: Option<Version>,
+    /// Every version the profile keeps installed, the default one included.
+    pub versions: Vec<Version>,
+    /// Declared versions no available release satisfies.
+    pub unsatisfiable: Vec<String>,
+}
+
+impl ProfileVersions {
+    /// Exact requirements of the versions, for [`crate::plan`].
+    pub fn requirements(&self) -> Vec<VersionReq> {
+        self.versions
+            .iter()
+            .map(|version| VersionReq::parse(&format!("={}", version)).expect("a valid version"))
+            .collect()
+    }
+}
+
+/// The active profile of the config and its name, `None` if no profile is active.
+pub fn active_profile() -> Result<Option<(String, ProfileConfig)>, VyperVmError> {
+    let config = Config::load()?;
+    match config.profile {
+        Some(name) => match config.profiles.get(&name) {
+            Some(profile) => Ok(Some((name, profile.clone()))),
+            None => Err(VyperVmError::UnknownProfile(name)),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Makes `name` the active profile, returning it.
+pub fn use_profile(name: &str) -> Result<ProfileConfig, VyperVmError> {
+    let mut config = Config::load()?;
+    let profile = config
+        .profiles
+        .get(name)
+        .cloned()
+        .ok_or_else(|| VyperVmError::UnknownProfile(name.to_string()))?;
+    config.profile = Some(name.to_string());
+    config.save()?;
+    Ok(profile)
+}
+
+/// Resolves the versions of `profile` among `available`, `stable` being the release of the
+/// `stable` channel. Requirements resolve to the newest matching release, prereleases included
+/// if the profile allows them.
+pub fn profile_versions(
+    profile: &ProfileConfig,
+    available: &[Version],
+    stable: Option<&Version>,
+) -> Result<ProfileVersions, VyperVmError> {
+    let mut resolved = ProfileVersions::default();
+    let declared = profile.default.iter().chain(profile.versions.iter());
+    for (i, input) in declared.enumerate() {
+        let version = match parse_declared(input)? {
+            Declared::Stable => stable.cloned(),
+            Declared::Exact(version) => Some(version),
+            Declared::Requirement(req) => available
+                .iter()
+                .rev()
+                .find(|version| matches(&req, version, profile.prereleases))
+                .cloned(),
+        };
+        match version {
+            Some(version) => {
+                if i == 0 && profile.default.is_some() {
+                    resolved.default = Some(version.clone());
+                }
+                if !resolved.versions.contains(&version) {
+                    resolved.versions.push(version);
+                }
+            }
+            None => resolved.unsatisfiable.push(input.clone()),
+        }
+    }
+    resolved.versions.sort();
+    Ok(resolved)
+}
+
+/// A version declared by a profile.
+enum Declared {
+    Stable,
+    Exact(Version),
+    Requirement(VersionReq),
+}
+
+/// Parses a declared version: anything [`VersionInput::parse`] accepts, a release line
+/// standing for its newest release, or a semver requirement, with or without an operator.
+fn parse_declared(input: &str) -> Result<Declared, VyperVmError> {
+    match VersionInput::parse(input) {
+        Ok(VersionInput::Stable) => Ok(Declared::Stable),
+        Ok(VersionInput::Exact(version)) => Ok(Declared::Exact(version)),
+        Ok(VersionInput::Latest) => Ok(Declared::Requirement(VersionReq::STAR)),
+        Ok(VersionInput::Requirement(req)) => Ok(Declared::Requirement(req)),
+        Ok(VersionInput::Minor { major, minor }) => Ok(Declared::Requirement(VersionReq::parse(
+            &format!("~{}.{}", major, minor),
+        )?)),
+        Err(_) => VersionReq::parse(input)
+            .map(Declared::Requirement)
+            .map_err(|err| VyperVmError::InvalidConfig(format!("{}: {}", input, err))),
+    }
+}
+
+/// Whether `version` satisfies `req`. Semver requirements never match prereleases of other
+/// versions, so with `prereleases` a prerelease matches if its release would.
+fn matches(req: &VersionReq, version: &Version, prereleases: bool) -> bool {
+    req.matches(version)
+        || (prereleases
+            && !version.pre.is_empty()
+            && req.matches(&Version::new(version.major, version.minor, version.patch)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_profile_versions() {
+        let available = ["0.2.16", "0.3.7", "0.3.9", "0.3.10", "0.4.0-rc.1"]
+            .iter()
+            .map(|version| Version::parse(version).unwrap())
+            .collect::<Vec<_>>();
+        let stable = Version::new(0, 3, 10);
+        let mut profile = ProfileConfig {
+            default: Some("0.3".to_string()),
+            prereleases: false,
+            versions: vec![
+                "v0.3.7".to_string(),
+                ">=0.3.9".to_string(),
+                "stable".to_string(),
+                "0.5".to_string(),
+            ],
+        };
+
+        let resolved = profile_versions(&profile, &available, Some(&stable)).unwrap();
+        assert_eq!(resolved.default, Some(Version::new(0, 3, 10)));
+        assert_eq!(
+            resolved.versions,
+            vec![Version::new(0, 3, 7), Version::new(0, 3, 10)]
+        );
+        assert_eq!(resolved.unsatisfiable, vec!["0.5"]);
+
+        profile.prereleases = true;
+        let resolved = profile_versions(&profile, &available, Some(&stable)).unwrap();
+        assert_eq!(
+            resolved.versions.last(),
+            Some(&Version::parse("0.4.0-rc.1").unwrap())
+        );
+        assert_eq!(
+            resolved.requirements()[0],
+            VersionReq::parse("=0.3.7").unwrap()
+        );
+
+        profile.versions = vec!["not a version".to_string()];
+        assert!(profile_versions(&profile, &available, None).is_err());
+    }
+}
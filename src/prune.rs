@@ -0,0 +1,239 @@
+use semver::Version;
+
+use std::{fmt, fs, time::Duration, time::UNIX_EPOCH};
+
+use crate::{
+    binary_path, current_version,
+    error::VyperVmError,
+    installed_versions,
+    manifest::{last_used, VersionManifest, VersionOrigin},
+    releases::Releases,
+    stats::unix_timestamp,
+    stats::UsageStats,
+};
+
+/// Parses a duration like `90d`, `2w`, `12h`, `30m` or `45s`.
+pub fn parse_duration(s: &str) -> Result<Duration, VyperVmError> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| VyperVmError::InvalidDuration(s.to_string()))?;
+    let (amount, unit) = s.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| VyperVmError::InvalidDuration(s.to_string()))?;
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(VyperVmError::InvalidDuration(s.to_string())),
+    };
+    let secs = amount
+        .checked_mul(secs)
+        .ok_or_else(|| VyperVmError::InvalidDuration(s.to_string()))?;
+    Ok(Duration::from_secs(secs))
+}
+
+/// Returns the installed versions that were neither used nor installed within `unused_for`,
+/// based on the last use recorded in their manifests. The global version is never returned.
+pub fn unused_versions(unused_for: Duration) -> Result<Vec<Version>, VyperVmError> {
+    let stats = UsageStats::read()?;
+    let current = current_version()?;
+    let cutoff = unix_timestamp().saturating_sub(unused_for.as_secs());
+
+    let mut unused = vec![];
+    for version in installed_versions()? {
+        if current.as_ref() == Some(&version) {
+            continue;
+        }
+        let last_used = last_used(&version, &stats).unwrap_or_default();
+        if last_used.max(installed_at(&version)) < cutoff {
+            unused.push(version);
+        }
+    }
+    Ok(unused)
+}
+
+/// Why an installed version is orphaned, see [`orphaned_versions`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OrphanReason {
+    /// The release is gone upstream, or no longer has an artifact for this platform.
+    NotUpstream,
+    /// The binary is missing or does not match the digests recorded when it was installed.
+    IntegrityFailed,
+}
+
+impl fmt::Display for OrphanReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrphanReason::NotUpstream => f.write_str("no longer released for this platform"),
+            OrphanReason::IntegrityFailed => f.write_str("binary fails its integrity check"),
+        }
+    }
+}
+
+/// An installed version [`orphaned_versions`] found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrphanedVersion {
+    pub version: Version,
+    pub reason: OrphanReason,
+}
+
+/// Returns the installed versions that fail their integrity check, or that `releases`, the
+/// release list of this platform, no longer has, e.g. after a yanked asset or a removed tag.
+/// `releases` must be freshly fetched, see [`fresh_releases`](crate::fresh_releases): a version
+/// missing from a stored list or the bundled snapshot may well be released.
+///
+/// Versions imported from a file or built from source are never upstream, and versions outside
+/// the range of the list are only missing from an outdated or truncated list, so neither counts
+/// as gone.
+pub fn orphaned_versions(releases: &Releases) -> Result<Vec<OrphanedVersion>, VyperVmError> {
+    let oldest = releases.releases.keys().next();
+    let newest = releases.releases.keys().next_back();
+    let mut orphaned = vec![];
+    for version in installed_versions()? {
+        let manifest = VersionManifest::read(&version)?;
+        let intact = match fs::read(binary_path(&version)?) {
+            Ok(binbytes) => {
+                !binbytes.is_empty()
+                    && manifest
+                        .digests
+                        .as_ref()
+                        .is_none_or(|digests| digests.mismatch(&binbytes).is_none())
+            }
+            Err(_) => false,
+        };
+        let upstream = matches!(
+            manifest.origin,
+            Some(VersionOrigin::LocalFile | VersionOrigin::Source)
+        ) || newest.is_none_or(|newest| version > *newest)
+            || oldest.is_none_or(|oldest| version < *oldest)
+            || releases.releases.contains_key(&version);
+        let reason = if !intact {
+            OrphanReason::IntegrityFailed
+        } else if !upstream {
+            OrphanReason::NotUpstream
+        } else {
+            continue;
+        };
+        orphaned.push(OrphanedVersion { version, reason });
+    }
+    Ok(orphaned)
+}
+
+/// Returns the unix timestamp at which the version was installed, approximated by the
+/// modification time of its binary. Unlike the version directory, which changes with every
+/// rewrite of the manifest, the binary is never written again once installed.
+pub(crate) fn installed_at(version: &Version) -> u64 {
+    binary_path(version)
+        .ok()
+        .and_then(|path| fs::metadata(path).ok())
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(
+            parse_duration("90d").unwrap(),
+            Duration::from_secs(90 * 86400)
+        );
+        assert_eq!(
+            parse_duration("2w").unwrap(),
+            Duration::from_secs(14 * 86400)
+        );
+        assert_eq!(
+            parse_duration("12h").unwrap(),
+            Duration::from_secs(12 * 3600)
+        );
+        assert!(parse_duration("90").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("5y").is_err());
+        assert!(matches!(
+            parse_duration("99999999999999999w"),
+            Err(VyperVmError::InvalidDuration(_))
+        ));
+    }
+
+    #[test]
+    fn manifest_updates_do_not_change_install_time() {
+        let version = Version::new(0, 0, 26);
+        crate::testing::sandbox().unwrap();
+        crate::setup_version(version.to_string().as_str()).unwrap();
+        fs::write(binary_path(&version).unwrap(), b"fake vyper").unwrap();
+        let installed = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        fs::File::options()
+            .write(true)
+            .open(binary_path(&version).unwrap())
+            .unwrap()
+            .set_modified(installed)
+            .unwrap();
+
+        VersionManifest::default().write(&version).unwrap();
+        crate::manifest::record_last_used(&version).unwrap();
+        assert_eq!(installed_at(&version), 1_600_000_000);
+
+        crate::remove_version(&version).unwrap();
+    }
+
+    #[test]
+    fn finds_orphaned_versions() {
+        let tampered = Version::new(0, 0, 20);
+        let yanked = Version::new(0, 0, 21);
+        let released = Version::new(0, 0, 22);
+        crate::testing::sandbox().unwrap();
+        for version in [&yanked, &tampered, &released] {
+            crate::setup_version(version.to_string().as_str()).unwrap();
+            fs::write(binary_path(version).unwrap(), b"fake vyper").unwrap();
+        }
+        let manifest = VersionManifest {
+            digests: Some(crate::Digests::compute(b"fake vyper")),
+            ..Default::default()
+        };
+        manifest.write(&tampered).unwrap();
+        fs::write(binary_path(&tampered).unwrap(), b"evil vyper").unwrap();
+
+        let mut releases = Releases::default();
+        for version in [&tampered, &released] {
+            releases
+                .releases
+                .insert(version.clone(), format!("vyper.{}.linux", version));
+        }
+        let orphaned = orphaned_versions(&releases).unwrap();
+        let reason = |version: &Version| {
+            orphaned
+                .iter()
+                .find(|orphan| orphan.version == *version)
+                .map(|orphan| orphan.reason.clone())
+        };
+        assert_eq!(reason(&yanked), Some(OrphanReason::NotUpstream));
+        assert_eq!(reason(&tampered), Some(OrphanReason::IntegrityFailed));
+        assert_eq!(reason(&released), None);
+
+        // a partial list, e.g. a snapshot of the newer releases only, never orphans older versions
+        let mut partial = Releases::default();
+        partial
+            .releases
+            .insert(released.clone(), format!("vyper.{}.linux", released));
+        let orphaned = orphaned_versions(&partial).unwrap();
+        assert_eq!(
+            orphaned,
+            vec![OrphanedVersion {
+                version: tampered.clone(),
+                reason: OrphanReason::IntegrityFailed,
+            }]
+        );
+
+        for version in [&yanked, &tampered, &released] {
+            crate::remove_version(version).unwrap();
+        }
+    }
+}
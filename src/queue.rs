@@ -0,0 +1,411 @@
+use futures_util::{stream, StreamExt};
+use once_cell::sync::Lazy;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{Semaphore, SemaphorePermit},
+    time::{self, Instant},
+};
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    binary_path,
+    config::Config,
+    error::VyperVmError,
+    events::{InstallEvent, Reporter},
+    home::active_home,
+    installed_versions,
+    notify::DesktopNotifier,
+    plan::plan_install,
+    plan::InstallPlan,
+    setup_home,
+};
+
+/// File name of the persisted state of the download queue in the VVM home directory.
+const QUEUE_STATE_FILENAME: &str = ".download-queue.json";
+
+/// Number of concurrent downloads of a queue when not specified otherwise.
+pub const DEFAULT_DOWNLOAD_JOBS: usize = 4;
+
+/// Versions a process installs at the same time when `max_concurrent_installs` is not set.
+pub const DEFAULT_MAX_CONCURRENT_INSTALLS: usize = 4;
+
+/// How long a blocking install waits before trying again to get an install slot.
+const BLOCKING_SLOT_POLL: Duration = Duration::from_millis(50);
+
+/// Slots of every install of this process, see [`InstallSlots`].
+static INSTALLS: Lazy<InstallSlots> = Lazy::new(|| {
+    let max = Config::load()
+        .ok()
+        .and_then(|config| config.max_concurrent_installs)
+        .unwrap_or(DEFAULT_MAX_CONCURRENT_INSTALLS);
+    InstallSlots::new(max)
+});
+
+/// Versions being installed by a queue of this process, so concurrent queues never download the
+/// same artifact twice.
+static IN_FLIGHT: Lazy<Mutex<HashMap<Version, Arc<tokio::sync::Mutex<()>>>>> =
+    Lazy::new(Default::default);
+
+/// Versions a queue still has to install, persisted so an interrupted run can be resumed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct QueueState {
+    pending: Vec<Version>,
+}
+
+/// Installs many versions at once: at most `jobs` downloads run at a time, sharing an optional
+/// bandwidth limit, and every artifact is verified before it is installed, see
+/// [`InstallPlan::apply`].
+///
+/// The versions left to install are persisted in the VVM home directory, shared by the queues
+/// of every process. Versions left over by an interrupted run are only installed again by
+/// [`DownloadQueue::resume`].
+#[derive(Clone, Debug)]
+pub struct DownloadQueue {
+    jobs: usize,
+    /// Bytes per second shared by all downloads.
+    bandwidth: Option<u64>,
+    reporters: Vec<Arc<dyn Reporter>>,
+}
+
+impl DownloadQueue {
+    /// A queue running `jobs` downloads at a time, limited to `download_bandwidth` from the
+    /// config, and reporting to a desktop notification when `notifications` is set.
+    pub fn new(jobs: usize) -> Self {
+        let config = Config::load().unwrap_or_default();
+        let mut queue = Self {
+            jobs: jobs.max(1),
+            bandwidth: config.download_bandwidth,
+            reporters: vec![],
+        };
+        if config.notifications {
+            queue = queue.reporter(Arc::new(DesktopNotifier::new()));
+        }
+        queue
+    }
+
+    /// Adds a reporter receiving the events of every install.
+    pub fn reporter(mut self, reporter: Arc<dyn Reporter>) -> Self {
+        self.reporters.push(reporter);
+        self
+    }
+
+    /// Limits the bandwidth shared by all downloads, in bytes per second.
+    pub fn bandwidth(mut self, bandwidth: Option<u64>) -> Self {
+        self.bandwidth = bandwidth;
+        self
+    }
+
+    /// Versions an interrupted run did not install, along with those of queues still running.
+    pub fn interrupted() -> Result<Vec<Version>, VyperVmError> {
+        let _lock = lock_state()?;
+        Ok(read_state()?.pending)
+    }
+
+    /// Installs the versions an interrupted run did not install, see
+    /// [`DownloadQueue::interrupted`].
+    ///
+    /// Returns the result of every install, in the order they finished.
+    pub async fn resume(
+        &self,
+    ) -> Result<Vec<(Version, Result<PathBuf, VyperVmError>)>, VyperVmError> {
+        Ok(self.run(&Self::interrupted()?).await)
+    }
+
+    /// Installs `versions`, skipping installed ones.
+    ///
+    /// Returns the result of every install, in the order they finished.
+    pub async fn run(&self, versions: &[Version]) -> Vec<(Version, Result<PathBuf, VyperVmError>)> {
+        self.run_items(versions.iter().map(|v| (v.clone(), None)).collect())
+            .await
+    }
+
+    /// Applies already computed install plans, see [`DownloadQueue::run`].
+    pub async fn run_plans(
+        &self,
+        plans: &[InstallPlan],
+    ) -> Vec<(Version, Result<PathBuf, VyperVmError>)> {
+        self.run_items(
+            plans
+                .iter()
+                .map(|plan| (plan.version.clone(), Some(plan.clone())))
+                .collect(),
+        )
+        .await
+    }
+
+    async fn run_items(
+        &self,
+        mut items: Vec<(Version, Option<InstallPlan>)>,
+    ) -> Vec<(Version, Result<PathBuf, VyperVmError>)> {
+        let mut seen = HashSet::new();
+        items.retain(|(version, _)| seen.insert(version.clone()));
+        // the state is best effort, a queue that cannot be persisted still installs
+        let _ = update_state(|state| {
+            for (version, _) in &items {
+                if !state.pending.contains(version) {
+                    state.pending.push(version.clone());
+                }
+            }
+        });
+
+        let throttle = self.bandwidth.map(Throttle::new);
+        let (throttle, reporters) = (&throttle, &self.reporters);
+        let results = stream::iter(items)
+            .map(|(version, plan)| async move {
+                let report = |event: InstallEvent| {
+                    reporters
+                        .iter()
+                        .for_each(|reporter| reporter.on_event(&version, &event))
+                };
+                let result = install_once(&version, plan, throttle.as_ref(), report).await;
+                match &result {
+                    Ok(path) => report(InstallEvent::Installed(path.clone())),
+                    Err(err) => report(InstallEvent::Failed(err.to_string())),
+                }
+                let _ = update_state(|state| state.pending.retain(|v| *v != version));
+                (version, result)
+            })
+            .buffer_unordered(self.jobs)
+            .collect()
+            .await;
+        self.reporters
+            .iter()
+            .for_each(|reporter| reporter.on_finished());
+        results
+    }
+}
+
+/// Installs a version unless it is installed already, waiting for another queue of this process
+/// downloading it. The last event of the install is left to the caller.
+async fn install_once(
+    version: &Version,
+    plan: Option<InstallPlan>,
+    throttle: Option<&Throttle>,
+    report: impl Fn(InstallEvent),
+) -> Result<PathBuf, VyperVmError> {
+    let lock = IN_FLIGHT
+        .lock()
+        .unwrap()
+        .entry(version.clone())
+        .or_default()
+        .clone();
+    let _guard = lock.lock().await;
+
+    setup_home()?;
+    if installed_versions()?.contains(version) {
+        return binary_path(version);
+    }
+    let plan = match plan {
+        Some(plan) => plan,
+        None => plan_install(version).await?,
+    };
+    plan.apply_throttled(
+        |event| {
+            if !matches!(event, InstallEvent::Installed(_) | InstallEvent::Failed(_)) {
+                report(event)
+            }
+        },
+        throttle,
+    )
+    .await
+}
+
+/// Limits the installs of different versions running at the same time in this process, whether
+/// they come from queues, `sync` or independent calls, so small runners keep some disk and
+/// network for everything else. Installs of the same version are serialized by their lock file.
+#[derive(Debug)]
+pub(crate) struct InstallSlots {
+    permits: Semaphore,
+}
+
+impl InstallSlots {
+    fn new(max: usize) -> Self {
+        Self {
+            permits: Semaphore::new(max.max(1)),
+        }
+    }
+
+    /// Waits for a free slot, the install runs while the permit is held.
+    async fn acquire(&self) -> SemaphorePermit<'_> {
+        self.permits
+            .acquire()
+            .await
+            .expect("the semaphore is never closed")
+    }
+
+    /// Blocks the thread until a slot is free, for installs outside of a runtime.
+    fn blocking_acquire(&self) -> SemaphorePermit<'_> {
+        loop {
+            if let Ok(permit) = self.permits.try_acquire() {
+                return permit;
+            }
+            std::thread::sleep(BLOCKING_SLOT_POLL);
+        }
+    }
+}
+
+/// Waits until this process may start another install, the permit must be held until it is
+/// done.
+pub(crate) async fn install_permit() -> SemaphorePermit<'static> {
+    INSTALLS.acquire().await
+}
+
+/// Blocking version of [`install_permit`].
+#[cfg_attr(not(feature = "blocking"), allow(dead_code))]
+pub(crate) fn blocking_install_permit() -> SemaphorePermit<'static> {
+    INSTALLS.blocking_acquire()
+}
+
+/// Keeps the total download rate of many downloads under a number of bytes per second.
+#[derive(Debug)]
+pub(crate) struct Throttle {
+    bytes_per_sec: u64,
+    start: Instant,
+    downloaded: Mutex<u64>,
+}
+
+impl Throttle {
+    fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec: bytes_per_sec.max(1),
+            start: Instant::now(),
+            downloaded: Mutex::new(0),
+        }
+    }
+
+    /// Accounts for `bytes` more downloaded bytes, sleeping while over the limit.
+    pub(crate) async fn consume(&self, bytes: usize) {
+        let downloaded = {
+            let mut downloaded = self.downloaded.lock().unwrap();
+            *downloaded += bytes as u64;
+            *downloaded
+        };
+        let due = Duration::from_secs_f64(downloaded as f64 / self.bytes_per_sec as f64);
+        time::sleep_until(self.start + due).await;
+    }
+}
+
+pub(crate) fn queue_state_path() -> Result<PathBuf, VyperVmError> {
+    Ok(active_home()?.join(QUEUE_STATE_FILENAME))
+}
+
+fn read_state() -> Result<QueueState, VyperVmError> {
+    match fs::read(queue_state_path()?) {
+        Ok(content) => Ok(serde_json::from_slice(&content)?),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(QueueState::default()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Locks the state exclusively until the returned file is dropped, so the queues of concurrent
+/// processes don't lose each other's versions.
+fn lock_state() -> Result<fs::File, VyperVmError> {
+    use fs2::FileExt;
+    // never removed, so that every process locks the same file
+    let lock_file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(active_home()?.join(".lock-download-queue"))?;
+    lock_file.lock_exclusive()?;
+    Ok(lock_file)
+}
+
+/// Applies `f` to the persisted state while holding its lock.
+fn update_state<F: FnOnce(&mut QueueState)>(f: F) -> Result<(), VyperVmError> {
+    let _lock = lock_state()?;
+    let mut state = read_state()?;
+    f(&mut state);
+    write_state(&state)
+}
+
+fn write_state(state: &QueueState) -> Result<(), VyperVmError> {
+    if state.pending.is_empty() {
+        return match fs::remove_file(queue_state_path()?) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        };
+    }
+    fs::write(queue_state_path()?, serde_json::to_vec(state)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn skips_installed_versions_and_clears_state() {
+        let version = Version::new(0, 0, 10);
+        crate::testing::sandbox().unwrap();
+        crate::setup_version(version.to_string().as_str()).unwrap();
+        fs::write(binary_path(&version).unwrap(), b"fake vyper").unwrap();
+
+        let results = DownloadQueue::new(2)
+            .run(&[version.clone(), version.clone()])
+            .await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].1.as_ref().unwrap(),
+            &binary_path(&version).unwrap()
+        );
+        assert!(!DownloadQueue::interrupted().unwrap().contains(&version));
+        crate::remove_version(&version).unwrap();
+    }
+
+    #[tokio::test]
+    async fn leaves_interrupted_versions_to_resume() {
+        let version = Version::new(0, 0, 23);
+        let interrupted = Version::new(0, 0, 24);
+        crate::testing::sandbox().unwrap();
+        crate::setup_version(version.to_string().as_str()).unwrap();
+        fs::write(binary_path(&version).unwrap(), b"fake vyper").unwrap();
+        update_state(|state| state.pending.push(interrupted.clone())).unwrap();
+
+        let results = DownloadQueue::new(2)
+            .run(std::slice::from_ref(&version))
+            .await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, version);
+        assert!(DownloadQueue::interrupted().unwrap().contains(&interrupted));
+
+        update_state(|state| state.pending.retain(|v| *v != interrupted)).unwrap();
+        crate::remove_version(&version).unwrap();
+    }
+
+    #[tokio::test]
+    async fn limits_concurrent_installs() {
+        let slots = Arc::new(InstallSlots::new(1));
+        let first = slots.acquire().await;
+        assert!(slots.permits.try_acquire().is_err());
+
+        let waiting = Arc::clone(&slots);
+        let blocked = std::thread::spawn(move || {
+            let start = std::time::Instant::now();
+            drop(waiting.blocking_acquire());
+            start.elapsed()
+        });
+        std::thread::sleep(Duration::from_millis(100));
+        drop(first);
+        assert!(blocked.join().unwrap() >= Duration::from_millis(50));
+        drop(slots.acquire().await);
+    }
+
+    #[tokio::test]
+    async fn throttles_downloads() {
+        let throttle = Throttle::new(1000);
+        let start = Instant::now();
+        throttle.consume(100).await;
+        throttle.consume(100).await;
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+}
@@ -1,4 +1,8 @@
-use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+use once_cell::sync::Lazy;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, AUTHORIZATION, LINK, USER_AGENT},
+    StatusCode,
+};
 use semver::Version;
 use serde::{
     de::{self, Deserializer},
@@ -7,11 +11,64 @@ use serde::{
 use std::collections::BTreeMap;
 use url::Url;
 
-use std::env;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock,
+    },
+    time::Duration,
+};
 
-use crate::{error::VyperVmError, platform::Platform};
+use crate::{
+    checksums::ChecksumManifest,
+    config::Config,
+    digest::{Digests, HashAlgorithm},
+    error::VyperVmError,
+    home::active_home,
+    http, metrics,
+    platform::Platform,
+    politeness,
+};
 
 const GITHUB_RELEASES: &str = "https://api.github.com/repos/vyperlang/vyper/releases?per_page=100";
+/// Environment variables holding the GitHub token, in priority order.
+const GITHUB_TOKEN_ENVS: &[&str] = &["VVM_GITHUB_TOKEN", "GITHUB_TOKEN"];
+
+/// Pages of the release list fetched when `github_max_release_pages` is not set.
+pub const DEFAULT_MAX_RELEASE_PAGES: usize = 10;
+
+const GITHUB_RELEASE_BY_TAG: &str = "https://api.github.com/repos/vyperlang/vyper/releases/tags/";
+
+/// File name of the last fetched release list in the VVM home directory.
+pub const RELEASES_CACHE_FILENAME: &str = "releases.json";
+
+/// How long the fetched release list is used without fetching it again, unless configured with
+/// `releases_cache_ttl`.
+const DEFAULT_RELEASES_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Whether vvm runs offline, see [`set_offline`].
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Environment variable overriding `artifact_selector` of the config, see
+/// [`ArtifactSelector::parse`].
+pub const ARTIFACT_SELECTOR_ENV: &str = "VVM_ARTIFACT";
+
+/// Selector set with [`set_artifact_selector`], overriding the environment and the config.
+static ARTIFACT_SELECTOR: Lazy<RwLock<Option<ArtifactSelector>>> = Lazy::new(Default::default);
+
+/// Suffixes of checksum files attached next to a single artifact, e.g.
+/// `vyper.0.4.1+commit.8a93dd27.linux.sha256`.
+const ARTIFACT_CHECKSUM_SUFFIXES: &[&str] = &[".sha256", ".sha256sum"];
+
+/// Names of checksum files listing every artifact of a release, as written by `sha256sum`.
+const CHECKSUM_LIST_NAMES: &[&str] = &["sha256sums", "sha256sums.txt", "checksums.txt"];
+
+/// Zstd compressed snapshot of historic releases, generated at build time from
+/// `releases-snapshot.json`.
+const RELEASE_SNAPSHOT: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/releases-snapshot.json.zst"));
 
 /// Defines the struct that the JSON-formatted release list can be deserialized into.
 ///
@@ -34,12 +91,21 @@ const GITHUB_RELEASES: &str = "https://api.github.com/repos/vyperlang/vyper/rele
 struct VyperAsset {
     name: String,
     browser_download_url: String,
+    #[serde(default)]
+    size: u64,
+    /// Digest published by GitHub for the asset, e.g. `sha256:…`, missing for older releases.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    digest: Option<String>,
 }
 /// Both the key and value are deserialized into semver::Version.
 #[derive(Debug, Serialize, Deserialize)]
 struct VyperReleases {
     tag_name: String,
     assets: Vec<VyperAsset>,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    published_at: Option<String>,
 }
 
 /// Both the key and value are deserialized into semver::Version.
@@ -47,17 +113,62 @@ struct VyperReleases {
 pub struct Releases {
     pub builds: Vec<BuildInfo>,
     pub releases: BTreeMap<Version, String>,
+    /// Releases that were skipped because none of their assets is usable on the platform.
+    #[serde(default)]
+    pub unmatched: Vec<UnmatchedRelease>,
+    /// Other assets matching the platform that lost to the chosen artifact, per version.
+    #[serde(default)]
+    pub alternatives: BTreeMap<Version, Vec<String>>,
+    /// Size in bytes of the chosen artifact, per version.
+    #[serde(default)]
+    pub sizes: BTreeMap<Version, u64>,
+    /// UTC publication date of the release, e.g. `2023-10-04T16:27:20Z`, per version.
+    #[serde(default)]
+    pub published: BTreeMap<Version, String>,
+    /// Versions marked as prerelease upstream.
+    #[serde(default)]
+    pub prereleases: Vec<Version>,
+    /// URL of the checksum file attached to the release covering the chosen artifact, per
+    /// version.
+    #[serde(default)]
+    pub checksum_files: BTreeMap<Version, String>,
+}
+
+/// A remote release without a usable asset for the platform, kept for diagnostics.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnmatchedRelease {
+    pub tag: String,
+    /// The version of the release, `None` if the tag is not a valid semver version.
+    pub version: Option<Version>,
+    /// Names of all the assets of the release.
+    pub assets: Vec<String>,
 }
 
 impl Releases {
-    /// NOTE: vyper binaries dont support checksums
+    /// Returns the releases stored by the last fetch, or the bundled snapshot, without network
+    /// access.
+    pub fn from_cache(platform: Platform) -> Result<Self, VyperVmError> {
+        Ok(into_releases(stored_releases()?, platform))
+    }
+
+    /// Returns the SHA256 checksum of the artifact of the version, from its build info or else
+    /// from the manifest of known checksums, see [`ChecksumManifest`].
     pub fn get_checksum(&self, v: &Version) -> Option<Vec<u8>> {
-        for build in self.builds.iter() {
-            if build.version.eq(v) {
-                return Some(build.sha256.clone());
-            }
+        if let Some(checksum) = self.published_checksum(v) {
+            return Some(checksum);
         }
-        None
+        ChecksumManifest::load()
+            .ok()?
+            .checksum(v, self.get_artifact(v)?)?
+            .get(HashAlgorithm::Sha256)
+    }
+
+    /// Returns the SHA256 checksum GitHub publishes for the artifact of the version, if any.
+    pub fn published_checksum(&self, v: &Version) -> Option<Vec<u8>> {
+        self.builds
+            .iter()
+            .find(|build| build.version == *v && !build.sha256.is_empty())
+            .map(|build| build.sha256.clone())
     }
 
     /// Returns the artifact of the version if any
@@ -82,7 +193,7 @@ pub struct BuildInfo {
 }
 
 /// Helper serde module to serialize and deserialize bytes as hex.
-mod hex_string {
+pub(crate) mod hex_string {
     use super::*;
     use serde::Serializer;
     pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
@@ -104,98 +215,763 @@ mod hex_string {
     }
 }
 
+/// Makes vvm run offline, e.g. for `--offline`: releases come from the cached list only, see
+/// [`Releases::from_cache`], and installs fail with [`VyperVmError::Offline`].
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+/// Returns true if vvm runs offline, see [`set_offline`].
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// Fails with [`VyperVmError::Offline`] if vvm runs offline, `action` naming what needs the
+/// network.
+pub(crate) fn ensure_online(action: impl Into<String>) -> Result<(), VyperVmError> {
+    if is_offline() {
+        return Err(VyperVmError::Offline(action.into()));
+    }
+    Ok(())
+}
+
 /// Blocking version for [`all_releases`]
 #[cfg(feature = "blocking")]
 pub fn blocking_all_releases(platform: Platform) -> Result<Releases, VyperVmError> {
-    let vyper_releases = blocking_get_releases()?;
+    if let Some(releases) = cached_releases(platform)? {
+        return Ok(releases);
+    }
+    Ok(into_releases(
+        with_offline_fallback(blocking_get_releases())?,
+        platform,
+    ))
+}
 
-    let mut builds: Vec<BuildInfo> = Vec::new();
-    let mut releases: BTreeMap<Version, String> = BTreeMap::new();
-    let platform_str = &platform.to_string();
-    for vyper_release in vyper_releases {
-        for asset in vyper_release.assets {
-            if asset.name.contains(platform_str) {
-                let version =
-                    Version::parse(&vyper_release.tag_name.trim_start_matches("v")).unwrap();
-                builds.push(BuildInfo {
-                    version: version.clone(),
-                    sha256: Vec::new(),
-                });
-                releases.insert(version, asset.name);
+/// Fetch all releases available for the provided platform.
+///
+/// The fetched list is stored in `~/.vvm/releases.json` and used instead of fetching it again
+/// for `releases_cache_ttl` seconds, or always when offline. Without network access, the stored
+/// list is used instead, and as a last resort the snapshot of historic releases shipped with vvm.
+pub async fn all_releases(platform: Platform) -> Result<Releases, VyperVmError> {
+    if let Some(releases) = cached_releases(platform)? {
+        return Ok(releases);
+    }
+    Ok(into_releases(
+        with_offline_fallback(get_releases().await)?,
+        platform,
+    ))
+}
+
+/// Returns the stored release list if vvm runs offline or it is younger than
+/// `releases_cache_ttl`, `None` if the releases must be fetched.
+fn cached_releases(platform: Platform) -> Result<Option<Releases>, VyperVmError> {
+    if is_offline() {
+        return Releases::from_cache(platform).map(Some);
+    }
+    let ttl = Config::load()?
+        .releases_cache_ttl
+        .map_or(DEFAULT_RELEASES_CACHE_TTL, Duration::from_secs);
+    let path = releases_cache_path()?;
+    if !is_fresh(&path, ttl) {
+        return Ok(None);
+    }
+    // a damaged list is fetched again
+    Ok(fs::read(path)
+        .ok()
+        .and_then(|content| serde_json::from_slice(&content).ok())
+        .map(|releases| into_releases(releases, platform)))
+}
+
+/// Returns true if the file at `path` was modified less than `ttl` ago.
+fn is_fresh(path: &Path, ttl: Duration) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age < ttl)
+}
+
+/// Fetches the release list of the provided platform from GitHub, bypassing the stored list and
+/// the bundled snapshot, for decisions an outdated list would get wrong, e.g. that a release is
+/// gone upstream. Fails offline or when the list cannot be fetched.
+pub async fn fresh_releases(platform: Platform) -> Result<Releases, VyperVmError> {
+    ensure_online("fetching the release list")?;
+    let releases = get_releases().await?;
+    store_releases(&releases);
+    Ok(into_releases(releases, platform))
+}
+
+/// Fetches the full release list from GitHub in the format of `releases-snapshot.json`, the
+/// snapshot of historic releases built into vvm, see `vvm snapshot-releases`.
+pub async fn release_snapshot() -> Result<Vec<u8>, VyperVmError> {
+    ensure_online("fetching the release list")?;
+    let releases = get_releases().await?;
+    let mut snapshot = serde_json::to_vec_pretty(&releases)?;
+    snapshot.push(b'\n');
+    Ok(snapshot)
+}
+
+/// Fetches the release list from GitHub and returns the download URL of every build published
+/// with a release, for every platform, by version and artifact name. Checksum files are left out.
+pub(crate) async fn published_artifacts(
+) -> Result<BTreeMap<Version, BTreeMap<String, Url>>, VyperVmError> {
+    ensure_online("fetching the release list")?;
+    let releases = get_releases().await?;
+    store_releases(&releases);
+    let mut artifacts = BTreeMap::new();
+    for release in releases {
+        let version = match Version::parse(release.tag_name.trim_start_matches('v')) {
+            Ok(version) => version,
+            Err(_) => continue,
+        };
+        let builds = release
+            .assets
+            .into_iter()
+            .filter(|asset| !is_checksum_file(&asset.name))
+            .map(|asset| Ok((asset.name, Url::parse(&asset.browser_download_url)?)))
+            .collect::<Result<BTreeMap<_, _>, VyperVmError>>()?;
+        if !builds.is_empty() {
+            artifacts.insert(version, builds);
+        }
+    }
+    Ok(artifacts)
+}
+
+/// Stores the fetched releases, the cache is only an optimization for offline use.
+fn store_releases(releases: &[VyperReleases]) {
+    if let (Ok(path), Ok(content)) = (releases_cache_path(), serde_json::to_vec(releases)) {
+        let _ = fs::write(path, content);
+    }
+}
+
+/// Stores the fetched releases, or falls back to the stored ones or the bundled snapshot when
+/// GitHub cannot be reached. A rate limit or a rejected token is returned instead, as waiting or
+/// fixing the token gets a fresh list.
+fn with_offline_fallback(
+    fetched: Result<Vec<VyperReleases>, VyperVmError>,
+) -> Result<Vec<VyperReleases>, VyperVmError> {
+    match fetched {
+        Ok(releases) => {
+            store_releases(&releases);
+            Ok(releases)
+        }
+        Err(err) if !is_unreachable(&err) => Err(err),
+        Err(err) => {
+            tracing::warn!(
+                "failed to fetch releases, using offline release list: {}",
+                err
+            );
+            stored_releases()
+        }
+    }
+}
+
+/// Returns true if `err` means GitHub could not be reached: a failed connection, running offline
+/// or a server error.
+fn is_unreachable(err: &VyperVmError) -> bool {
+    match err {
+        VyperVmError::ReqwestError(_) | VyperVmError::Offline(_) => true,
+        VyperVmError::UnsuccessfulResponse(_, status) => status.is_server_error(),
+        _ => false,
+    }
+}
+
+/// Returns the releases stored by the last fetch, or the bundled snapshot, without network
+/// access, e.g. for shell completions. See [`Releases::from_cache`].
+pub fn offline_releases(platform: Platform) -> Result<Releases, VyperVmError> {
+    Releases::from_cache(platform)
+}
+
+fn stored_releases() -> Result<Vec<VyperReleases>, VyperVmError> {
+    match fs::read(releases_cache_path()?) {
+        Ok(content) => Ok(serde_json::from_slice(&content)?),
+        Err(_) => snapshot_releases(),
+    }
+}
+
+/// Decompresses the snapshot of historic releases embedded at build time.
+fn snapshot_releases() -> Result<Vec<VyperReleases>, VyperVmError> {
+    let content = zstd::decode_all(RELEASE_SNAPSHOT)?;
+    Ok(serde_json::from_slice(&content)?)
+}
+
+/// Derive path to the last fetched release list.
+pub fn releases_cache_path() -> Result<PathBuf, VyperVmError> {
+    Ok(active_home()?.join(RELEASES_CACHE_FILENAME))
+}
+
+/// Blocking version for [`release_for_version`]
+#[cfg(feature = "blocking")]
+pub fn blocking_release_for_version(
+    platform: Platform,
+    version: &Version,
+) -> Result<Releases, VyperVmError> {
+    ensure_online(format!("fetching the release {}", version))?;
+    let mut headers = HeaderMap::new();
+    // add the user-agent header required by github
+    headers.insert(USER_AGENT, HeaderValue::from_static("reqwest"));
+
+    add_gh_token(&mut headers);
+
+    let url = release_tag_url(version)?;
+    let res = http::blocking_client()
+        .get(url.clone())
+        .headers(headers)
+        .send()?;
+    metrics::record_response(res.status(), res.headers());
+
+    if res.status() == StatusCode::NOT_FOUND {
+        return Err(VyperVmError::UnknownVersion);
+    }
+    if !res.status().is_success() {
+        return Err(github_error(url, res.status(), res.headers()));
+    }
+
+    let body = res.bytes()?;
+    metrics::record_bytes(body.len());
+    Ok(into_releases(
+        vec![serde_json::from_slice(&body)?],
+        platform,
+    ))
+}
+
+/// Fetch only the release tagged with the provided version, keeping it if it has an artifact
+/// for the platform. This avoids listing every release when the exact version is already known.
+pub async fn release_for_version(
+    platform: Platform,
+    version: &Version,
+) -> Result<Releases, VyperVmError> {
+    ensure_online(format!("fetching the release {}", version))?;
+    let mut headers = HeaderMap::new();
+    // add the user-agent header required by github
+    headers.insert(USER_AGENT, HeaderValue::from_static("reqwest"));
+
+    add_gh_token(&mut headers);
+
+    let url = release_tag_url(version)?;
+    let _permit = politeness::github_permit().await;
+    let res = http::client()
+        .get(url.clone())
+        .headers(headers)
+        .send()
+        .await?;
+    metrics::record_response(res.status(), res.headers());
+
+    if res.status() == StatusCode::NOT_FOUND {
+        return Err(VyperVmError::UnknownVersion);
+    }
+    if !res.status().is_success() {
+        return Err(github_error(url, res.status(), res.headers()));
+    }
+
+    let body = res.bytes().await?;
+    metrics::record_bytes(body.len());
+    Ok(into_releases(
+        vec![serde_json::from_slice(&body)?],
+        platform,
+    ))
+}
+
+/// How the assets of a platform are named in an era of releases.
+struct AssetNaming {
+    platform: Platform,
+    /// First release of the era.
+    since: (u64, u64, u64),
+    /// Tokens of the asset name, see [`name_tokens`], any of which identifies the platform.
+    tokens: &'static [&'static str],
+}
+
+/// Asset naming rules, newest era first for each platform. The first rule of the platform whose
+/// era contains the version of a release is used to match its assets.
+const ASSET_NAMING: &[AssetNaming] = &[
+    // vyper.0.3.3+commit.48e326f0.linux
+    AssetNaming {
+        platform: Platform::Linux,
+        since: (0, 0, 0),
+        tokens: &["linux"],
+    },
+    // vyper.0.3.3+commit.48e326f0.darwin, vyper.0.3.10+commit.91361694.darwin.arm64
+    AssetNaming {
+        platform: Platform::MacOs,
+        since: (0, 0, 0),
+        tokens: &["darwin"],
+    },
+    // vyper.0.3.3+commit.48e326f0.windows.exe, some builds lack the `.exe` suffix
+    AssetNaming {
+        platform: Platform::Windows,
+        since: (0, 2, 0),
+        tokens: &["windows"],
+    },
+    // early builds are tagged with the target instead, e.g. vyper.0.1.0b17.win64.exe
+    AssetNaming {
+        platform: Platform::Windows,
+        since: (0, 0, 0),
+        tokens: &["windows", "win64", "win32"],
+    },
+];
+
+/// Splits an asset name on `.`, `-` and `+`, so e.g. `darwin` never matches `win`.
+fn name_tokens(name: &str) -> impl Iterator<Item = String> + '_ {
+    name.split(['.', '-', '+']).map(str::to_lowercase)
+}
+
+/// Returns true if the asset is a build of a release for the platform, following the naming of
+/// the era of the release when its version is known.
+fn asset_matches(name: &str, platform: Platform, version: Option<&Version>) -> bool {
+    let naming = ASSET_NAMING
+        .iter()
+        .filter(|naming| naming.platform == platform)
+        .find(|naming| {
+            let (major, minor, patch) = naming.since;
+            // prereleases of an era's first version belong to the previous era
+            version.is_none_or(|version| *version >= Version::new(major, minor, patch))
+        });
+    match naming {
+        Some(naming) => name_tokens(name).any(|token| naming.tokens.contains(&token.as_str())),
+        None => false,
+    }
+}
+
+/// Architecture names used in asset names for each `std::env::consts::ARCH`.
+fn arch_aliases(arch: &str) -> &'static [&'static str] {
+    match arch {
+        "x86_64" => &["x86_64", "amd64"],
+        "aarch64" => &["aarch64", "arm64"],
+        _ => &[],
+    }
+}
+
+/// Ranks an asset name for the given architecture, lower is better: native builds first, then
+/// universal builds, then builds without an architecture and finally builds for another
+/// architecture.
+fn asset_rank(name: &str, arch: &str) -> u8 {
+    let name = name.to_lowercase();
+    if arch_aliases(arch).iter().any(|alias| name.contains(alias)) {
+        0
+    } else if name.contains("universal") {
+        1
+    } else if ["x86_64", "aarch64"]
+        .iter()
+        .flat_map(|arch| arch_aliases(arch))
+        .any(|alias| name.contains(alias))
+    {
+        3
+    } else {
+        2
+    }
+}
+
+/// Chooses the artifact of a release among the assets matching the platform, e.g. for Linux
+/// users on musl or an older glibc needing another build than the default one.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ArtifactSelector {
+    /// Patterns the artifact name must all contain, releases without such an asset are unusable.
+    pub require: Vec<String>,
+    /// Patterns in order of preference, an asset containing an earlier one is chosen over the
+    /// architecture ranking.
+    pub prefer: Vec<String>,
+}
+
+impl ArtifactSelector {
+    /// Parses a comma separated list of patterns, preferred in order unless prefixed with
+    /// `require:`, e.g. `musl,linux-gnu` or `require:musl`. Patterns match names case
+    /// insensitively.
+    pub fn parse(spec: &str) -> Self {
+        let mut selector = Self::default();
+        for pattern in spec.split(',').map(|p| p.trim().to_lowercase()) {
+            match pattern.strip_prefix("require:") {
+                Some(required) if !required.is_empty() => selector.require.push(required.into()),
+                Some(_) => {}
+                None if !pattern.is_empty() => selector.prefer.push(pattern),
+                None => {}
             }
         }
+        selector
+    }
+
+    /// The selector in use: the one set with [`set_artifact_selector`], else
+    /// [`ARTIFACT_SELECTOR_ENV`], else `artifact_selector` of the config, else none.
+    pub fn load() -> Self {
+        if let Some(selector) = ARTIFACT_SELECTOR.read().expect("not poisoned").as_ref() {
+            return selector.clone();
+        }
+        env::var(ARTIFACT_SELECTOR_ENV)
+            .ok()
+            .or_else(|| Config::load().ok()?.artifact_selector)
+            .map(|spec| Self::parse(&spec))
+            .unwrap_or_default()
     }
 
-    Ok(Releases { builds, releases })
+    /// Whether the asset contains every required pattern.
+    fn accepts(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.require.iter().all(|pattern| name.contains(pattern))
+    }
+
+    /// Position of the first preferred pattern the asset contains, lower is better.
+    fn rank(&self, name: &str) -> usize {
+        let name = name.to_lowercase();
+        self.prefer
+            .iter()
+            .position(|pattern| name.contains(pattern))
+            .unwrap_or(self.prefer.len())
+    }
 }
 
-/// Fetch all releases available for the provided platform.
-pub async fn all_releases(platform: Platform) -> Result<Releases, VyperVmError> {
-    let vyper_releases = get_releases().await?;
+/// Makes every release list choose artifacts with `selector`, e.g. for `--artifact`, or with the
+/// selector of the environment and the config again with `None`.
+pub fn set_artifact_selector(selector: Option<ArtifactSelector>) {
+    *ARTIFACT_SELECTOR.write().expect("not poisoned") = selector;
+}
+
+/// Collects the assets matching the platform out of the raw GitHub releases.
+///
+/// If a release has several matching assets accepted by the [`ArtifactSelector`], the one
+/// preferred by it is chosen, then the one ranked best by [`asset_rank`], ties being broken by
+/// name so the choice is deterministic.
+fn into_releases(vyper_releases: Vec<VyperReleases>, platform: Platform) -> Releases {
+    into_releases_for_arch(
+        vyper_releases,
+        platform,
+        env::consts::ARCH,
+        &ArtifactSelector::load(),
+    )
+}
 
+fn into_releases_for_arch(
+    vyper_releases: Vec<VyperReleases>,
+    platform: Platform,
+    arch: &str,
+    selector: &ArtifactSelector,
+) -> Releases {
     let mut builds: Vec<BuildInfo> = Vec::new();
     let mut releases: BTreeMap<Version, String> = BTreeMap::new();
-    let platform_str = &platform.to_string();
+    let mut unmatched: Vec<UnmatchedRelease> = Vec::new();
+    let mut alternatives: BTreeMap<Version, Vec<String>> = BTreeMap::new();
+    let mut sizes: BTreeMap<Version, u64> = BTreeMap::new();
+    let mut published: BTreeMap<Version, String> = BTreeMap::new();
+    let mut prereleases: Vec<Version> = Vec::new();
+    let mut checksum_files: BTreeMap<Version, String> = BTreeMap::new();
     for vyper_release in vyper_releases {
-        for asset in vyper_release.assets {
-            if asset.name.contains(platform_str) {
-                let version =
-                    Version::parse(vyper_release.tag_name.trim_start_matches('v')).unwrap();
+        let version = Version::parse(vyper_release.tag_name.trim_start_matches('v')).ok();
+        let mut matching = vyper_release
+            .assets
+            .iter()
+            .filter(|asset| !is_checksum_file(&asset.name))
+            .filter(|asset| asset_matches(&asset.name, platform, version.as_ref()))
+            .filter(|asset| selector.accepts(&asset.name))
+            .map(|asset| asset.name.clone())
+            .collect::<Vec<_>>();
+        matching.sort_by(|a, b| {
+            selector
+                .rank(a)
+                .cmp(&selector.rank(b))
+                .then_with(|| asset_rank(a, arch).cmp(&asset_rank(b, arch)))
+                .then_with(|| a.cmp(b))
+        });
+        match version {
+            Some(version) if !matching.is_empty() => {
+                let chosen = matching.remove(0);
+                let asset = vyper_release.assets.iter().find(|a| a.name == chosen);
+                if let Some(asset) = asset {
+                    sizes.insert(version.clone(), asset.size);
+                }
+                if let Some(file) = checksum_file_for(&vyper_release.assets, &chosen) {
+                    checksum_files.insert(version.clone(), file.browser_download_url.clone());
+                }
                 builds.push(BuildInfo {
                     version: version.clone(),
-                    sha256: Vec::new(),
+                    sha256: asset
+                        .and_then(|asset| asset.digest.as_deref()?.strip_prefix("sha256:"))
+                        .and_then(|digest| hex::decode(digest).ok())
+                        .unwrap_or_default(),
                 });
-                releases.insert(version, asset.name);
+                if !matching.is_empty() {
+                    alternatives.insert(version.clone(), matching);
+                }
+                if let Some(published_at) = vyper_release.published_at {
+                    published.insert(version.clone(), published_at);
+                }
+                if vyper_release.prerelease {
+                    prereleases.push(version.clone());
+                }
+                releases.insert(version, chosen);
             }
+            version => unmatched.push(UnmatchedRelease {
+                tag: vyper_release.tag_name,
+                version,
+                assets: vyper_release.assets.into_iter().map(|a| a.name).collect(),
+            }),
         }
     }
 
-    Ok(Releases { builds, releases })
+    Releases {
+        builds,
+        releases,
+        unmatched,
+        alternatives,
+        sizes,
+        published,
+        prereleases,
+        checksum_files,
+    }
 }
 
-async fn get_releases() -> Result<Vec<VyperReleases>, VyperVmError> {
+fn release_tag_url(version: &Version) -> Result<Url, VyperVmError> {
+    Ok(Url::parse(&format!(
+        "{}v{}",
+        GITHUB_RELEASE_BY_TAG, version
+    ))?)
+}
+
+/// Returns the URL of the next page of a paginated GitHub response from its `Link` header, e.g.
+/// `<https://api.github.com/...&page=2>; rel="next", <...>; rel="last"`.
+fn next_page(link: &str) -> Option<Url> {
+    link.split(',').find_map(|link| {
+        let (url, params) = link.split_once(';')?;
+        if !params
+            .split(';')
+            .any(|param| param.trim() == r#"rel="next""#)
+        {
+            return None;
+        }
+        Url::parse(url.trim().trim_start_matches('<').trim_end_matches('>')).ok()
+    })
+}
+
+fn next_page_of(headers: &HeaderMap) -> Option<Url> {
+    next_page(headers.get(LINK)?.to_str().ok()?)
+}
+
+/// Pages of the release list fetched at most, see [`DEFAULT_MAX_RELEASE_PAGES`].
+fn max_release_pages() -> usize {
+    Config::load()
+        .ok()
+        .and_then(|config| config.github_max_release_pages)
+        .unwrap_or(DEFAULT_MAX_RELEASE_PAGES)
+        .max(1)
+}
+
+fn release_list_headers() -> HeaderMap {
     let mut headers = HeaderMap::new();
     // add the user-agent header required by github
     headers.insert(USER_AGENT, HeaderValue::from_static("reqwest"));
 
     add_gh_token(&mut headers);
+    headers
+}
 
-    let vyper_releases = reqwest::Client::new()
-        .get(GITHUB_RELEASES)
-        .headers(headers)
-        .send()
-        .await?
-        .json::<Vec<VyperReleases>>()
-        .await?;
+/// Fetches every page of the release list, following the `Link` headers of GitHub up to
+/// `github_max_release_pages` pages.
+async fn get_releases() -> Result<Vec<VyperReleases>, VyperVmError> {
+    let client = http::client();
+    let mut releases = vec![];
+    let mut url = Some(Url::parse(GITHUB_RELEASES)?);
+    let max_pages = max_release_pages();
+    for _ in 0..max_pages {
+        let page = match url.take() {
+            Some(page) => page,
+            None => return Ok(releases),
+        };
+        let _permit = politeness::github_permit().await;
+        let res = client
+            .get(page.clone())
+            .headers(release_list_headers())
+            .send()
+            .await?;
+        metrics::record_response(res.status(), res.headers());
+        if !res.status().is_success() {
+            return Err(github_error(page, res.status(), res.headers()));
+        }
+        url = next_page_of(res.headers());
+        let body = res.bytes().await?;
+        metrics::record_bytes(body.len());
+        releases.extend(serde_json::from_slice::<Vec<VyperReleases>>(&body)?);
+    }
+    if url.is_some() {
+        tracing::warn!(
+            "release list truncated to {} pages, raise github_max_release_pages",
+            max_pages
+        );
+    }
+    Ok(releases)
+}
 
-    Ok(vyper_releases)
+/// Returns the GitHub token requests are authenticated with: `VVM_GITHUB_TOKEN`, else
+/// `GITHUB_TOKEN`, else `github_token` in the config.
+pub(crate) fn github_token() -> Option<String> {
+    GITHUB_TOKEN_ENVS
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|token| !token.trim().is_empty()))
+        .or_else(|| Config::load().ok().and_then(|config| config.github_token))
 }
 
-fn add_gh_token(headers: &mut HeaderMap) {
-    if let Ok(gh_token) = env::var("GITHUB_TOKEN") {
-        let auth_string = format!("Basic {}", &gh_token);
+pub(crate) fn add_gh_token(headers: &mut HeaderMap) {
+    if let Some(gh_token) = github_token() {
+        let auth_string = format!("Bearer {}", gh_token.trim());
         if let Ok(auth_header) = HeaderValue::from_str(auth_string.as_str()) {
-            // println!("GITHUB_TOKEN found! using it to fetch releases");
-            headers.insert("Authorization", auth_header);
+            headers.insert(AUTHORIZATION, auth_header);
         }
     }
 }
 
-#[allow(dead_code)]
+/// The error of an unsuccessful response of the GitHub API to a request for `url`:
+/// [`VyperVmError::RateLimited`] when the rate limit is exhausted, which GitHub answers with a
+/// 403 or 429.
+pub(crate) fn github_error(url: Url, status: StatusCode, headers: &HeaderMap) -> VyperVmError {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+    let exhausted = header("x-ratelimit-remaining") == Some(0);
+    if status == StatusCode::TOO_MANY_REQUESTS || (status == StatusCode::FORBIDDEN && exhausted) {
+        return VyperVmError::RateLimited {
+            reset_at: header("x-ratelimit-reset"),
+        };
+    }
+    VyperVmError::UnsuccessfulResponse(url, status)
+}
+
+/// Blocking version of [`get_releases`].
+#[cfg(feature = "blocking")]
 fn blocking_get_releases() -> Result<Vec<VyperReleases>, VyperVmError> {
-    let mut headers = HeaderMap::new();
-    // add the user-agent header required by github
-    headers.insert(USER_AGENT, HeaderValue::from_static("reqwest"));
+    let client = http::blocking_client();
+    let mut releases = vec![];
+    let mut url = Some(Url::parse(GITHUB_RELEASES)?);
+    let max_pages = max_release_pages();
+    for _ in 0..max_pages {
+        let page = match url.take() {
+            Some(page) => page,
+            None => return Ok(releases),
+        };
+        let res = client
+            .get(page.clone())
+            .headers(release_list_headers())
+            .send()?;
+        metrics::record_response(res.status(), res.headers());
+        if !res.status().is_success() {
+            return Err(github_error(page, res.status(), res.headers()));
+        }
+        url = next_page_of(res.headers());
+        let body = res.bytes()?;
+        metrics::record_bytes(body.len());
+        releases.extend(serde_json::from_slice::<Vec<VyperReleases>>(&body)?);
+    }
+    if url.is_some() {
+        tracing::warn!(
+            "release list truncated to {} pages, raise github_max_release_pages",
+            max_pages
+        );
+    }
+    Ok(releases)
+}
 
-    add_gh_token(&mut headers);
+/// Returns the digests the release of `version` publishes for `artifact`: the digest GitHub
+/// reports for the asset, or else the line of the checksum file attached to the release. `None`
+/// if the release publishes neither or its checksum file cannot be fetched or does not list the
+/// artifact, the bundled [`ChecksumManifest`] being the fallback.
+pub async fn published_digests(
+    releases: &Releases,
+    version: &Version,
+    artifact: &str,
+) -> Option<Digests> {
+    if let Some(checksum) = releases.published_checksum(version) {
+        return Some(sha256_digests(&checksum));
+    }
+    let url = releases.checksum_files.get(version)?;
+    let _permit = politeness::github_permit().await;
+    let fetched = async {
+        let res = http::client().get(url).send().await?;
+        metrics::record_response(res.status(), res.headers());
+        let res = res.error_for_status()?;
+        let body = res.text().await?;
+        metrics::record_bytes(body.len());
+        Ok::<_, VyperVmError>(body)
+    };
+    match fetched.await {
+        Ok(content) => parse_checksum_file(&content, artifact).map(|c| sha256_digests(&c)),
+        Err(err) => {
+            tracing::warn!("fetching the checksum file {} failed: {}", url, err);
+            None
+        }
+    }
+}
 
-    let vyper_releases = reqwest::blocking::Client::new()
-        .get(GITHUB_RELEASES)
-        .headers(headers)
-        .send()?
-        .json::<Vec<VyperReleases>>()?;
+/// Blocking version of [`published_digests`]
+#[cfg(feature = "blocking")]
+pub fn blocking_published_digests(
+    releases: &Releases,
+    version: &Version,
+    artifact: &str,
+) -> Option<Digests> {
+    if let Some(checksum) = releases.published_checksum(version) {
+        return Some(sha256_digests(&checksum));
+    }
+    let url = releases.checksum_files.get(version)?;
+    let fetched = || {
+        let res = http::blocking_client().get(url).send()?;
+        metrics::record_response(res.status(), res.headers());
+        let body = res.error_for_status()?.text()?;
+        metrics::record_bytes(body.len());
+        Ok::<_, VyperVmError>(body)
+    };
+    match fetched() {
+        Ok(content) => parse_checksum_file(&content, artifact).map(|c| sha256_digests(&c)),
+        Err(err) => {
+            tracing::warn!("fetching the checksum file {} failed: {}", url, err);
+            None
+        }
+    }
+}
+
+fn sha256_digests(checksum: &[u8]) -> Digests {
+    let mut digests = Digests::default();
+    digests.insert(HashAlgorithm::Sha256, checksum);
+    digests
+}
+
+/// Returns true if the asset is a checksum file rather than a build.
+fn is_checksum_file(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ARTIFACT_CHECKSUM_SUFFIXES
+        .iter()
+        .chain(CHECKSUM_LIST_NAMES)
+        .any(|suffix| name.ends_with(suffix))
+}
+
+/// Returns the checksum file of the assets covering `artifact`: the one attached next to it,
+/// or else a list of the checksums of the release.
+fn checksum_file_for<'a>(assets: &'a [VyperAsset], artifact: &str) -> Option<&'a VyperAsset> {
+    let next_to_artifact = |asset: &&VyperAsset| {
+        ARTIFACT_CHECKSUM_SUFFIXES
+            .iter()
+            .any(|suffix| asset.name == format!("{}{}", artifact, suffix))
+    };
+    let list = |asset: &&VyperAsset| {
+        CHECKSUM_LIST_NAMES
+            .iter()
+            .any(|name| asset.name.to_lowercase().ends_with(name))
+    };
+    assets
+        .iter()
+        .find(next_to_artifact)
+        .or_else(|| assets.iter().find(list))
+}
 
-    Ok(vyper_releases)
+/// Parses the SHA256 checksum of `artifact` out of a checksum file, either `sha256sum` output
+/// (`<hex>  <name>`, the name optionally prefixed by `*`) or a bare checksum.
+fn parse_checksum_file(content: &str, artifact: &str) -> Option<Vec<u8>> {
+    content.lines().find_map(|line| {
+        let mut tokens = line.split_whitespace();
+        let checksum = tokens.next()?;
+        match tokens.next().map(|name| name.trim_start_matches('*')) {
+            Some(name) if name.rsplit('/').next() != Some(artifact) => None,
+            _ if checksum.len() != 64 => None,
+            _ => hex::decode(checksum).ok(),
+        }
+    })
 }
 
 /// Construct the URL to the Vyper binary for the specified release version and target platform.
@@ -215,6 +991,45 @@ pub fn artifact_url(
 mod tests {
     use super::*;
 
+    #[test]
+    fn detects_rate_limits() {
+        let url = Url::parse(GITHUB_RELEASES).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("1700000000"));
+        assert!(matches!(
+            github_error(url.clone(), StatusCode::FORBIDDEN, &headers),
+            VyperVmError::RateLimited {
+                reset_at: Some(1700000000)
+            }
+        ));
+        assert!(matches!(
+            github_error(
+                url.clone(),
+                StatusCode::TOO_MANY_REQUESTS,
+                &HeaderMap::new()
+            ),
+            VyperVmError::RateLimited { reset_at: None }
+        ));
+        // a forbidden request with quota left is not rate limited, e.g. a rejected token
+        assert!(matches!(
+            github_error(url, StatusCode::FORBIDDEN, &HeaderMap::new()),
+            VyperVmError::UnsuccessfulResponse(_, StatusCode::FORBIDDEN)
+        ));
+    }
+
+    #[test]
+    fn follows_next_page_links() {
+        let link = r#"<https://api.github.com/repositories/1/releases?per_page=100&page=2>; rel="next", <https://api.github.com/repositories/1/releases?per_page=100&page=3>; rel="last""#;
+        assert_eq!(
+            next_page(link).unwrap().as_str(),
+            "https://api.github.com/repositories/1/releases?per_page=100&page=2"
+        );
+        let last = r#"<https://api.github.com/repositories/1/releases?per_page=100&page=1>; rel="first", <https://api.github.com/repositories/1/releases?per_page=100&page=2>; rel="prev""#;
+        assert_eq!(next_page(last), None);
+        assert_eq!(next_page(""), None);
+    }
+
     // #[tokio::test]
     // async fn test_macos_aarch64() {
     //     let releases = all_releases(Platform::MacOs)
@@ -238,18 +1053,378 @@ mod tests {
     //     assert!(url2.to_string().contains(MACOS_AARCH64_URL_PREFIX));
     // }
 
+    #[test]
+    fn into_releases_filters_by_platform() {
+        crate::testing::sandbox().unwrap();
+        let vyper_releases = vec![VyperReleases {
+            tag_name: "v0.3.3".to_string(),
+            assets: vec![
+                VyperAsset {
+                    name: "vyper.0.3.3+commit.48e326f0.darwin".to_string(),
+                    browser_download_url: String::new(),
+                    size: 0,
+                    digest: None,
+                },
+                VyperAsset {
+                    name: "vyper.0.3.3+commit.48e326f0.linux".to_string(),
+                    browser_download_url: String::new(),
+                    size: 0,
+                    digest: None,
+                },
+            ],
+            prerelease: false,
+            published_at: None,
+        }];
+        let releases = into_releases(vyper_releases, Platform::Linux);
+        assert_eq!(
+            releases.get_artifact(&Version::new(0, 3, 3)).unwrap(),
+            "vyper.0.3.3+commit.48e326f0.linux"
+        );
+    }
+
+    #[test]
+    fn into_releases_prefers_native_assets() {
+        crate::testing::sandbox().unwrap();
+        let asset = |name: &str| VyperAsset {
+            name: name.to_string(),
+            browser_download_url: String::new(),
+            size: 0,
+            digest: None,
+        };
+        let vyper_releases = || {
+            vec![VyperReleases {
+                tag_name: "v0.3.10".to_string(),
+                assets: vec![
+                    asset("vyper.0.3.10+commit.91361694.darwin.arm64"),
+                    asset("vyper.0.3.10+commit.91361694.darwin"),
+                    asset("vyper.0.3.10+commit.91361694.darwin.universal"),
+                    asset("vyper.0.3.10+commit.91361694.darwin.x86_64"),
+                ],
+                prerelease: false,
+                published_at: None,
+            }]
+        };
+        let version = Version::new(0, 3, 10);
+
+        let releases = into_releases_for_arch(
+            vyper_releases(),
+            Platform::MacOs,
+            "aarch64",
+            &ArtifactSelector::default(),
+        );
+        assert_eq!(
+            releases.get_artifact(&version).unwrap(),
+            "vyper.0.3.10+commit.91361694.darwin.arm64"
+        );
+        assert_eq!(releases.builds.len(), 1);
+        assert_eq!(
+            releases.alternatives[&version],
+            vec![
+                "vyper.0.3.10+commit.91361694.darwin.universal",
+                "vyper.0.3.10+commit.91361694.darwin",
+                "vyper.0.3.10+commit.91361694.darwin.x86_64",
+            ]
+        );
+
+        let mut without_native = vyper_releases();
+        without_native[0].assets.remove(0);
+        let releases = into_releases_for_arch(
+            without_native,
+            Platform::MacOs,
+            "aarch64",
+            &ArtifactSelector::default(),
+        );
+        assert_eq!(
+            releases.get_artifact(&version).unwrap(),
+            "vyper.0.3.10+commit.91361694.darwin.universal"
+        );
+    }
+
+    #[test]
+    fn selects_artifacts_by_pattern() {
+        let asset = |name: &str| VyperAsset {
+            name: name.to_string(),
+            browser_download_url: String::new(),
+            size: 0,
+            digest: None,
+        };
+        let vyper_releases = || {
+            vec![
+                VyperReleases {
+                    tag_name: "v0.4.1".to_string(),
+                    assets: vec![
+                        asset("vyper.0.4.1+commit.8a93dd27.linux"),
+                        asset("vyper.0.4.1+commit.8a93dd27.linux-musl"),
+                        asset("vyper.0.4.1+commit.8a93dd27.linux-gnu"),
+                    ],
+                    prerelease: false,
+                    published_at: None,
+                },
+                VyperReleases {
+                    tag_name: "v0.3.10".to_string(),
+                    assets: vec![asset("vyper.0.3.10+commit.91361694.linux")],
+                    prerelease: false,
+                    published_at: None,
+                },
+            ]
+        };
+        let newer = Version::new(0, 4, 1);
+        let older = Version::new(0, 3, 10);
+        let select = |spec: &str| {
+            into_releases_for_arch(
+                vyper_releases(),
+                Platform::Linux,
+                "x86_64",
+                &ArtifactSelector::parse(spec),
+            )
+        };
+
+        assert_eq!(
+            select("").get_artifact(&newer).unwrap(),
+            "vyper.0.4.1+commit.8a93dd27.linux"
+        );
+        let preferred = select("MUSL, linux-gnu");
+        assert_eq!(
+            preferred.get_artifact(&newer).unwrap(),
+            "vyper.0.4.1+commit.8a93dd27.linux-musl"
+        );
+        // preferring is no requirement
+        assert!(preferred.get_artifact(&older).is_some());
+
+        let required = select("require:linux-gnu");
+        assert_eq!(
+            required.get_artifact(&newer).unwrap(),
+            "vyper.0.4.1+commit.8a93dd27.linux-gnu"
+        );
+        assert!(required.get_artifact(&older).is_none());
+        assert_eq!(required.unmatched[0].tag, "v0.3.10");
+    }
+
+    #[test]
+    fn into_releases_follows_windows_naming_eras() {
+        crate::testing::sandbox().unwrap();
+        let vyper_releases: Vec<VyperReleases> =
+            serde_json::from_str(include_str!("../test-data/windows-releases.json")).unwrap();
+        let releases = into_releases(vyper_releases, Platform::Windows);
+
+        let artifact = |major, minor, patch| {
+            releases
+                .get_artifact(&Version::new(major, minor, patch))
+                .map(String::as_str)
+        };
+        assert_eq!(
+            artifact(0, 3, 3),
+            Some("vyper.0.3.3+commit.48e326f0.windows.exe")
+        );
+        assert_eq!(
+            artifact(0, 2, 8),
+            Some("vyper.0.2.8+commit.069936f.windows")
+        );
+        assert_eq!(
+            releases
+                .get_artifact(&Version::parse("0.1.0-beta.17").unwrap())
+                .map(String::as_str),
+            Some("vyper.0.1.0b17.win64.exe")
+        );
+        // `darwin` contains `win` but is not a Windows build
+        assert_eq!(
+            releases
+                .unmatched
+                .iter()
+                .map(|r| r.tag.as_str())
+                .collect::<Vec<_>>(),
+            vec!["v0.2.0-beta.1"]
+        );
+        // win64 only identified Windows builds before 0.2.0
+        assert!(!asset_matches(
+            "vyper.0.3.3.win64.exe",
+            Platform::Windows,
+            Some(&Version::new(0, 3, 3))
+        ));
+    }
+
+    #[test]
+    fn into_releases_collects_published_checksums() {
+        crate::testing::sandbox().unwrap();
+        let asset = |name: &str, digest: Option<&str>| VyperAsset {
+            name: name.to_string(),
+            browser_download_url: format!("https://example.com/{}", name),
+            size: 0,
+            digest: digest.map(str::to_string),
+        };
+        let checksum = "aa".repeat(32);
+        let vyper_releases = vec![
+            VyperReleases {
+                tag_name: "v0.4.1".to_string(),
+                assets: vec![
+                    asset("vyper.0.4.1+commit.8a93dd27.linux.sha256", None),
+                    asset(
+                        "vyper.0.4.1+commit.8a93dd27.linux",
+                        Some(&format!("sha256:{}", checksum)),
+                    ),
+                ],
+                prerelease: false,
+                published_at: None,
+            },
+            VyperReleases {
+                tag_name: "v0.4.0".to_string(),
+                assets: vec![
+                    asset("vyper.0.4.0+commit.e9db8d9f.linux", None),
+                    asset("SHA256SUMS", None),
+                ],
+                prerelease: false,
+                published_at: None,
+            },
+        ];
+        let releases = into_releases(vyper_releases, Platform::Linux);
+        let (newer, older) = (Version::new(0, 4, 1), Version::new(0, 4, 0));
+        assert_eq!(
+            releases.get_artifact(&newer).unwrap(),
+            "vyper.0.4.1+commit.8a93dd27.linux"
+        );
+        assert_eq!(
+            releases.published_checksum(&newer),
+            hex::decode(&checksum).ok()
+        );
+        assert_eq!(
+            releases.checksum_files[&newer],
+            "https://example.com/vyper.0.4.1+commit.8a93dd27.linux.sha256"
+        );
+        assert_eq!(releases.published_checksum(&older), None);
+        assert_eq!(
+            releases.checksum_files[&older],
+            "https://example.com/SHA256SUMS"
+        );
+
+        let sums = format!(
+            "{}  vyper.0.4.0+commit.e9db8d9f.darwin\n{} *vyper.0.4.0+commit.e9db8d9f.linux\n",
+            "bb".repeat(32),
+            checksum
+        );
+        let linux = "vyper.0.4.0+commit.e9db8d9f.linux";
+        assert_eq!(
+            parse_checksum_file(&sums, linux),
+            hex::decode(&checksum).ok()
+        );
+        assert_eq!(
+            parse_checksum_file(&checksum, linux),
+            hex::decode(&checksum).ok()
+        );
+        assert_eq!(parse_checksum_file("not a checksum", linux), None);
+    }
+
+    #[test]
+    fn uses_fresh_release_lists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(RELEASES_CACHE_FILENAME);
+        assert!(!is_fresh(&path, DEFAULT_RELEASES_CACHE_TTL));
+        fs::write(&path, "[]").unwrap();
+        assert!(is_fresh(&path, DEFAULT_RELEASES_CACHE_TTL));
+        assert!(!is_fresh(&path, Duration::ZERO));
+    }
+
+    #[test]
+    fn surfaces_rate_limits_instead_of_falling_back() {
+        crate::testing::sandbox().unwrap();
+        let url = Url::parse(GITHUB_RELEASES).unwrap();
+        assert!(matches!(
+            with_offline_fallback(Err(VyperVmError::RateLimited { reset_at: None })),
+            Err(VyperVmError::RateLimited { .. })
+        ));
+        assert!(
+            with_offline_fallback(Err(VyperVmError::UnsuccessfulResponse(
+                url.clone(),
+                StatusCode::UNAUTHORIZED
+            )))
+            .is_err()
+        );
+        assert!(
+            with_offline_fallback(Err(VyperVmError::UnsuccessfulResponse(
+                url,
+                StatusCode::BAD_GATEWAY
+            )))
+            .is_ok()
+        );
+        assert!(with_offline_fallback(Err(VyperVmError::Offline("testing".to_string()))).is_ok());
+    }
+
+    #[test]
+    fn snapshot_has_linux_releases() {
+        crate::testing::sandbox().unwrap();
+        let releases = into_releases(snapshot_releases().unwrap(), Platform::Linux);
+        let artifact = releases.get_artifact(&Version::new(0, 3, 3)).unwrap();
+        assert_eq!(artifact, "vyper.0.3.3+commit.48e326f0.linux");
+        assert!(releases.unmatched.is_empty());
+    }
+
+    #[ignore = "releases-snapshot.json must first be regenerated with `vvm snapshot-releases`"]
+    #[test]
+    fn snapshot_covers_release_history() {
+        // the first release of Vyper, a snapshot without it misses older releases
+        const OLDEST_RELEASE: &str = "v0.1.0-beta.1";
+        let snapshot = snapshot_releases().unwrap();
+        assert!(snapshot
+            .iter()
+            .any(|release| release.tag_name == OLDEST_RELEASE));
+    }
+
+    #[test]
+    fn into_releases_reports_unmatched() {
+        crate::testing::sandbox().unwrap();
+        let vyper_releases = vec![
+            VyperReleases {
+                tag_name: "v0.1.0-beta.1".to_string(),
+                assets: vec![],
+                prerelease: false,
+                published_at: None,
+            },
+            VyperReleases {
+                tag_name: "not-a-version".to_string(),
+                assets: vec![VyperAsset {
+                    name: "vyper.linux".to_string(),
+                    browser_download_url: String::new(),
+                    size: 0,
+                    digest: None,
+                }],
+                prerelease: false,
+                published_at: None,
+            },
+        ];
+        let releases = into_releases(vyper_releases, Platform::Linux);
+        assert!(releases.releases.is_empty());
+        assert_eq!(releases.unmatched.len(), 2);
+        assert_eq!(
+            releases.unmatched[0].version,
+            Some(Version::parse("0.1.0-beta.1").unwrap())
+        );
+        assert_eq!(releases.unmatched[1].version, None);
+    }
+
+    #[tokio::test]
+    async fn test_release_for_version() {
+        crate::testing::sandbox().unwrap();
+        let version = Version::new(0, 3, 3);
+        let releases = release_for_version(Platform::Linux, &version)
+            .await
+            .unwrap();
+        assert!(releases.get_artifact(&version).is_some());
+    }
+
     #[tokio::test]
     async fn test_all_releases_macos() {
+        crate::testing::sandbox().unwrap();
         assert!(all_releases(Platform::MacOs).await.is_ok());
     }
 
     #[tokio::test]
     async fn test_all_releases_linux() {
+        crate::testing::sandbox().unwrap();
         assert!(all_releases(Platform::Linux).await.is_ok());
     }
 
     #[tokio::test]
     async fn releases_roundtrip() {
+        crate::testing::sandbox().unwrap();
         let releases = all_releases(Platform::Linux).await.unwrap();
         let s = serde_json::to_string(&releases).unwrap();
         let de_releases: Releases = serde_json::from_str(&s).unwrap();
@@ -0,0 +1,190 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::{Config, CONFIG_FILENAME},
+    descriptor::{EnvDescriptor, ENV_DESCRIPTOR_FILENAME},
+    error::VyperVmError,
+    home::active_home,
+    home::{VvmHome, VVM_HOME_ENV},
+    layout, lock_home_exclusive, shim,
+};
+
+/// Moves the whole VVM home, i.e. versions, caches, manifests, config and shims, to `new_home`
+/// and makes it the home of every later run of vvm.
+///
+/// The default home `~/.vvm` then only holds a `config.toml` whose `home` points at the new
+/// location. The move is a rename when both are on the same filesystem, a copy followed by a
+/// removal otherwise. Shims and environment descriptors pointing into the old home are rewritten.
+///
+/// Returns the absolute path of the new home.
+pub fn relocate_home(new_home: impl AsRef<Path>) -> Result<PathBuf, VyperVmError> {
+    let new_home = match new_home.as_ref() {
+        path if path.is_absolute() => path.to_path_buf(),
+        path => env::current_dir()?.join(path),
+    };
+    if env::var_os(VVM_HOME_ENV).is_some_and(|path| !path.is_empty()) {
+        return Err(VyperVmError::InvalidRelocation(format!(
+            "the home is set with {}, move it and update the variable instead",
+            VVM_HOME_ENV
+        )));
+    }
+    let _home_lock = lock_home_exclusive()?;
+    relocate(active_home()?, &new_home, &VvmHome::default_path()?)?;
+    Ok(new_home)
+}
+
+/// Moves `old` to `new`, leaving a config pointing at `new` in `default` unless it is `new`.
+fn relocate(old: &Path, new: &Path, default: &Path) -> Result<(), VyperVmError> {
+    if new.starts_with(old) || old.starts_with(new) {
+        return Err(VyperVmError::InvalidRelocation(format!(
+            "{} and {} overlap",
+            old.display(),
+            new.display()
+        )));
+    }
+    if new.exists() && new != default && fs::read_dir(new)?.next().is_some() {
+        return Err(VyperVmError::InvalidRelocation(format!(
+            "{} is not empty",
+            new.display()
+        )));
+    }
+
+    // the pointer is staged first, so the default home only misses between two renames
+    let pointer = (new != default).then(|| default.with_extension("relocate-tmp"));
+    if let Some(pointer) = &pointer {
+        fs::create_dir_all(pointer)?;
+        let config = Config {
+            home: Some(new.to_path_buf()),
+            ..Default::default()
+        };
+        config.write_to(&pointer.join(CONFIG_FILENAME))?;
+    }
+
+    if new == default && new.exists() {
+        // moving back to the default home, which only holds the pointer
+        fs::remove_dir_all(new)?;
+    }
+    if let Some(parent) = new.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    move_dir(old, new)?;
+    reroot_files(old, new)?;
+
+    if let Some(pointer) = pointer {
+        if default.exists() {
+            fs::remove_dir_all(default)?;
+        }
+        fs::rename(pointer, default)?;
+    }
+    Ok(())
+}
+
+/// Renames `from` to `to`, copying it when they are on different filesystems.
+fn move_dir(from: &Path, to: &Path) -> Result<(), VyperVmError> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    let staging = to.with_extension(format!("tmp-{}", std::process::id()));
+    copy_dir(from, &staging)?;
+    fs::rename(&staging, to)?;
+    fs::remove_dir_all(from)?;
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Points the shims and the environment descriptors of the home moved to `new` at `new`.
+fn reroot_files(old: &Path, new: &Path) -> Result<(), VyperVmError> {
+    let reroot = |path: &Path| {
+        path.strip_prefix(old)
+            .map(|relative| new.join(relative))
+            .ok()
+    };
+
+    let bin_dir = new.join("bin");
+    if bin_dir.is_dir() {
+        for entry in fs::read_dir(&bin_dir)? {
+            let path = entry?.path();
+            if let Some(target) = shim::shim_target(&path).as_deref().and_then(reroot) {
+                shim::write_shim(&path, &target)?;
+            }
+        }
+    }
+
+    let mut install_dirs = vec![];
+    for entry in fs::read_dir(new)? {
+        let version_dir = entry?.path();
+        install_dirs.extend(layout::platform_dirs(&version_dir));
+        install_dirs.push(version_dir);
+    }
+    for install_dir in install_dirs {
+        let descriptor_path = install_dir.join(ENV_DESCRIPTOR_FILENAME);
+        let content = match fs::read(&descriptor_path) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        let mut descriptor: EnvDescriptor = serde_json::from_slice(&content)?;
+        if let Some(path) = reroot(&descriptor.path) {
+            descriptor.path = path;
+            fs::write(&descriptor_path, serde_json::to_vec_pretty(&descriptor)?)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relocates_home_and_rewrites_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let default = dir.path().join(".vvm");
+        let new = dir.path().join("disk").join("vvm");
+        let binary = default.join("0.3.3").join("vyper-0.3.3");
+        fs::create_dir_all(default.join("bin")).unwrap();
+        fs::create_dir_all(binary.parent().unwrap()).unwrap();
+        fs::write(&binary, b"fake vyper").unwrap();
+        shim::write_shim(&default.join("bin").join("vyper"), &binary).unwrap();
+
+        relocate(&default, &new, &default).unwrap();
+        let moved = new.join("0.3.3").join("vyper-0.3.3");
+        assert_eq!(fs::read(&moved).unwrap(), b"fake vyper");
+        assert_eq!(
+            shim::shim_target(new.join("bin").join("vyper")),
+            Some(moved)
+        );
+        let pointer = Config::read(&default.join(CONFIG_FILENAME)).unwrap();
+        assert_eq!(pointer.home, Some(new.clone()));
+        assert_eq!(fs::read_dir(&default).unwrap().count(), 1);
+
+        assert!(relocate(&new, &new.join("nested"), &default).is_err());
+
+        // moving back to the default home drops the pointer
+        relocate(&new, &default, &default).unwrap();
+        assert!(!new.exists());
+        assert_eq!(
+            Config::read(&default.join(CONFIG_FILENAME)).unwrap().home,
+            None
+        );
+        assert_eq!(
+            shim::shim_target(default.join("bin").join("vyper")),
+            Some(binary)
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{binary_path, compile::CompileOutput, error::VyperVmError};
+
+/// Version of the [`BuildReport`] format, incremented on every backwards incompatible change.
+const BUILD_REPORT_VERSION: u32 = 1;
+
+/// Summary of an invocation of the wrapper, written with `vyper --report <path>` so build systems
+/// can surface toolchain statistics and notice cache effectiveness regressions.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildReport {
+    pub schema_version: u32,
+    pub compiler_version: Version,
+    /// Absolute path of the compiler binary.
+    pub compiler: PathBuf,
+    /// Whether every file compiled.
+    pub success: bool,
+    /// Duration of the whole invocation in milliseconds.
+    pub duration_ms: u64,
+    pub cache_hits: usize,
+    pub cache_misses: usize,
+    /// Warnings of all files.
+    pub warnings: usize,
+    pub files: Vec<FileReport>,
+}
+
+/// The compilation of a single file in a [`BuildReport`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileReport {
+    pub file: PathBuf,
+    /// Whether the output was served from the cache.
+    pub cached: bool,
+    pub success: bool,
+    pub warnings: usize,
+    /// Time spent compiling the file in milliseconds, zero when cached.
+    pub duration_ms: u64,
+    /// File the compiler wrote the output to, `None` when it was printed.
+    pub artifact: Option<PathBuf>,
+}
+
+impl FileReport {
+    /// The report of `output`, printed to stdout.
+    pub fn new(output: &CompileOutput) -> Self {
+        Self {
+            file: output.file.clone(),
+            cached: output.cached,
+            success: output.success,
+            warnings: output.warnings().len(),
+            duration_ms: output.duration.as_millis() as u64,
+            artifact: None,
+        }
+    }
+}
+
+impl BuildReport {
+    /// The report of compiling `files` with `version` in `duration`.
+    pub fn new(
+        version: &Version,
+        files: Vec<FileReport>,
+        duration: Duration,
+    ) -> Result<Self, VyperVmError> {
+        Ok(Self {
+            schema_version: BUILD_REPORT_VERSION,
+            compiler_version: version.clone(),
+            compiler: binary_path(version)?,
+            success: files.iter().all(|file| file.success),
+            duration_ms: duration.as_millis() as u64,
+            cache_hits: files.iter().filter(|file| file.cached).count(),
+            cache_misses: files.iter().filter(|file| !file.cached).count(),
+            warnings: files.iter().map(|file| file.warnings).sum(),
+            files,
+        })
+    }
+
+    /// Writes the report as JSON to `path`, replacing a previous one.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), VyperVmError> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_outputs() {
+        crate::testing::sandbox().unwrap();
+        let output = |file: &str, cached: bool, stderr: &str| CompileOutput {
+            file: PathBuf::from(file),
+            success: true,
+            stdout: "0x00".to_string(),
+            stderr: stderr.to_string(),
+            cached,
+            duration: if cached {
+                Duration::ZERO
+            } else {
+                Duration::from_millis(120)
+            },
+        };
+        let files = vec![
+            FileReport::new(&output("Token.vy", true, "")),
+            FileReport::new(&output(
+                "Vault.vy",
+                false,
+                "Warning: unused\nWarning: shadowed\n",
+            )),
+        ];
+        let report =
+            BuildReport::new(&Version::new(0, 3, 10), files, Duration::from_secs(1)).unwrap();
+        assert!(report.success);
+        assert_eq!((report.cache_hits, report.cache_misses), (1, 1));
+        assert_eq!(report.warnings, 2);
+        assert_eq!(report.duration_ms, 1000);
+        assert_eq!(report.files[1].duration_ms, 120);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.json");
+        report.write(&path).unwrap();
+        let read: BuildReport = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(read, report);
+    }
+}
@@ -0,0 +1,131 @@
+use std::{io, time::Duration};
+
+use crate::config::Config;
+
+/// Retries of a busy file when none are configured.
+pub const DEFAULT_BUSY_RETRIES: u32 = 5;
+
+/// Delay between retries of a busy file when none is configured.
+pub const DEFAULT_BUSY_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// How operations on a freshly installed binary are retried while another process holds it, e.g.
+/// an antivirus scanning a new executable on Windows. Only errors of a busy file are retried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BusyRetry {
+    pub retries: u32,
+    pub delay: Duration,
+}
+
+impl Default for BusyRetry {
+    fn default() -> Self {
+        Self {
+            retries: DEFAULT_BUSY_RETRIES,
+            delay: DEFAULT_BUSY_RETRY_DELAY,
+        }
+    }
+}
+
+impl BusyRetry {
+    /// The policy set with `busy_retries` and `busy_retry_delay_ms` in the config.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            retries: config.busy_retries.unwrap_or(DEFAULT_BUSY_RETRIES),
+            delay: config
+                .busy_retry_delay_ms
+                .map_or(DEFAULT_BUSY_RETRY_DELAY, Duration::from_millis),
+        }
+    }
+
+    /// The policy of the config, the default one if it cannot be loaded.
+    pub fn load() -> Self {
+        Config::load()
+            .map(|config| Self::from_config(&config))
+            .unwrap_or_default()
+    }
+
+    /// Runs `op`, sleeping and running it again as long as it fails with a busy file.
+    pub fn run<T>(&self, mut op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+        let mut attempt = 0;
+        loop {
+            match op() {
+                Err(err) if attempt < self.retries && is_busy(&err) => {
+                    tracing::debug!("file busy, retrying in {:?}: {}", self.delay, err);
+                    attempt += 1;
+                    std::thread::sleep(self.delay);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Like [`BusyRetry::run`], without blocking the runtime while waiting.
+    pub async fn run_async<T>(&self, mut op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+        let mut attempt = 0;
+        loop {
+            match op() {
+                Err(err) if attempt < self.retries && is_busy(&err) => {
+                    tracing::debug!("file busy, retrying in {:?}: {}", self.delay, err);
+                    attempt += 1;
+                    tokio::time::sleep(self.delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Returns true if the error is caused by another process holding the file: a sharing or lock
+/// violation on Windows, where scanners also cause spurious access denied errors, or an
+/// executable still open for writing on Unix.
+pub fn is_busy(err: &io::Error) -> bool {
+    // ERROR_ACCESS_DENIED, ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION
+    #[cfg(target_os = "windows")]
+    const BUSY_CODES: &[i32] = &[5, 32, 33];
+    // ETXTBSY
+    #[cfg(not(target_os = "windows"))]
+    const BUSY_CODES: &[i32] = &[26];
+
+    err.raw_os_error()
+        .is_some_and(|code| BUSY_CODES.contains(&code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_busy_files_only() {
+        let busy = || io::Error::from_raw_os_error(if cfg!(windows) { 32 } else { 26 });
+        let retry = BusyRetry {
+            retries: 2,
+            delay: Duration::ZERO,
+        };
+
+        let mut attempts = 0;
+        let result = retry.run(|| {
+            attempts += 1;
+            if attempts < 3 {
+                Err(busy())
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        let mut attempts = 0;
+        let result: io::Result<()> = retry.run(|| {
+            attempts += 1;
+            Err(busy())
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+
+        let mut attempts = 0;
+        let result: io::Result<()> = retry.run(|| {
+            attempts += 1;
+            Err(io::Error::from(io::ErrorKind::NotFound))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+}
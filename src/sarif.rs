@@ -0,0 +1,213 @@
+use semver::Version;
+use serde_json::{json, Value};
+
+use std::path::{Path, PathBuf};
+
+/// Version of the SARIF format written by [`sarif_log`].
+const SARIF_VERSION: &str = "2.1.0";
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// How severe a [`Diagnostic`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiagnosticLevel {
+    Error,
+    Warning,
+}
+
+impl DiagnosticLevel {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticLevel::Error => "error",
+            DiagnosticLevel::Warning => "warning",
+        }
+    }
+}
+
+/// An error or warning the compiler printed for a source file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub level: DiagnosticLevel,
+    /// Name of the exception, e.g. `StructureException`, or `Warning`.
+    pub rule: String,
+    pub message: String,
+    pub file: PathBuf,
+    /// 1-based line of the source, if reported.
+    pub line: Option<u64>,
+    /// 1-based column of the source, if reported.
+    pub column: Option<u64>,
+}
+
+/// Extracts the diagnostics of compiling `file` from the stderr of the compiler: the exception
+/// of a failed compilation, e.g. `vyper.exceptions.StructureException: …` followed by
+/// `line 12:4`, or every warning of a successful one.
+pub fn parse_diagnostics(file: &Path, stderr: &str, success: bool) -> Vec<Diagnostic> {
+    let lines = stderr
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>();
+    if success {
+        return lines
+            .iter()
+            .map(|line| {
+                let (line_number, column) = source_position(line).unzip();
+                Diagnostic {
+                    level: DiagnosticLevel::Warning,
+                    rule: "Warning".to_string(),
+                    message: line.trim().to_string(),
+                    file: file.to_path_buf(),
+                    line: line_number,
+                    column: column.flatten(),
+                }
+            })
+            .collect();
+    }
+    if lines.is_empty() {
+        return vec![];
+    }
+
+    let exception = lines.iter().enumerate().find_map(|(i, line)| {
+        let (name, message) = line.trim().split_once(": ")?;
+        let name = name.rsplit('.').next()?;
+        (name.ends_with("Exception") || name.ends_with("Error"))
+            .then(|| (i, name.to_string(), message.to_string()))
+    });
+    let (start, rule, message) = match exception {
+        Some(exception) => exception,
+        None => (0, "CompilerError".to_string(), lines.join("\n")),
+    };
+    let (line, column) = lines[start..]
+        .iter()
+        .find_map(|line| source_position(line))
+        .unzip();
+    vec![Diagnostic {
+        level: DiagnosticLevel::Error,
+        rule,
+        message,
+        file: file.to_path_buf(),
+        line,
+        column: column.flatten(),
+    }]
+}
+
+/// Finds `line N` or `line N:M` in a line of compiler output, returning the 1-based line and
+/// column. The compiler reports 0-based columns.
+fn source_position(text: &str) -> Option<(u64, Option<u64>)> {
+    text.match_indices("line ").find_map(|(i, _)| {
+        let rest = &text[i + "line ".len()..];
+        let digits = |s: &str| {
+            s.chars()
+                .take_while(char::is_ascii_digit)
+                .collect::<String>()
+        };
+        let line = digits(rest);
+        let column = rest[line.len()..]
+            .strip_prefix(':')
+            .map(digits)
+            .and_then(|column| column.parse::<u64>().ok())
+            .map(|column| column + 1);
+        Some((line.parse().ok()?, column))
+    })
+}
+
+/// Converts `diagnostics` of compiler `version` to a SARIF log, e.g. for GitHub code scanning.
+/// Source paths are made relative to `root` where possible, as SARIF consumers expect paths
+/// relative to the repository.
+pub fn sarif_log(diagnostics: &[Diagnostic], version: &Version, root: &Path) -> Value {
+    let mut rules = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.rule.as_str())
+        .collect::<Vec<_>>();
+    rules.sort_unstable();
+    rules.dedup();
+
+    let results = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let uri = diagnostic
+                .file
+                .strip_prefix(root)
+                .unwrap_or(&diagnostic.file)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let mut location = json!({ "artifactLocation": { "uri": uri } });
+            if let Some(line) = diagnostic.line {
+                location["region"] = json!({ "startLine": line });
+                if let Some(column) = diagnostic.column {
+                    location["region"]["startColumn"] = json!(column);
+                }
+            }
+            json!({
+                "ruleId": diagnostic.rule,
+                "level": diagnostic.level.as_str(),
+                "message": { "text": diagnostic.message },
+                "locations": [{ "physicalLocation": location }],
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": SARIF_VERSION,
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "vyper",
+                    "version": version.to_string(),
+                    "informationUri": "https://github.com/vyperlang/vyper",
+                    "rules": rules.iter().map(|rule| json!({ "id": rule })).collect::<Vec<_>>(),
+                }
+            },
+            "results": results,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_compiler_diagnostics() {
+        let root = Path::new("/repo");
+        let file = root.join("contracts/Token.vy");
+        let stderr = "Error compiling: contracts/Token.vy\n\
+            vyper.exceptions.StructureException: Invalid top-level statement\n  \
+            contract \"contracts/Token.vy:12\", function \"foo\", line 12:4\n";
+        let diagnostics = parse_diagnostics(&file, stderr, false);
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                level: DiagnosticLevel::Error,
+                rule: "StructureException".to_string(),
+                message: "Invalid top-level statement".to_string(),
+                file: file.clone(),
+                line: Some(12),
+                column: Some(5),
+            }]
+        );
+
+        let warnings = parse_diagnostics(&file, "Warning: unused variable x\n", true);
+        assert_eq!(warnings[0].level, DiagnosticLevel::Warning);
+        assert_eq!(warnings[0].line, None);
+        assert!(parse_diagnostics(&file, "", true).is_empty());
+        assert_eq!(
+            parse_diagnostics(&file, "killed", false)[0].rule,
+            "CompilerError"
+        );
+
+        let log = sarif_log(
+            &[diagnostics, warnings].concat(),
+            &Version::new(0, 3, 10),
+            root,
+        );
+        assert_eq!(log["version"], SARIF_VERSION);
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"].as_array().unwrap().len(), 2);
+        let location = &run["results"][0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "contracts/Token.vy");
+        assert_eq!(location["region"]["startLine"], 12);
+        assert_eq!(location["region"]["startColumn"], 5);
+        assert_eq!(run["results"][1]["level"], "warning");
+    }
+}
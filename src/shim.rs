@@ -0,0 +1,183 @@
+use semver::Version;
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+#[cfg(target_family = "unix")]
+use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+
+use crate::{bin_path, binary_path, error::VyperVmError};
+
+/// Prefix of the line recording the target of a shim.
+const SHIM_TARGET_MARKER: &str = "vvm-shim-target: ";
+
+/// Line marking a shim running the `vyper` wrapper, see [`install_wrapper_shim`].
+const WRAPPER_SHIM_MARKER: &str = "vvm-shim-wrapper";
+
+/// Derive path to the `vyper` shim in the shim directory.
+pub fn shim_path() -> Result<PathBuf, VyperVmError> {
+    Ok(if cfg!(target_os = "windows") {
+        bin_path()?.join("vyper.cmd")
+    } else {
+        bin_path()?.join("vyper")
+    })
+}
+
+/// Points the shims at the binary of `version`, if the shim directory exists. A shim running the
+/// wrapper is left alone, the wrapper follows the global version by itself.
+///
+/// The target is validated before anything is written and the shim is replaced atomically, so
+/// the shim never points at a missing compiler.
+pub fn regenerate_shims(version: &Version) -> Result<(), VyperVmError> {
+    let shim = shim_path()?;
+    if !bin_path()?.exists() || is_wrapper_shim(&shim) {
+        return Ok(());
+    }
+    let target = binary_path(version)?;
+    if !target.is_file() {
+        return Err(VyperVmError::VersionNotInstalled(version.to_string()));
+    }
+    write_shim(&shim, &target)
+}
+
+/// Writes a `vyper` shim pointing at the binary of `version` in a new temporary directory, to
+/// put in front of `PATH` for a session running `version` without changing the global version.
+/// The directory is deleted when dropped.
+pub fn session_shims(version: &Version) -> Result<tempfile::TempDir, VyperVmError> {
+    let target = binary_path(version)?;
+    if !target.is_file() {
+        return Err(VyperVmError::VersionNotInstalled(version.to_string()));
+    }
+    let dir = tempfile::Builder::new()
+        .prefix(&format!("vvm-shell-{}-", version))
+        .tempdir()?;
+    let name = shim_path()?
+        .file_name()
+        .expect("the shim has a file name")
+        .to_owned();
+    write_shim(&dir.path().join(name), &target)?;
+    Ok(dir)
+}
+
+/// Removes the shims, if any. Used when no global version is set anymore, a shim running the
+/// wrapper is kept.
+pub fn remove_shims() -> Result<(), VyperVmError> {
+    let shim = shim_path()?;
+    if shim.exists() && !is_wrapper_shim(&shim) {
+        fs::remove_file(shim)?;
+    }
+    Ok(())
+}
+
+/// Returns the target of the shim at `path`, if it is a vvm shim.
+pub fn shim_target(path: impl AsRef<Path>) -> Option<PathBuf> {
+    let content = fs::read_to_string(path).ok()?;
+    content.lines().find_map(|line| {
+        let (_, target) = line.split_once(SHIM_TARGET_MARKER)?;
+        Some(PathBuf::from(target.trim()))
+    })
+}
+
+/// Writes a `vyper` shim running the `vyper` wrapper at `wrapper` to the shim directory, so
+/// `vyper` on `PATH` always resolves through vvm: pinned versions, the global version and the
+/// compile cache. `vvm use` leaves it in place from then on.
+///
+/// Returns the path of the shim.
+pub fn install_wrapper_shim(wrapper: impl AsRef<Path>) -> Result<PathBuf, VyperVmError> {
+    // fails if the wrapper does not exist
+    let wrapper = wrapper.as_ref().canonicalize()?;
+    fs::create_dir_all(bin_path()?)?;
+    let shim = shim_path()?;
+    write_shim_file(&shim, &wrapper, true)?;
+    Ok(shim)
+}
+
+/// Returns true if the shim at `path` runs the `vyper` wrapper rather than a compiler.
+pub fn is_wrapper_shim(path: impl AsRef<Path>) -> bool {
+    fs::read_to_string(path).is_ok_and(|content| {
+        content
+            .lines()
+            .any(|line| line.ends_with(WRAPPER_SHIM_MARKER))
+    })
+}
+
+/// Returns the shims whose target does not exist anymore, along with that target.
+pub fn broken_shims() -> Result<Vec<(PathBuf, PathBuf)>, VyperVmError> {
+    let bin_dir = bin_path()?;
+    if !bin_dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut broken = vec![];
+    for entry in fs::read_dir(bin_dir)? {
+        let path = entry?.path();
+        if let Some(target) = shim_target(&path) {
+            if !target.is_file() {
+                broken.push((path, target));
+            }
+        }
+    }
+    Ok(broken)
+}
+
+pub(crate) fn write_shim(shim: &Path, target: &Path) -> Result<(), VyperVmError> {
+    write_shim_file(shim, target, false)
+}
+
+fn write_shim_file(shim: &Path, target: &Path, wrapper: bool) -> Result<(), VyperVmError> {
+    let content = if cfg!(target_os = "windows") {
+        format!(
+            "@echo off\r\nREM {}{}\r\n{}\"{}\" %*\r\n",
+            SHIM_TARGET_MARKER,
+            target.display(),
+            if wrapper {
+                format!("REM {}\r\n", WRAPPER_SHIM_MARKER)
+            } else {
+                String::new()
+            },
+            target.display()
+        )
+    } else {
+        format!(
+            "#!/bin/sh\n# {}{}\n{}exec \"{}\" \"$@\"\n",
+            SHIM_TARGET_MARKER,
+            target.display(),
+            if wrapper {
+                format!("# {}\n", WRAPPER_SHIM_MARKER)
+            } else {
+                String::new()
+            },
+            target.display()
+        )
+    };
+
+    let tmp_path = shim.with_extension(format!("tmp-{}", std::process::id()));
+    let mut f = fs::File::create(&tmp_path)?;
+    f.write_all(content.as_bytes())?;
+    #[cfg(target_family = "unix")]
+    f.set_permissions(Permissions::from_mode(0o755))?;
+    drop(f);
+    fs::rename(tmp_path, shim)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shim_records_its_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let shim = dir.path().join("vyper");
+        let target = dir.path().join("0.3.3").join("vyper-0.3.3");
+        write_shim(&shim, &target).unwrap();
+        assert_eq!(shim_target(&shim), Some(target.clone()));
+        assert!(!is_wrapper_shim(&shim));
+
+        write_shim_file(&shim, &target, true).unwrap();
+        assert_eq!(shim_target(&shim), Some(target));
+        assert!(is_wrapper_shim(&shim));
+    }
+}
@@ -0,0 +1,478 @@
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    checksums, checksums::ChecksumManifest, config::Config, digest::Digests, error::VyperVmError,
+    home::active_home, platform::Platform, releases,
+};
+
+/// Name of the GitHub releases in `download_sources`.
+pub const GITHUB_SOURCE: &str = "github";
+
+/// File name of the health state of the download sources in the VVM home directory.
+const SOURCE_HEALTH_FILENAME: &str = ".source-health.json";
+
+/// Back off of a source after its first failure, doubled with every consecutive failure.
+const BACKOFF: Duration = Duration::from_secs(60);
+
+/// Longest back off of a failing source.
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// Recent failures of the download sources, by source origin.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceHealth {
+    pub sources: BTreeMap<String, SourceState>,
+}
+
+/// Consecutive failures of a download source.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceState {
+    pub failures: u32,
+    /// Unix timestamp in seconds of the last failure.
+    pub last_failure: u64,
+}
+
+impl SourceState {
+    /// Returns true if the source failed recently and should be tried after the healthy ones.
+    fn backing_off(&self, now: u64) -> bool {
+        if self.failures == 0 {
+            return false;
+        }
+        let backoff = BACKOFF
+            .saturating_mul(2u32.saturating_pow(self.failures - 1))
+            .min(MAX_BACKOFF);
+        now < self.last_failure + backoff.as_secs()
+    }
+}
+
+impl SourceHealth {
+    /// Reads the health state, empty if there is none.
+    pub fn read() -> Result<Self, VyperVmError> {
+        match fs::read(source_health_path()?) {
+            Ok(content) => Ok(serde_json::from_slice(&content)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn write(&self) -> Result<(), VyperVmError> {
+        fs::write(source_health_path()?, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Orders `urls` keeping their priority, sources backing off after recent failures last.
+    fn order(&self, urls: Vec<Url>, now: u64) -> Vec<Url> {
+        let (healthy, backing_off): (Vec<_>, Vec<_>) = urls.into_iter().partition(|url| {
+            !self
+                .sources
+                .get(&source_key(url))
+                .is_some_and(|state| state.backing_off(now))
+        });
+        healthy.into_iter().chain(backing_off).collect()
+    }
+}
+
+/// URLs to download an artifact from, in priority order: the `download_sources` of the config,
+/// GitHub only by default, with the sources that failed recently moved last.
+pub fn artifact_urls(
+    platform: Platform,
+    version: &Version,
+    artifact: &str,
+) -> Result<Vec<Url>, VyperVmError> {
+    let config = Config::load()?;
+    let sources = if config.download_sources.is_empty() {
+        vec![GITHUB_SOURCE.to_string()]
+    } else {
+        config.download_sources.clone()
+    };
+    let urls = sources
+        .iter()
+        .map(|source| {
+            let artifact = source_artifact(&config, source, platform, version, artifact)?;
+            source_url(source, platform, version, &artifact)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(SourceHealth::read()
+        .unwrap_or_default()
+        .order(urls, unix_now()))
+}
+
+/// The URL of an artifact on a source: GitHub, or a mirror with the layout of the GitHub
+/// downloads, i.e. `<mirror>/v<version>/<artifact>`.
+fn source_url(
+    source: &str,
+    platform: Platform,
+    version: &Version,
+    artifact: &str,
+) -> Result<Url, VyperVmError> {
+    if source == GITHUB_SOURCE {
+        return releases::artifact_url(platform, version, artifact);
+    }
+    Ok(Url::parse(&format!(
+        "{}/v{}/{}",
+        source.trim_end_matches('/'),
+        version,
+        artifact
+    ))?)
+}
+
+/// The name of `artifact`, a release artifact, on `source`: the `artifact_name` of the mirror if
+/// set, else the same.
+fn source_artifact(
+    config: &Config,
+    source: &str,
+    platform: Platform,
+    version: &Version,
+    artifact: &str,
+) -> Result<String, VyperVmError> {
+    match config
+        .mirrors
+        .get(source)
+        .and_then(|mirror| mirror.artifact_name.as_deref())
+    {
+        Some(template) => render_artifact_name(template, platform, version, artifact),
+        None => Ok(artifact.to_string()),
+    }
+}
+
+/// Renders an `artifact_name` template for the release artifact `artifact`.
+fn render_artifact_name(
+    template: &str,
+    platform: Platform,
+    version: &Version,
+    artifact: &str,
+) -> Result<String, VyperVmError> {
+    let invalid = |reason: String| VyperVmError::InvalidArtifactName(template.to_string(), reason);
+    let mut name = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| invalid("unclosed `{`".to_string()))?;
+        match &rest[start + 1..start + end] {
+            "version" => name.push_str(&version.to_string()),
+            "platform" => name.push_str(&platform.to_string()),
+            "commit" => name.push_str(release_commit(artifact).ok_or_else(|| {
+                invalid(format!("the release artifact {} has no commit", artifact))
+            })?),
+            other => return Err(invalid(format!("unknown placeholder `{{{}}}`", other))),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    name.push_str(rest);
+    Ok(name)
+}
+
+/// The commit of a release artifact, e.g. `91361694` in `vyper.0.3.10+commit.91361694.linux`.
+fn release_commit(artifact: &str) -> Option<&str> {
+    let (_, commit) = artifact.split_once("+commit.")?;
+    commit.split('.').next().filter(|commit| !commit.is_empty())
+}
+
+/// File name of the signed checksum manifest at the base URL of a mirror.
+const MIRROR_INDEX_FILENAME: &str = "index.json";
+
+/// How an artifact downloaded from a source must be verified.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum MirrorPolicy {
+    /// GitHub, or a mirror allowed to be unsigned: only the known checksums are verified.
+    Trusted,
+    /// A mirror whose signed index must list the artifact, at this URL.
+    SignedIndex { index: Url, public_key: String },
+}
+
+/// The mirror of `download_sources` serving `url`, `None` for GitHub.
+fn mirror_of<'a>(config: &'a Config, url: &Url) -> Option<&'a String> {
+    config
+        .download_sources
+        .iter()
+        .filter(|source| *source != GITHUB_SOURCE)
+        .find(|source| {
+            url.as_str()
+                .starts_with(&format!("{}/", source.trim_end_matches('/')))
+        })
+}
+
+/// Returns how artifacts downloaded from `url` are verified according to `config`. Mirrors
+/// without a public key are rejected unless `allow_unsigned` is set.
+fn mirror_policy(config: &Config, url: &Url) -> Result<MirrorPolicy, VyperVmError> {
+    let base = match mirror_of(config, url) {
+        Some(base) => base,
+        None => return Ok(MirrorPolicy::Trusted),
+    };
+    let mirror = config.mirrors.get(base).cloned().unwrap_or_default();
+    match mirror.public_key {
+        Some(public_key) => Ok(MirrorPolicy::SignedIndex {
+            index: Url::parse(&format!(
+                "{}/{}",
+                base.trim_end_matches('/'),
+                MIRROR_INDEX_FILENAME
+            ))?,
+            public_key,
+        }),
+        None if mirror.allow_unsigned => Ok(MirrorPolicy::Trusted),
+        None => Err(VyperVmError::UnsignedMirror(base.clone())),
+    }
+}
+
+/// Digests an artifact downloaded from a source must match.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SourceDigests {
+    /// Digests listed by the signed index of the mirror, `None` for GitHub and unsigned mirrors
+    /// allowed by the config.
+    pub listed: Option<Digests>,
+    /// Whether the source serves the release artifact itself, so its known checksums apply. Not
+    /// the case of mirrors with their own `artifact_name`.
+    pub release_artifact: bool,
+}
+
+/// Returns the digests the artifact downloaded from `url` must match according to its source.
+///
+/// Fails if the mirror is unsigned and not allowed, if its index is not signed by the key of the
+/// config, or if the index does not list the artifact.
+pub(crate) async fn source_digests(
+    url: &Url,
+    platform: Platform,
+    version: &Version,
+    artifact: &str,
+) -> Result<SourceDigests, VyperVmError> {
+    let source = SourceArtifact::new(url, platform, version, artifact)?;
+    let listed = match &source.policy {
+        MirrorPolicy::Trusted => None,
+        MirrorPolicy::SignedIndex { index, public_key } => {
+            Some(checksums::fetch_signed(index, public_key).await?)
+        }
+    };
+    source.digests(version, listed)
+}
+
+/// Blocking version of [`source_digests`]
+#[cfg(feature = "blocking")]
+pub(crate) fn blocking_source_digests(
+    url: &Url,
+    platform: Platform,
+    version: &Version,
+    artifact: &str,
+) -> Result<SourceDigests, VyperVmError> {
+    let source = SourceArtifact::new(url, platform, version, artifact)?;
+    let listed = match &source.policy {
+        MirrorPolicy::Trusted => None,
+        MirrorPolicy::SignedIndex { index, public_key } => {
+            Some(checksums::blocking_fetch_signed(index, public_key)?)
+        }
+    };
+    source.digests(version, listed)
+}
+
+/// An artifact as served by a download source, with how it must be verified.
+struct SourceArtifact {
+    /// Name of the artifact on the source.
+    artifact: String,
+    release_artifact: bool,
+    policy: MirrorPolicy,
+}
+
+impl SourceArtifact {
+    fn new(
+        url: &Url,
+        platform: Platform,
+        version: &Version,
+        artifact: &str,
+    ) -> Result<Self, VyperVmError> {
+        let config = Config::load()?;
+        let (artifact, release_artifact) = match mirror_of(&config, url) {
+            Some(base) => (
+                source_artifact(&config, base, platform, version, artifact)?,
+                config
+                    .mirrors
+                    .get(base)
+                    .is_none_or(|mirror| mirror.artifact_name.is_none()),
+            ),
+            None => (artifact.to_string(), true),
+        };
+        Ok(Self {
+            artifact,
+            release_artifact,
+            policy: mirror_policy(&config, url)?,
+        })
+    }
+
+    /// The digests to match, given the content of the signed index of the mirror if it has one.
+    fn digests(
+        self,
+        version: &Version,
+        index: Option<Vec<u8>>,
+    ) -> Result<SourceDigests, VyperVmError> {
+        let listed = match (self.policy, index) {
+            (MirrorPolicy::SignedIndex { index: url, .. }, Some(content)) => {
+                let listed = ChecksumManifest::parse(&content)?.checksum(version, &self.artifact);
+                Some(listed.ok_or(VyperVmError::UnlistedArtifact(
+                    url.to_string(),
+                    self.artifact,
+                ))?)
+            }
+            _ => None,
+        };
+        Ok(SourceDigests {
+            listed,
+            release_artifact: self.release_artifact,
+        })
+    }
+}
+
+/// Records the outcome of a download from `url`, a success resets the failures of the source.
+/// The health state is best effort, errors are ignored.
+pub(crate) fn record_download(url: &Url, success: bool) {
+    let mut health = SourceHealth::read().unwrap_or_default();
+    let key = source_key(url);
+    if success {
+        if health.sources.remove(&key).is_none() {
+            return;
+        }
+    } else {
+        let state = health.sources.entry(key).or_default();
+        state.failures += 1;
+        state.last_failure = unix_now();
+    }
+    let _ = health.write();
+}
+
+fn source_key(url: &Url) -> String {
+    url.origin().ascii_serialization()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Derive path to the health state of the download sources.
+pub fn source_health_path() -> Result<PathBuf, VyperVmError> {
+    Ok(active_home()?.join(SOURCE_HEALTH_FILENAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MirrorConfig;
+
+    #[test]
+    fn failing_sources_are_tried_last() {
+        let github =
+            Url::parse("https://github.com/vyperlang/vyper/releases/download/v0.3.3/a").unwrap();
+        let mirror = Url::parse("https://mirror.example.com/vyper/v0.3.3/a").unwrap();
+        let mut health = SourceHealth::default();
+        let now = 10_000;
+
+        let urls = vec![mirror.clone(), github.clone()];
+        assert_eq!(health.order(urls.clone(), now), urls);
+
+        health.sources.insert(
+            source_key(&mirror),
+            SourceState {
+                failures: 2,
+                last_failure: now - 60,
+            },
+        );
+        assert_eq!(
+            health.order(urls.clone(), now),
+            vec![github.clone(), mirror.clone()]
+        );
+        // two failures back off for two minutes
+        assert_eq!(health.order(urls.clone(), now + 61), urls);
+
+        assert_eq!(
+            source_url(
+                "https://mirror.example.com/vyper/",
+                Platform::Linux,
+                &Version::new(0, 3, 3),
+                "a"
+            )
+            .unwrap(),
+            mirror
+        );
+    }
+
+    #[test]
+    fn renders_artifact_names_of_mirrors() {
+        let base = "https://builds.example.com/vyper-fork";
+        let mut config = Config {
+            download_sources: vec![base.to_string()],
+            ..Default::default()
+        };
+        let version = Version::new(0, 3, 10);
+        let release = "vyper.0.3.10+commit.91361694.darwin";
+        let name =
+            |config: &Config| source_artifact(config, base, Platform::MacOs, &version, release);
+        assert_eq!(name(&config).unwrap(), release);
+
+        let mut mirror = MirrorConfig {
+            artifact_name: Some("vyper-fork-{version}-{commit}-{platform}".to_string()),
+            ..Default::default()
+        };
+        config.mirrors.insert(base.to_string(), mirror.clone());
+        assert_eq!(name(&config).unwrap(), "vyper-fork-0.3.10-91361694-darwin");
+
+        mirror.artifact_name = Some("vyper-{arch}".to_string());
+        config.mirrors.insert(base.to_string(), mirror);
+        assert!(matches!(
+            name(&config),
+            Err(VyperVmError::InvalidArtifactName(_, reason)) if reason.contains("{arch}")
+        ));
+        assert_eq!(release_commit("vyper.0.1.0-beta.17.linux"), None);
+    }
+
+    #[test]
+    fn unsigned_mirrors_need_opt_in() {
+        let base = "https://mirror.example.com/vyper";
+        let mirror = Url::parse(&format!("{}/v0.3.3/a", base)).unwrap();
+        let github =
+            Url::parse("https://github.com/vyperlang/vyper/releases/download/v0.3.3/a").unwrap();
+        let mut config = Config {
+            download_sources: vec![base.to_string(), GITHUB_SOURCE.to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            mirror_policy(&config, &github).unwrap(),
+            MirrorPolicy::Trusted
+        );
+        assert!(matches!(
+            mirror_policy(&config, &mirror),
+            Err(VyperVmError::UnsignedMirror(_))
+        ));
+
+        config.mirrors.insert(
+            base.to_string(),
+            MirrorConfig {
+                public_key: None,
+                allow_unsigned: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            mirror_policy(&config, &mirror).unwrap(),
+            MirrorPolicy::Trusted
+        );
+
+        config.mirrors.get_mut(base).unwrap().public_key = Some("key".to_string());
+        assert_eq!(
+            mirror_policy(&config, &mirror).unwrap(),
+            MirrorPolicy::SignedIndex {
+                index: Url::parse("https://mirror.example.com/vyper/index.json").unwrap(),
+                public_key: "key".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{config::Config, error::VyperVmError, home::active_home, manifest::record_last_used};
+
+/// File name of the local usage statistics in the VVM home directory.
+pub const STATS_FILENAME: &str = "stats.json";
+
+/// Local usage statistics, only kept if `usage_stats` is enabled in the config. They are never
+/// reported anywhere and only serve smarter cleanup, like pruning versions unused for a while.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageStats {
+    /// Number of times each version was installed.
+    pub installs: BTreeMap<Version, u64>,
+    /// Number of wrapper invocations served from the cache.
+    pub cache_hits: u64,
+    /// Number of wrapper invocations that had to run the compiler.
+    pub cache_misses: u64,
+    /// Unix timestamp (in seconds) of the last time each version was run. Also recorded in the
+    /// manifest of the version, whether statistics are enabled or not.
+    pub last_used: BTreeMap<Version, u64>,
+}
+
+impl UsageStats {
+    /// Reads the usage statistics, returning empty statistics if there are none.
+    pub fn read() -> Result<Self, VyperVmError> {
+        match fs::read(stats_path()?) {
+            Ok(content) => Ok(serde_json::from_slice(&content)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn write(&self) -> Result<(), VyperVmError> {
+        let path = stats_path()?;
+        let tmp_path = path.with_extension(format!("json.{}", std::process::id()));
+        fs::write(&tmp_path, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+}
+
+/// Derive path to VVM's usage statistics file.
+pub fn stats_path() -> Result<PathBuf, VyperVmError> {
+    Ok(active_home()?.join(STATS_FILENAME))
+}
+
+/// Records an install of `version`.
+pub fn record_install(version: &Version) -> Result<(), VyperVmError> {
+    update_stats(|stats| *stats.installs.entry(version.clone()).or_default() += 1)
+}
+
+/// Records that `version` was run, in its manifest and in the statistics.
+pub fn record_use(version: &Version) -> Result<(), VyperVmError> {
+    record_last_used(version)?;
+    let now = unix_timestamp();
+    update_stats(|stats| {
+        stats.last_used.insert(version.clone(), now);
+    })
+}
+
+/// Records a wrapper invocation served from the cache.
+pub fn record_cache_hit() -> Result<(), VyperVmError> {
+    update_stats(|stats| stats.cache_hits += 1)
+}
+
+/// Records a wrapper invocation that missed the cache.
+pub fn record_cache_miss() -> Result<(), VyperVmError> {
+    update_stats(|stats| stats.cache_misses += 1)
+}
+
+/// Applies `f` to the statistics if they are enabled, holding a lock so concurrent processes
+/// don't lose each other's updates.
+fn update_stats<F: FnOnce(&mut UsageStats)>(f: F) -> Result<(), VyperVmError> {
+    use fs2::FileExt;
+
+    if !Config::load()?.usage_stats {
+        return Ok(());
+    }
+
+    let lock_file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(active_home()?.join(".lock-stats"))?;
+    lock_file.lock_exclusive()?;
+
+    let mut stats = UsageStats::read()?;
+    f(&mut stats);
+    stats.write()
+}
+
+pub(crate) fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,86 @@
+use semver::Version;
+
+use crate::{
+    platform::{platform, Platform},
+    releases,
+};
+
+/// Platforms Vyper publishes binaries for.
+const PLATFORMS: [Platform; 3] = [Platform::Linux, Platform::MacOs, Platform::Windows];
+
+/// Maximum number of nearest versions suggested.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// What to suggest instead of a version unavailable for the platform.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionSuggestions {
+    pub version: Version,
+    /// Available versions closest to the requested one, closest first.
+    pub nearest: Vec<Version>,
+    /// Other platforms with binaries of the requested version.
+    pub other_platforms: Vec<Platform>,
+}
+
+/// Suggests versions of `available`, the versions of the current platform, for an unavailable
+/// `version`. The other platforms are looked up in the release list stored by the last fetch,
+/// without network access.
+pub fn suggest_versions(version: &Version, available: &[Version]) -> VersionSuggestions {
+    let other_platforms = PLATFORMS
+        .iter()
+        .filter(|other| **other != platform())
+        .filter(|other| {
+            releases::offline_releases(**other)
+                .is_ok_and(|releases| releases.releases.contains_key(version))
+        })
+        .copied()
+        .collect();
+    VersionSuggestions {
+        version: version.clone(),
+        nearest: nearest_versions(version, available),
+        other_platforms,
+    }
+}
+
+/// The releases of the same minor version with the nearest patches, newer first on ties, or the
+/// versions right below and above when the minor version has no release.
+fn nearest_versions(version: &Version, available: &[Version]) -> Vec<Version> {
+    let mut same_minor = available
+        .iter()
+        .filter(|v| v.major == version.major && v.minor == version.minor && *v != version)
+        .collect::<Vec<_>>();
+    if !same_minor.is_empty() {
+        same_minor.sort_by_key(|v| (v.patch.abs_diff(version.patch), std::cmp::Reverse(*v)));
+        return same_minor
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .cloned()
+            .collect();
+    }
+    let below = available.iter().filter(|v| *v < version).max();
+    let above = available.iter().filter(|v| *v > version).min();
+    below.into_iter().chain(above).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggests_nearest_versions() {
+        let available = ["0.2.16", "0.3.1", "0.3.7", "0.3.9", "0.3.10", "0.4.0"]
+            .iter()
+            .map(|version| Version::parse(version).unwrap())
+            .collect::<Vec<_>>();
+        let nearest = |version: &str| {
+            nearest_versions(&Version::parse(version).unwrap(), &available)
+                .iter()
+                .map(Version::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(nearest("0.3.8"), vec!["0.3.9", "0.3.7", "0.3.10"]);
+        assert_eq!(nearest("0.3.11"), vec!["0.3.10", "0.3.9", "0.3.7"]);
+        assert_eq!(nearest("0.1.0"), vec!["0.2.16"]);
+        assert_eq!(nearest("0.2.20"), vec!["0.2.16"]);
+        assert_eq!(nearest("0.5.0"), vec!["0.4.0"]);
+    }
+}
@@ -0,0 +1,217 @@
+//! Helpers for testing integrations with vvm without network access or real compilers, enabled
+//! with the `testing` feature.
+//!
+//! ```no_run
+//! # #[cfg(target_family = "unix")]
+//! # async fn example() -> Result<(), vvm_lib::VyperVmError> {
+//! use semver::Version;
+//! use vvm_lib::testing::{sandbox, FakeVyper};
+//!
+//! let sandbox = sandbox()?;
+//! let version = Version::new(0, 3, 7);
+//! sandbox.register(&version, &FakeVyper::new().stdout("0x6000"))?;
+//! sandbox.use_version(&version)?;
+//! let outputs = sandbox.compile(&version, &["Token.vy".into()]).await?;
+//! assert_eq!(outputs[0].stdout, "0x6000\n");
+//! # Ok(())
+//! # }
+//! ```
+
+use once_cell::sync::OnceCell;
+use semver::Version;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cache::{get_cache_path, VyperFilesCache},
+    compile::{compile_project, CompileOutput},
+    error::VyperVmError,
+    home::VvmHome,
+    installed_versions, setup_home,
+};
+
+/// Home of the sandbox, the same for the whole process since a home can only be activated once.
+static SANDBOX_HOME: OnceCell<&'static Path> = OnceCell::new();
+
+/// A VVM home in a temporary directory, deleted at the end of the process.
+#[derive(Clone, Copy, Debug)]
+pub struct Sandbox {
+    home: &'static Path,
+}
+
+/// Activates the sandbox home for the whole process and returns it. It must be called before any
+/// other vvm function, fails with [`VyperVmError::HomeAlreadyInUse`] otherwise, so tests never
+/// touch the real home.
+///
+/// Tests of a process share the sandbox, so they should register distinct versions.
+pub fn sandbox() -> Result<Sandbox, VyperVmError> {
+    let home = SANDBOX_HOME.get_or_try_init(|| VvmHome::ephemeral()?.activate())?;
+    setup_home()?;
+    Ok(Sandbox { home })
+}
+
+impl Sandbox {
+    pub fn home(&self) -> &'static Path {
+        self.home
+    }
+
+    /// Installs `fake` as the binary of `version`, without downloading anything.
+    #[cfg(target_family = "unix")]
+    pub fn register(&self, version: &Version, fake: &FakeVyper) -> Result<PathBuf, VyperVmError> {
+        use crate::{binary_path, setup_version};
+        use std::os::unix::fs::PermissionsExt;
+
+        setup_version(version.to_string().as_str())?;
+        let path = binary_path(version)?;
+        fs::write(&path, fake.binary(version))?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        Ok(path)
+    }
+
+    /// Sets the global version the wrapper runs, like `vvm use`.
+    pub fn use_version(&self, version: &Version) -> Result<(), VyperVmError> {
+        crate::use_version(version)
+    }
+
+    pub fn installed(&self) -> Result<Vec<Version>, VyperVmError> {
+        installed_versions()
+    }
+
+    /// Compiles `files` like the wrapper compiles several files, using and updating the cache.
+    pub async fn compile(
+        &self,
+        version: &Version,
+        files: &[PathBuf],
+    ) -> Result<Vec<CompileOutput>, VyperVmError> {
+        compile_project(version, files, 1, |_, _, _| {}).await
+    }
+
+    /// The cache of compiled bytecode of the sandbox.
+    pub fn cache(&self) -> VyperFilesCache {
+        VyperFilesCache::get()
+    }
+
+    /// Removes every cached output, e.g. between tests.
+    pub fn clear_cache(&self) -> Result<(), VyperVmError> {
+        match fs::remove_file(get_cache_path()?) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A fake vyper binary, a shell script printing canned output for any source file and the
+/// version it is registered as for `--version`. Only available on Unix, where a script can stand
+/// in for the binary.
+#[cfg(target_family = "unix")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FakeVyper {
+    stdout: String,
+    stderr: String,
+    exit_code: i32,
+    version: Option<String>,
+    shell: Option<String>,
+}
+
+#[cfg(target_family = "unix")]
+impl FakeVyper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Output printed on stdout, e.g. bytecode.
+    pub fn stdout(mut self, stdout: impl Into<String>) -> Self {
+        self.stdout = stdout.into();
+        self
+    }
+
+    /// Output printed on stderr, e.g. warnings or errors.
+    pub fn stderr(mut self, stderr: impl Into<String>) -> Self {
+        self.stderr = stderr.into();
+        self
+    }
+
+    pub fn exit_code(mut self, exit_code: i32) -> Self {
+        self.exit_code = exit_code;
+        self
+    }
+
+    /// Output printed for `--version`, the version the binary is registered as by default, e.g.
+    /// `0.3.10+commit.91361694` like a real release.
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Shell commands run for anything but `--version`, instead of printing the canned output,
+    /// e.g. to print output depending on the arguments.
+    pub fn shell(mut self, commands: impl Into<String>) -> Self {
+        self.shell = Some(commands.into());
+        self
+    }
+
+    /// The content of the fake binary of `version`, e.g. to install it like a download.
+    pub fn binary(&self, version: &Version) -> Vec<u8> {
+        let lines = |output: &str, suffix: &str| {
+            output
+                .replace('\'', r"'\''")
+                .lines()
+                .map(|line| format!("echo '{}'{}\n", line, suffix))
+                .collect::<String>()
+        };
+        let version = match &self.version {
+            Some(version) => version.clone(),
+            None => version.to_string(),
+        };
+        let run = match &self.shell {
+            Some(commands) => format!("{}\n", commands),
+            None => format!(
+                "{}{}exit {}\n",
+                lines(&self.stdout, ""),
+                lines(&self.stderr, " >&2"),
+                self.exit_code
+            ),
+        };
+        format!(
+            "#!/bin/sh\nif [ \"$1\" = --version ]; then echo '{}'; exit 0; fi\n{}",
+            version.replace('\'', r"'\''"),
+            run
+        )
+        .into_bytes()
+    }
+}
+
+#[cfg(all(test, target_family = "unix"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn compiles_with_fake_vyper() {
+        let sandbox = sandbox().unwrap();
+        assert_eq!(sandbox.home(), crate::active_home().unwrap());
+        let version = Version::new(0, 0, 12);
+        let fake = FakeVyper::new()
+            .stdout("0x6000")
+            .stderr("Warning: it's fake");
+        sandbox.register(&version, &fake).unwrap();
+        assert!(sandbox.installed().unwrap().contains(&version));
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("Token.vy");
+        fs::write(&file, "# @version 0.0.12").unwrap();
+        let outputs = sandbox
+            .compile(&version, std::slice::from_ref(&file))
+            .await
+            .unwrap();
+        assert!(outputs[0].success);
+        assert_eq!(outputs[0].stdout, "0x6000\n");
+        assert_eq!(outputs[0].warnings(), vec!["Warning: it's fake"]);
+
+        let probe = crate::probe_version(&version).unwrap();
+        assert_eq!(probe.version(), Some(version.clone()));
+        crate::remove_version(&version).unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::error::VyperVmError;
+
+/// Name of the file declaring the toolchain of a project.
+pub const TOOLCHAIN_FILENAME: &str = "vvm.toml";
+
+/// The toolchain a project declares in `vvm.toml`, e.g.
+///
+/// ```toml
+/// global = "latest"
+/// versions = ["0.3.7", ">=0.3.9, <0.4"]
+///
+/// [aliases]
+/// latest = "0.3.10"
+/// ```
+///
+/// Bare versions are exact requirements, anything else is a semver requirement. Aliases are
+/// required too and can be referred to by `global`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolchainFile {
+    /// Version, requirement or alias to use as the global version.
+    pub global: Option<String>,
+    pub versions: Vec<String>,
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl ToolchainFile {
+    /// Reads the toolchain file at `path`.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, VyperVmError> {
+        let content = fs::read_to_string(path.as_ref())?;
+        toml::from_str(&content).map_err(|err| VyperVmError::InvalidToolchainFile(err.to_string()))
+    }
+
+    /// Every required version: the listed versions, the aliases and the global version.
+    pub fn requirements(&self) -> Result<Vec<VersionReq>, VyperVmError> {
+        let mut reqs = self
+            .versions
+            .iter()
+            .chain(self.aliases.values())
+            .map(|req| parse_requirement(req))
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(global) = self.global_requirement()? {
+            if !reqs.contains(&global) {
+                reqs.push(global);
+            }
+        }
+        Ok(reqs)
+    }
+
+    /// The requirement the global version must satisfy, resolving aliases.
+    pub fn global_requirement(&self) -> Result<Option<VersionReq>, VyperVmError> {
+        self.global
+            .as_ref()
+            .map(|global| parse_requirement(self.aliases.get(global).unwrap_or(global)))
+            .transpose()
+    }
+}
+
+/// Finds the nearest toolchain file, walking up from `dir` to the filesystem root.
+pub fn find_toolchain_file(dir: impl AsRef<Path>) -> Option<PathBuf> {
+    dir.as_ref()
+        .ancestors()
+        .map(|ancestor| ancestor.join(TOOLCHAIN_FILENAME))
+        .find(|path| path.is_file())
+}
+
+fn parse_requirement(req: &str) -> Result<VersionReq, VyperVmError> {
+    let req = req.trim();
+    match Version::parse(req.trim_start_matches('v')) {
+        Ok(version) => Ok(VersionReq::parse(&format!("={}", version))?),
+        Err(_) => VersionReq::parse(req)
+            .map_err(|err| VyperVmError::InvalidToolchainFile(format!("{}: {}", req, err))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_requirements() {
+        let file: ToolchainFile = toml::from_str(
+            r#"
+            global = "latest"
+            versions = ["0.3.7", ">=0.3.9, <0.4"]
+
+            [aliases]
+            latest = "0.3.10"
+            "#,
+        )
+        .unwrap();
+
+        let reqs = file.requirements().unwrap();
+        assert_eq!(reqs.len(), 3);
+        assert!(reqs[0].matches(&Version::new(0, 3, 7)));
+        assert!(!reqs[0].matches(&Version::new(0, 3, 8)));
+        assert_eq!(
+            file.global_requirement().unwrap(),
+            Some(VersionReq::parse("=0.3.10").unwrap())
+        );
+
+        let file = ToolchainFile {
+            versions: vec!["not a version".to_string()],
+            ..Default::default()
+        };
+        assert!(file.requirements().is_err());
+    }
+}
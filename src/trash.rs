@@ -0,0 +1,148 @@
+use semver::Version;
+
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{error::VyperVmError, home::active_home, install_path, installed_versions, layout};
+
+/// Name of the directory removed versions are moved to when `trash_days` is set in the config.
+pub const TRASH_DIRNAME: &str = ".trash";
+
+/// A removed version kept in the trash.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrashEntry {
+    pub version: Version,
+    /// Unix timestamp in seconds of the removal.
+    pub trashed_at: u64,
+    pub path: PathBuf,
+}
+
+/// Moves an installed version to the trash, from where [`restore_version`] brings it back.
+///
+/// Returns the path of the version in the trash.
+pub fn trash_version(version: &Version) -> Result<PathBuf, VyperVmError> {
+    let installed = install_path(version)?;
+    if !installed.is_dir() {
+        return Err(VyperVmError::VersionNotInstalled(version.to_string()));
+    }
+    // the timestamp comes first, versions may contain dashes
+    let path = trash_path()?.join(format!("{}-{}", unix_now(), version));
+    fs::create_dir_all(trash_path()?)?;
+    layout::move_install(version, &path)?;
+    Ok(path)
+}
+
+/// Lists the versions in the trash, most recently removed first.
+pub fn trashed_versions() -> Result<Vec<TrashEntry>, VyperVmError> {
+    let entries = match fs::read_dir(trash_path()?) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err.into()),
+    };
+    let mut trashed = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let parsed = name.split_once('-').and_then(|(trashed_at, version)| {
+            Some((trashed_at.parse().ok()?, Version::parse(version).ok()?))
+        });
+        if let Some((trashed_at, version)) = parsed {
+            trashed.push(TrashEntry {
+                version,
+                trashed_at,
+                path,
+            });
+        }
+    }
+    trashed.sort_by_key(|entry| std::cmp::Reverse(entry.trashed_at));
+    Ok(trashed)
+}
+
+/// Restores the most recently removed copy of `version` from the trash.
+///
+/// Returns the path of the restored install directory.
+pub fn restore_version(version: &Version) -> Result<PathBuf, VyperVmError> {
+    if installed_versions()?.contains(version) {
+        return Err(VyperVmError::VersionAlreadyInstalled(version.to_string()));
+    }
+    let entry = trashed_versions()?
+        .into_iter()
+        .find(|entry| entry.version == *version)
+        .ok_or_else(|| VyperVmError::VersionNotInTrash(version.to_string()))?;
+    let restored = layout::new_install_path(version)?;
+    if let Some(parent) = restored.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(entry.path, &restored)?;
+    Ok(restored)
+}
+
+/// Permanently deletes the versions removed at least `ttl` ago, returns their paths.
+pub fn expire_trash(ttl: Duration) -> Result<Vec<PathBuf>, VyperVmError> {
+    let deadline = unix_now().saturating_sub(ttl.as_secs());
+    let mut expired = vec![];
+    for entry in trashed_versions()? {
+        if entry.trashed_at <= deadline {
+            fs::remove_dir_all(&entry.path)?;
+            expired.push(entry.path);
+        }
+    }
+    Ok(expired)
+}
+
+/// Derive path to the trash in the VVM home directory.
+pub fn trash_path() -> Result<PathBuf, VyperVmError> {
+    Ok(active_home()?.join(TRASH_DIRNAME))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restores_trashed_version() {
+        let version = Version::new(0, 0, 14);
+        crate::testing::sandbox().unwrap();
+        crate::setup_version(version.to_string().as_str()).unwrap();
+        fs::write(crate::binary_path(&version).unwrap(), b"fake vyper").unwrap();
+
+        let trashed = trash_version(&version).unwrap();
+        assert!(!installed_versions().unwrap().contains(&version));
+        assert!(trashed_versions()
+            .unwrap()
+            .iter()
+            .any(|entry| entry.version == version && entry.path == trashed));
+        // removed just now, not expired yet
+        expire_trash(Duration::from_secs(3600)).unwrap();
+
+        restore_version(&version).unwrap();
+        assert_eq!(
+            fs::read(crate::binary_path(&version).unwrap()).unwrap(),
+            b"fake vyper"
+        );
+        assert!(matches!(
+            restore_version(&version),
+            Err(VyperVmError::VersionAlreadyInstalled(_))
+        ));
+
+        trash_version(&version).unwrap();
+        expire_trash(Duration::ZERO).unwrap();
+        assert!(matches!(
+            restore_version(&version),
+            Err(VyperVmError::VersionNotInTrash(_))
+        ));
+    }
+}
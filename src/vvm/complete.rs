@@ -0,0 +1,139 @@
+use clap::{App, AppSettings, ArgEnum, ArgSettings};
+use clap_complete::Shell;
+use itertools::Itertools;
+use semver::Version;
+
+/// Where completions take the versions available for install from. Completions never use the
+/// network, the release list is only refreshed by commands like `vvm list` or `vvm install`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ArgEnum)]
+pub enum VersionsSource {
+    /// The last fetched release list, however old.
+    Cached,
+    /// Only the installed versions, not even reading the release list.
+    Installed,
+}
+
+impl VersionsSource {
+    fn name(self) -> &'static str {
+        match self {
+            VersionsSource::Cached => "cached",
+            VersionsSource::Installed => "installed",
+        }
+    }
+}
+
+/// Returns the completions of the last of `words`, the arguments of `vvm` up to the cursor, one
+/// per line for the shell scripts of [`script`].
+///
+/// Versions are completed from the installed versions and, with [`VersionsSource::Cached`], the
+/// last fetched release list, so the completions follow installs without regenerating the
+/// scripts, and without network access.
+pub fn candidates(app: &App, words: &[String], source: VersionsSource) -> Vec<String> {
+    let (current, previous) = match words.split_last() {
+        Some((current, previous)) => (current.as_str(), previous),
+        None => ("", words),
+    };
+    let subcommand = previous.iter().find(|word| !word.starts_with('-'));
+
+    let candidates = match subcommand.and_then(|name| app.find_subcommand(name)) {
+        None => app
+            .get_subcommands()
+            .filter(|cmd| !cmd.is_set(AppSettings::Hidden))
+            .map(|cmd| cmd.get_name().to_string())
+            .collect(),
+        Some(cmd) if current.starts_with('-') => cmd
+            .get_arguments()
+            .filter(|arg| !arg.is_set(ArgSettings::Hidden))
+            .filter_map(|arg| arg.get_long())
+            .map(|long| format!("--{}", long))
+            .collect(),
+        Some(cmd) => match cmd.get_name() {
+            "use" | "shell" => std::iter::once(vvm_lib::STABLE_CHANNEL.to_string())
+                .chain(installed())
+                .collect(),
+            "remove" | "info" | "diff-output" => installed(),
+            "restore" => vvm_lib::trashed_versions()
+                .unwrap_or_default()
+                .iter()
+                .map(|entry| entry.version.to_string())
+                .unique()
+                .collect(),
+            "install" => std::iter::once(vvm_lib::STABLE_CHANNEL.to_string())
+                .chain(match source {
+                    VersionsSource::Cached => available(),
+                    VersionsSource::Installed => vec![],
+                })
+                .collect(),
+            "bundle" if previous.last().map(String::as_str) == Some("--versions") => installed(),
+            "profile" if previous.last().map(String::as_str) == Some("use") => {
+                vvm_lib::Config::load()
+                    .map(|config| config.profiles.into_keys().collect())
+                    .unwrap_or_default()
+            }
+            "bundle" | "checksums" | "lock" | "profile" if previous.len() == 1 => cmd
+                .get_subcommands()
+                .map(|cmd| cmd.get_name().to_string())
+                .collect(),
+            _ => vec![],
+        },
+    };
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(current))
+        .collect()
+}
+
+fn installed() -> Vec<String> {
+    vvm_lib::installed_versions()
+        .unwrap_or_default()
+        .iter()
+        .map(Version::to_string)
+        .collect()
+}
+
+/// Versions of the last fetched release list that are not installed, newest first.
+fn available() -> Vec<String> {
+    let installed = vvm_lib::installed_versions().unwrap_or_default();
+    vvm_lib::offline_releases(vvm_lib::platform())
+        .map(|releases| releases.into_versions())
+        .unwrap_or_default()
+        .into_iter()
+        .rev()
+        .filter(|version| !installed.contains(version))
+        .map(|version| version.to_string())
+        .collect()
+}
+
+/// Returns the completion script calling `vvm __complete` for shells supporting dynamic
+/// completions, `None` for the shells that only get the static script generated by clap.
+pub fn script(shell: Shell, source: VersionsSource) -> Option<String> {
+    let script = match shell {
+        Shell::Bash => Some(
+            r#"_vvm() {
+    local IFS=$'\n'
+    COMPREPLY=($(vvm __complete -- "${COMP_WORDS[@]:1:$COMP_CWORD}" 2>/dev/null))
+}
+complete -o default -F _vvm vvm
+"#,
+        ),
+        Shell::Zsh => Some(
+            r#"#compdef vvm
+_vvm() {
+    local -a candidates
+    candidates=(${(f)"$(vvm __complete -- "${(@)words[2,$CURRENT]}" 2>/dev/null)"})
+    compadd -Q -a candidates
+}
+compdef _vvm vvm
+"#,
+        ),
+        Shell::Fish => Some(
+            r#"complete -c vvm -f -a '(vvm __complete -- (commandline -opc)[2..] (commandline -ct) 2>/dev/null)'
+"#,
+        ),
+        _ => None,
+    }?;
+    Some(script.replace(
+        "vvm __complete --",
+        &format!("vvm __complete --versions-source {} --", source.name()),
+    ))
+}
@@ -1,58 +1,801 @@
-use clap::Parser;
+use clap::{AppSettings, IntoApp, Parser, Subcommand};
+use clap_complete::Shell;
 use dialoguer::Input;
-use semver::Version;
+use futures_util::{stream, StreamExt};
+use indicatif::MultiProgress;
+use semver::{Version, VersionReq};
 
-use std::collections::HashSet;
+use std::{collections::HashSet, env, fs, io, path::PathBuf, process::Command, time::Duration};
 
+mod complete;
 mod print;
+mod prompt;
+mod shell;
 
 #[derive(Debug, Parser)]
 #[clap(name = "vvm", about = "Vyper Version Manager", version)]
+struct Opts {
+    #[clap(
+        short,
+        long,
+        parse(from_occurrences),
+        global = true,
+        help = "Show diagnostics, e.g. unusable releases in list; -vv also shows HTTP metrics"
+    )]
+    verbose: u64,
+    #[clap(
+        long,
+        global = true,
+        help = "Use only the cached release list and installed versions, never the network"
+    )]
+    offline: bool,
+    #[clap(
+        long,
+        global = true,
+        help = "Install release artifacts no known checksum covers, unverified"
+    )]
+    insecure: bool,
+    #[clap(
+        long,
+        global = true,
+        value_name = "PATTERNS",
+        help = "Choose release artifacts by name, e.g. `musl,linux-gnu` to prefer or `require:musl` to require them, overrides VVM_ARTIFACT"
+    )]
+    artifact: Option<String>,
+    #[clap(subcommand)]
+    command: VyperVm,
+}
+
+#[derive(Debug, Subcommand)]
 enum VyperVm {
     #[clap(about = "List all versions of Vyper")]
-    List,
+    List {
+        #[clap(long, help = "Print a JSON document, see --schema")]
+        json: bool,
+        #[clap(
+            long,
+            requires = "json",
+            help = "Include versions available for install"
+        )]
+        remote: bool,
+        #[clap(long, requires = "json", help = "Only list the newest versions")]
+        limit: Option<usize>,
+        #[clap(long, help = "Print the JSON Schema of the --json output")]
+        schema: bool,
+        #[clap(
+            long,
+            conflicts_with = "remote",
+            help = "Only list installed versions, one per line"
+        )]
+        installed: bool,
+        #[clap(
+            long,
+            requires = "installed",
+            help = "Print the version and the path of the binary separated by a tab"
+        )]
+        paths: bool,
+        #[clap(
+            short,
+            long,
+            conflicts_with_all = &["json", "installed"],
+            help = "Only print every installed and available version, one per line, newest first"
+        )]
+        quiet: bool,
+    },
     #[clap(about = "Install Vyper versions")]
-    Install { versions: Vec<String> },
+    Install {
+        #[clap(required_unless_present_any = &["all", "resume"])]
+        versions: Vec<String>,
+        #[clap(
+            long,
+            help = "Install every available version",
+            conflicts_with = "versions"
+        )]
+        all: bool,
+        #[clap(
+            long,
+            requires = "all",
+            help = "Only install versions matching this range"
+        )]
+        range: Option<String>,
+        #[clap(
+            long,
+            help = "Install the versions an interrupted run did not install",
+            conflicts_with_all = &["versions", "all", "dry-run"]
+        )]
+        resume: bool,
+        #[clap(long, default_value = "4", help = "Number of concurrent installs")]
+        jobs: usize,
+        #[clap(long, help = "Only print what would be downloaded and written")]
+        dry_run: bool,
+    },
     #[clap(about = "Use a Vyper version")]
     Use { version: String },
     #[clap(about = "Remove a Vyper version")]
-    Remove { version: String },
+    Remove {
+        #[clap(required_unless_present = "orphaned")]
+        version: Option<String>,
+        #[clap(
+            long,
+            conflicts_with = "version",
+            help = "Remove the versions no longer released for this platform or failing their integrity check"
+        )]
+        orphaned: bool,
+        #[clap(long, help = "Only print what would be deleted")]
+        dry_run: bool,
+    },
+    #[clap(about = "Restore a version removed to the trash, see trash_days in config.toml")]
+    Restore { version: String },
+    #[clap(about = "Print the shell snippet that adds the vvm shim directory to PATH")]
+    Env {
+        #[clap(long, arg_enum)]
+        shell: Option<Shell>,
+        #[clap(
+            long,
+            conflicts_with = "shell",
+            help = "Print the home layout and the version in use as JSON, for build tools"
+        )]
+        json: bool,
+    },
+    #[clap(about = "Put a `vyper` shim running the vyper wrapper in the vvm shim directory")]
+    Shim {
+        #[clap(long, arg_enum)]
+        shell: Option<Shell>,
+        #[clap(
+            long,
+            help = "The vyper wrapper to run, by default the one next to vvm"
+        )]
+        wrapper: Option<PathBuf>,
+    },
+    #[clap(about = "Print the shell hook exporting VYPER for the project's pinned version")]
+    Hook {
+        #[clap(arg_enum)]
+        shell: Option<Shell>,
+    },
+    #[clap(setting = AppSettings::Hidden)]
+    HookEnv {
+        #[clap(long, arg_enum)]
+        shell: Option<Shell>,
+    },
+    #[clap(about = "Generate shell completions for vvm")]
+    Completions {
+        #[clap(arg_enum)]
+        shell: Shell,
+        #[clap(
+            long,
+            help = "Generate the static clap script instead of calling `vvm __complete`"
+        )]
+        r#static: bool,
+        #[clap(
+            long,
+            arg_enum,
+            default_value = "cached",
+            help = "Versions completed for install: the cached release list or none; completions never use the network"
+        )]
+        list_versions_source: complete::VersionsSource,
+    },
+    #[clap(
+        about = "Write the full release list to the snapshot built into vvm",
+        setting = AppSettings::Hidden
+    )]
+    SnapshotReleases {
+        #[clap(default_value = "releases-snapshot.json")]
+        output: PathBuf,
+    },
+    #[clap(name = "__complete", setting = AppSettings::Hidden)]
+    Complete {
+        #[clap(long, arg_enum, default_value = "cached")]
+        versions_source: complete::VersionsSource,
+        #[clap(last = true)]
+        words: Vec<String>,
+    },
+    #[clap(
+        about = "Run the project's pinned (or the global) Vyper version with the given arguments"
+    )]
+    Run {
+        #[clap(long, help = "Install the version first if it is missing")]
+        install: bool,
+        #[clap(last = true)]
+        args: Vec<String>,
+    },
+    #[clap(
+        about = "Start a shell, or run a command, where vyper is the given version, leaving the global version and project pins untouched"
+    )]
+    Shell {
+        version: String,
+        #[clap(long, help = "Install the version first if it is missing")]
+        install: bool,
+        #[clap(last = true, help = "Command to run instead of an interactive shell")]
+        command: Vec<String>,
+    },
+    #[clap(about = "Run the same Vyper command with every installed version")]
+    Exec {
+        #[clap(long, help = "Run every installed version")]
+        each: bool,
+        #[clap(long, help = "Only run the installed versions matching this range")]
+        range: Option<String>,
+        #[clap(last = true)]
+        args: Vec<String>,
+    },
+    #[clap(about = "Compare the bytecode size, ABI and warnings of two compiler versions")]
+    DiffOutput {
+        source: PathBuf,
+        old: String,
+        new: String,
+    },
+    #[clap(about = "Print the path to the Vyper binary used for a file, without side effects")]
+    Which {
+        #[clap(help = "Source file or directory, defaults to the current directory")]
+        file: Option<PathBuf>,
+        #[clap(long, help = "Resolve a version requirement instead, e.g. \"^0.3.7\"")]
+        resolve: Option<VersionReq>,
+        #[clap(long)]
+        json: bool,
+    },
+    #[clap(about = "Show an overview of the vvm setup")]
+    Status,
+    #[clap(about = "Show where an installed Vyper version came from and where it lives")]
+    Info { version: String },
+    #[clap(about = "Fail if an installed Vyper version has a forbidden origin")]
+    Audit {
+        #[clap(
+            long,
+            use_delimiter = true,
+            help = "Origins to reject, defaults to audit_forbidden_origins in config.toml"
+        )]
+        forbid: Vec<vvm_lib::VersionOrigin>,
+    },
+    #[clap(about = "Remove Vyper versions that were not used recently")]
+    Prune {
+        #[clap(
+            long,
+            parse(try_from_str = vvm_lib::parse_duration),
+            help = "Remove versions unused for this long, e.g. 90d"
+        )]
+        unused_for: Duration,
+        #[clap(long, help = "Only list the versions that would be removed")]
+        dry_run: bool,
+    },
+    #[clap(about = "Show or toggle the local usage statistics")]
+    Stats {
+        #[clap(long, conflicts_with = "disable")]
+        enable: bool,
+        #[clap(long)]
+        disable: bool,
+    },
+    #[clap(about = "Remove lock files, partial downloads and stale cache entries")]
+    Gc,
+    #[clap(about = "Diagnose the VVM home, the global version and, optionally, the network")]
+    Doctor {
+        #[clap(
+            long,
+            help = "Check proxy, DNS, TLS, GitHub API, rate limit and mirrors, layer by layer"
+        )]
+        network: bool,
+        #[clap(
+            long,
+            help = "Remove partial installs, stale lock files and corrupted caches, and repair the global version before checking"
+        )]
+        fix: bool,
+    },
+    #[clap(about = "Move the VVM home, with all versions, caches and shims, to a new directory")]
+    Relocate {
+        #[clap(help = "New VVM home, must not exist or be empty")]
+        new_home: PathBuf,
+    },
+    #[clap(
+        about = "Move installed versions into <platform>-<arch> directories, for a home shared across platforms"
+    )]
+    MigrateLayout,
+    #[clap(about = "Export or import installed versions for offline machines")]
+    Bundle {
+        #[clap(subcommand)]
+        command: BundleCommand,
+    },
+    #[clap(
+        about = "Converge installed versions to the active profile or the toolchain declared in vvm.toml"
+    )]
+    Sync {
+        #[clap(
+            long,
+            help = "Toolchain file, defaults to the active profile or the nearest vvm.toml"
+        )]
+        file: Option<PathBuf>,
+        #[clap(
+            long,
+            help = "Remove installed versions the toolchain does not require"
+        )]
+        prune: bool,
+        #[clap(long, help = "Only print what would be installed and removed")]
+        dry_run: bool,
+    },
+    #[clap(about = "Set up vvm: default version, PATH, GitHub token and config.toml")]
+    Init {
+        #[clap(
+            long,
+            help = "Do not prompt, install the latest version and keep defaults"
+        )]
+        defaults: bool,
+    },
+    #[clap(about = "Manage the manifest of known release checksums")]
+    Checksums {
+        #[clap(subcommand)]
+        command: ChecksumsCommand,
+    },
+    #[clap(about = "Lock the digests of installed Vyper binaries in vvm.lock and verify them")]
+    Lock {
+        #[clap(subcommand)]
+        command: LockCommand,
+    },
+    #[clap(about = "Switch between the toolchain profiles of the config")]
+    Profile {
+        #[clap(subcommand)]
+        command: ProfileCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum BundleCommand {
+    #[clap(about = "Package installed Vyper versions into a bundle")]
+    Create {
+        #[clap(long, required = true, use_delimiter = true)]
+        versions: Vec<String>,
+        path: PathBuf,
+    },
+    #[clap(about = "Install the Vyper versions packaged in a bundle")]
+    Install { path: PathBuf },
+}
+
+#[derive(Debug, Subcommand)]
+enum ChecksumsCommand {
+    #[clap(about = "Fetch and verify the latest signed checksum manifest")]
+    Update {
+        #[clap(long, default_value = vvm_lib::CHECKSUMS_URL)]
+        url: url::Url,
+    },
+    #[clap(
+        about = "Download every published release artifact and write the manifest of their digests, to sign with minisign"
+    )]
+    Generate {
+        #[clap(long, help = "Serial of the manifest, above the last published one")]
+        serial: u64,
+        #[clap(long, default_value = "checksums.json")]
+        output: PathBuf,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum LockCommand {
+    #[clap(about = "Write the digests of installed versions, all by default, to vvm.lock")]
+    Update {
+        versions: Vec<String>,
+        #[clap(long, default_value = vvm_lib::LOCK_FILENAME)]
+        file: PathBuf,
+    },
+    #[clap(
+        about = "Fail unless the installed binaries match vvm.lock exactly, installing nothing"
+    )]
+    Verify {
+        #[clap(long, help = "Lock file, defaults to the nearest vvm.lock")]
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ProfileCommand {
+    #[clap(about = "Make a profile the active one, `vvm sync` then installs its versions")]
+    Use { name: String },
+    #[clap(about = "List the profiles of the config")]
+    List,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let opt = VyperVm::parse();
+    let Opts {
+        verbose,
+        offline,
+        insecure,
+        artifact,
+        command: opt,
+    } = Opts::parse();
+    // hot paths only read local state, whatever the freshness of the release list
+    vvm_lib::set_offline(
+        offline || matches!(opt, VyperVm::Complete { .. } | VyperVm::Which { .. }),
+    );
+    vvm_lib::set_insecure(insecure);
+    if let Some(artifact) = artifact {
+        vvm_lib::set_artifact_selector(Some(vvm_lib::ArtifactSelector::parse(&artifact)));
+    }
 
+    let first_run = !vvm_lib::active_home()?.exists();
     vvm_lib::setup_home()?;
+    if !matches!(opt, VyperVm::Complete { .. }) {
+        if let Some(repair) = vvm_lib::repair_global_version()? {
+            print::global_version_repaired(&repair);
+        }
+    }
+
+    if first_run
+        && !matches!(opt, VyperVm::Init { .. } | VyperVm::Complete { .. })
+        && console::user_attended()
+        && prompt::confirm(
+            "This is the first time vvm runs, would you like to set it up?",
+            true,
+        )?
+    {
+        handle_init(false).await?;
+    }
 
     match opt {
-        VyperVm::List => {
-            handle_list().await?;
+        VyperVm::List {
+            json,
+            remote,
+            limit,
+            schema,
+            installed,
+            paths,
+            quiet,
+        } => {
+            if schema {
+                println!("{}", vvm_lib::LIST_JSON_SCHEMA);
+            } else if installed && !json {
+                handle_list_installed(paths)?;
+            } else if json {
+                let remote = if remote {
+                    Some(vvm_lib::all_versions().await?)
+                } else {
+                    None
+                };
+                let listing = vvm_lib::version_listing(remote.as_deref(), limit)?;
+                println!("{}", serde_json::to_string_pretty(&listing)?);
+            } else if quiet {
+                handle_list_quiet().await?;
+            } else {
+                handle_list(verbose > 0).await?;
+            }
         }
-        VyperVm::Install { versions } => {
-            for v in versions {
-                handle_install(Version::parse(&v)?).await?;
+        VyperVm::Install {
+            versions,
+            all,
+            range,
+            resume,
+            jobs,
+            dry_run,
+        } => {
+            if resume {
+                handle_install_resume(jobs).await?;
+            } else if dry_run {
+                let versions = if all {
+                    let range = range.as_deref().map(VersionReq::parse).transpose()?;
+                    let installed_versions = vvm_lib::installed_versions().unwrap_or_default();
+                    vvm_lib::all_versions()
+                        .await?
+                        .into_iter()
+                        .filter(|v| range.as_ref().is_none_or(|r| r.matches(v)))
+                        .filter(|v| !installed_versions.contains(v))
+                        .collect()
+                } else {
+                    resolve_versions(&versions).await?
+                };
+                for version in versions {
+                    print::install_plan(&vvm_lib::plan_install(&version).await?);
+                }
+            } else if all {
+                let range = range.as_deref().map(VersionReq::parse).transpose()?;
+                handle_install_all(range, jobs).await?;
+            } else {
+                match resolve_versions(&versions).await?.as_slice() {
+                    [version] => handle_install(version.clone()).await?,
+                    versions => handle_install_versions(versions, jobs).await?,
+                }
             }
         }
         VyperVm::Use { version } => {
-            handle_use(Version::parse(&version)?).await?;
+            handle_use(resolve_use_version(&version).await?).await?;
+        }
+        // clap requires a version without --orphaned
+        VyperVm::Remove {
+            version: None,
+            dry_run,
+            ..
+        } => {
+            handle_remove_orphaned(dry_run).await?;
+        }
+        VyperVm::Remove {
+            version: Some(version),
+            dry_run,
+            ..
+        } if dry_run => {
+            let versions = match version.as_str() {
+                "ALL" | "all" => vvm_lib::installed_versions()?,
+                _ => vec![vvm_lib::resolve_version(&version).await?],
+            };
+            print::remove_plan(&vvm_lib::plan_remove(&versions)?);
         }
-        VyperVm::Remove { version } => match version.as_str() {
+        VyperVm::Remove {
+            version: Some(version),
+            ..
+        } => match version.as_str() {
             "ALL" | "all" => {
-                for v in vvm_lib::installed_versions().unwrap_or_default() {
-                    vvm_lib::remove_version(&v)?;
-                }
+                remove_versions(&vvm_lib::installed_versions().unwrap_or_default())?;
                 vvm_lib::unset_global_version()?;
             }
-            _ => handle_remove(Version::parse(&version)?)?,
+            _ => handle_remove(vvm_lib::resolve_version(&version).await?)?,
+        },
+        VyperVm::Restore { version } => {
+            let version = vvm_lib::resolve_version(&version).await?;
+            vvm_lib::restore_version(&version)?;
+            print::restored(&version);
+        }
+        VyperVm::Env {
+            shell: _,
+            json: true,
+        } => {
+            let descriptor = vvm_lib::home_descriptor(env::current_dir()?)?;
+            println!("{}", serde_json::to_string_pretty(&descriptor)?);
+        }
+        VyperVm::Env { shell, json: false } => {
+            let shell = shell.unwrap_or_else(shell::detect);
+            println!("{}", shell::path_snippet(shell, &vvm_lib::bin_path()?));
+        }
+        VyperVm::Shim { shell, wrapper } => {
+            handle_shim(shell.unwrap_or_else(shell::detect), wrapper)?;
+        }
+        VyperVm::Hook { shell } => {
+            println!("{}", shell::hook(shell.unwrap_or_else(shell::detect)));
+        }
+        VyperVm::HookEnv { shell } => {
+            handle_hook_env(shell.unwrap_or_else(shell::detect))?;
+        }
+        VyperVm::Completions {
+            shell,
+            r#static,
+            list_versions_source,
+        } => match complete::script(shell, list_versions_source) {
+            Some(script) if !r#static => print!("{}", script),
+            _ => clap_complete::generate(shell, &mut Opts::into_app(), "vvm", &mut io::stdout()),
+        },
+        VyperVm::Complete {
+            words,
+            versions_source,
+        } => {
+            for candidate in complete::candidates(&Opts::into_app(), &words, versions_source) {
+                println!("{}", candidate);
+            }
+        }
+        VyperVm::Run { install, args } => {
+            handle_run(install, args).await?;
+        }
+        VyperVm::Shell {
+            version,
+            install,
+            command,
+        } => {
+            handle_shell(vvm_lib::resolve_version(&version).await?, install, command).await?;
+        }
+        VyperVm::Exec { each, range, args } => {
+            if !each && range.is_none() {
+                anyhow::bail!("Pass --each or --range, or use `vvm run` to run a single version");
+            }
+            let range = range.as_deref().map(VersionReq::parse).transpose()?;
+            let versions = vvm_lib::installed_versions()?
+                .into_iter()
+                .filter(|v| range.as_ref().is_none_or(|r| r.matches(v)))
+                .collect::<Vec<_>>();
+            let outputs = vvm_lib::exec_each(&versions, &args, vvm_lib::compile_jobs()).await?;
+            print::exec_outputs(&outputs);
+        }
+        VyperVm::DiffOutput { source, old, new } => {
+            let diff = vvm_lib::diff_output(
+                &source,
+                &vvm_lib::resolve_version(&old).await?,
+                &vvm_lib::resolve_version(&new).await?,
+            )
+            .await?;
+            print::output_diff(&diff);
+        }
+        VyperVm::Which {
+            file,
+            resolve,
+            json,
+        } => {
+            handle_which(file, resolve, json).await?;
+        }
+        VyperVm::Status => {
+            handle_status().await?;
+        }
+        VyperVm::Info { version } => {
+            handle_info(vvm_lib::resolve_version(&version).await?)?;
+        }
+        VyperVm::Audit { forbid } => {
+            let forbid = if forbid.is_empty() {
+                vvm_lib::Config::load()?.audit_forbidden_origins
+            } else {
+                forbid
+            };
+            let findings = vvm_lib::audit_origins(&forbid)?;
+            print::audit_findings(&findings);
+            if !findings.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        VyperVm::Prune {
+            unused_for,
+            dry_run,
+        } => {
+            handle_prune(unused_for, dry_run)?;
+        }
+        VyperVm::Stats { enable, disable } => {
+            let mut config = vvm_lib::Config::load()?;
+            if enable || disable {
+                config.usage_stats = enable;
+                config.save()?;
+            }
+            print::usage_stats(config.usage_stats, &vvm_lib::UsageStats::read()?);
+        }
+        VyperVm::Gc => {
+            print::gc_report(&vvm_lib::gc()?);
+        }
+        VyperVm::Doctor { network, fix } => {
+            handle_doctor(network, fix).await?;
+        }
+        VyperVm::Relocate { new_home } => {
+            handle_relocate(new_home)?;
+        }
+        VyperVm::MigrateLayout => {
+            let mut migrated = vec![];
+            for version in vvm_lib::migrate_to_platform_dirs()? {
+                let path = vvm_lib::install_path(&version)?;
+                migrated.push((version, path));
+            }
+            print::migrated_layout(&migrated);
+        }
+        VyperVm::Sync {
+            file,
+            prune,
+            dry_run,
+        } => {
+            handle_sync(file, prune, dry_run).await?;
+        }
+        VyperVm::Init { defaults } => {
+            handle_init(defaults).await?;
+        }
+        VyperVm::SnapshotReleases { output } => {
+            fs::write(&output, vvm_lib::release_snapshot().await?)?;
+            println!("Release snapshot written to {}", output.display());
+        }
+        VyperVm::Checksums { command } => match command {
+            ChecksumsCommand::Update { url } => {
+                let manifest = vvm_lib::update_checksums(&url).await?;
+                print::checksums_updated(&manifest, &vvm_lib::checksums_path()?);
+            }
+            ChecksumsCommand::Generate { serial, output } => {
+                let spinner = print::generating_checksums();
+                let manifest = vvm_lib::generate_checksums(serial).await?;
+                fs::write(
+                    &output,
+                    format!("{}\n", serde_json::to_string_pretty(&manifest)?),
+                )?;
+                spinner.finish_and_clear();
+                print::checksums_generated(&manifest, &output);
+            }
         },
+        VyperVm::Lock { command } => match command {
+            LockCommand::Update { versions, file } => {
+                let versions = if versions.is_empty() {
+                    vvm_lib::installed_versions()?
+                } else {
+                    let mut resolved = Vec::with_capacity(versions.len());
+                    for version in versions {
+                        resolved.push(vvm_lib::resolve_version(&version).await?);
+                    }
+                    resolved
+                };
+                vvm_lib::VersionLock::from_installed(&versions)?.write(&file)?;
+                println!(
+                    "Locked {} Vyper versions in {}",
+                    versions.len(),
+                    file.display()
+                );
+            }
+            LockCommand::Verify { file } => {
+                let file = match file {
+                    Some(file) => file,
+                    None => vvm_lib::find_lock_file(env::current_dir()?).ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "No {} found in this directory or its parents",
+                            vvm_lib::LOCK_FILENAME
+                        )
+                    })?,
+                };
+                let statuses = vvm_lib::VersionLock::read(&file)?.verify();
+                print::lock_statuses(&statuses);
+                if !statuses.iter().all(|(_, status)| status.is_match()) {
+                    std::process::exit(1);
+                }
+            }
+        },
+        VyperVm::Profile { command } => match command {
+            ProfileCommand::Use { name } => {
+                let profile = vvm_lib::use_profile(&name)?;
+                print::profile_used(&name, &profile);
+            }
+            ProfileCommand::List => {
+                let config = vvm_lib::Config::load()?;
+                print::profiles(&config.profiles, config.profile.as_deref());
+            }
+        },
+        VyperVm::Bundle { command } => match command {
+            BundleCommand::Create { versions, path } => {
+                let versions = resolve_versions(&versions).await?;
+                let installed = vvm_lib::installed_versions()?;
+                let missing = versions
+                    .iter()
+                    .filter(|v| !installed.contains(v))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if !missing.is_empty() {
+                    let spinner = print::installing_versions(missing.len());
+                    let results =
+                        vvm_lib::install_many(&missing, vvm_lib::DEFAULT_DOWNLOAD_JOBS).await;
+                    spinner.finish_and_clear();
+                    for (_, result) in results {
+                        result?;
+                    }
+                }
+                vvm_lib::create_bundle(&versions, &path)?;
+                print::bundle_created(&versions, &path);
+            }
+            BundleCommand::Install { path } => {
+                let current_version = vvm_lib::current_version()?;
+                let installed = vvm_lib::install_bundle(&path)?;
+                print::bundle_installed(installed.len(), &path);
+                if current_version.is_none() {
+                    if let Some(version) = vvm_lib::installed_versions()?.pop() {
+                        vvm_lib::use_version(&version)?;
+                        print::set_global_version(&version);
+                    }
+                }
+            }
+        },
+    }
+
+    if verbose > 1 {
+        print::http_metrics(&vvm_lib::http_metrics());
     }
 
     Ok(())
 }
 
-async fn handle_list() -> anyhow::Result<()> {
-    let all_versions = vvm_lib::all_versions().await?;
+/// Prints the installed versions for scripts, with the path of their binary if `paths` is set.
+fn handle_list_installed(paths: bool) -> anyhow::Result<()> {
+    for version in vvm_lib::installed_versions()?.iter().rev() {
+        if paths {
+            println!("{}\t{}", version, vvm_lib::binary_path(version)?.display());
+        } else {
+            println!("{}", version);
+        }
+    }
+    Ok(())
+}
+
+async fn handle_list_quiet() -> anyhow::Result<()> {
+    let mut versions = vvm_lib::all_versions().await?;
+    versions.extend(vvm_lib::installed_versions()?);
+    versions.sort();
+    versions.dedup();
+    for version in versions.iter().rev() {
+        println!("{}", version);
+    }
+    Ok(())
+}
+
+async fn handle_list(verbose: bool) -> anyhow::Result<()> {
+    let mut releases = vvm_lib::all_releases(vvm_lib::platform()).await?;
+    let unmatched = std::mem::take(&mut releases.unmatched);
+    let alternatives = std::mem::take(&mut releases.alternatives);
+    let chosen = releases.releases.clone();
+    let all_versions = releases.into_versions();
     let installed_versions = vvm_lib::installed_versions().unwrap_or_default();
     let current_version = vvm_lib::current_version()?;
 
@@ -64,12 +807,283 @@ async fn handle_list() -> anyhow::Result<()> {
     available_versions.sort();
 
     print::current_version(current_version);
-    print::installed_versions(installed_versions);
+    let stats = vvm_lib::UsageStats::read().unwrap_or_default();
+    print::installed_versions(
+        installed_versions
+            .iter()
+            .map(|version| (version.clone(), vvm_lib::last_used(version, &stats)))
+            .collect(),
+    );
     print::available_versions(available_versions);
+    if verbose {
+        let artifacts = chosen
+            .iter()
+            .map(|(version, artifact)| {
+                let urls = vvm_lib::artifact_urls(vvm_lib::platform(), version, artifact)?;
+                Ok((version.clone(), artifact.clone(), urls))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        print::artifact_urls(&artifacts);
+        print::chosen_assets(&chosen, &alternatives);
+        print::unmatched_releases(&unmatched);
+    }
 
     Ok(())
 }
 
+async fn handle_run(install: bool, args: Vec<String>) -> anyhow::Result<()> {
+    let resolved = vvm_lib::resolve_for_dir(env::current_dir()?)?
+        .ok_or(vvm_lib::VyperVmError::GlobalVersionNotSet)?;
+    let version = resolved.version;
+    let vyper_path = vvm_lib::binary_path(&version)?;
+
+    if !vyper_path.exists() {
+        if !install {
+            anyhow::bail!(
+                "Vyper {} ({}) is not installed, run with --install to install it",
+                version,
+                resolved.source
+            );
+        }
+        let mut progress = print::InstallProgress::new(&version);
+        vvm_lib::install_with_observer(&version, &mut progress).await?;
+        progress.finish_with_message(format!("Downloaded Vyper: {}", version));
+    }
+
+    let _ = vvm_lib::record_use(&version);
+    let status = Command::new(vyper_path).args(args).status()?;
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+async fn handle_shell(version: Version, install: bool, command: Vec<String>) -> anyhow::Result<()> {
+    if !vvm_lib::binary_path(&version)?.exists() {
+        if !install {
+            anyhow::bail!(
+                "Vyper {} is not installed, run with --install to install it",
+                version
+            );
+        }
+        let mut progress = print::InstallProgress::new(&version);
+        vvm_lib::install_with_observer(&version, &mut progress).await?;
+        progress.finish_with_message(format!("Downloaded Vyper: {}", version));
+    }
+
+    let shims = vvm_lib::session_shims(&version)?;
+    let mut paths = vec![shims.path().to_path_buf()];
+    paths.extend(env::split_paths(&env::var_os("PATH").unwrap_or_default()));
+    let (program, args) = match command.split_first() {
+        Some((program, args)) => (program.clone(), args.to_vec()),
+        None => {
+            eprintln!(
+                "Starting a shell with Vyper {}, exit it to go back",
+                version
+            );
+            (session_shell(), vec![])
+        }
+    };
+
+    let _ = vvm_lib::record_use(&version);
+    let status = Command::new(program)
+        .args(args)
+        .env("PATH", env::join_paths(paths)?)
+        .env("VYPER", vvm_lib::binary_path(&version)?)
+        .env("VVM_VERSION", version.to_string())
+        .status()?;
+    drop(shims);
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+/// The interactive shell of the user.
+fn session_shell() -> String {
+    if cfg!(target_os = "windows") {
+        env::var("COMSPEC").unwrap_or_else(|_| "cmd.exe".to_string())
+    } else {
+        env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string())
+    }
+}
+
+async fn handle_status() -> anyhow::Result<()> {
+    let cwd = env::current_dir()?;
+    let current_version = vvm_lib::current_version()?;
+    let pinned = vvm_lib::pinned_version(&cwd)?;
+    let resolved = vvm_lib::resolve_for_dir(&cwd)?;
+    let installed_versions = vvm_lib::installed_versions().unwrap_or_default();
+    let cache_size = fs::metadata(vvm_lib::get_cache_path()?)
+        .map(|m| m.len())
+        .unwrap_or_default();
+
+    print::status_line(
+        "Global version",
+        current_version.map_or_else(|| "not set".to_string(), |v| v.to_string()),
+    );
+    print::status_line(
+        "Project pin",
+        pinned.map_or_else(
+            || "none".to_string(),
+            |(v, path)| format!("{} ({})", v, path.display()),
+        ),
+    );
+    print::status_line(
+        "Effective version",
+        resolved.as_ref().map_or_else(
+            || "none".to_string(),
+            |r| {
+                let installed = if installed_versions.contains(&r.version) {
+                    ""
+                } else {
+                    ", not installed"
+                };
+                format!("{} ({}{})", r.version, r.source, installed)
+            },
+        ),
+    );
+    print::status_line("VVM home", vvm_lib::active_home()?.display());
+    let shim = vvm_lib::shim_path()?;
+    if let Some(target) = vvm_lib::shim_target(&shim) {
+        let state = if target.is_file() {
+            "ok"
+        } else {
+            "broken, target removed"
+        };
+        print::status_line(
+            "Shim",
+            format!("{} -> {} ({})", shim.display(), target.display(), state),
+        );
+    }
+    print::status_line("Installed versions", installed_versions.len());
+    print::status_line("Cache size", indicatif::HumanBytes(cache_size));
+
+    let latest = match vvm_lib::all_versions().await {
+        Ok(versions) => match (
+            versions.into_iter().filter(|v| v.pre.is_empty()).max(),
+            resolved,
+        ) {
+            (Some(latest), Some(r)) if latest > r.version => format!("{} available", latest),
+            (Some(latest), None) => format!("{} available", latest),
+            (Some(_), Some(_)) => "up to date".to_string(),
+            (None, _) => "no releases found".to_string(),
+        },
+        Err(err) => format!("unknown ({})", err),
+    };
+    print::status_line("Newer release", latest);
+
+    Ok(())
+}
+
+async fn handle_which(
+    file: Option<PathBuf>,
+    resolve: Option<VersionReq>,
+    json: bool,
+) -> anyhow::Result<()> {
+    let which = match resolve {
+        Some(req) => vvm_lib::which_for_req(&req).await?,
+        None => {
+            let path = match file {
+                Some(file) => fs::canonicalize(&file).unwrap_or(file),
+                None => env::current_dir()?,
+            };
+            let dir = if path.is_dir() {
+                path.as_path()
+            } else {
+                path.parent().unwrap_or(path.as_path())
+            };
+            vvm_lib::which_for_dir(dir)?
+        }
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&which)?);
+    } else {
+        match which {
+            Some(which) => print::which(&which),
+            None => anyhow::bail!("No Vyper version resolved, pin one or run `vvm use`"),
+        }
+    }
+    Ok(())
+}
+
+fn handle_info(version: Version) -> anyhow::Result<()> {
+    let entry = vvm_lib::version_listing(None, None)?
+        .versions
+        .into_iter()
+        .find(|entry| entry.version == version)
+        .ok_or_else(|| vvm_lib::VyperVmError::VersionNotInstalled(version.to_string()))?;
+    let descriptor = vvm_lib::env_descriptor(&version)?;
+
+    print::status_line("Version", &version);
+    print::status_line("Path", descriptor.path.display());
+    print::status_line(
+        "Origin",
+        entry
+            .origin
+            .map_or_else(|| "unknown".to_string(), |origin| origin.to_string()),
+    );
+    print::status_line(
+        "Commit",
+        descriptor.commit.unwrap_or_else(|| "unknown".to_string()),
+    );
+    print::status_line("SHA256", hex::encode(&descriptor.sha256));
+    print::status_line(
+        "Size",
+        indicatif::HumanBytes(entry.size_bytes.unwrap_or_default()),
+    );
+    let capabilities = vvm_lib::capabilities(&version);
+    print::status_line("EVM versions", capabilities.evm_versions.join(", "));
+    let features = [
+        ("abi_python", capabilities.abi_python),
+        ("standard-json", capabilities.standard_json),
+        ("blueprint", capabilities.blueprint),
+    ];
+    print::status_line(
+        "Supports",
+        features
+            .iter()
+            .filter(|(_, supported)| *supported)
+            .map(|(feature, _)| *feature)
+            .collect::<Vec<_>>()
+            .join(", "),
+    );
+    Ok(())
+}
+
+fn handle_hook_env(shell: Shell) -> anyhow::Result<()> {
+    let bin_path = vvm_lib::bin_path()?;
+    let on_path = env::var_os("PATH")
+        .map(|path| env::split_paths(&path).any(|p| p == bin_path))
+        .unwrap_or_default();
+    if !on_path {
+        println!("{}", shell::prepend_path(shell, &bin_path));
+    }
+
+    match vvm_lib::resolve_for_dir(env::current_dir()?)? {
+        Some(resolved) => {
+            let vyper = vvm_lib::binary_path(&resolved.version)?;
+            println!(
+                "{}",
+                shell::set_var(shell, "VYPER", &vyper.display().to_string())
+            );
+            println!(
+                "{}",
+                shell::set_var(shell, "VVM_VERSION", &resolved.version.to_string())
+            );
+        }
+        None => {
+            println!("{}", shell::unset_var(shell, "VYPER"));
+            println!("{}", shell::unset_var(shell, "VVM_VERSION"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses the versions given on the command line, resolving channels like `stable`.
+async fn resolve_versions(versions: &[String]) -> anyhow::Result<Vec<Version>> {
+    let mut resolved = Vec::with_capacity(versions.len());
+    for version in versions {
+        resolved.push(vvm_lib::resolve_version(version).await?);
+    }
+    Ok(resolved)
+}
+
 async fn handle_install(version: Version) -> anyhow::Result<()> {
     let all_versions = vvm_lib::all_versions().await?;
     let installed_versions = vvm_lib::installed_versions().unwrap_or_default();
@@ -77,30 +1091,172 @@ async fn handle_install(version: Version) -> anyhow::Result<()> {
 
     if installed_versions.contains(&version) {
         println!("Vyper {} is already installed", version);
-        let input: String = Input::new()
-            .with_prompt("Would you like to set it as the global version?")
-            .with_initial_text("Y")
-            .default("N".into())
-            .interact_text()?;
-        if matches!(input.as_str(), "y" | "Y" | "yes" | "Yes") {
+        if prompt::confirm("Would you like to set it as the global version?", true)? {
             vvm_lib::use_version(&version)?;
             print::set_global_version(&version);
         }
     } else if all_versions.contains(&version) {
-        let spinner = print::installing_version(&version);
-        vvm_lib::install(&version).await?;
-        spinner.finish_with_message(format!("Downloaded Vyper: {}", version));
+        let mut progress = print::InstallProgress::new(&version);
+        vvm_lib::install_with_observer(&version, &mut progress).await?;
+        progress.finish_with_message(format!("Downloaded Vyper: {}", version));
         if current_version.is_none() {
             vvm_lib::use_version(&version)?;
             print::set_global_version(&version);
         }
     } else {
-        print::unsupported_version(&version);
+        print::unsupported_version(&vvm_lib::suggest_versions(&version, &all_versions));
+    }
+
+    Ok(())
+}
+
+/// Installs several versions at the same time, at most `jobs` at once, each with its own progress
+/// bar, then prints which installs succeeded and which failed.
+async fn handle_install_versions(versions: &[Version], jobs: usize) -> anyhow::Result<()> {
+    let all_versions = vvm_lib::all_versions().await?;
+    let installed_versions = vvm_lib::installed_versions().unwrap_or_default();
+    let current_version = vvm_lib::current_version()?;
+
+    let mut pending = vec![];
+    let mut seen = HashSet::new();
+    for version in versions.iter().filter(|version| seen.insert(*version)) {
+        if installed_versions.contains(version) {
+            println!("Vyper {} is already installed", version);
+        } else if all_versions.contains(version) {
+            pending.push(version.clone());
+        } else {
+            print::unsupported_version(&vvm_lib::suggest_versions(version, &all_versions));
+        }
+    }
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    // indicatif draws the bars of a group from a thread of its own
+    let group = MultiProgress::new();
+    let installs = pending
+        .iter()
+        .map(|version| {
+            let mut progress = print::InstallProgress::in_group(&group, version);
+            async move {
+                let result = vvm_lib::install_with_observer(version, &mut progress).await;
+                match &result {
+                    Ok(_) => progress.finish_with_message(format!("Downloaded Vyper: {}", version)),
+                    Err(_) => progress.abandon_with_message(format!("Failed Vyper: {}", version)),
+                }
+                (version.clone(), result)
+            }
+        })
+        .collect::<Vec<_>>();
+    let drawing = tokio::task::spawn_blocking(move || group.join());
+    let results = stream::iter(installs)
+        .buffer_unordered(jobs.max(1))
+        .collect::<Vec<_>>()
+        .await;
+    drawing.await??;
+
+    let mut installed = vec![];
+    let mut failed = vec![];
+    for (version, result) in results {
+        match result {
+            Ok(_) => installed.push(version),
+            Err(err) => failed.push((version, err)),
+        }
+    }
+    installed.sort();
+    failed.sort_by(|(a, _), (b, _)| a.cmp(b));
+    println!();
+    print::install_summary(&installed, &failed);
+
+    if current_version.is_none() {
+        if let Some(version) = installed.last() {
+            vvm_lib::use_version(version)?;
+            print::set_global_version(version);
+        }
+    }
+
+    if !failed.is_empty() {
+        anyhow::bail!("{} of {} installs failed", failed.len(), pending.len());
+    }
+    Ok(())
+}
+
+async fn handle_install_all(range: Option<VersionReq>, jobs: usize) -> anyhow::Result<()> {
+    let installed_versions = vvm_lib::installed_versions().unwrap_or_default();
+    let versions = vvm_lib::all_versions()
+        .await?
+        .into_iter()
+        .filter(|v| range.as_ref().is_none_or(|r| r.matches(v)))
+        .filter(|v| !installed_versions.contains(v))
+        .collect::<Vec<_>>();
+    handle_install_queued(&versions, jobs).await
+}
+
+async fn handle_install_resume(jobs: usize) -> anyhow::Result<()> {
+    let versions = vvm_lib::DownloadQueue::interrupted()?;
+    if versions.is_empty() {
+        println!("No interrupted installs to resume");
+        return Ok(());
+    }
+    handle_install_queued(&versions, jobs).await
+}
+
+/// Installs `versions` through a download queue, which records them until they are installed so
+/// `vvm install --resume` picks up an interrupted run.
+async fn handle_install_queued(versions: &[Version], jobs: usize) -> anyhow::Result<()> {
+    let current_version = vvm_lib::current_version()?;
+    let spinner = print::installing_versions(versions.len());
+    let results = vvm_lib::install_many(versions, jobs).await;
+    spinner.finish_and_clear();
+
+    let mut installed = vec![];
+    let mut failed = vec![];
+    for (version, result) in results {
+        match result {
+            Ok(_) => installed.push(version),
+            Err(err) => failed.push((version, err)),
+        }
+    }
+    installed.sort();
+    failed.sort_by(|(a, _), (b, _)| a.cmp(b));
+    print::install_summary(&installed, &failed);
+
+    if current_version.is_none() {
+        if let Some(version) = installed.last() {
+            vvm_lib::use_version(version)?;
+            print::set_global_version(version);
+        }
     }
 
+    if !failed.is_empty() {
+        anyhow::bail!("{} of {} installs failed", failed.len(), versions.len());
+    }
     Ok(())
 }
 
+/// Resolves the version of `vvm use`, also accepting an unambiguous prefix of an installed or
+/// available version, see [`vvm_lib::match_prefix`].
+async fn resolve_use_version(input: &str) -> anyhow::Result<Version> {
+    let exact = match vvm_lib::VersionInput::parse(input) {
+        Ok(vvm_lib::VersionInput::Exact(version)) => Some(version),
+        Ok(_) => return Ok(vvm_lib::resolve_version(input).await?),
+        Err(_) => None,
+    };
+    let installed = vvm_lib::installed_versions().unwrap_or_default();
+    if let Some(version) = exact.as_ref().filter(|v| installed.contains(v)) {
+        return Ok(version.clone());
+    }
+    let available = vvm_lib::all_versions().await?;
+    if let Some(version) = exact.as_ref().filter(|v| available.contains(v)) {
+        return Ok(version.clone());
+    }
+    match vvm_lib::match_prefix(input, &installed, &available)? {
+        Some(version) => Ok(version),
+        // reported as unsupported, with suggestions
+        None => Ok(vvm_lib::resolve_version(input).await?),
+    }
+}
+
 async fn handle_use(version: Version) -> anyhow::Result<()> {
     let all_versions = vvm_lib::all_versions().await?;
     let installed_versions = vvm_lib::installed_versions().unwrap_or_default();
@@ -110,45 +1266,348 @@ async fn handle_use(version: Version) -> anyhow::Result<()> {
         print::set_global_version(&version);
     } else if all_versions.contains(&version) {
         println!("Vyper {} is not installed", version);
-        let input: String = Input::new()
-            .with_prompt("Would you like to install it?")
-            .with_initial_text("Y")
-            .default("N".into())
-            .interact_text()?;
-        if matches!(input.as_str(), "y" | "Y" | "yes" | "Yes") {
+        if prompt::confirm("Would you like to install it?", true)? {
             handle_install(version).await?;
         }
     } else {
-        print::unsupported_version(&version);
+        print::unsupported_version(&vvm_lib::suggest_versions(&version, &all_versions));
     }
 
     Ok(())
 }
 
-fn handle_remove(version: Version) -> anyhow::Result<()> {
-    let mut installed_versions = vvm_lib::installed_versions().unwrap_or_default();
-    let current_version = vvm_lib::current_version()?;
+async fn handle_doctor(network: bool, fix: bool) -> anyhow::Result<()> {
+    if fix {
+        print::home_repair(&vvm_lib::repair_home()?);
+        println!();
+    }
+    let mut checks = vvm_lib::local_diagnostics();
+    if network {
+        checks.extend(vvm_lib::network_diagnostics().await);
+    }
+    print::doctor_checks(&checks);
+    let failed = checks
+        .iter()
+        .filter(|check| check.status == vvm_lib::CheckStatus::Failed)
+        .count();
+    if failed > 0 {
+        anyhow::bail!("{} of {} checks failed", failed, checks.len());
+    }
+    Ok(())
+}
 
-    if installed_versions.contains(&version) {
+async fn handle_sync(file: Option<PathBuf>, prune: bool, dry_run: bool) -> anyhow::Result<()> {
+    let profile = match file {
+        Some(_) => None,
+        None => vvm_lib::active_profile()?,
+    };
+    let (reqs, global, source) = match profile {
+        Some((name, profile)) => {
+            let (reqs, global) = profile_requirements(&profile).await?;
+            (reqs, global, format!("profile {}", name))
+        }
+        None => {
+            let file = match file {
+                Some(file) => file,
+                None => vvm_lib::find_toolchain_file(env::current_dir()?).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "No {} found in this directory or its parents",
+                        vvm_lib::TOOLCHAIN_FILENAME
+                    )
+                })?,
+            };
+            let toolchain = vvm_lib::ToolchainFile::read(&file)?;
+            (
+                toolchain.requirements()?,
+                toolchain.global_requirement()?,
+                file.display().to_string(),
+            )
+        }
+    };
+
+    let task_plan = vvm_lib::plan(&reqs).await?;
+    if !task_plan.unsatisfiable.is_empty() {
+        anyhow::bail!(
+            "No available version satisfies {}",
+            task_plan
+                .unsatisfiable
+                .iter()
+                .map(|req| req.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    if dry_run {
+        print::task_plan(&if prune {
+            task_plan
+        } else {
+            task_plan.keep_extras()
+        });
+        return Ok(());
+    }
+
+    if !task_plan.installs.is_empty() {
+        let spinner = print::installing_versions(task_plan.installs.len());
+        let installed = vvm_lib::apply(&task_plan.keep_extras()).await?;
+        spinner.finish_with_message(format!("Downloaded {} Vyper versions", installed.len()));
+    }
+
+    if let Some(req) = global {
+        let version = vvm_lib::installed_versions()?
+            .into_iter()
+            .rev()
+            .find(|v| req.matches(v))
+            // installed by the plan, unless removed concurrently
+            .ok_or_else(|| vvm_lib::VyperVmError::VersionNotInstalled(req.to_string()))?;
+        if vvm_lib::current_version()?.as_ref() != Some(&version) {
+            vvm_lib::use_version(&version)?;
+            print::set_global_version(&version);
+        }
+    }
+
+    // planned again so the previous global version can be pruned too
+    if prune {
+        let removals = vvm_lib::plan(&reqs).await?;
+        if !removals.removals.entries.is_empty() {
+            print::remove_plan(&removals.removals);
+            vvm_lib::apply(&removals).await?;
+        }
+    }
+
+    println!("Toolchain in sync with {}", source);
+    Ok(())
+}
+
+/// Resolves the versions of a profile to exact requirements, and the requirement of its default
+/// version.
+async fn profile_requirements(
+    profile: &vvm_lib::ProfileConfig,
+) -> anyhow::Result<(Vec<VersionReq>, Option<VersionReq>)> {
+    let available = vvm_lib::all_versions().await?;
+    let stable = if profile
+        .default
+        .iter()
+        .chain(profile.versions.iter())
+        .any(|version| version.trim() == vvm_lib::STABLE_CHANNEL)
+    {
+        Some(vvm_lib::stable_version().await?)
+    } else {
+        None
+    };
+    let resolved = vvm_lib::profile_versions(profile, &available, stable.as_ref())?;
+    if !resolved.unsatisfiable.is_empty() {
+        anyhow::bail!(
+            "No available version satisfies {}",
+            resolved.unsatisfiable.join(", ")
+        );
+    }
+    let global = resolved
+        .default
+        .as_ref()
+        .map(|version| VersionReq::parse(&format!("={}", version)))
+        .transpose()?;
+    Ok((resolved.requirements(), global))
+}
+
+async fn handle_init(defaults: bool) -> anyhow::Result<()> {
+    let mut config = vvm_lib::Config::load()?;
+
+    // releases may not be reachable, the rest of the setup is still useful
+    let latest = vvm_lib::all_versions()
+        .await
+        .ok()
+        .and_then(|versions| versions.into_iter().rev().find(|v| v.pre.is_empty()));
+    let version = if defaults {
+        latest
+    } else {
         let input: String = Input::new()
-            .with_prompt("Are you sure?")
-            .with_initial_text("Y")
-            .default("N".into())
+            .with_prompt(
+                "Which Vyper version should be installed and used by default? (none to skip)",
+            )
+            .default(
+                latest
+                    .map(|v| v.to_string())
+                    .unwrap_or_else(|| "none".into()),
+            )
             .interact_text()?;
-        if matches!(input.as_str(), "y" | "Y" | "yes" | "Yes") {
+        match input.as_str() {
+            "none" | "" => None,
+            v => Some(vvm_lib::resolve_version(v).await?),
+        }
+    };
+    if let Some(version) = version {
+        if !vvm_lib::installed_versions()?.contains(&version) {
+            let mut progress = print::InstallProgress::new(&version);
+            vvm_lib::install_with_observer(&version, &mut progress).await?;
+            progress.finish_with_message(format!("Downloaded Vyper: {}", version));
+        }
+        vvm_lib::use_version(&version)?;
+        print::set_global_version(&version);
+    }
+
+    println!(
+        "\n{}",
+        shell::path_snippet(shell::detect(), &vvm_lib::bin_path()?)
+    );
+
+    if !defaults {
+        if env::var("VVM_GITHUB_TOKEN").is_err()
+            && env::var("GITHUB_TOKEN").is_err()
+            && config.github_token.is_none()
+        {
+            let token: String = Input::new()
+                .with_prompt("GitHub token to avoid API rate limits (leave empty to skip)")
+                .allow_empty(true)
+                .interact_text()?;
+            if !token.trim().is_empty() {
+                config.github_token = Some(token.trim().to_string());
+            }
+        }
+
+        config.usage_stats =
+            prompt::opt_in("Keep local usage statistics? They never leave this machine")?;
+    }
+
+    config.save()?;
+    println!(
+        "\nConfiguration written to {}",
+        vvm_lib::config_path()?.display()
+    );
+
+    Ok(())
+}
+
+fn handle_shim(shell: Shell, wrapper: Option<PathBuf>) -> anyhow::Result<()> {
+    let wrapper = match wrapper {
+        Some(wrapper) => wrapper,
+        None => env::current_exe()?.with_file_name(if cfg!(target_os = "windows") {
+            "vyper.exe"
+        } else {
+            "vyper"
+        }),
+    };
+    if !wrapper.is_file() {
+        anyhow::bail!(
+            "No vyper wrapper at {}, install it next to vvm or pass --wrapper",
+            wrapper.display()
+        );
+    }
+    let shim = vvm_lib::install_wrapper_shim(&wrapper)?;
+    println!(
+        "{} now runs the vyper wrapper {}\n\n{}",
+        shim.display(),
+        wrapper.display(),
+        shell::path_snippet(shell, &vvm_lib::bin_path()?)
+    );
+    Ok(())
+}
+
+fn handle_prune(unused_for: Duration, dry_run: bool) -> anyhow::Result<()> {
+    if !vvm_lib::Config::load()?.usage_stats {
+        println!("Usage statistics are disabled, only install dates are taken into account");
+    }
+    let unused = vvm_lib::unused_versions(unused_for)?;
+    if unused.is_empty() {
+        println!("No unused versions to prune");
+        return Ok(());
+    }
+
+    if dry_run {
+        print::remove_plan(&vvm_lib::plan_remove(&unused)?);
+        return Ok(());
+    }
+    print::prune_candidates(&unused);
+
+    if prompt::confirm("Are you sure?", true)? {
+        let _home_lock = vvm_lib::lock_home_exclusive()?;
+        for version in unused {
             vvm_lib::remove_version(&version)?;
-            if let Some(v) = current_version {
-                if version == v {
-                    if let Some(i) = installed_versions.iter().position(|x| *x == v) {
-                        installed_versions.remove(i);
-                        if let Some(new_version) = installed_versions.pop() {
-                            vvm_lib::use_version(&new_version)?;
-                            print::set_global_version(&new_version);
-                        } else {
-                            vvm_lib::unset_global_version()?;
-                        }
-                    }
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_relocate(new_home: PathBuf) -> anyhow::Result<()> {
+    let message = format!(
+        "Move {} to {}?",
+        vvm_lib::active_home()?.display(),
+        new_home.display()
+    );
+    if !prompt::confirm(&message, true)? {
+        return Ok(());
+    }
+    let new_home = vvm_lib::relocate_home(&new_home)?;
+    print::relocated(&new_home);
+    Ok(())
+}
+
+async fn handle_remove_orphaned(dry_run: bool) -> anyhow::Result<()> {
+    // a stored or bundled list is missing releases, which would remove valid installs
+    let releases = vvm_lib::fresh_releases(vvm_lib::platform()).await?;
+    let orphaned = vvm_lib::orphaned_versions(&releases)?;
+    if orphaned.is_empty() {
+        println!("No orphaned versions to remove");
+        return Ok(());
+    }
+    let versions = orphaned
+        .iter()
+        .map(|orphan| orphan.version.clone())
+        .collect::<Vec<_>>();
+
+    if dry_run {
+        print::remove_plan(&vvm_lib::plan_remove(&versions)?);
+        return Ok(());
+    }
+    print::orphaned_versions(&orphaned);
+
+    if prompt::confirm("Are you sure?", true)? {
+        remove_versions(&versions)?;
+        if let Some(repair) = vvm_lib::repair_global_version()? {
+            print::global_version_repaired(&repair);
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes installed versions, into the trash if `trash_days` is set in the config, like
+/// [`handle_remove`] does for a single version.
+fn remove_versions(versions: &[Version]) -> anyhow::Result<()> {
+    let trash_days = vvm_lib::Config::load()?.trash_days;
+    let _home_lock = vvm_lib::lock_home_exclusive()?;
+    for version in versions {
+        match trash_days {
+            Some(_) => {
+                vvm_lib::trash_version(version)?;
+            }
+            None => vvm_lib::remove_version(version)?,
+        }
+    }
+    if let Some(days) = trash_days {
+        vvm_lib::expire_trash(Duration::from_secs(days * 24 * 60 * 60))?;
+    }
+    Ok(())
+}
+
+fn handle_remove(version: Version) -> anyhow::Result<()> {
+    if vvm_lib::installed_versions()
+        .unwrap_or_default()
+        .contains(&version)
+    {
+        if prompt::confirm("Are you sure?", true)? {
+            match vvm_lib::Config::load()?.trash_days {
+                Some(days) => {
+                    vvm_lib::trash_version(&version)?;
+                    vvm_lib::expire_trash(Duration::from_secs(days * 24 * 60 * 60))?;
+                    print::trashed(&version);
                 }
+                None => vvm_lib::remove_version(&version)?,
+            }
+            if let Some(vvm_lib::GlobalVersionRepair::Switched { new, .. }) =
+                vvm_lib::repair_global_version()?
+            {
+                print::set_global_version(&new);
             }
         }
     } else {
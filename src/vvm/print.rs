@@ -1,8 +1,14 @@
 use console::style;
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressStyle};
 use itertools::Itertools;
 use semver::Version;
 
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
 pub fn current_version(version: Option<Version>) {
     match version {
         Some(v) => {
@@ -14,10 +20,18 @@ pub fn current_version(version: Option<Version>) {
     }
 }
 
-pub fn installed_versions(versions: Vec<Version>) {
+pub fn installed_versions(versions: Vec<(Version, Option<u64>)>) {
     println!("\n{}", style("Installed Versions").bold());
-    versions.iter().for_each(|v| {
-        println!("{}", style(v.to_string().as_str()).yellow());
+    versions.iter().for_each(|(v, last_used)| {
+        println!(
+            "{:<12} {}",
+            style(v.to_string().as_str()).yellow(),
+            style(format!(
+                "last used {}",
+                last_used.map_or_else(|| "never".to_string(), time_ago)
+            ))
+            .dim()
+        );
     });
 }
 
@@ -37,6 +51,97 @@ pub fn available_versions(versions: Vec<Version>) {
     }
 }
 
+/// Prints the artifact of every version with the URLs it is downloaded from, fallbacks dimmed.
+pub fn artifact_urls(artifacts: &[(Version, String, Vec<url::Url>)]) {
+    println!("\n{}", style("Artifacts").bold());
+    for (version, artifact, urls) in artifacts {
+        println!(
+            "{}: {}",
+            style(version.to_string().as_str()).yellow(),
+            artifact
+        );
+        for (i, url) in urls.iter().enumerate() {
+            if i == 0 {
+                println!("  {}", url);
+            } else {
+                println!("  {}", style(format!("{} (fallback)", url)).dim());
+            }
+        }
+    }
+}
+
+pub fn chosen_assets(
+    chosen: &BTreeMap<Version, String>,
+    alternatives: &BTreeMap<Version, Vec<String>>,
+) {
+    if alternatives.is_empty() {
+        return;
+    }
+    println!(
+        "\n{}",
+        style("Assets Chosen among Several Candidates").bold()
+    );
+    for (version, others) in alternatives {
+        if let Some(artifact) = chosen.get(version) {
+            println!(
+                "{}: {} {}",
+                style(version.to_string().as_str()).yellow(),
+                artifact,
+                style(format!("(over {})", others.join(", "))).dim()
+            );
+        }
+    }
+}
+
+pub fn unmatched_releases(releases: &[vvm_lib::UnmatchedRelease]) {
+    println!("\n{}", style("Unavailable on this Platform").bold());
+    for release in releases {
+        let reason = match (&release.version, release.assets.is_empty()) {
+            (None, _) => "tag is not a valid version".to_string(),
+            (Some(_), true) => "release has no assets".to_string(),
+            (Some(_), false) => format!("no asset matches, found: {}", release.assets.join(", ")),
+        };
+        println!("{} ({})", style(release.tag.as_str()).dim(), reason);
+    }
+}
+
+pub fn profile_used(name: &str, profile: &vvm_lib::ProfileConfig) {
+    println!("Active profile: {}", style(name).green());
+    if let Some(default) = &profile.default {
+        println!("Default version: {}", default);
+    }
+    println!("Run `vvm sync` to install its versions");
+}
+
+pub fn profiles(profiles: &BTreeMap<String, vvm_lib::ProfileConfig>, active: Option<&str>) {
+    if profiles.is_empty() {
+        println!("No profiles, declare them under [profiles.<name>] in the config");
+        return;
+    }
+    for (name, profile) in profiles {
+        let versions = profile
+            .default
+            .iter()
+            .chain(profile.versions.iter())
+            .join(", ");
+        let prereleases = if profile.prereleases {
+            " (prereleases)"
+        } else {
+            ""
+        };
+        if active == Some(name.as_str()) {
+            println!(
+                "{} (active): {}{}",
+                style(name).green(),
+                versions,
+                prereleases
+            );
+        } else {
+            println!("{}: {}{}", name, versions, prereleases);
+        }
+    }
+}
+
 pub fn installing_version(version: &Version) -> ProgressBar {
     let spinner = ProgressBar::new_spinner();
     spinner.enable_steady_tick(120);
@@ -52,17 +157,470 @@ pub fn installing_version(version: &Version) -> ProgressBar {
     spinner
 }
 
-pub fn unsupported_version(version: &Version) {
+/// Shows the download of a version as a progress bar once its size is known, and as the
+/// [`installing_version`] spinner until then.
+pub struct InstallProgress {
+    bar: ProgressBar,
+}
+
+impl InstallProgress {
+    pub fn new(version: &Version) -> Self {
+        Self {
+            bar: installing_version(version),
+        }
+    }
+
+    /// The progress of one of several versions installed at the same time, drawn below the
+    /// others of `group`.
+    pub fn in_group(group: &MultiProgress, version: &Version) -> Self {
+        Self {
+            bar: group.add(installing_version(version)),
+        }
+    }
+
+    pub fn abandon_with_message(&self, message: String) {
+        self.bar.abandon_with_message(message);
+    }
+
+    pub fn finish_with_message(&self, message: String) {
+        self.bar.finish_with_message(message);
+    }
+}
+
+impl vvm_lib::InstallObserver for InstallProgress {
+    fn on_progress(&mut self, downloaded: u64, total: Option<u64>) {
+        if let Some(total) = total {
+            if self.bar.length() != total {
+                self.bar.set_length(total);
+                self.bar.set_style(
+                    ProgressStyle::default_bar()
+                        .template("{msg} [{bar:30.green}] {bytes}/{total_bytes} ({eta})")
+                        .progress_chars("=> "),
+                );
+            }
+        }
+        self.bar.set_position(downloaded);
+    }
+}
+
+pub fn installing_versions(count: usize) -> ProgressBar {
+    let spinner = ProgressBar::new_spinner();
+    spinner.enable_steady_tick(120);
+    spinner.set_message(format!("Downloading {} Vyper versions", count));
+    spinner.set_style(ProgressStyle::default_spinner().template("{spinner:.green} {msg}"));
+    spinner
+}
+
+pub fn install_summary(installed: &[Version], failed: &[(Version, vvm_lib::VyperVmError)]) {
     println!(
         "{}",
-        style(format!("Version: {} unsupported", version)).red()
+        style(format!("Installed {} version(s)", installed.len())).bold()
+    );
+    installed.iter().for_each(|v| {
+        println!("{}", style(v.to_string().as_str()).green());
+    });
+    if !failed.is_empty() {
+        println!(
+            "\n{}",
+            style(format!("Failed to install {} version(s)", failed.len())).bold()
+        );
+        failed.iter().for_each(|(v, err)| {
+            println!("{}: {}", style(v.to_string().as_str()).red(), err);
+        });
+    }
+}
+
+pub fn exec_outputs(outputs: &[vvm_lib::ExecOutput]) {
+    for output in outputs {
+        let status = match (output.success, output.code) {
+            (true, _) => style("ok".to_string()).green(),
+            (false, Some(code)) => style(format!("exit code {}", code)).red(),
+            (false, None) => style("killed".to_string()).red(),
+        };
+        println!(
+            "{} {}",
+            style(format!("Vyper {}", output.version)).bold(),
+            status
+        );
+        for line in output.stdout.lines().chain(output.stderr.lines()) {
+            println!("  {}", line);
+        }
+    }
+
+    let (ok, failed): (Vec<_>, Vec<_>) = outputs.iter().partition(|output| output.success);
+    println!(
+        "\n{} succeeded: {}",
+        ok.len(),
+        ok.iter()
+            .map(|output| output.version.to_string())
+            .join(", ")
+    );
+    println!(
+        "{} failed: {}",
+        failed.len(),
+        failed
+            .iter()
+            .map(|output| output.version.to_string())
+            .join(", ")
     );
 }
 
+pub fn output_diff(diff: &vvm_lib::OutputDiff) {
+    let (old, new) = (&diff.old, &diff.new);
+    println!(
+        "{}",
+        style(format!("Vyper {} -> {}", old.version, new.version)).bold()
+    );
+    let delta = new.bytecode_size as i64 - old.bytecode_size as i64;
+    println!(
+        "Bytecode size: {} -> {} bytes ({:+})",
+        old.bytecode_size, new.bytecode_size, delta
+    );
+    if diff.is_empty() {
+        println!("ABI and warnings are identical");
+        return;
+    }
+    diff.abi_added
+        .iter()
+        .for_each(|sig| println!("{}", style(format!("+ abi {}", sig)).green()));
+    diff.abi_removed
+        .iter()
+        .for_each(|sig| println!("{}", style(format!("- abi {}", sig)).red()));
+    diff.abi_changed
+        .iter()
+        .for_each(|sig| println!("{}", style(format!("~ abi {}", sig)).yellow()));
+    diff.warnings_added
+        .iter()
+        .for_each(|w| println!("{}", style(format!("+ {}", w)).green()));
+    diff.warnings_removed
+        .iter()
+        .for_each(|w| println!("{}", style(format!("- {}", w)).red()));
+}
+
+pub fn unsupported_version(suggestions: &vvm_lib::VersionSuggestions) {
+    println!(
+        "{}",
+        style(format!(
+            "Version: {} unsupported on {}",
+            suggestions.version,
+            vvm_lib::platform()
+        ))
+        .red()
+    );
+    if !suggestions.other_platforms.is_empty() {
+        println!(
+            "It is only available for {}",
+            suggestions.other_platforms.iter().join(", ")
+        );
+    }
+    if !suggestions.nearest.is_empty() {
+        println!(
+            "Closest available versions: {}",
+            style(suggestions.nearest.iter().join(", ")).green()
+        );
+    }
+}
+
 pub fn set_global_version(version: &Version) {
     ProgressBar::new_spinner().finish_with_message(format!("Global version set: {}", version));
 }
 
+pub fn global_version_repaired(repair: &vvm_lib::GlobalVersionRepair) {
+    eprintln!("{}", style(repair).yellow());
+}
+
 pub fn version_not_found(version: &Version) {
     println!("{}", style(format!("Version: {} not found", version)).red());
 }
+
+pub fn bundle_created(versions: &[Version], path: &Path) {
+    println!(
+        "Bundled {} into {}",
+        versions.iter().map(|v| v.to_string()).join(", "),
+        style(path.display()).green()
+    );
+}
+
+pub fn bundle_installed(count: usize, path: &Path) {
+    println!(
+        "Installed {} version(s) from {}",
+        count,
+        style(path.display()).green()
+    );
+}
+
+pub fn checksums_updated(manifest: &vvm_lib::ChecksumManifest, path: &Path) {
+    println!(
+        "Checksum manifest {} with {} checksums saved to {}",
+        style(manifest.serial).green(),
+        manifest.len(),
+        path.display()
+    );
+}
+
+pub fn generating_checksums() -> ProgressBar {
+    let spinner = ProgressBar::new_spinner();
+    spinner.enable_steady_tick(120);
+    spinner.set_message("Downloading the release artifacts");
+    spinner.set_style(ProgressStyle::default_spinner().template("{spinner:.green} {msg}"));
+    spinner
+}
+
+pub fn checksums_generated(manifest: &vvm_lib::ChecksumManifest, path: &Path) {
+    println!(
+        "Checksum manifest {} with {} checksums written to {}, sign it with `minisign -Sm {}`",
+        style(manifest.serial).green(),
+        manifest.len(),
+        path.display(),
+        path.display()
+    );
+}
+
+pub fn install_plan(plan: &vvm_lib::InstallPlan) {
+    println!(
+        "{}",
+        style(format!("Install Vyper {}", plan.version)).bold()
+    );
+    println!("{}", plan);
+}
+
+pub fn task_plan(plan: &vvm_lib::TaskPlan) {
+    if plan.is_empty() {
+        println!("Nothing to do");
+        return;
+    }
+    for install in plan.installs.iter() {
+        install_plan(install);
+    }
+    if !plan.removals.entries.is_empty() {
+        remove_plan(&plan.removals);
+    }
+}
+
+pub fn remove_plan(plan: &vvm_lib::RemovePlan) {
+    println!("{}", style("Remove").bold());
+    println!("{}", plan);
+}
+
+pub fn http_metrics(metrics: &vvm_lib::HttpMetrics) {
+    eprintln!(
+        "{} {} requests, {} received, {} not modified, rate limit remaining: {}",
+        style("HTTP").dim(),
+        metrics.requests,
+        HumanBytes(metrics.bytes),
+        metrics.not_modified,
+        metrics
+            .rate_limit_remaining
+            .map(|remaining| remaining.to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    );
+}
+
+pub fn trashed(version: &Version) {
+    println!(
+        "Moved {} to the trash, undo with `vvm restore {}`",
+        style(version).yellow(),
+        version
+    );
+}
+
+pub fn restored(version: &Version) {
+    println!("Restored {} from the trash", style(version).green());
+}
+
+pub fn relocated(new_home: &Path) {
+    println!("VVM home moved to {}", style(new_home.display()).green());
+    println!(
+        "Shims are now in {}, update PATH with the output of `vvm env`",
+        style(new_home.join("bin").display()).yellow()
+    );
+}
+
+pub fn migrated_layout(versions: &[(Version, PathBuf)]) {
+    if versions.is_empty() {
+        println!("No flat installs to migrate");
+        return;
+    }
+    for (version, path) in versions {
+        println!(
+            "Moved {} to {}",
+            style(version).green(),
+            style(path.display()).yellow()
+        );
+    }
+}
+
+pub fn gc_report(report: &vvm_lib::GcReport) {
+    if report.is_empty() {
+        println!("Nothing to clean up");
+        return;
+    }
+    let sections = [
+        ("Orphaned lock files", &report.lock_files),
+        ("Partial downloads", &report.partial_downloads),
+        ("Empty version directories", &report.empty_version_dirs),
+        ("Stale cache entries", &report.cache_entries),
+        ("Expired trash", &report.expired_trash),
+    ];
+    for (title, paths) in sections.iter().filter(|(_, paths)| !paths.is_empty()) {
+        println!("{}", style(title).bold());
+        paths
+            .iter()
+            .for_each(|p| println!("{}", style(p.display()).yellow()));
+    }
+    println!(
+        "\nReclaimed {}",
+        style(HumanBytes(report.reclaimed_bytes)).green()
+    );
+}
+
+pub fn which(which: &vvm_lib::WhichVersion) {
+    println!("{}", which.path.display());
+    if which.install_required {
+        eprintln!(
+            "{}",
+            style(format!(
+                "Vyper {} ({}) is not installed, run `vvm install {}`",
+                which.version, which.source, which.version
+            ))
+            .yellow()
+        );
+    }
+}
+
+pub fn audit_findings(findings: &[(Version, vvm_lib::VersionOrigin)]) {
+    if findings.is_empty() {
+        println!(
+            "{}",
+            style("No installed version has a forbidden origin").green()
+        );
+    }
+    for (version, origin) in findings {
+        println!(
+            "{}",
+            style(format!("Vyper {} has forbidden origin {}", version, origin)).red()
+        );
+    }
+}
+
+pub fn lock_statuses(statuses: &[(Version, vvm_lib::LockStatus)]) {
+    for (version, status) in statuses {
+        let line = format!("Vyper {} {}", version, status);
+        if status.is_match() {
+            println!("{}", style(line).green());
+        } else {
+            println!("{}", style(line).red());
+        }
+    }
+}
+
+pub fn doctor_checks(checks: &[vvm_lib::DoctorCheck]) {
+    for check in checks {
+        let status = match check.status {
+            vvm_lib::CheckStatus::Ok => style(format!("{:<6}", "ok")).green(),
+            vvm_lib::CheckStatus::Warning => style(format!("{:<6}", "warn")).yellow(),
+            vvm_lib::CheckStatus::Failed => style(format!("{:<6}", "FAIL")).red().bold(),
+            vvm_lib::CheckStatus::Skipped => style(format!("{:<6}", "skip")).dim(),
+        };
+        println!(
+            "{}{:<16}{}  {}",
+            status,
+            check.layer,
+            style(&check.target).bold(),
+            check.detail
+        );
+    }
+}
+
+pub fn home_repair(repair: &vvm_lib::HomeRepair) {
+    if repair.is_empty() {
+        println!("Nothing to repair");
+        return;
+    }
+    if !repair.corrupted_caches.is_empty() {
+        println!("{}", style("Corrupted caches").bold());
+        repair
+            .corrupted_caches
+            .iter()
+            .for_each(|p| println!("{}", style(p.display()).yellow()));
+    }
+    if !repair.leftovers.is_empty() {
+        gc_report(&repair.leftovers);
+    }
+    if let Some(global_version) = &repair.global_version {
+        println!("{}", global_version);
+    }
+    if let Some(content) = &repair.invalid_global_version {
+        println!(
+            "The global version file held `{}`, not a version, unset it",
+            content
+        );
+    }
+}
+
+pub fn status_line(label: &str, value: impl Display) {
+    println!("{:<20}{}", style(label).bold(), value);
+}
+
+pub fn usage_stats(enabled: bool, stats: &vvm_lib::UsageStats) {
+    if !enabled {
+        println!("Usage statistics are disabled, enable them with `vvm stats --enable`");
+        return;
+    }
+    println!(
+        "Cache hits: {}, misses: {}",
+        style(stats.cache_hits).green(),
+        style(stats.cache_misses).yellow()
+    );
+    println!("\n{}", style("Versions").bold());
+    let versions = stats
+        .installs
+        .keys()
+        .chain(stats.last_used.keys())
+        .sorted()
+        .dedup();
+    for v in versions {
+        println!(
+            "{} installed {} time(s), last used: {}",
+            style(v.to_string().as_str()).yellow(),
+            stats.installs.get(v).copied().unwrap_or_default(),
+            stats
+                .last_used
+                .get(v)
+                .map_or_else(|| "never".to_string(), |t| time_ago(*t))
+        );
+    }
+}
+
+/// Formats a unix timestamp relative to now, e.g. "3 days ago".
+fn time_ago(timestamp: u64) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let secs = now.saturating_sub(timestamp);
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{} minute(s) ago", secs / 60),
+        3600..=86399 => format!("{} hour(s) ago", secs / 3600),
+        _ => format!("{} day(s) ago", secs / 86400),
+    }
+}
+
+pub fn orphaned_versions(orphaned: &[vvm_lib::OrphanedVersion]) {
+    println!("{}", style("Orphaned versions").bold());
+    orphaned.iter().for_each(|orphan| {
+        println!(
+            "{}  {}",
+            style(orphan.version.to_string().as_str()).yellow(),
+            orphan.reason
+        );
+    });
+}
+
+pub fn prune_candidates(versions: &[Version]) {
+    println!("{}", style("Unused versions").bold());
+    versions.iter().for_each(|v| {
+        println!("{}", style(v.to_string().as_str()).yellow());
+    });
+}
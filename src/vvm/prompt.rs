@@ -0,0 +1,47 @@
+use dialoguer::Confirm;
+use vvm_lib::PromptPolicy;
+
+use std::env;
+
+/// Environment variable overriding `prompt_policy` from the config, e.g. in CI.
+const PROMPT_POLICY_ENV: &str = "VVM_PROMPT_POLICY";
+
+/// Asks a yes/no question, or answers it right away according to the prompt policy:
+/// `VVM_PROMPT_POLICY` if set, `prompt_policy` from the config otherwise.
+///
+/// Fails with the `ask` policy when there is no terminal to ask on, rather than guessing.
+pub fn confirm(prompt: &str, default: bool) -> anyhow::Result<bool> {
+    match policy()? {
+        PromptPolicy::AlwaysYes => Ok(true),
+        PromptPolicy::AlwaysNo => Ok(false),
+        PromptPolicy::Ask if !console::user_attended() => anyhow::bail!(
+            "{} Cannot ask without a terminal, set {} or prompt_policy in {} to always-yes or always-no",
+            prompt,
+            PROMPT_POLICY_ENV,
+            vvm_lib::config_path()?.display()
+        ),
+        PromptPolicy::Ask => Ok(Confirm::new()
+            .with_prompt(prompt)
+            .default(default)
+            .interact()?),
+    }
+}
+
+/// Asks a yes/no question opting in to something, e.g. collecting data. Only a person at a
+/// terminal can opt in: the answer is no, without asking, unless the policy is to ask.
+pub fn opt_in(prompt: &str) -> anyhow::Result<bool> {
+    match policy()? {
+        PromptPolicy::Ask if console::user_attended() => Ok(Confirm::new()
+            .with_prompt(prompt)
+            .default(false)
+            .interact()?),
+        _ => Ok(false),
+    }
+}
+
+fn policy() -> anyhow::Result<PromptPolicy> {
+    match env::var(PROMPT_POLICY_ENV) {
+        Ok(policy) => policy.parse().map_err(anyhow::Error::msg),
+        Err(_) => Ok(vvm_lib::Config::load()?.prompt_policy),
+    }
+}
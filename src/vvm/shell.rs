@@ -0,0 +1,123 @@
+use clap_complete::Shell;
+
+use std::{env, path::Path};
+
+/// Best-effort detection of the user's shell, used when `--shell` is not provided.
+pub fn detect() -> Shell {
+    if cfg!(target_os = "windows") {
+        return Shell::PowerShell;
+    }
+    match env::var("SHELL") {
+        Ok(s) if s.ends_with("zsh") => Shell::Zsh,
+        Ok(s) if s.ends_with("fish") => Shell::Fish,
+        Ok(s) if s.ends_with("elvish") => Shell::Elvish,
+        Ok(s) if s.ends_with("pwsh") || s.ends_with("powershell") => Shell::PowerShell,
+        _ => Shell::Bash,
+    }
+}
+
+/// Returns the snippet that puts the shim directory in front of `PATH` for the given shell,
+/// preceded by a comment explaining how to persist it in the shell's profile.
+pub fn path_snippet(shell: Shell, bin_dir: &Path) -> String {
+    let bin_dir = bin_dir.display();
+    match shell {
+        Shell::Zsh => format!(
+            "# Add the following line to ~/.zshrc:\n#   eval \"$(vvm env --shell zsh)\"\nexport PATH=\"{}:$PATH\"",
+            bin_dir
+        ),
+        Shell::Fish => format!(
+            "# Add the following line to ~/.config/fish/config.fish:\n#   vvm env --shell fish | source\nset -gx PATH \"{}\" $PATH",
+            bin_dir
+        ),
+        Shell::Elvish => format!(
+            "# Add the following line to ~/.config/elvish/rc.elv:\n#   eval (vvm env --shell elvish | slurp)\nset paths = [\"{}\" $@paths]",
+            bin_dir
+        ),
+        Shell::PowerShell => format!(
+            "# Add the following line to your PowerShell profile ($PROFILE):\n#   vvm env --shell powershell | Out-String | Invoke-Expression\n$env:Path = \"{}\" + [IO.Path]::PathSeparator + $env:Path",
+            bin_dir
+        ),
+        _ => format!(
+            "# Add the following line to ~/.bashrc:\n#   eval \"$(vvm env --shell bash)\"\nexport PATH=\"{}:$PATH\"",
+            bin_dir
+        ),
+    }
+}
+
+/// Returns the statement exporting the environment variable in the given shell.
+pub fn set_var(shell: Shell, name: &str, value: &str) -> String {
+    match shell {
+        Shell::Fish => format!("set -gx {} \"{}\";", name, value),
+        Shell::Elvish => format!("set-env {} \"{}\"", name, value),
+        Shell::PowerShell => format!("$env:{} = \"{}\"", name, value),
+        _ => format!("export {}=\"{}\";", name, value),
+    }
+}
+
+/// Returns the statement removing the environment variable in the given shell.
+pub fn unset_var(shell: Shell, name: &str) -> String {
+    match shell {
+        Shell::Fish => format!("set -e {};", name),
+        Shell::Elvish => format!("unset-env {}", name),
+        Shell::PowerShell => format!("Remove-Item Env:{} -ErrorAction SilentlyContinue", name),
+        _ => format!("unset {};", name),
+    }
+}
+
+/// Returns the statement putting `dir` in front of `PATH` in the given shell.
+pub fn prepend_path(shell: Shell, dir: &Path) -> String {
+    let dir = dir.display();
+    match shell {
+        Shell::Fish => format!("set -gx PATH \"{}\" $PATH;", dir),
+        Shell::Elvish => format!("set paths = [\"{}\" $@paths]", dir),
+        Shell::PowerShell => format!(
+            "$env:Path = \"{}\" + [IO.Path]::PathSeparator + $env:Path",
+            dir
+        ),
+        _ => format!("export PATH=\"{}:$PATH\";", dir),
+    }
+}
+
+/// Returns the shell code that re-evaluates `vvm hook-env` whenever the working directory
+/// changes, so `VYPER` always points at the project's pinned compiler.
+pub fn hook(shell: Shell) -> String {
+    match shell {
+        Shell::Zsh => r#"_vvm_hook() {
+  eval "$(vvm hook-env --shell zsh)";
+}
+typeset -ag chpwd_functions;
+if [[ -z "${chpwd_functions[(r)_vvm_hook]+1}" ]]; then
+  chpwd_functions=(_vvm_hook $chpwd_functions);
+fi
+_vvm_hook"#
+            .to_string(),
+        Shell::Fish => r#"function _vvm_hook --on-variable PWD
+  vvm hook-env --shell fish | source
+end
+_vvm_hook"#
+            .to_string(),
+        Shell::Elvish => {
+            r#"set after-chdir = [$@after-chdir {|_| eval (vvm hook-env --shell elvish | slurp) }]
+eval (vvm hook-env --shell elvish | slurp)"#
+                .to_string()
+        }
+        Shell::PowerShell => r#"function global:_vvm_hook {
+  vvm hook-env --shell powershell | Out-String | Invoke-Expression
+}
+$global:_vvm_previous_prompt = $function:prompt
+function global:prompt {
+  _vvm_hook
+  & $global:_vvm_previous_prompt
+}"#
+        .to_string(),
+        _ => r#"_vvm_hook() {
+  local previous_exit_status=$?;
+  eval "$(vvm hook-env --shell bash)";
+  return $previous_exit_status;
+};
+if [[ ";${PROMPT_COMMAND[*]:-};" != *";_vvm_hook;"* ]]; then
+  PROMPT_COMMAND="_vvm_hook${PROMPT_COMMAND:+;$PROMPT_COMMAND}";
+fi"#
+        .to_string(),
+    }
+}
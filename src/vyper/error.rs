@@ -15,19 +15,14 @@ pub enum VyperError {
     Io(#[from] VyperIoError),
     #[error(transparent)]
     VvmError(#[from] vvm_lib::VyperVmError),
-
-    /// General purpose message
-    #[error("{0}")]
-    Message(String),
+    #[error("Unknown diagnostics format {0}, expected text or sarif")]
+    DiagnosticsFormat(String),
 }
 
 impl VyperError {
     pub(crate) fn io(err: io::Error, path: impl Into<PathBuf>) -> Self {
         VyperIoError::new(err, path).into()
     }
-    pub fn msg(msg: impl Into<String>) -> Self {
-        VyperError::Message(msg.into())
-    }
 }
 
 #[derive(Debug, Error)]
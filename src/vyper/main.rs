@@ -1,78 +1,433 @@
-mod cache;
 mod error;
 
-use cache::VyperFilesCache;
+use semver::Version;
 use std::{
     env, fs,
-    path::PathBuf,
-    process::{Command, Stdio},
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Instant,
 };
+use tokio::process::Command;
 
 use crate::error::VyperError;
+use vvm_lib::{CacheKey, CompileSettings, OutputStream, VyperFilesCache};
 
+/// Wrapper flag failing the compilation when the compiler prints warnings, never passed to the
+/// compiler.
+const WARNINGS_AS_ERRORS: &str = "--warnings-as-errors";
+
+/// Wrapper flag printing the cache key of the invocation instead of compiling.
+const SHOW_CACHE_KEY: &str = "--show-cache-key";
+
+/// Wrapper flags compiling every source under the given directories, the working directory by
+/// default, to fill the cache ahead of the first build, e.g. when baking CI images.
+const WARM: &[&str] = &["--warm", "--init-cache"];
+
+/// Wrapper flag checking the arguments against the capabilities of the compiler before running
+/// it, like `validate_args = true` in the config.
+const VALIDATE_ARGS: &str = "--validate-args";
+
+/// Wrapper flag choosing how compiler errors and warnings are printed on stderr: `text`, as the
+/// compiler prints them, or `sarif` for code scanning dashboards.
+const DIAGNOSTICS_FORMAT: &str = "--diagnostics-format";
+
+/// Wrapper flag writing a JSON summary of the invocation to the given path, see
+/// [`vvm_lib::BuildReport`].
+const REPORT: &str = "--report";
+
+/// How compiler errors and warnings are printed, see [`DIAGNOSTICS_FORMAT`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DiagnosticsFormat {
+    Text,
+    Sarif,
+}
+
+// Only compiler output goes to stdout, so it can be piped, e.g. `vyper Token.vy > Token.bin`.
+// Compiler warnings and errors and the notices of the wrapper go to stderr.
 #[tokio::main]
 async fn main() -> error::Result<()> {
-    let args = env::args().skip(1).collect::<Vec<String>>();
+    let start = Instant::now();
+    let mut args = env::args().skip(1).collect::<Vec<String>>();
+    let warnings_as_errors = take_flag(&mut args, WARNINGS_AS_ERRORS);
+    let show_cache_key = take_flag(&mut args, SHOW_CACHE_KEY);
+    let warm = WARM.iter().any(|flag| take_flag(&mut args, flag));
+    let validate_args = take_flag(&mut args, VALIDATE_ARGS)
+        || vvm_lib::Config::load().is_ok_and(|config| config.validate_args);
+    let diagnostics = match take_value(&mut args, DIAGNOSTICS_FORMAT).as_deref() {
+        None | Some("text") => DiagnosticsFormat::Text,
+        Some("sarif") => DiagnosticsFormat::Sarif,
+        Some(format) => return Err(VyperError::DiagnosticsFormat(format.to_string())),
+    };
+    let report = take_value(&mut args, REPORT).map(PathBuf::from);
 
     // setup .vvm/ dir in home directory
     vvm_lib::setup_home()?;
+    if let Some(repair) = vvm_lib::repair_global_version()? {
+        eprintln!("{}", repair);
+    }
+    // the nearest .vyper-version pins the version of a project, over the global version
+    let resolved =
+        vvm_lib::resolve_for_dir(env::current_dir().map_err(|err| VyperError::io(err, "."))?)?
+            .ok_or(vvm_lib::VyperVmError::GlobalVersionNotSet)?;
+    let version = resolved.version;
+    if !vvm_lib::binary_path(&version)?.exists() {
+        eprintln!(
+            "Vyper {} ({}) is not installed, run `vvm install {}`",
+            version, resolved.source, version
+        );
+        return Err(vvm_lib::VyperVmError::VersionNotInstalled(version.to_string()).into());
+    }
+    if validate_args {
+        args = vvm_lib::capabilities(&version).check_args(&version, &args)?;
+    }
+
+    if show_cache_key {
+        return print_cache_keys(&args, &version);
+    }
+    if warm {
+        return warm_cache(&args, &version, report.as_deref(), start).await;
+    }
 
+    // several source files are compiled concurrently, with labeled output
+    if args.len() > 1 && args.iter().all(|arg| !arg.starts_with('-')) {
+        return compile_many(
+            &args,
+            &version,
+            warnings_as_errors,
+            diagnostics,
+            report.as_deref(),
+            start,
+        )
+        .await;
+    }
+
+    // a cache hit is a use of the version too, keeping it from being pruned as unused
+    let _ = vvm_lib::record_use(&version);
     let mut cache = VyperFilesCache::get();
-    let mut file_name: Option<PathBuf> = None;
-
-    if args.len() == 1 && !args[0].starts_with('-') {
-        let file_name_canon =
-            fs::canonicalize(&args[0]).map_err(|err| VyperError::io(err, &args[0]))?;
-        file_name = Some(file_name_canon);
-        // support cache only for single file inputs
-        if let Some(entry) = cache.entry(file_name.clone().unwrap()) {
-            if !entry.is_dirty() {
+    // support cache only for a single source file with optimization settings and a bytecode
+    // output format, keyed by its content, the compiler version and the settings. Only outputs
+    // without warnings are cached
+    let mut cacheable: Option<(PathBuf, Vec<String>)> = None;
+    if let Some((file_name, flags)) = CompileSettings::split_source(&args) {
+        let file_name =
+            fs::canonicalize(&file_name).map_err(|err| VyperError::io(err, &file_name))?;
+        record_compile_commands(&version, std::slice::from_ref(&file_name), &flags);
+        if let Some(key) = CacheKey::new(&file_name, &version, &flags)? {
+            if let Some(entry) = cache.lookup(&key) {
                 // print out cached version
                 println!("{}", entry.deployed_bytecode);
-                return Ok(());
+                let _ = vvm_lib::record_cache_hit();
+                let file = vvm_lib::FileReport {
+                    file: file_name,
+                    cached: true,
+                    success: true,
+                    warnings: 0,
+                    duration_ms: 0,
+                    artifact: None,
+                };
+                return write_report(report.as_deref(), &version, vec![file], start);
             }
         }
+        // if we are here it means cache entry was not found or was dirty
+        // compile as normal and update/create cache file
+        let _ = vvm_lib::record_cache_miss();
+        cacheable = Some((file_name, flags));
     }
 
-    // if we are here it means cache entry was not found or was dirty
-    // compile as normal and update/create cache file
-    let version = vvm_lib::current_version()?.ok_or(vvm_lib::VyperVmError::GlobalVersionNotSet)?;
-    let mut version_path = vvm_lib::version_path(version.to_string().as_str());
-    version_path.push(format!("vyper-{}", version.to_string().as_str()));
-
-    let child = Command::new(version_path)
+    let mut command = Command::new(vvm_lib::binary_path(&version)?);
+    command
         .args(args.clone())
         .stdout(Stdio::piped())
-        .spawn()
-        .expect("Vyper wrapper: failed to execute vyper command");
-
-    let output = child
-        .wait_with_output()
-        .expect("Vyper wrapper: failed to wait for child output");
-
-    if output.status.success() {
-        println!("{}", std::str::from_utf8(&output.stdout).unwrap());
-        // cache house keeping
-        if args.len() == 1 && !args[0].starts_with('-') {
-            if let Some(bytecode) = get_bytecode(&output.stdout) {
-                if cache.add_entry(file_name.unwrap(), &bytecode).is_ok() {
-                    let _ = cache.write(cache::get_cache_path());
-                    // ignore errors
-                    // TODO: add debug statements
-                }
+        .stderr(Stdio::piped());
+    let name = cacheable
+        .as_ref()
+        .map(|(file, _)| file.display().to_string())
+        .unwrap_or_else(|| format!("vyper {}", args.join(" ")));
+    // a timed out compilation returns early, without touching the cache
+    let compile_start = Instant::now();
+    let output =
+        vvm_lib::output_with_timeout(command, &name, vvm_lib::compile_timeout(), |elapsed| {
+            if console::user_attended_stderr() {
+                eprintln!("vyper: still compiling after {}s", elapsed.as_secs());
             }
+        })
+        .await?;
+    let source = cacheable
+        .as_ref()
+        .map(|(file, _)| file.clone())
+        .or_else(|| {
+            args.iter()
+                .find(|arg| arg.ends_with(".vy"))
+                .map(PathBuf::from)
+        });
+    if let Some(file) = &source {
+        let output = vvm_lib::CompileOutput {
+            file: file.clone(),
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            cached: false,
+            duration: compile_start.elapsed(),
+        };
+        let file = vvm_lib::FileReport {
+            artifact: output_path(&args),
+            ..vvm_lib::FileReport::new(&output)
+        };
+        write_report(report.as_deref(), &version, vec![file], start)?;
+    } else {
+        write_report(report.as_deref(), &version, vec![], start)?;
+    }
+
+    match diagnostics {
+        DiagnosticsFormat::Text => eprint!("{}", String::from_utf8_lossy(&output.stderr)),
+        DiagnosticsFormat::Sarif => {
+            let file = source.unwrap_or_default();
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let diagnostics = vvm_lib::parse_diagnostics(&file, &stderr, output.status.success());
+            print_sarif(&diagnostics, &version)?;
         }
+    }
+    if !output.status.success() {
+        std::process::exit(output.status.code().unwrap_or(1));
+    }
+    print!("{}", String::from_utf8_lossy(&output.stdout));
+
+    let has_warnings = !String::from_utf8_lossy(&output.stderr).trim().is_empty();
+    if has_warnings && warnings_as_errors {
+        eprintln!("vyper: compiler warnings are treated as errors");
+        std::process::exit(1);
+    }
+    // cache house keeping
+    if let Some((file_name, flags)) = cacheable.filter(|_| !has_warnings) {
+        if let Some(bytecode) = get_bytecode(&output.stdout) {
+            if cache
+                .add_entry(file_name, &bytecode, &version, &flags)
+                .is_ok()
+            {
+                let _ = vvm_lib::get_cache_path().and_then(|path| cache.write(path));
+                // ignore errors
+                // TODO: add debug statements
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn compile_many(
+    args: &[String],
+    version: &Version,
+    warnings_as_errors: bool,
+    diagnostics: DiagnosticsFormat,
+    report: Option<&Path>,
+    start: Instant,
+) -> error::Result<()> {
+    let files = canonicalize_all(args)?;
+    let _ = vvm_lib::record_use(version);
+    record_compile_commands(version, &files, &[]);
+
+    let outputs = vvm_lib::compile_project(
+        version,
+        &files,
+        vvm_lib::compile_jobs(),
+        |file, stream, line| match stream {
+            OutputStream::Stdout => println!("{}: {}", file.display(), line),
+            OutputStream::Stderr if diagnostics == DiagnosticsFormat::Text => {
+                eprintln!("{}: {}", file.display(), line)
+            }
+            OutputStream::Stderr => {}
+        },
+    )
+    .await?;
+    if diagnostics == DiagnosticsFormat::Sarif {
+        let diagnostics = outputs
+            .iter()
+            .flat_map(|output| {
+                vvm_lib::parse_diagnostics(&output.file, &output.stderr, output.success)
+            })
+            .collect::<Vec<_>>();
+        print_sarif(&diagnostics, version)?;
+    }
+    for output in outputs.iter().filter(|output| output.cached) {
+        let _ = vvm_lib::record_cache_hit();
+        println!("{}: {}", output.file.display(), output.stdout);
+    }
+    for _ in outputs.iter().filter(|output| !output.cached) {
+        let _ = vvm_lib::record_cache_miss();
+    }
+    let files = outputs.iter().map(vvm_lib::FileReport::new).collect();
+    write_report(report, version, files, start)?;
+    if outputs.iter().any(|output| !output.success) {
+        std::process::exit(1);
+    }
+    if warnings_as_errors && outputs.iter().any(|output| !output.warnings().is_empty()) {
+        eprintln!("vyper: compiler warnings are treated as errors");
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Compiles the sources of the project directories `dirs` that are not cached yet, printing only
+/// failures and a summary.
+async fn warm_cache(
+    dirs: &[String],
+    version: &Version,
+    report: Option<&Path>,
+    start: Instant,
+) -> error::Result<()> {
+    let dirs = if dirs.is_empty() {
+        vec![".".to_string()]
     } else {
-        println!("{}", std::str::from_utf8(&output.stderr).unwrap());
+        dirs.to_vec()
+    };
+    let mut files = vec![];
+    for dir in canonicalize_all(&dirs)? {
+        files.extend(vvm_lib::project_sources(&dir).map_err(|err| match err {
+            vvm_lib::VyperVmError::IoError(err) => VyperError::io(err, &dir),
+            err => err.into(),
+        })?);
     }
 
+    let outputs = vvm_lib::compile_project(
+        version,
+        &files,
+        vvm_lib::compile_jobs(),
+        |file, stream, line| {
+            if let OutputStream::Stderr = stream {
+                eprintln!("{}: {}", file.display(), line)
+            }
+        },
+    )
+    .await?;
+    let cached = outputs.iter().filter(|output| output.cached).count();
+    let failed = outputs.iter().filter(|output| !output.success).count();
+    eprintln!(
+        "vyper: {} sources, {} already cached, {} compiled, {} failed",
+        outputs.len(),
+        cached,
+        outputs.len() - cached - failed,
+        failed
+    );
+    let files = outputs.iter().map(vvm_lib::FileReport::new).collect();
+    write_report(report, version, files, start)?;
+    if failed > 0 {
+        std::process::exit(1);
+    }
     Ok(())
 }
 
+/// Prints the components of the cache key of every source file the invocation compiles, or why
+/// its output is not cached.
+fn print_cache_keys(args: &[String], version: &Version) -> error::Result<()> {
+    let (files, flags) = match CompileSettings::split_source(args) {
+        Some((file, flags)) => (canonicalize_all(&[file])?, flags),
+        None if args.len() > 1 && args.iter().all(|arg| !arg.starts_with('-')) => {
+            (canonicalize_all(args)?, vec![])
+        }
+        None => {
+            println!(
+                "not cacheable: only source files with optimization settings and the output \
+                 formats {} are cached",
+                vvm_lib::CACHEABLE_FORMATS.join(", ")
+            );
+            return Ok(());
+        }
+    };
+    let keys = files
+        .iter()
+        .filter_map(|file| CacheKey::new(file, version, &flags).transpose())
+        .collect::<Result<Vec<_>, _>>()?;
+    println!(
+        "{}",
+        keys.iter()
+            .map(CacheKey::to_string)
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    );
+    Ok(())
+}
+
+fn canonicalize_all(args: &[String]) -> error::Result<Vec<PathBuf>> {
+    args.iter()
+        .map(|arg| fs::canonicalize(arg).map_err(|err| VyperError::io(err, arg)))
+        .collect()
+}
+
+/// Removes a wrapper flag from the compiler arguments, returns true if it was given.
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let given = args.iter().any(|arg| arg == flag);
+    args.retain(|arg| arg != flag);
+    given
+}
+
+/// Removes a wrapper flag taking a value, as `--flag value` or `--flag=value`, from the
+/// arguments, returning its value.
+fn take_value(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let position = args
+        .iter()
+        .position(|arg| arg == flag || arg.starts_with(&format!("{}=", flag)))?;
+    let arg = args.remove(position);
+    match arg.split_once('=') {
+        Some((_, value)) => Some(value.to_string()),
+        None if position < args.len() => Some(args.remove(position)),
+        None => None,
+    }
+}
+
+/// Writes the build report of `files` to `path` if one was asked for with [`REPORT`].
+fn write_report(
+    path: Option<&Path>,
+    version: &Version,
+    files: Vec<vvm_lib::FileReport>,
+    start: Instant,
+) -> error::Result<()> {
+    if let Some(path) = path {
+        vvm_lib::BuildReport::new(version, files, start.elapsed())
+            .and_then(|report| report.write(path))
+            .map_err(|err| match err {
+                vvm_lib::VyperVmError::IoError(err) => VyperError::io(err, path),
+                err => err.into(),
+            })?;
+    }
+    Ok(())
+}
+
+/// The file the compiler writes its output to, given with `-o`.
+fn output_path(args: &[String]) -> Option<PathBuf> {
+    let position = args.iter().position(|arg| arg == "-o")?;
+    args.get(position + 1).map(PathBuf::from)
+}
+
+/// Prints `diagnostics` as a SARIF log on stderr, source paths relative to the working
+/// directory.
+fn print_sarif(diagnostics: &[vvm_lib::Diagnostic], version: &Version) -> error::Result<()> {
+    let root = env::current_dir().map_err(|err| VyperError::io(err, "."))?;
+    let log = vvm_lib::sarif_log(diagnostics, version, &root);
+    eprintln!("{}", serde_json::to_string_pretty(&log)?);
+    Ok(())
+}
+
+/// Adds the compilation of `files` to the compile database if enabled in the config, failures
+/// never fail the compilation.
+fn record_compile_commands(version: &Version, files: &[PathBuf], flags: &[String]) {
+    let enabled = vvm_lib::Config::load().is_ok_and(|config| config.compile_commands);
+    if !enabled {
+        return;
+    }
+    let commands = files
+        .iter()
+        .map(|file| vvm_lib::CompileCommand::new(version, file, flags))
+        .collect::<Result<Vec<_>, _>>();
+    if let Ok(commands) = commands {
+        let _ = vvm_lib::write_compile_commands(vvm_lib::COMPILE_COMMANDS_FILENAME, &commands);
+    }
+}
+
 fn get_bytecode(bytecode: &[u8]) -> Option<String> {
-    return match std::str::from_utf8(bytecode) {
-        Ok(b) if b.starts_with("0x") => Some(b.to_string()),
+    match std::str::from_utf8(bytecode) {
+        Ok(b) if b.starts_with("0x") => Some(b.trim().to_string()),
         Ok(_) => None,
         _ => None,
-    };
+    }
 }
@@ -0,0 +1,92 @@
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use std::path::{Path, PathBuf};
+
+use crate::{all_versions, binary_path, error::VyperVmError, installed_versions, pin};
+
+/// The compiler a file or a requirement resolves to, the output of `vvm which --json`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WhichVersion {
+    pub version: Version,
+    /// Path to the binary, where it would be installed if it is not yet.
+    pub path: PathBuf,
+    /// Where the version comes from: a pin file, the global version or a requirement.
+    pub source: String,
+    pub installed: bool,
+    pub install_required: bool,
+}
+
+impl WhichVersion {
+    fn new(version: Version, source: String, installed: bool) -> Result<Self, VyperVmError> {
+        Ok(Self {
+            path: binary_path(&version)?,
+            version,
+            source,
+            installed,
+            install_required: !installed,
+        })
+    }
+}
+
+/// Resolves the compiler used in `dir`, see [`pin::resolve_for_dir`]. Returns `None` if neither
+/// a pin nor a global version is set.
+pub fn which_for_dir(dir: impl AsRef<Path>) -> Result<Option<WhichVersion>, VyperVmError> {
+    let installed = installed_versions()?;
+    pin::resolve_for_dir(dir)?
+        .map(|resolved| {
+            let is_installed = installed.contains(&resolved.version);
+            WhichVersion::new(resolved.version, resolved.source.to_string(), is_installed)
+        })
+        .transpose()
+}
+
+/// Resolves the newest version matching `req`, preferring installed versions so no network
+/// access is needed when one matches. Nothing is installed. Returns `None` if no known version
+/// matches.
+pub async fn which_for_req(req: &VersionReq) -> Result<Option<WhichVersion>, VyperVmError> {
+    let source = format!("requirement {}", req);
+    if let Some(version) = installed_versions()?
+        .into_iter()
+        .rev()
+        .find(|v| req.matches(v))
+    {
+        return WhichVersion::new(version, source, true).map(Some);
+    }
+    all_versions()
+        .await?
+        .into_iter()
+        .rev()
+        .find(|v| req.matches(v))
+        .map(|version| WhichVersion::new(version, source, false))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[tokio::test]
+    async fn resolves_installed_versions_first() {
+        let version = Version::new(0, 0, 9);
+        crate::testing::sandbox().unwrap();
+        crate::setup_version(version.to_string().as_str()).unwrap();
+        fs::write(binary_path(&version).unwrap(), b"fake vyper").unwrap();
+
+        let which = which_for_req(&VersionReq::parse("=0.0.9").unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(which.version, version);
+        assert_eq!(which.path, binary_path(&version).unwrap());
+        assert!(which.installed && !which.install_required);
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(".vyper-version"), "0.0.9").unwrap();
+        let which = which_for_dir(dir.path()).unwrap().unwrap();
+        assert_eq!(which.version, version);
+        assert!(which.source.starts_with("pinned by"));
+        crate::remove_version(&version).unwrap();
+    }
+}